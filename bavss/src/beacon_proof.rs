@@ -0,0 +1,312 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use config::{Committee, SharedCommittee};
+use crypto::{Digest, PublicKey};
+use model::beacon_output::OutputDerivation;
+use model::breeze_universal::{BreezeCertificate, CommonReferenceString};
+use model::types_and_const::{Epoch, RandomNum};
+use crate::breeze_structs::SingleShare;
+#[cfg(not(feature = "pq"))]
+use crate::breeze_structs::WitnessBreeze;
+#[cfg(not(feature = "pq"))]
+use curve25519_dalek::Scalar;
+#[cfg(not(feature = "pq"))]
+use crate::breeze_origin::breeze_reconstruct_dealer::BreezeReconResult;
+#[cfg(not(feature = "pq"))]
+use crate::breeze_origin::breeze_share_dealer::merkle_leaf_count;
+#[cfg(not(feature = "pq"))]
+use crate::merkletree::verify_merkle_proof;
+#[cfg(feature = "pq")]
+use crate::breeze_structs::PQCrs;
+#[cfg(feature = "pq")]
+use crate::breeze_pq::breeze_reconstruct_dealer::BreezeReconResult;
+#[cfg(feature = "pq")]
+use crate::breeze_pq::breeze_share_dealer::{commitment_digest, Shares};
+
+#[cfg(test)]
+#[cfg(not(feature = "pq"))]
+#[path = "tests/beacon_proof_tests.rs"]
+pub mod beacon_proof_tests;
+
+#[cfg(test)]
+#[cfg(feature = "pq")]
+#[path = "tests/beacon_proof_pq_tests.rs"]
+pub mod beacon_proof_pq_tests;
+
+/// Asks for the proof of the beacon at `(epoch, index)` of the avss batch, serialized with
+/// bincode. The reply is `None` if the beacon was not reconstructed here lately.
+pub type ProofQuery = ((Epoch, usize), oneshot::Sender<Option<Vec<u8>>>);
+
+/// The number of epochs (the latest ones) whose beacons can be proven.
+const RETAINED_EPOCHS: Epoch = 2;
+
+/// Binds the shares of one dealer to the digest `c` certified in the common core.
+#[cfg(not(feature = "pq"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DealingWitness {
+    /// Commitment to the polynomial of the reconstructed index, with its merkle branch under `c`.
+    pub commitment: WitnessBreeze,
+    /// The avss batch size, which sets the shape of the merkle tree.
+    pub batch_size: usize,
+}
+
+/// Binds the shares of one dealer to the digest `c` certified in the common core.
+#[cfg(feature = "pq")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DealingWitness {
+    /// The digest of the dealer's commitment `t`.
+    pub commitment: Digest,
+    /// The merkle roots of the evaluations, hashed into `c` along with `commitment`.
+    pub merkle_roots: Vec<Digest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DealingProof {
+    pub c: Digest,
+    /// At least f+1 shares, each one tagged with the authority holding it.
+    pub shares: Vec<(PublicKey, SingleShare)>,
+    pub witness: DealingWitness,
+}
+
+/// A transferable proof that `value` is the beacon output at `(epoch, index)`, i.e. the sum of the
+/// secrets dealt by the decided common core.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeaconProof {
+    pub epoch: Epoch,
+    /// Index in the avss batch (starting at 1), as sent in `BreezeReconRequest`.
+    pub index: usize,
+    pub value: RandomNum,
+    pub common_core: BTreeSet<BreezeCertificate>,
+    pub dealings: Vec<DealingProof>,
+}
+
+/// Checks offline that `proof` is a valid beacon output for the committee of its epoch, derived
+/// with `derivation`.
+pub fn verify_beacon(
+    committees: &SharedCommittee,
+    crs: &CommonReferenceString,
+    derivation: &OutputDerivation,
+    proof: &BeaconProof,
+) -> bool {
+    let committee = &*committees.at(proof.epoch);
+    let quorum_threshold = committee.weighted_quorum_threshold();
+
+    // The dealers of the common core must carry f+1 of the weight, with one certificate of the
+    // epoch each, signed by a quorum of members.
+    let dealers: HashSet<&PublicKey> = proof.common_core.iter().map(|cer| &cer.dealer).collect();
+    if proof.index == 0
+        || dealers.len() != proof.common_core.len()
+        || committee.weight_of(dealers) < committee.weighted_validity_threshold()
+    {
+        return false;
+    }
+    let signing_key = |pk: &PublicKey| committees.signing_key(pk, proof.epoch);
+    for cer in proof.common_core.iter() {
+        if cer.epoch != proof.epoch || cer.verify(|pk| committee.weight(pk), signing_key, quorum_threshold).is_err() {
+            return false;
+        }
+    }
+
+    // There must be exactly one dealing per certificate of the common core.
+    let certified: HashSet<Digest> = proof.common_core.iter().map(|cer| cer.c).collect();
+    let dealt: HashSet<Digest> = proof.dealings.iter().map(|d| d.c).collect();
+    if certified != dealt || dealt.len() != proof.dealings.len() {
+        return false;
+    }
+
+    #[cfg(not(feature = "pq"))]
+    let value = {
+        let fault_tolerance = committee.authorities_fault_tolerance();
+        let mut cumulated_output = Scalar::ZERO;
+        for dealing in proof.dealings.iter() {
+            match verify_dealing(committee, crs, proof.index, fault_tolerance, dealing) {
+                Some(secret) => cumulated_output += secret,
+                None => return false,
+            }
+        }
+        BreezeReconResult::new(cumulated_output).derive_output(derivation, proof.epoch, proof.index)
+    };
+
+    #[cfg(feature = "pq")]
+    let value = {
+        let crs = PQCrs::from(crs);
+        let mut cumulated_output = vec![0; crs.g];
+        for dealing in proof.dealings.iter() {
+            if !verify_dealing(committee, &crs, proof.index, dealing, &mut cumulated_output) {
+                return false;
+            }
+        }
+        BreezeReconResult::new(cumulated_output).derive_output(derivation, proof.epoch, proof.index)
+    };
+
+    value == proof.value
+}
+
+/// Returns the holders' ids if the shares come from distinct members and suffice to reconstruct.
+fn holder_ids(committee: &Committee, dealing: &DealingProof) -> Option<Vec<usize>> {
+    let holders: HashSet<&PublicKey> = dealing.shares.iter().map(|(pk, _)| pk).collect();
+    if holders.len() != dealing.shares.len() || !committee.can_reconstruct(holders) {
+        return None;
+    }
+    dealing
+        .shares
+        .iter()
+        .map(|(pk, _)| committee.get_id(pk))
+        .collect()
+}
+
+/// Interpolates the polynomial of the dealing and checks it against the dealer's commitment.
+/// Returns the secret of the dealing.
+#[cfg(not(feature = "pq"))]
+fn verify_dealing(
+    committee: &Committee,
+    crs: &CommonReferenceString,
+    index: usize,
+    fault_tolerance: usize,
+    dealing: &DealingProof,
+) -> Option<Scalar> {
+    let ids = holder_ids(committee, dealing)?;
+    if dealing.shares.iter().any(|(_, share)| share.c != dealing.c) {
+        return None;
+    }
+    let values: Vec<Scalar> = dealing.shares.iter().map(|(_, share)| share.y).collect();
+
+    // f+1 shares define the polynomial, the remaining ones must lie on it.
+    let t = fault_tolerance;
+    let coefficients = BreezeReconResult::interpolate_coefficients(&ids[..t + 1], &values[..t + 1]);
+    if ids.iter().zip(values.iter()).skip(t + 1).any(|(id, y)| BreezeReconResult::evaluate(&coefficients, *id) != *y) {
+        return None;
+    }
+
+    // The polynomial must be the one committed at this index under `c`.
+    let witness = &dealing.witness;
+    if crs.g.len() != coefficients.len() || witness.commitment.merkle_branch.0 != index - 1 {
+        return None;
+    }
+    let commitment = crs
+        .g
+        .iter()
+        .zip(coefficients.iter())
+        .map(|(g, a)| g * a)
+        .sum();
+    if witness.commitment.poly_commit != commitment {
+        return None;
+    }
+    let leaf = witness.commitment.poly_commit.compress().to_bytes().to_vec();
+    let leaf_count = merkle_leaf_count(witness.batch_size, committee.size());
+    match verify_merkle_proof(&leaf, witness.commitment.merkle_branch.clone(), dealing.c, leaf_count) {
+        Ok(true) => Some(coefficients[0]),
+        _ => None,
+    }
+}
+
+/// Checks every share against the merkle roots committed under `c`, as the reconstruction does,
+/// and adds the secret of the dealing to `cumulated`.
+#[cfg(feature = "pq")]
+fn verify_dealing(
+    committee: &Committee,
+    crs: &PQCrs,
+    index: usize,
+    dealing: &DealingProof,
+    cumulated: &mut Vec<u64>,
+) -> bool {
+    let ids = match holder_ids(committee, dealing) {
+        Some(ids) => ids,
+        None => return false,
+    };
+    let witness = &dealing.witness;
+    if commitment_digest(&witness.commitment, &witness.merkle_roots) != dealing.c
+        || witness.merkle_roots.len() < index * crs.g
+    {
+        return false;
+    }
+    let roots = &witness.merkle_roots[(index - 1) * crs.g..index * crs.g];
+    for (id, (_, share)) in ids.iter().zip(dealing.shares.iter()) {
+        if share.c != dealing.c || !Shares::verify_merkle(*id, &share.y, &share.merkle_proof.1, roots, committee.size()) {
+            return false;
+        }
+    }
+    let values = dealing.shares.iter().map(|(_, s)| s.y.clone()).collect();
+    BreezeReconResult::interpolate(&ids, &values, crs.q, cumulated);
+    true
+}
+
+/// A beacon value, with the shares of each dealing (by commitment) it was reconstructed from.
+type Reconstruction = (RandomNum, Vec<(Digest, Vec<(PublicKey, SingleShare)>)>);
+
+/// The shares of the beacons reconstructed in the last epochs, kept to prove them on demand.
+#[derive(Default)]
+pub(crate) struct ProofCache {
+    common_cores: HashMap<Epoch, BTreeSet<BreezeCertificate>>,
+    reconstructions: HashMap<(Epoch, usize), Reconstruction>,
+}
+
+impl ProofCache {
+    /// Records the common core of `epoch`, and forgets the epochs too old to be proven.
+    pub fn insert_common_core(&mut self, epoch: Epoch, common_core: &BTreeSet<BreezeCertificate>) {
+        if self.common_cores.contains_key(&epoch) {
+            return;
+        }
+        self.common_cores.insert(epoch, common_core.clone());
+        let newest = self.common_cores.keys().max().copied().unwrap_or(epoch);
+        let oldest = newest.saturating_sub(RETAINED_EPOCHS - 1);
+        self.common_cores.retain(|e, _| *e >= oldest);
+        self.reconstructions.retain(|(e, _), _| *e >= oldest);
+    }
+
+    /// Records the beacon at `(epoch, index)`, keeping just enough shares of each dealing to
+    /// reconstruct it.
+    pub fn insert(
+        &mut self,
+        committee: &Committee,
+        epoch: Epoch,
+        index: usize,
+        value: RandomNum,
+        dealings: Vec<(Digest, Vec<(PublicKey, SingleShare)>)>,
+    ) {
+        if !self.common_cores.contains_key(&epoch) {
+            return;
+        }
+        let dealings = dealings
+            .into_iter()
+            .map(|(c, mut shares)| {
+                shares.sort_by_key(|(pk, _)| committee.get_id(pk));
+                let mut holders = Vec::new();
+                shares.retain(|(pk, _)| {
+                    let needed = !committee.can_reconstruct(holders.iter());
+                    holders.push(*pk);
+                    needed
+                });
+                (c, shares)
+            })
+            .collect();
+        self.reconstructions.insert((epoch, index), (value, dealings));
+    }
+
+    /// Builds the proof of the beacon at `(epoch, index)`, with the `witness` of the dealing of
+    /// each digest, out of the shares it was reconstructed from.
+    pub fn proof<W>(&self, epoch: Epoch, index: usize, witness: W) -> Option<BeaconProof>
+    where
+        W: Fn(&Digest, &[(PublicKey, SingleShare)]) -> Option<DealingWitness>,
+    {
+        let (value, dealings) = self.reconstructions.get(&(epoch, index))?;
+        let dealings = dealings
+            .iter()
+            .map(|(c, shares)| {
+                Some(DealingProof {
+                    c: *c,
+                    shares: shares.clone(),
+                    witness: witness(c, shares)?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(BeaconProof {
+            epoch,
+            index,
+            value: value.clone(),
+            common_core: self.common_cores.get(&epoch)?.clone(),
+            dealings,
+        })
+    }
+}
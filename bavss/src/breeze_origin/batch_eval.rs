@@ -1,7 +1,7 @@
 use curve25519_dalek::traits::Identity;
 use curve25519_dalek::{ Scalar};
 use curve25519_dalek::ristretto::RistrettoPoint;
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use rs_merkle::algorithms::Sha256;
 use rs_merkle::Hasher;
 use std::ops::Mul;
@@ -18,9 +18,10 @@ pub fn batch_eval(
     y: &Vec<Vec<Scalar>>,
     s_hat: &Vec<RistrettoPoint>,
     t: usize,
-    _n: usize
+    _n: usize,
+    rng: &mut (impl RngCore + CryptoRng),
 )->(Vec<Vec<Scalar>>,Vec<PhiElement>) {
-    let (d, d_hat) = generate_blind_mask(crs, t);
+    let (d, d_hat) = generate_blind_mask(crs, t, rng);
     let (v, v_d) = generate_eval_matrices(&d, s, y);
     let (gamma, sigma) = generate_sigma(s, s_hat);
     let mut sigma_plus_d: Vec<Scalar> = sigma.iter().zip(d.iter()).map(|(a, b)| a + b).collect();
@@ -78,8 +79,7 @@ pub fn batch_verify_eval(crs: &CommonReferenceString, s_hat: &Vec<RistrettoPoint
 
 
 
-fn generate_blind_mask(crs: &CommonReferenceString, t: usize) -> (Vec<Scalar>, RistrettoPoint) {
-    let mut rng = rand::thread_rng();
+fn generate_blind_mask(crs: &CommonReferenceString, t: usize, rng: &mut (impl RngCore + CryptoRng)) -> (Vec<Scalar>, RistrettoPoint) {
     let mut d: Vec<Scalar> = Vec::with_capacity(t + 1);
     for _ in 0..t + 1 {
         let random_val = rng.gen_range(0..(t+1) as u64);
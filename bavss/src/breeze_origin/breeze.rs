@@ -1,3 +1,4 @@
+use super::breeze_complaint::BreezeComplaint;
 use super::breeze_confirm::BreezeConfirm;
 use super::breeze_message_handler::BreezeMessageHandler;
 use super::breeze_reconstruct::BreezeReconstruct;
@@ -5,37 +6,52 @@ use super::breeze_reply::BreezeReply;
 use super::breeze_result::BreezeResult;
 use super::breeze_share::BreezeShare;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use network::{Receiver as NetworkReceiver, ReliableSender};
+use network::{Receiver as NetworkReceiver, ReliableSender, Transport};
 use std::sync::Arc;
+use rand::rngs::StdRng;
+use rand::SeedableRng as _;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::RwLock;
-use config::{Committee, KeyPair};
-use model::breeze_universal::{BreezeCertificate, BreezeReconRequest, CommonReferenceString};
-use model::types_and_const::{Epoch, Id, RandomNum, CHANNEL_CAPACITY};
-use crypto::{Digest};
+use config::SharedCommittee;
+use model::beacon_output::OutputDerivation;
+use model::evidence::Evidence;
+use model::breeze_universal::{
+    BreezeCertificate, BreezeReconRequest, CommonReferenceString, Complaint, ComplaintQuery, ComplaintStatus,
+};
+use model::types_and_const::{Epoch, RandomNum, CHANNEL_CAPACITY};
+use crypto::{Digest, PublicKey, SignatureService};
+use store::Store;
+use crate::beacon_proof::ProofQuery;
+use crate::breeze_store::BreezeStore;
 use crate::breeze_structs::BreezeMessage;
 
 pub struct Breeze;
 
 impl Breeze {
-    pub fn spawn(
-        keypair: KeyPair,
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spawn(
+        name: PublicKey,
+        signature_service: SignatureService,
+        transport: Arc<dyn Transport>,
         address: SocketAddr,
-        id:Id,
-        committee: Committee,
+        committee: SharedCommittee,
         breeze_share_cmd_receiver: Receiver<Epoch>,
         breeze_certificate_sender: Sender<BreezeCertificate>,
         breeze_reconstruct_cmd_receiver: Receiver<BreezeReconRequest>,
         breeze_result_sender: Sender<(Epoch, usize, RandomNum)>,
+        rx_proof_query: Receiver<ProofQuery>,
+        tx_evidence: Sender<Evidence>,
+        tx_complaint: Sender<Complaint>,
+        rx_complaint_query: Receiver<ComplaintQuery>,
 
         common_reference_string: CommonReferenceString,
+        beacon_output: OutputDerivation,
+        robust_reconstruction: bool,
+        store: Store,
+        dealing_seed: [u8; 32],
     ) {
-        let pk = keypair.name;
-        let sk = keypair.secret;
-        let node_id = (pk,id);
-        
         let (breeze_share_sender, breeze_share_receiver) =
             channel::<BreezeMessage>(CHANNEL_CAPACITY);
         let (breeze_confirm_sender, breeze_confirm_receiver) =
@@ -44,71 +60,106 @@ impl Breeze {
             channel::<BreezeMessage>(CHANNEL_CAPACITY);
         let (breeze_reconstruct_secret_sender, breeze_reconstruct_secret_receiver) =
             channel::<BreezeMessage>(CHANNEL_CAPACITY);
+        let (breeze_complaint_sender, breeze_complaint_receiver) =
+            channel::<BreezeMessage>(CHANNEL_CAPACITY);
+        let (confirm_complaint_sender, confirm_complaint_receiver) =
+            channel::<ComplaintStatus>(CHANNEL_CAPACITY);
         
         let common_reference_string = Arc::new(RwLock::new(common_reference_string));
         
-        let my_shares =Arc::new(RwLock::new(Vec::new()));
+        // Reload the shares dealt and received before a restart.
+        let mut breeze_store = BreezeStore::new(store);
+        let my_shares = Arc::new(RwLock::new(breeze_store.load_shares().await));
         
-        let my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>> = Arc::new(RwLock::new(HashMap::new()));
+        let my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>> = Arc::new(RwLock::new(breeze_store.load_dealer_shares().await));
+        let my_dealings = Arc::new(RwLock::new(breeze_store.load_dealings().await));
         
         
-        NetworkReceiver::spawn(
+        NetworkReceiver::spawn_with_transport(
+            transport.clone(),
             address,
             BreezeMessageHandler {
-                breeze_share_sender,
+                breeze_share_sender: breeze_share_sender.clone(),
                 breeze_confirm_sender,
                 breeze_out_sender,
-                breeze_reconstruct_secret_sender
+                breeze_reconstruct_secret_sender,
+                breeze_complaint_sender,
             },
         );
 
         let (breeze_recon_certificate_sender, breeze_recon_certificate_receiver) =
-            channel::<(HashSet<Digest>,Epoch, usize)>(CHANNEL_CAPACITY);
+            channel::<BreezeReconRequest>(CHANNEL_CAPACITY);
 
         BreezeResult::spawn(
             committee.clone(),
             breeze_recon_certificate_receiver,
             breeze_reconstruct_secret_receiver,
-            breeze_result_sender
+            breeze_result_sender,
+            tx_evidence.clone(),
+            rx_proof_query,
+            Arc::clone(&my_shares),
+            beacon_output,
+            robust_reconstruction,
         );
 
         //reconstruct phase
         BreezeReconstruct::spawn(
-            node_id,
+            name,
+            signature_service.clone(),
             committee.clone(),
             breeze_reconstruct_cmd_receiver,
             breeze_recon_certificate_sender,
-            ReliableSender::new(),
+            ReliableSender::with_transport(transport.clone()),
             Arc::clone(&my_shares)
         );
-        let committee = Arc::new(RwLock::new(committee));
         //confirm phase
         BreezeConfirm::spawn(
-            node_id,
-            Arc::clone(&committee),
+            name,
+            committee.clone(),
             breeze_confirm_receiver,
             breeze_certificate_sender,
             Arc::clone(&my_dealer_shares),
+            confirm_complaint_receiver,
+        );
+        //complaint phase
+        BreezeComplaint::spawn(
+            name,
+            committee.clone(),
+            breeze_complaint_receiver,
+            breeze_share_sender,
+            ReliableSender::with_transport(transport.clone()),
+            Arc::clone(&my_dealings),
+            Arc::clone(&common_reference_string),
+            confirm_complaint_sender,
+            tx_complaint,
+            rx_complaint_query,
+            tx_evidence.clone(),
         );
         //reply phase
         BreezeReply::spawn(
-            node_id,
-            sk,
-            Arc::clone(&committee),
+            name,
+            signature_service.clone(),
+            committee.clone(),
             breeze_share_receiver,
-            ReliableSender::new(),
+            ReliableSender::with_transport(transport.clone()),
             Arc::clone(&my_shares),
             Arc::clone(&common_reference_string),
+            breeze_store.clone(),
+            tx_evidence,
         );
 
         //share phase
         BreezeShare::spawn(
-            node_id,
-            Arc::clone(&committee),
+            name,
+            signature_service,
+            committee,
             breeze_share_cmd_receiver,
-            ReliableSender::new(),
+            ReliableSender::with_transport(transport),
             Arc::clone(&common_reference_string),
-            Arc::clone(&my_dealer_shares)
+            Arc::clone(&my_dealer_shares),
+            my_dealings,
+            breeze_store,
+            StdRng::from_seed(dealing_seed),
         );
     }
 }
@@ -1,38 +1,48 @@
-use log::{ error, info};
+use log::{ error, info, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::RwLock;
-use config::Committee;
+use config::SharedCommittee;
 use crypto::{Digest, PublicKey};
-use model::breeze_universal::BreezeCertificate;
-use model::types_and_const::{Epoch, Id};
+use model::breeze_universal::{BreezeCertificate, ComplaintStatus, Complaints};
+use model::types_and_const::Epoch;
 use crate::breeze_structs::{BreezeContent, BreezeMessage};
 
 pub struct BreezeConfirm {
-    node_id: (PublicKey,Id),
-    committee: Arc<RwLock<Committee>>,
+    name: PublicKey,
+    committee: SharedCommittee,
     breeze_confirm_receiver: Receiver<BreezeMessage>,
     breeze_certificate_sender: Sender<BreezeCertificate>,
     my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+    complaint_receiver: Receiver<ComplaintStatus>,
+    /// A dealing accused and not yet resolved is not certified, even with a quorum of replies.
+    complaints: Complaints,
+    certificates: HashMap<Epoch, BreezeCertificate>,
+    delivered_certificates: Vec<Epoch>,
 }
 
 impl BreezeConfirm {
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        committee: Arc<RwLock<Committee>>,
+        name: PublicKey,
+        committee: SharedCommittee,
         breeze_confirm_receiver: Receiver<BreezeMessage>,
         breeze_certificate_sender: Sender<BreezeCertificate>,
         my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+        complaint_receiver: Receiver<ComplaintStatus>,
     ) {
 
         tokio::spawn(async move {
             Self {
-                node_id,
+                name,
                 committee,
                 breeze_confirm_receiver,
                 breeze_certificate_sender,
-                my_dealer_shares
+                my_dealer_shares,
+                complaint_receiver,
+                complaints: Complaints::default(),
+                certificates: HashMap::new(),
+                delivered_certificates: Vec::new(),
             }
             .run()
             .await;
@@ -41,11 +51,10 @@ impl BreezeConfirm {
 
     pub async fn run(&mut self) {
         info!("Breeze confirm start to listen");
-        let mut certificates: HashMap<Epoch, BreezeCertificate> = HashMap::new();
-        let mut delivered_certificates: Vec<Epoch> = Vec::new();
         loop {
-            match self.breeze_confirm_receiver.recv().await.unwrap() {
-                message => {
+            tokio::select! {
+                biased;
+                Some(message) = self.breeze_confirm_receiver.recv() => {
                     
                     let epoch = match message.get_epoch() {
                         Some(epoch) => epoch,
@@ -56,45 +65,62 @@ impl BreezeConfirm {
                     let signature;
                     let receiver = message.sender;
                     if let BreezeContent::Reply(rm) = message.content {
-                        if rm.dealer != self.node_id.0 {
+                        if rm.dealer != self.name {
                             continue;
                         }
                         signature = rm.signature;
                     } else {
                         continue;
                     }
-                    let committee = self.committee.read().await;
+                    let committee = self.committee.at(epoch);
                     let my_dealer_shares = self.my_dealer_shares.read().await;
-                    match my_dealer_shares.get(&epoch) {
-                        Some(c) => {
-                            if signature.verify(c, &receiver).is_ok() {
-                                certificates
-                                    .entry(epoch)
-                                    .and_modify(|cert| cert.insert(receiver, signature.clone()))
-                                    .or_insert(BreezeCertificate::new(*c, receiver,epoch, signature));
-
-                                let quorum_threshold = committee.authorities_quorum_threshold();
-                                match certificates.get(&epoch){
-                                    Some(cert) => {
-                                        if cert.get_len() >= quorum_threshold
-                                            && !delivered_certificates.contains(&epoch)
-                                        {
-                                            if let Err(_) = self.breeze_certificate_sender.send(cert.clone()).await {
-                                                error!("fail to send certificate to BFT-SMR")
-                                            }
-                                            delivered_certificates.push(epoch.clone());
-                                            certificates.retain(|&e, _| e > epoch);
-                                        }
-                                    }
-                                    _ => {continue;}
-                                }
-                            }
+                    if let Some(c) = my_dealer_shares.get(&epoch) {
+                        let signing_key = self.committee.signing_key(&receiver, epoch);
+                        let digest = BreezeCertificate::signed_digest(c, &self.name);
+                        if committee.weight(&receiver) > 0 && signature.verify(&digest, &signing_key).is_ok() {
+                            self.certificates
+                                .entry(epoch)
+                                .and_modify(|cert| cert.insert(receiver, signature.clone()))
+                                .or_insert(BreezeCertificate::new(*c, self.name, receiver,epoch, signature));
                         }
-                        None => {}
                     }
                     drop(my_dealer_shares);
+                    self.try_deliver(epoch).await;
+                }
+                Some(status) = self.complaint_receiver.recv() => {
+                    if self.complaints.update(&status) {
+                        let epoch = match status {
+                            ComplaintStatus::Accused { epoch, .. }
+                            | ComplaintStatus::Resolved { epoch, .. }
+                            | ComplaintStatus::Excluded { epoch, .. } => epoch,
+                        };
+                        self.try_deliver(epoch).await;
+                    }
                 }
             }
         }
     }
+
+    /// Delivers the certificate of our dealing of `epoch` once a quorum replied, unless some
+    /// holder accused it and we did not answer yet.
+    async fn try_deliver(&mut self, epoch: Epoch) {
+        let cert = match self.certificates.get(&epoch) {
+            Some(cert) if !self.delivered_certificates.contains(&epoch) => cert,
+            _ => return,
+        };
+        let committee = self.committee.at(epoch);
+        let quorum_threshold = committee.weighted_quorum_threshold();
+        if committee.weight_of(cert.certificates.iter().map(|(pk, _)| pk)) < quorum_threshold {
+            return;
+        }
+        if self.complaints.is_blocked(epoch, &cert.c) {
+            warn!("Our dealing of epoch {} is accused, holding its certificate back", epoch);
+            return;
+        }
+        if self.breeze_certificate_sender.send(cert.clone()).await.is_err() {
+            error!("fail to send certificate to BFT-SMR")
+        }
+        self.delivered_certificates.push(epoch);
+        self.certificates.retain(|&e, _| e > epoch);
+    }
 }
@@ -1,38 +1,42 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use bytes::Bytes;
 use log::{info};
 use network::{CancelHandler, ReliableSender};
+use std::ops::Range;
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::RwLock;
-use config::Committee;
-use crypto::{Digest, PublicKey};
+use config::SharedCommittee;
+use crypto::{Hash as _, PublicKey, SignatureService};
 use model::breeze_universal::BreezeReconRequest;
-use model::types_and_const::{Epoch, Id};
+use model::types_and_const::Epoch;
 use crate::breeze_structs::{BreezeContent, BreezeMessage, ReconstructShare, SingleShare};
 
 pub struct BreezeReconstruct {
-    node_id: (PublicKey,Id),
-    committee: Committee,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_reconstruct_cmd_receiver: Receiver<BreezeReconRequest>,
-    breeze_recon_certificate_sender: Sender<(HashSet<Digest>,Epoch, usize)>,
+    breeze_recon_certificate_sender: Sender<BreezeReconRequest>,
     network: ReliableSender,
     my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
-    cancel_handlers: HashMap<(Epoch, usize), Vec<CancelHandler>>,
+    cancel_handlers: HashMap<(Epoch, Range<usize>), Vec<CancelHandler>>,
 }
 
 impl BreezeReconstruct {
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        committee: Committee,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_reconstruct_cmd_receiver: Receiver<BreezeReconRequest>,
-        breeze_recon_certificate_sender: Sender<(HashSet<Digest>,Epoch, usize)>,
+        breeze_recon_certificate_sender: Sender<BreezeReconRequest>,
         network: ReliableSender,
         my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
+                name,
+                signature_service,
                 committee,
                 breeze_reconstruct_cmd_receiver,
                 breeze_recon_certificate_sender,
@@ -51,40 +55,50 @@ impl BreezeReconstruct {
             match self.breeze_reconstruct_cmd_receiver.recv().await.unwrap() {
                 message => {
                     self.breeze_recon_certificate_sender
-                        .send((message.c.clone(), message.epoch, message.index))
+                        .send(message.clone())
                         .await
                         .unwrap();
                     let shares = self.my_shares.read().await; // Get read lock on my_shares
 
-                    let mut my_secrets_to_broadcast = Vec::new();
-                    for c in message.c {
+                    // One batch of shares per index, all sent in a single message.
+                    let mut my_secrets_to_broadcast = vec![Vec::new(); message.indices.len()];
+                    for c in message.digests() {
                         for bm in shares.iter() {
-                            if let BreezeContent::Share(share) = &bm.content {
-                                if share.epoch == message.epoch && share.c == c
-                                    && message.index <= share.y_k.len()
-                                {
-                                    let index = message.index - 1;
-                                    let single_share = SingleShare{
-                                        c:share.c,
-                                        y: share.y_k[index]
-                                    };
-                                    my_secrets_to_broadcast.push(single_share);
-
+                            if let BreezeContent::Share(share, _) = &bm.content {
+                                if share.epoch == message.epoch && share.c == c {
+                                    for (secrets, index) in my_secrets_to_broadcast.iter_mut().zip(message.indices.clone()) {
+                                        if index == 0 || index > share.y_k.len() {
+                                            continue;
+                                        }
+                                        secrets.push(SingleShare{
+                                            c:share.c,
+                                            y: share.y_k[index - 1],
+                                            merkle_branch: share.y_witness[index - 1].clone(),
+                                        });
+                                    }
                                 }
                             }
                         }
                     }
+                    drop(shares);
+                    // Signing the shares makes an invalid one a proof against us.
+                    let reconstruct_share = ReconstructShare::new(my_secrets_to_broadcast, message.epoch, message.indices.clone());
+                    let signature = self
+                        .signature_service
+                        .request_signature_at(message.epoch, reconstruct_share.digest())
+                        .await;
                     let reconstruct_message = BreezeMessage::new_reconstruct_message(
-                        self.node_id.0,
-                        ReconstructShare::new(my_secrets_to_broadcast, message.epoch, message.index),
+                        self.name,
+                        reconstruct_share,
+                        signature,
                     );
-                    let addresses = self.committee.all_breeze_addresses().iter().map(|a| a.1).collect::<Vec<_>>();
+                    let addresses = self.committee.at(message.epoch).all_breeze_addresses().iter().map(|a| a.1).collect::<Vec<_>>();
                     let bytes = bincode::serialize(&reconstruct_message).expect(
                         "Failed to serialize shares for reconstruction in BreezeReconstruct",
                     );
                     let handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
                     self.cancel_handlers
-                        .entry((message.epoch, message.index))
+                        .entry((message.epoch, message.indices))
                         .or_insert_with(Vec::new)
                         .extend(handlers);
                 }
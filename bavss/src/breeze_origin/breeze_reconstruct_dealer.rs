@@ -1,5 +1,7 @@
 use curve25519_dalek::scalar::Scalar;
-use model::types_and_const::{Id, RandomNum};
+use crate::output_derivation::derive_output;
+use model::beacon_output::OutputDerivation;
+use model::types_and_const::{Epoch, Id, RandomNum};
 
 pub struct BreezeReconResult{
     pub value: Scalar,
@@ -12,39 +14,73 @@ impl BreezeReconResult {
         }
     }
     
-    pub fn interpolate(evaluate_ids: &Vec<Id>, shares: &Vec<Scalar>) -> Scalar {
-        let evaluate_points = Self::generate_evaluation_points_n(evaluate_ids);
-        Self::lagrange_interpolation_at_zero(&evaluate_points,shares)
+    /// The Lagrange coefficients interpolating at zero from the shares of `evaluate_ids`. They only
+    /// depend on the holders, so a batch of secrets held by the same ones shares a single inversion.
+    pub fn lagrange_coefficients_at_zero(evaluate_ids: &[Id]) -> Vec<Scalar> {
+        let points: Vec<Scalar> = evaluate_ids.iter().map(|id| Scalar::from(*id as u64)).collect();
+        let mut numerators = vec![Scalar::ONE; points.len()];
+        let mut denominators = vec![Scalar::ONE; points.len()];
+        for i in 0..points.len() {
+            for j in 0..points.len() {
+                if i != j {
+                    numerators[i] *= -points[j];
+                    denominators[i] *= points[i] - points[j];
+                }
+            }
+        }
+        Scalar::batch_invert(&mut denominators);
+        numerators
+            .iter()
+            .zip(denominators.iter())
+            .map(|(numerator, denominator)| numerator * denominator)
+            .collect()
     }
 
-    pub fn secret_to_number(&self) -> RandomNum {
+    /// Interpolates at zero with the coefficients of `lagrange_coefficients_at_zero`.
+    pub fn interpolate_with(coefficients: &[Scalar], shares: &[Scalar]) -> Scalar {
+        coefficients.iter().zip(shares.iter()).map(|(l, y)| l * y).sum()
+    }
+
+    /// The beacon output at `(epoch, index)`. The full output is the encoding of the secret.
+    pub fn derive_output(&self, derivation: &OutputDerivation, epoch: Epoch, index: usize) -> RandomNum {
         let bytes = self.value.to_bytes();
-        RandomNum::from_le_bytes(bytes[..16].try_into().unwrap())
+        derive_output(derivation, bytes, &bytes, epoch, index)
     }
 
-    fn lagrange_interpolation_at_zero(points: &Vec<Scalar>, values: &Vec<Scalar>) -> Scalar {
-        let mut result = Scalar::ZERO;
+    /// Recovers the coefficients (lowest degree first) of the unique polynomial of degree
+    /// `ids.len() - 1` going through the points `(id, share)`.
+    pub fn interpolate_coefficients(evaluate_ids: &[Id], shares: &[Scalar]) -> Vec<Scalar> {
+        let points: Vec<Scalar> = evaluate_ids.iter().map(|id| Scalar::from(*id as u64)).collect();
+        let mut coefficients = vec![Scalar::ZERO; points.len()];
 
         for i in 0..points.len() {
-            let mut term = values[i];
-
+            // Build the i-th Lagrange basis polynomial one factor at a time.
+            let mut basis = vec![Scalar::ONE];
+            let mut denominator = Scalar::ONE;
             for j in 0..points.len() {
                 if i != j {
-                    term *= -points[j] * (points[i] - points[j]).invert();
+                    let mut next = vec![Scalar::ZERO; basis.len() + 1];
+                    for (k, b) in basis.iter().enumerate() {
+                        next[k] -= points[j] * b;
+                        next[k + 1] += b;
+                    }
+                    basis = next;
+                    denominator *= points[i] - points[j];
                 }
             }
-            result += term;
+            let factor = shares[i] * denominator.invert();
+            for (k, b) in basis.iter().enumerate() {
+                coefficients[k] += factor * b;
+            }
         }
-
-        result
+        coefficients
     }
-    pub fn generate_evaluation_points_n(ids: &Vec<Id>) -> Vec<Scalar> {
-        let mut res: Vec<Scalar> = Vec::new();
 
-        for id in ids {
-            let base = Scalar::from(*id as u64);
-            res.push(base);
-        }
-        res
+    pub fn evaluate(coefficients: &[Scalar], id: Id) -> Scalar {
+        let x = Scalar::from(id as u64);
+        coefficients
+            .iter()
+            .rev()
+            .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient)
     }
 }
\ No newline at end of file
@@ -1,47 +1,57 @@
 use std::collections::HashMap;
 use bytes::Bytes;
-use log::{error, info};
+use log::{error, info, warn};
 use network::{CancelHandler, ReliableSender};
 use std::sync::Arc;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::RwLock;
-use config::Committee;
-use crypto::{PublicKey, SecretKey, Signature};
-use model::breeze_universal::CommonReferenceString;
-use model::types_and_const::{Epoch, Id};
-use crate::breeze_origin::breeze_share_dealer::Shares;
-use crate::breeze_structs::{BreezeContent, BreezeMessage};
+use config::SharedCommittee;
+use crypto::{Digest, Hash as _, PublicKey, Signature, SignatureService};
+use model::breeze_universal::{BreezeCertificate, CommonReferenceString};
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::Epoch;
+use crate::breeze_origin::breeze_complaint::verify_share;
+use crate::breeze_store::BreezeStore;
+use crate::breeze_structs::{Accusation, BreezeContent, BreezeMessage};
 
 pub struct BreezeReply {
-    node_id: (PublicKey,Id),
-    signing_key: SecretKey,
-    committee: Arc<RwLock<Committee>>,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_share_receiver: Receiver<BreezeMessage>,
     network: ReliableSender,
     my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
     common_reference_string: Arc<RwLock<CommonReferenceString>>,
+    breeze_store: BreezeStore,
+    /// Reports the dealers of invalid or conflicting shares.
+    tx_evidence: Sender<Evidence>,
     cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
 }
 
 impl BreezeReply {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        signing_key: SecretKey,
-        committee: Arc<RwLock<Committee>>,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_share_receiver: Receiver<BreezeMessage>,
         network: ReliableSender,
         my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
         common_reference_string: Arc<RwLock<CommonReferenceString>>,
+        breeze_store: BreezeStore,
+        tx_evidence: Sender<Evidence>,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
-                signing_key,
+                name,
+                signature_service,
                 committee,
                 breeze_share_receiver,
                 network,
                 my_shares,
                 common_reference_string,
+                breeze_store,
+                tx_evidence,
                 cancel_handlers: HashMap::new(),
             }
             .run()
@@ -55,58 +65,83 @@ impl BreezeReply {
         loop {
             match self.breeze_share_receiver.recv().await.unwrap() {
                 message => {
-                    let my_share = match message.content {
-                        BreezeContent::Share(ref share) => share.clone(),
+                    let (my_share, dealer_signature) = match &message.content {
+                        BreezeContent::Share(share, signature) => (share.clone(), signature.clone()),
                         _ => {
                             continue;
                         }
                     };
                     
-                    let crs = self.common_reference_string.read().await;
-                    let committee = self.committee.read().await;
-                    if !Shares::verify(
-                        &crs,
-                        self.node_id.1,
-                        committee.authorities_fault_tolerance(),
-                        my_share.clone(),
-                    ) {
+                    // The share is evaluated at our id in the committee of its epoch.
+                    let epoch = my_share.epoch;
+                    let committee = self.committee.at(epoch);
+                    let dealer = message.sender;
+                    let (id, address) = match (committee.get_id(&self.name), committee.breeze_address(&dealer)) {
+                        (Some(id), Ok(address)) => (id, address),
+                        _ => continue,
+                    };
+
+                    // Only a share signed by its dealer can be held against it.
+                    let digest = my_share.digest();
+                    if dealer_signature.verify(&digest, &self.committee.signing_key(&dealer, epoch)).is_err() {
+                        continue;
+                    }
+                    let valid = {
+                        let crs = self.common_reference_string.read().await;
+                        verify_share(&committee, &crs, id, &my_share)
+                    };
+                    if !valid {
+                        self.accuse(dealer, epoch, my_share.c).await;
+                        let dealing = SignedMessage::new(&my_share, digest, dealer_signature);
+                        self.report(Evidence::new(dealer, epoch, Misbehaviour::InvalidDealing(dealing))).await;
                         continue;
                     }
-                    let dealer = message.sender;
 
-                    let signature = Signature::new(&my_share.c, &self.signing_key);
-                    
-                    let epoch = my_share.epoch;
+                    let signature = self
+                        .signature_service
+                        .request_signature_at(epoch, BreezeCertificate::signed_digest(&my_share.c, &dealer))
+                        .await;
 
                     {
                         let mut my_shares = self.my_shares.write().await;
-                        let has_duplicate = my_shares
+                        let previous = my_shares
                             .iter()
                             .filter(|msg| msg.sender == message.sender)
-                            .any(|msg| match &msg.content {
-                                BreezeContent::Share(existing_share) => {
-                                    existing_share.epoch == epoch
+                            .find_map(|msg| match &msg.content {
+                                BreezeContent::Share(existing_share, existing_signature)
+                                    if existing_share.epoch == epoch =>
+                                {
+                                    Some((existing_share.clone(), existing_signature.clone()))
                                 }
-                                _ => false,
+                                _ => None,
                             });
 
 
-                        if has_duplicate {
-                            error!("Duplicate message content found for sender_id {}, skipping insertion", dealer);
-                            continue;
+                        match previous {
+                            // The dealer sends its dealing again after a restart, and may have
+                            // lost our reply.
+                            Some((existing_share, _)) if existing_share.digest() == digest => (),
+                            Some((existing_share, existing_signature)) => {
+                                error!("Duplicate message content found for sender_id {}, skipping insertion", dealer);
+                                drop(my_shares);
+                                let evidence = Evidence::new(dealer, epoch, Misbehaviour::Equivocation(
+                                    SignedMessage::new(&existing_share, existing_share.digest(), existing_signature),
+                                    SignedMessage::new(&my_share, digest, dealer_signature),
+                                ));
+                                self.report(evidence).await;
+                                continue;
+                            }
+                            None => {
+                                my_shares.push(message.clone());
+                                drop(my_shares);
+                                self.breeze_store.persist_share(epoch, &message).await;
+                            }
                         }
-                        my_shares.push(message);
                     }
 
-                    let reply = BreezeMessage::new_reply_message(dealer, self.node_id.0, my_share.c, signature, epoch);
+                    let reply = BreezeMessage::new_reply_message(dealer, self.name, my_share.c, signature, epoch);
                     let bytes = bincode::serialize(&reply)
                         .expect("Failed to serialize reply in BreezeReply");
-                    let address = self
-                        .committee
-                        .read()
-                        .await
-                        .breeze_address(&dealer)
-                        .unwrap();
                     let handler = self.network.send(address, Bytes::from(bytes)).await;
 
                     self.cancel_handlers
@@ -117,4 +152,36 @@ impl BreezeReply {
             }
         }
     }
+
+    /// Broadcasts a signed accusation against the dealing `c` of `dealer`, which must then reveal
+    /// our share publicly.
+    async fn accuse(&mut self, dealer: PublicKey, epoch: Epoch, c: Digest) {
+        let mut accusation = Accusation {
+            dealer,
+            epoch,
+            c,
+            signature: Signature::default(),
+        };
+        accusation.signature = self.signature_service.request_signature_at(epoch, accusation.digest()).await;
+        let message = BreezeMessage::new_accusation_message(self.name, accusation);
+        let bytes = bincode::serialize(&message).expect("Failed to serialize accusation in BreezeReply");
+        let addresses = self
+            .committee
+            .at(epoch)
+            .all_breeze_addresses()
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect();
+        let handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
+        self.cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(handlers);
+    }
+
+    async fn report(&self, evidence: Evidence) {
+        if let Err(e) = self.tx_evidence.send(evidence).await {
+            warn!("Failed to deliver evidence to the collector: {}", e);
+        }
+    }
 }
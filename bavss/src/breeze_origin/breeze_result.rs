@@ -1,42 +1,79 @@
 
-use log::{info};
-use std::collections::{HashMap, HashSet};
+use log::{info, warn};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
 use curve25519_dalek::Scalar;
 use tokio::sync::mpsc::{Receiver, Sender};
-use config::Committee;
-use crypto::{Digest, PublicKey};
-use model::types_and_const::{Epoch, RandomNum};
+use tokio::sync::RwLock;
+use config::{Committee, SharedCommittee};
+use crypto::{Digest, Hash as _, PublicKey};
+use model::beacon_output::OutputDerivation;
+use model::breeze_universal::BreezeReconRequest;
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::{Epoch, Id, RandomNum, BEACON_PER_EPOCH, MAX_EPOCH};
+use crate::beacon_proof::{BeaconProof, DealingWitness, ProofCache, ProofQuery};
 use crate::breeze_origin::breeze_reconstruct_dealer::BreezeReconResult;
-use crate::breeze_structs::{BreezeContent, BreezeMessage};
+use crate::breeze_structs::{BreezeContent, BreezeMessage, SingleShare};
+use crate::reed_solomon::{decode, ScalarField};
 use crate::Secret;
 
 pub struct BreezeResult {
-    committee: Committee,
-    breeze_recon_certificate_receiver: Receiver<(HashSet<Digest>,Epoch, usize)>,
+    committee: SharedCommittee,
+    breeze_recon_certificate_receiver: Receiver<BreezeReconRequest>,
     breeze_reconstruct_secret_receiver: Receiver<BreezeMessage>,
     breeze_result_sender: Sender<(Epoch, usize, RandomNum)>,
+    /// Reports the holders of invalid or inconsistent reconstruction shares.
+    tx_evidence: Sender<Evidence>,
+    rx_proof_query: Receiver<ProofQuery>,
+    /// The shares we hold, whose commitments witness the dealings in the proofs.
+    my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
+    derivation: OutputDerivation,
+    /// The number of polynomials of each dealing.
+    batch_size: usize,
+    /// Whether to wait for shares carrying 2f+1 of the weight and correct the wrong ones before
+    /// reconstructing.
+    robust_reconstruction: bool,
 
-    certificates_to_reconstruct_buffer: Vec<(HashSet<Digest>, Epoch, usize)>,
-    shares_to_cumulate: HashMap<(Epoch, usize), HashMap<Digest, HashSet<(PublicKey,Secret)>>>,
-    reconstructed_epoch_wave: Vec<(Epoch, usize)>
+    certificates_to_reconstruct_buffer: Vec<(BTreeSet<Digest>, Epoch, usize)>,
+    shares_to_cumulate: HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, SingleShare>>>,
+    /// The signed message carrying the shares of each holder, kept in robust mode to be held
+    /// against the holders of inconsistent shares.
+    signed_shares: HashMap<(Epoch, usize), HashMap<PublicKey, Arc<SignedMessage>>>,
+    reconstructed_epoch_wave: HashSet<(Epoch, usize)>,
+    proofs: ProofCache,
 }
 
 impl BreezeResult {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        committee: Committee,
-        breeze_recon_certificate_receiver: Receiver<(HashSet<Digest>,Epoch, usize)>,
+        committee: SharedCommittee,
+        breeze_recon_certificate_receiver: Receiver<BreezeReconRequest>,
         breeze_reconstruct_secret_receiver: Receiver<BreezeMessage>,
         breeze_result_sender: Sender<(Epoch, usize, RandomNum)>,
+        tx_evidence: Sender<Evidence>,
+        rx_proof_query: Receiver<ProofQuery>,
+        my_shares: Arc<RwLock<Vec<BreezeMessage>>>,
+        derivation: OutputDerivation,
+        robust_reconstruction: bool,
     ) {
+        let batch_size = (*MAX_EPOCH.get().unwrap() + *BEACON_PER_EPOCH.get().unwrap()) as usize;
         tokio::spawn(async move {
             Self {
                 committee,
                 breeze_recon_certificate_receiver,
                 breeze_reconstruct_secret_receiver,
                 breeze_result_sender,
+                tx_evidence,
+                rx_proof_query,
+                my_shares,
+                derivation,
+                batch_size,
+                robust_reconstruction,
                 certificates_to_reconstruct_buffer: Vec::new(),
                 shares_to_cumulate: HashMap::new(),
-                reconstructed_epoch_wave: Vec::new()
+                signed_shares: HashMap::new(),
+                reconstructed_epoch_wave: HashSet::new(),
+                proofs: ProofCache::default(),
             }
             .run()
             .await;
@@ -45,56 +82,105 @@ impl BreezeResult {
 
     pub async fn run(&mut self) {
         info!("Breeze result start to listen");
-        let threshold = self.committee.authorities_fault_tolerance() + 1;
         loop {
             tokio::select! {
-                Some(certificates_to_reconstruct) = self.breeze_recon_certificate_receiver.recv() => {
-                    let key = (certificates_to_reconstruct.1, certificates_to_reconstruct.2);
-                    if !self.reconstructed_epoch_wave.contains(&key) {
-                        let exists = self.certificates_to_reconstruct_buffer.iter().any(|(_, e, w)| e == &certificates_to_reconstruct.1 && w == &certificates_to_reconstruct.2);
-                        if !exists {
-                            self.certificates_to_reconstruct_buffer.push(certificates_to_reconstruct);
+                biased;
+                Some(request) = self.breeze_recon_certificate_receiver.recv() => {
+                    let (digests, epoch) = (request.digests(), request.epoch);
+                    self.proofs.insert_common_core(epoch, &request.common_core);
+                    for index in request.indices {
+                        if !self.reconstructed_epoch_wave.contains(&(epoch, index)) {
+                            let exists = self.certificates_to_reconstruct_buffer.iter().any(|(_, e, w)| e == &epoch && w == &index);
+                            if !exists {
+                                self.certificates_to_reconstruct_buffer.push((digests.clone(), epoch, index));
+                            }
                         }
                     }
                 },
                 Some(shares_from_others) = self.breeze_reconstruct_secret_receiver.recv() => {
                     match shares_from_others.content {
-                        BreezeContent::Reconstruct(share) => {
-                            let key = (share.epoch, share.index);
-                            let shares_vec = self.shares_to_cumulate
-                                .entry(key)
-                                .or_insert_with(HashMap::new);
-                            for single_share in share.secrets {
-                                if !single_share.verify(){
-                                    continue;
+                        BreezeContent::Reconstruct(share, signature) => {
+                            // Only the members of the epoch's committee hold shares for it.
+                            let sender = shares_from_others.sender;
+                            let committee = self.committee.at(share.epoch);
+                            let id = match committee.get_id(&sender) {
+                                Some(id) => id,
+                                None => continue,
+                            };
+                            // Only shares signed by their holder can be held against it.
+                            let digest = share.digest();
+                            if signature.verify(&digest, &self.committee.signing_key(&sender, share.epoch)).is_err() {
+                                continue;
+                            }
+                            let signed = self.robust_reconstruction
+                                .then(|| Arc::new(SignedMessage::new(&share, digest, signature.clone())));
+                            let mut invalid = false;
+                            for (index, secrets) in share.by_index() {
+                                if let Some(signed) = &signed {
+                                    self.signed_shares
+                                        .entry((share.epoch, index))
+                                        .or_default()
+                                        .entry(sender)
+                                        .or_insert_with(|| signed.clone());
+                                }
+                                let shares_vec = self.shares_to_cumulate
+                                    .entry((share.epoch, index))
+                                    .or_insert_with(HashMap::new);
+                                for single_share in secrets {
+                                    if !single_share.verify(id, index, self.batch_size, committee.size()) {
+                                        warn!("Invalid share of {} at index {} from {}", single_share.c, index, sender);
+                                        invalid = true;
+                                        continue;
+                                    }
+                                    shares_vec
+                                        .entry(single_share.c)
+                                        .or_default()
+                                        .entry(sender)
+                                        .or_insert_with(|| single_share.clone());
                                 }
-                                let scalar_set = shares_vec
-                                    .entry(single_share.c)
-                                    .or_insert_with(HashSet::new);
-                                scalar_set.insert((shares_from_others.sender,single_share.y));
+                            }
+                            if invalid {
+                                let shares = SignedMessage::new(&share, digest, signature);
+                                self.report(Evidence::new(sender, share.epoch, Misbehaviour::InvalidShare(shares))).await;
                             }
                         }
                         _ => {}
                     }
+                },
+                Some(((epoch, index), reply)) = self.rx_proof_query.recv() => {
+                    let proof = self.proof(epoch, index).await.map(|proof| {
+                        bincode::serialize(&proof).expect("Failed to serialize beacon proof")
+                    });
+                    let _ = reply.send(proof);
+                    continue;
                 }
             }
 
             let mut secrets_to_reconstruct = Vec::new();
             self.certificates_to_reconstruct_buffer.retain(|(digests, epoch, index)| {
-                let mut digest_can_be_reconstructed = HashSet::new();
+                let mut digest_can_be_reconstructed = BTreeSet::new();
                 let mut secret_can_be_reconstructed = Vec::new();
                 let key = (*epoch, *index);
                 if let Some(shares) = self.shares_to_cumulate.get(&key) {
+                    let committee = self.committee.at(*epoch);
                     for (c,s) in shares.iter() {
-                        if s.len() >= threshold {
-                            let s: Vec<(PublicKey,Secret)> = s.iter().cloned().collect();
-                            secret_can_be_reconstructed.push((*c, s));
-                            digest_can_be_reconstructed.insert(*c);
-                        }
+                        let s = if self.robust_reconstruction {
+                            match consistent_shares(&committee, s) {
+                                Some(s) => s,
+                                None => continue,
+                            }
+                        } else if committee.can_reconstruct(s.keys()) {
+                            (s.iter().map(|(pk, share)| (*pk, share.clone())).collect(), Vec::new())
+                        } else {
+                            continue;
+                        };
+                        secret_can_be_reconstructed.push((*c, s));
+                        digest_can_be_reconstructed.insert(*c);
                     }
                     return if &digest_can_be_reconstructed == digests {
-                        secrets_to_reconstruct.push((*epoch, *index, secret_can_be_reconstructed));
-                        self.reconstructed_epoch_wave.push(key);
+                        let signed = self.signed_shares.remove(&key).unwrap_or_default();
+                        secrets_to_reconstruct.push((*epoch, *index, secret_can_be_reconstructed, signed));
+                        self.reconstructed_epoch_wave.insert(key);
                         self.shares_to_cumulate.remove(&key);
                         false
                     } else {
@@ -104,23 +190,92 @@ impl BreezeResult {
                 true
             });
 
-            for (epoch, index, secret_set) in secrets_to_reconstruct {
+            // The indices of a batch are usually held by the same nodes: compute the Lagrange
+            // coefficients of each set of holders only once.
+            let mut coefficients: HashMap<Vec<Id>, Vec<Scalar>> = HashMap::new();
+            for (epoch, index, secret_set, signed) in secrets_to_reconstruct {
+                let committee = self.committee.at(epoch);
                 let mut cumulated_output = Scalar::ZERO;
-                for (_,shares) in secret_set {
-                    let mut ids = Vec::new();
-                    let mut values = Vec::new();
-                    for (pk,value) in shares{
-                        let id = self.committee.get_id(&pk).unwrap();
-                        ids.push(id);
-                        values.push(value);
+                let mut dealings = Vec::new();
+                for (c, (shares, faulty)) in secret_set {
+                    for name in faulty {
+                        warn!("Inconsistent share of {} at index {} of epoch {} from {}", c, index, epoch, name);
+                        if let Some(message) = signed.get(&name) {
+                            let misbehaviour = Misbehaviour::InconsistentShare(message.as_ref().clone());
+                            self.report(Evidence::new(name, epoch, misbehaviour)).await;
+                        }
                     }
-                    cumulated_output += BreezeReconResult::interpolate(&ids, &values);
+                    let mut points: Vec<(Id, Secret)> = shares
+                        .iter()
+                        .map(|(pk, share)| (committee.get_id(pk).unwrap(), share.y))
+                        .collect();
+                    dealings.push((c, shares));
+                    points.sort_by_key(|(id, _)| *id);
+                    let (ids, values): (Vec<Id>, Vec<Secret>) = points.into_iter().unzip();
+                    let coefficients = coefficients
+                        .entry(ids)
+                        .or_insert_with_key(|ids| BreezeReconResult::lagrange_coefficients_at_zero(ids));
+                    cumulated_output += BreezeReconResult::interpolate_with(coefficients, &values);
                 }
-                let recon_output = BreezeReconResult::new(cumulated_output);
-                self.breeze_result_sender.send((epoch, index, recon_output.secret_to_number()))
+                let value = BreezeReconResult::new(cumulated_output).derive_output(&self.derivation, epoch, index);
+                self.proofs.insert(&committee, epoch, index, value.clone(), dealings);
+                self.breeze_result_sender.send((epoch, index, value))
                     .await
                     .expect("breeze_result_sender error to send");
             }
         }
     }
+
+    async fn report(&self, evidence: Evidence) {
+        if let Err(e) = self.tx_evidence.send(evidence).await {
+            warn!("Failed to deliver evidence to the collector: {}", e);
+        }
+    }
+
+    /// Builds the proof of a beacon we reconstructed, each dealing witnessed by the commitment in
+    /// the share we hold.
+    async fn proof(&self, epoch: Epoch, index: usize) -> Option<BeaconProof> {
+        let my_shares = self.my_shares.read().await;
+        self.proofs.proof(epoch, index, |c, _| {
+            my_shares.iter().find_map(|message| match &message.content {
+                BreezeContent::Share(share, _) if share.epoch == epoch && share.c == *c => Some(DealingWitness {
+                    commitment: share.r_witness.get(index - 1)?.clone(),
+                    batch_size: self.batch_size,
+                }),
+                _ => None,
+            })
+        })
+    }
+}
+
+/// The holders of the shares not lying on the decoded polynomial.
+type Faulty = Vec<PublicKey>;
+
+/// Once the shares of a dealing carry 2f+1 of the weight, decodes them with error correction.
+/// Returns the shares lying on the decoded polynomial, along with the holders of the other ones.
+fn consistent_shares(
+    committee: &Committee,
+    shares: &HashMap<PublicKey, SingleShare>,
+) -> Option<(Vec<(PublicKey, SingleShare)>, Faulty)> {
+    let t = committee.authorities_fault_tolerance();
+    if !committee.can_reconstruct_robustly(shares.keys()) {
+        return None;
+    }
+    let shares: Vec<(PublicKey, SingleShare)> = shares.iter().map(|(pk, share)| (*pk, share.clone())).collect();
+    let points: Vec<(Id, Secret)> = shares
+        .iter()
+        .map(|(pk, share)| (committee.get_id(pk).unwrap(), share.y))
+        .collect();
+    let decoded = decode(&ScalarField, &points, t)?;
+    let (faulty, consistent): (Vec<_>, Vec<_>) = shares
+        .into_iter()
+        .enumerate()
+        .partition(|(i, _)| decoded.errors.contains(i));
+    // Up to f of the weight may come from faulty holders: only a polynomial agreeing with shares
+    // carrying 2f+1 of it is the dealt one.
+    let consistent: Vec<(PublicKey, SingleShare)> = consistent.into_iter().map(|(_, share)| share).collect();
+    if !committee.can_reconstruct_robustly(consistent.iter().map(|(pk, _)| pk)) {
+        return None;
+    }
+    Some((consistent, faulty.into_iter().map(|(_, (pk, _))| pk).collect()))
 }
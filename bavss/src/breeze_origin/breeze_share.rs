@@ -1,45 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use bytes::Bytes;
 use log::{info};
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::RwLock;
-use config::Committee;
-use crypto::{Digest, PublicKey};
+use config::SharedCommittee;
+use crypto::{Digest, Hash as _, PublicKey, SignatureService};
 use model::breeze_universal::CommonReferenceString;
-use model::types_and_const::{Epoch, Id, BEACON_PER_EPOCH, MAX_EPOCH};
+use model::types_and_const::{Epoch, BEACON_PER_EPOCH, MAX_EPOCH};
 use network::{CancelHandler, ReliableSender};
+use rand::rngs::StdRng;
 use crate::breeze_origin::breeze_share_dealer::Shares;
-use crate::breeze_structs::BreezeMessage;
+use crate::breeze_store::BreezeStore;
+use crate::breeze_structs::{BreezeMessage, Dealing};
 
 pub struct BreezeShare{
-    node_id: (PublicKey,Id),
-    committee: Arc<RwLock<Committee>>,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_share_cmd_receiver: Receiver<Epoch>,
     network: ReliableSender,
     common_reference_string: Arc<RwLock<CommonReferenceString>>,
     my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+    my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+    breeze_store: BreezeStore,
+    /// Draws the polynomials of our dealings.
+    rng: StdRng,
     cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
 }
 
 impl BreezeShare {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        committee: Arc<RwLock<Committee>>,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_share_cmd_receiver: Receiver<Epoch>,
         network: ReliableSender,
         common_reference_string: Arc<RwLock<CommonReferenceString>>,
-        my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>
+        my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+        my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+        breeze_store: BreezeStore,
+        rng: StdRng,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
+                name,
+                signature_service,
                 committee,
                 breeze_share_cmd_receiver,
                 network,
                 common_reference_string,
                 my_dealer_shares,
+                my_dealings,
+                breeze_store,
+                rng,
                 cancel_handlers: HashMap::new(),
             }
             .run()
@@ -52,31 +68,63 @@ impl BreezeShare {
         loop {
             match self.breeze_share_cmd_receiver.recv().await.unwrap() {
                 epoch => {
-                    let committee = self.committee.read().await;
+                    // Only the members of the epoch's committee deal for it.
+                    let committee = self.committee.at(epoch);
+                    if committee.get_id(&self.name).is_none() {
+                        continue;
+                    }
+                    // We already dealt this epoch before a restart, dealing again would
+                    // conflict with the shares the other nodes hold. Send the same shares
+                    // again instead, in case the restart interrupted the dealing.
+                    let dealing = self.my_dealings.read().await.get(&epoch).cloned();
+                    if let Some(dealing) = dealing {
+                        self.send_dealing(epoch, dealing).await;
+                        continue;
+                    }
+                    if self.my_dealer_shares.read().await.contains_key(&epoch) {
+                        continue;
+                    }
                     let ids = committee.get_all_ids();
                     let fault_tolerance = committee.authorities_fault_tolerance();
                     let crs = self.common_reference_string.read().await;
                     let batch_size = *MAX_EPOCH.get().unwrap() + *BEACON_PER_EPOCH.get().unwrap();
-                    let shares = Shares::new(batch_size as usize, epoch, ids, fault_tolerance, &crs);
-                    
-                    let mut share_map_to_addresses: HashMap<SocketAddr, Bytes> = HashMap::new();
+                    let shares = Shares::new(batch_size as usize, epoch, ids, fault_tolerance, &crs, &mut self.rng);
+                    drop(crs);
                     let c = shares.get_c();
+                    let mut dealing = Dealing::new();
                     for (share, pk) in shares.0 {
-                        if let Ok(address) = committee.breeze_address(&pk) {
-                            let message = BreezeMessage::new_share_message(self.node_id.0, share);
-                            let bytes = bincode::serialize(&message).expect("Failed to serialize shares in BreezeShare");
-                            share_map_to_addresses.insert(address, Bytes::from(bytes));
-                        }
+                        // Signing the share makes an invalid one a proof against us.
+                        let signature = self.signature_service.request_signature_at(epoch, share.digest()).await;
+                        dealing.insert(pk, (share, signature));
                     }
+                    // The dealing is stored first, so that a restart finds it to send again.
+                    self.breeze_store.persist_dealing(epoch, &dealing).await;
+                    self.my_dealings.write().await.insert(epoch, dealing.clone());
                     let mut my_dealer_shares = self.my_dealer_shares.write().await;
                     my_dealer_shares.insert(epoch, c);
-                    let handlers = self.network.dispatch_to_addresses(share_map_to_addresses).await;
-                    self.cancel_handlers
-                        .entry(epoch)
-                        .or_insert_with(Vec::new)
-                        .extend(handlers);
+                    self.breeze_store.persist_dealer_shares(&my_dealer_shares).await;
+                    drop(my_dealer_shares);
+                    self.send_dealing(epoch, dealing).await;
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Sends each holder its share of our dealing of `epoch`.
+    async fn send_dealing(&mut self, epoch: Epoch, dealing: Dealing) {
+        let committee = self.committee.at(epoch);
+        let mut share_map_to_addresses: BTreeMap<SocketAddr, Bytes> = BTreeMap::new();
+        for (pk, (share, signature)) in dealing {
+            if let Ok(address) = committee.breeze_address(&pk) {
+                let message = BreezeMessage::new_share_message(self.name, share, signature);
+                let bytes = bincode::serialize(&message).expect("Failed to serialize shares in BreezeShare");
+                share_map_to_addresses.insert(address, Bytes::from(bytes));
+            }
+        }
+        let handlers = self.network.dispatch_to_addresses(share_map_to_addresses).await;
+        self.cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(handlers);
+    }
+}
@@ -2,7 +2,7 @@
 use curve25519_dalek::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::Identity;
-use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 use crypto::{Digest, PublicKey};
 use model::breeze_universal::CommonReferenceString;
@@ -10,7 +10,22 @@ use model::types_and_const::{Epoch, Id};
 use crate::breeze_origin::batch_eval::{batch_eval, batch_verify_eval};
 use crate::breeze_origin::merkletree::{generate_merkle_tree, verify_merkle_proof};
 use crate::breeze_origin::utils::transpose;
-use crate::breeze_structs::{Share, WitnessBreeze};
+use crate::breeze_structs::{Share, SingleShare, WitnessBreeze};
+
+#[cfg(test)]
+#[path = "../tests/breeze_share_dealer_tests.rs"]
+pub mod breeze_share_dealer_tests;
+
+/// Number of leaves of the merkle tree under `c`: the commitments of the `batch_size` polynomials,
+/// followed by their evaluations at each of the `n` holders.
+pub fn merkle_leaf_count(batch_size: usize, n: usize) -> usize {
+    batch_size * (n + 1)
+}
+
+/// Position under `c` of the evaluation at `index` (starting at 1) held by the holder `id`.
+pub fn evaluation_leaf(batch_size: usize, n: usize, index: usize, id: Id) -> usize {
+    batch_size + (index - 1) * n + (id - 1)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Shares (pub(crate) Vec<(Share, PublicKey)>);
@@ -19,9 +34,9 @@ impl Shares {
     pub fn get_c(&self) -> Digest{
         self.0[0].0.c.clone()
     }
-    fn generate_batched_polynomial(batch: usize, t: usize, mut rng: OsRng) -> Vec<Vec<Scalar>> {
+    fn generate_batched_polynomial(batch: usize, t: usize, rng: &mut (impl RngCore + CryptoRng)) -> Vec<Vec<Scalar>> {
         let batched_polynomial: Vec<Vec<Scalar>> = (0..batch)
-            .map(|_| (0..t + 1).map(|_| Scalar::random(&mut rng)).collect())
+            .map(|_| (0..t + 1).map(|_| Scalar::random(&mut *rng)).collect())
             .collect();
 
         batched_polynomial
@@ -92,6 +107,23 @@ impl Shares {
         result
     }
     pub fn verify(crs:&CommonReferenceString,node_id: Id,t:usize, share: Share) -> bool {
+        // Our evaluations must be the ones committed under `c`, so that the others can check them
+        // when we reveal them.
+        let batch_size = share.r_witness.len();
+        if share.y_k.len() != batch_size || share.y_witness.len() != batch_size {
+            return false;
+        }
+        for (index, (y, branch)) in share.y_k.iter().zip(share.y_witness.iter()).enumerate() {
+            let single_share = SingleShare {
+                c: share.c,
+                y: *y,
+                merkle_branch: branch.clone(),
+            };
+            if !single_share.verify(node_id, index + 1, batch_size, share.n) {
+                return false;
+            }
+        }
+
         let y = Self::generate_evaluation_points_for_verifier(t,node_id);
         if !batch_verify_eval(crs, &share.r_hat, share.y_k, y, share.phi_k, t, share.n){
             return false;
@@ -100,7 +132,7 @@ impl Shares {
         for wit in share.r_witness.iter(){
             let commit = wit.poly_commit;
             let poly_commit_data = commit.compress().to_bytes().to_vec();
-            match verify_merkle_proof(&poly_commit_data, wit.merkle_branch.clone(), share.c.clone(), share.r_witness.len()) {
+            match verify_merkle_proof(&poly_commit_data, wit.merkle_branch.clone(), share.c.clone(), merkle_leaf_count(batch_size, share.n)) {
                 Ok(res)=>{
                     if !res{
                         flag = false;
@@ -122,12 +154,20 @@ impl Shares {
         ids: Vec<(PublicKey,Id)>,
         t: usize,
         crs: &CommonReferenceString,
+        rng: &mut (impl RngCore + CryptoRng),
     ) -> Self {
-        let rng = OsRng;
         let n = ids.len();
         let batched_polynomial = Self::generate_batched_polynomial(batch_size, t, rng);
         let r_hat_breeze = Self::batch_commit(&crs, &batched_polynomial, t);
-        let data = Self::serialize_commitments(&r_hat_breeze);
+        let y_value = Self::generate_evaluation_points_n(t, &ids);
+        let (y_k, phi_k) = batch_eval(&crs, &batched_polynomial, &y_value, &r_hat_breeze, t, n, rng);
+
+        // Commit to the polynomials and to their evaluations, so that each evaluation can be checked
+        // on its own during reconstruction.
+        let mut data = Self::serialize_commitments(&r_hat_breeze);
+        for evaluations in y_k.iter() {
+            data.extend(evaluations.iter().map(|y| y.to_bytes().to_vec()));
+        }
 
         let merkle_tree_data = match generate_merkle_tree(data) {
             Ok(merkle_tree_data) => merkle_tree_data,
@@ -140,9 +180,6 @@ impl Shares {
                 merkle_branch: merkle_proofs[i].clone()
             })
             .collect();
-        let y_value = Self::generate_evaluation_points_n(t, &ids);
-        let (y_k, phi_k) = batch_eval(&crs, &batched_polynomial, &y_value, &r_hat_breeze, t, n);
-
         let y_k = transpose(y_k);
 
         assert_eq!(y_k.len(), n, "shards error");
@@ -155,6 +192,9 @@ impl Shares {
                 r_hat: r_hat_breeze.clone(),
                 r_witness: r_hat_witness.clone(),
                 y_k: y_k[i].clone(),
+                y_witness: (1..=batch_size)
+                    .map(|index| merkle_proofs[evaluation_leaf(batch_size, n, index, i + 1)].clone())
+                    .collect(),
                 phi_k: phi_k[i].clone(),
                 n,
                 epoch: epoch.clone(),
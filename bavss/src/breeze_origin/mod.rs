@@ -3,6 +3,7 @@ pub(crate) mod batch_eval;
 pub(crate) mod breeze_share_dealer;
 pub(crate) mod utils;
 pub(crate) mod breeze_reconstruct_dealer;
+pub(crate)mod breeze_complaint;
 pub(crate)mod breeze_confirm;
 pub(crate)mod breeze_message_handler;
 pub(crate)mod breeze;
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use bytes::Bytes;
+use log::{info, warn};
+use network::{CancelHandler, ReliableSender};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::RwLock;
+use config::SharedCommittee;
+use crypto::{Hash as _, PublicKey};
+use model::breeze_universal::{Complaint, ComplaintQuery, ComplaintStatus, Complaints};
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::Epoch;
+use crate::breeze_pq::breeze_share_dealer::Shares;
+use crate::breeze_structs::{Accusation, BreezeContent, BreezeMessage, Dealing, PQCrs, Revelation};
+
+/// The complaint phase. A holder whose share is invalid accuses the dealer, which must reveal the
+/// share publicly: a valid one resolves the complaint (and the accuser adopts it), an invalid one
+/// excludes the dealing. The confirm phase acts on the complaints as they arrive. The coordinator
+/// only acts on the ones committed by the consensus, so that all nodes agree on the common cores:
+/// we relay our accusations, and the revelations of and for us, through our headers, and check the
+/// committed ones for the coordinator.
+pub struct BreezeComplaint {
+    name: PublicKey,
+    committee: SharedCommittee,
+    breeze_complaint_receiver: Receiver<BreezeMessage>,
+    /// Hands the shares revealed to us over to the reply phase.
+    breeze_share_sender: Sender<BreezeMessage>,
+    network: ReliableSender,
+    my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+    common_reference_string: Arc<PQCrs>,
+    complaints: Complaints,
+    /// Tells the confirm phase about the changes in the complaints.
+    complaint_sender: Sender<ComplaintStatus>,
+    /// Relays complaints to our proposer, to be committed.
+    tx_complaint: Sender<Complaint>,
+    /// Receives the committed complaints from the coordinator.
+    rx_complaint_query: Receiver<ComplaintQuery>,
+    tx_evidence: Sender<Evidence>,
+    cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
+}
+
+impl BreezeComplaint {
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        name: PublicKey,
+        committee: SharedCommittee,
+        breeze_complaint_receiver: Receiver<BreezeMessage>,
+        breeze_share_sender: Sender<BreezeMessage>,
+        network: ReliableSender,
+        my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+        common_reference_string: Arc<PQCrs>,
+        complaint_sender: Sender<ComplaintStatus>,
+        tx_complaint: Sender<Complaint>,
+        rx_complaint_query: Receiver<ComplaintQuery>,
+        tx_evidence: Sender<Evidence>,
+    ) {
+        tokio::spawn(async move {
+            Self {
+                name,
+                committee,
+                breeze_complaint_receiver,
+                breeze_share_sender,
+                network,
+                my_dealings,
+                common_reference_string,
+                complaints: Complaints::default(),
+                complaint_sender,
+                tx_complaint,
+                rx_complaint_query,
+                tx_evidence,
+                cancel_handlers: HashMap::new(),
+            }
+            .run()
+            .await;
+        });
+    }
+
+    pub async fn run(&mut self) {
+        info!("Breeze complaint start to listen");
+        loop {
+            tokio::select! {
+                Some(message) = self.breeze_complaint_receiver.recv() => {
+                    if let Some(status) = self.check(&message).await {
+                        self.handle(message, status).await;
+                    }
+                },
+                Some((complaint, reply)) = self.rx_complaint_query.recv() => {
+                    let status = match bincode::deserialize(&complaint.0) {
+                        Ok(message) => self.check(&message).await,
+                        Err(_) => None,
+                    };
+                    let _ = reply.send(status);
+                },
+                else => break,
+            }
+        }
+    }
+
+    /// Returns the change `message` makes to the complaints, if it is a complaint signed by its
+    /// author. Only depends on the message and the committees, so that all nodes agree on the
+    /// committed ones.
+    async fn check(&self, message: &BreezeMessage) -> Option<ComplaintStatus> {
+        match &message.content {
+            BreezeContent::Accusation(accusation) => {
+                let (accuser, epoch) = (message.sender, accusation.epoch);
+                let committee = self.committee.at(epoch);
+                committee.get_id(&accuser)?;
+                committee.get_id(&accusation.dealer)?;
+                let signing_key = self.committee.signing_key(&accuser, epoch);
+                accusation.signature.verify(&accusation.digest(), &signing_key).ok()?;
+                Some(ComplaintStatus::Accused { epoch, c: accusation.c, accuser })
+            }
+            BreezeContent::Revelation(Revelation { accuser, share, signature }) => {
+                let (dealer, epoch, c) = (message.sender, share.epoch, share.c);
+                let committee = self.committee.at(epoch);
+                let id = committee.get_id(accuser)?;
+                committee.get_id(&dealer)?;
+
+                // Only a share signed by its dealer can be held against it.
+                let signing_key = self.committee.signing_key(&dealer, epoch);
+                signature.verify(&share.digest(), &signing_key).ok()?;
+                if Shares::verify_shares(&self.common_reference_string, share, id) {
+                    Some(ComplaintStatus::Resolved { epoch, c, accuser: *accuser })
+                } else {
+                    Some(ComplaintStatus::Excluded { epoch, c })
+                }
+            }
+            _ => None,
+        }
+    }
+
+    async fn handle(&mut self, message: BreezeMessage, status: ComplaintStatus) {
+        // Relay the complaints that concern us, so that they get committed.
+        let relay = match &message.content {
+            BreezeContent::Revelation(revelation) => message.sender == self.name || revelation.accuser == self.name,
+            _ => message.sender == self.name,
+        };
+        let bytes = relay.then(|| bincode::serialize(&message).expect("Failed to serialize complaint"));
+        let new = match message.content {
+            BreezeContent::Accusation(accusation) => self.handle_accusation(message.sender, accusation).await,
+            BreezeContent::Revelation(revelation) => self.handle_revelation(message.sender, revelation, status).await,
+            _ => false,
+        };
+        if let Some(bytes) = bytes.filter(|_| new) {
+            if let Err(e) = self.tx_complaint.send(Complaint(bytes)).await {
+                warn!("Failed to relay complaint to the proposer: {}", e);
+            }
+        }
+    }
+
+    /// Records a checked accusation, and reveals the share of the accuser if we are the dealer.
+    /// Returns whether the accusation is new.
+    async fn handle_accusation(&mut self, accuser: PublicKey, accusation: Accusation) -> bool {
+        let epoch = accusation.epoch;
+        if !self.complaints.accuse(epoch, accusation.c, accuser) {
+            return false;
+        }
+        warn!("{} accused the dealing {} of {} in epoch {}", accuser, accusation.c, accusation.dealer, epoch);
+        self.notify(ComplaintStatus::Accused { epoch, c: accusation.c, accuser }).await;
+
+        // Answer the complaints against our own dealing.
+        if accusation.dealer != self.name {
+            return true;
+        }
+        let revelation = match self.my_dealings.read().await.get(&epoch).and_then(|dealing| dealing.get(&accuser)) {
+            Some((share, signature)) if share.c == accusation.c => Revelation {
+                accuser,
+                share: share.clone(),
+                signature: signature.clone(),
+            },
+            _ => return true,
+        };
+        let message = BreezeMessage::new_revelation_message(self.name, revelation);
+        let bytes = bincode::serialize(&message).expect("Failed to serialize revelation in BreezeComplaint");
+        let addresses = self
+            .committee
+            .at(epoch)
+            .all_breeze_addresses()
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect();
+        let handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
+        self.cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(handlers);
+        true
+    }
+
+    /// Records a checked revelation: the accuser adopts a valid share, an invalid one excludes the
+    /// dealing. Returns whether the revelation is new.
+    async fn handle_revelation(&mut self, dealer: PublicKey, revelation: Revelation, status: ComplaintStatus) -> bool {
+        let Revelation { accuser, share, signature } = revelation;
+        let (epoch, c) = (share.epoch, share.c);
+        if let ComplaintStatus::Excluded { .. } = status {
+            if !self.complaints.exclude(epoch, c) {
+                return false;
+            }
+            warn!("{} revealed an invalid share, its dealing {} of epoch {} is excluded", dealer, c, epoch);
+            self.notify(ComplaintStatus::Excluded { epoch, c }).await;
+            let dealing = SignedMessage::new(&share, share.digest(), signature);
+            let evidence = Evidence::new(dealer, epoch, Misbehaviour::InvalidDealing(dealing));
+            if let Err(e) = self.tx_evidence.send(evidence).await {
+                warn!("Failed to deliver evidence to the collector: {}", e);
+            }
+            return true;
+        }
+        if !self.complaints.resolve(epoch, c, accuser) {
+            return false;
+        }
+        info!("{} revealed the share of {} for its dealing {} of epoch {}", dealer, accuser, c, epoch);
+        self.notify(ComplaintStatus::Resolved { epoch, c, accuser }).await;
+
+        // The reply phase stores the revealed share as if the dealer had sent it in the first place.
+        if accuser == self.name {
+            let message = BreezeMessage::new_share_message(dealer, share, signature);
+            self.breeze_share_sender
+                .send(message)
+                .await
+                .expect("Failed to send revealed share to breeze reply phase");
+        }
+        true
+    }
+
+    async fn notify(&self, status: ComplaintStatus) {
+        if let Err(e) = self.complaint_sender.send(status).await {
+            warn!("Failed to deliver complaint status: {}", e);
+        }
+    }
+}
@@ -11,7 +11,8 @@ pub struct BreezeMessageHandler {
     pub breeze_share_sender: Sender<BreezeMessage>,
     pub breeze_confirm_sender: Sender<BreezeMessage>,
     pub breeze_merkle_roots_sender: Sender<BreezeMessage>,
-    pub breeze_reconstruct_secret_sender: Sender<BreezeMessage>
+    pub breeze_reconstruct_secret_sender: Sender<BreezeMessage>,
+    pub breeze_complaint_sender: Sender<BreezeMessage>,
 }
 
 #[async_trait]
@@ -22,7 +23,7 @@ impl MessageHandler for BreezeMessageHandler {
         let message: BreezeMessage = bincode::deserialize(&serialized).unwrap();
 
         match message.content {
-            BreezeContent::Share(_) => {
+            BreezeContent::Share(..) => {
                 self.breeze_share_sender
                     .send(message)
                     .await
@@ -40,12 +41,18 @@ impl MessageHandler for BreezeMessageHandler {
                     .await
                     .expect("Failed to send merkle roots");
             }
-            BreezeContent::Reconstruct(_) => {
+            BreezeContent::Reconstruct(..) => {
                 self.breeze_reconstruct_secret_sender
                     .send(message)
                     .await
                     .expect("Failed to send reply to breeze reconstruct phase");
             }
+            BreezeContent::Accusation(_) | BreezeContent::Revelation(_) => {
+                self.breeze_complaint_sender
+                    .send(message)
+                    .await
+                    .expect("Failed to send complaint to breeze complaint phase");
+            }
         }
         Ok(())
     }
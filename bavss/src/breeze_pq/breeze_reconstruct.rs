@@ -1,40 +1,44 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use bytes::Bytes;
 use log::{info};
 use network::{CancelHandler, ReliableSender};
+use std::ops::Range;
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::RwLock;
-use config::Committee;
-use crypto::{Digest, PublicKey};
+use config::SharedCommittee;
+use crypto::{Hash as _, PublicKey, SignatureService};
 use model::breeze_universal::{BreezeReconRequest};
-use model::types_and_const::{Epoch, Id};
+use model::types_and_const::Epoch;
 use crate::breeze_structs::{ BreezeMessage, PQCrs, ReconstructShare, Share, SingleShare};
 
 pub struct BreezeReconstruct {
-    node_id: (PublicKey,Id),
-    committee: Committee,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_reconstruct_cmd_receiver: Receiver<BreezeReconRequest>,
-    breeze_recon_certificate_sender: Sender<(HashSet<Digest>,Epoch, usize)>,
+    breeze_recon_certificate_sender: Sender<BreezeReconRequest>,
     network: ReliableSender,
     valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
-    cancel_handlers: HashMap<(Epoch, usize), Vec<CancelHandler>>,
+    cancel_handlers: HashMap<(Epoch, Range<usize>), Vec<CancelHandler>>,
     common_reference_string: Arc<PQCrs>,
 }
 
 impl BreezeReconstruct {
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        committee: Committee,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_reconstruct_cmd_receiver: Receiver<BreezeReconRequest>,
-        breeze_recon_certificate_sender: Sender<(HashSet<Digest>,Epoch, usize)>,
+        breeze_recon_certificate_sender: Sender<BreezeReconRequest>,
         network: ReliableSender,
         valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
         common_reference_string: Arc<PQCrs>,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
+                name,
+                signature_service,
                 committee,
                 breeze_reconstruct_cmd_receiver,
                 breeze_recon_certificate_sender,
@@ -54,38 +58,63 @@ impl BreezeReconstruct {
         loop {
             match self.breeze_reconstruct_cmd_receiver.recv().await.unwrap() {
                 message => {
-                    let idx = (message.index - 1) * g;
                     self.breeze_recon_certificate_sender
-                        .send((message.c.clone(), message.epoch, message.index))
+                        .send(message.clone())
                         .await
                         .unwrap();
+                    let committee = self.committee.at(message.epoch);
+                    let id = match committee.get_id(&self.name) {
+                        Some(id) => id,
+                        None => continue,
+                    };
                     let shares_lock = self.valid_shares.read().await;
                     let epoch_shares = match shares_lock.get(&message.epoch) {
                         Some(shares) => shares,
                         None => continue,
                     };
-                    let my_secrets_to_broadcast: Vec<SingleShare> = epoch_shares
-                        .iter()
-                        .filter(|(_pk, share)| message.c.contains(&share.c))
-                        .map(|(pk, share)| SingleShare {
-                            dealer: *pk,
-                            c: share.c,
-                            y: share.y_k[idx..idx+g].to_vec(),
-                            merkle_proof: (self.node_id.1,share.merkle_proofs[idx..idx+g].to_vec()),
-                            total_party_num: share.total_party_num,
+                    // One batch of shares per index, all sent in a single message, in the order of
+                    // the common core.
+                    let my_secrets_to_broadcast: Vec<Vec<SingleShare>> = message
+                        .indices
+                        .clone()
+                        .map(|index| {
+                            message
+                                .common_core
+                                .iter()
+                                .filter_map(|cer| epoch_shares.get_key_value(&cer.dealer).filter(|(_pk, share)| share.c == cer.c))
+                                .filter(|(_pk, share)| index > 0 && index * g <= share.y_k.len())
+                                .map(|(pk, share)| {
+                                    let idx = (index - 1) * g;
+                                    SingleShare {
+                                        dealer: *pk,
+                                        c: share.c,
+                                        y: share.y_k[idx..idx+g].to_vec(),
+                                        merkle_proof: (id, share.merkle_proofs[idx..idx+g].to_vec()),
+                                        total_party_num: share.total_party_num,
+                                    }
+                                })
+                                .collect()
                         })
                         .collect();
+                    drop(shares_lock);
+                    // Signing the shares makes an invalid one a proof against us.
+                    let reconstruct_share = ReconstructShare::new(my_secrets_to_broadcast, message.epoch, message.indices.clone());
+                    let signature = self
+                        .signature_service
+                        .request_signature_at(message.epoch, reconstruct_share.digest())
+                        .await;
                     let reconstruct_message = BreezeMessage::new_reconstruct_message(
-                        self.node_id.0,
-                        ReconstructShare::new(my_secrets_to_broadcast, message.epoch, message.index),
+                        self.name,
+                        reconstruct_share,
+                        signature,
                     );
-                    let addresses = self.committee.all_breeze_addresses().iter().map(|a| a.1).collect::<Vec<_>>();
+                    let addresses = committee.all_breeze_addresses().iter().map(|a| a.1).collect::<Vec<_>>();
                     let bytes = bincode::serialize(&reconstruct_message).expect(
                         "Failed to serialize shares for reconstruction in BreezeReconstruct",
                     );
                     let handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
                     self.cancel_handlers
-                        .entry((message.epoch, message.index))
+                        .entry((message.epoch, message.indices))
                         .or_insert_with(Vec::new)
                         .extend(handlers);
                 }
@@ -1,5 +1,7 @@
 use sha2::{Digest as ShaDigest, Sha256};
-use model::types_and_const::{Id, RandomNum, ZqMod};
+use crate::output_derivation::derive_output;
+use model::beacon_output::OutputDerivation;
+use model::types_and_const::{Epoch, Id, RandomNum, ZqMod};
 use crate::Secret;
 
 pub struct BreezeReconResult{
@@ -20,13 +22,45 @@ impl BreezeReconResult {
             cumulated[idx] += res;
         }
     }
+
+    /// The Lagrange coefficients interpolating at zero from the shares of `evaluate_ids`. They only
+    /// depend on the holders, so a batch of secrets held by the same ones shares the inversions.
+    pub fn lagrange_coefficients_at_zero(evaluate_ids: &[Id], q: ZqMod) -> Vec<ZqMod> {
+        let points: Vec<ZqMod> = evaluate_ids.iter().map(|id| (*id as ZqMod) % q).collect();
+        (0..points.len())
+            .map(|i| {
+                let mut coefficient = 1;
+                for j in 0..points.len() {
+                    if i != j {
+                        let numerator = (q - points[j]) % q;
+                        let denominator = (points[i] + q - points[j]) % q;
+                        coefficient = (coefficient * numerator) % q;
+                        coefficient = (coefficient * mod_inverse(denominator, q)) % q;
+                    }
+                }
+                coefficient
+            })
+            .collect()
+    }
+
+    /// Same as `interpolate`, with the coefficients of `lagrange_coefficients_at_zero`.
+    pub fn interpolate_with(coefficients: &[ZqMod], shares: &[Vec<Secret>], q: ZqMod, cumulated: &mut [ZqMod]) {
+        for (idx, output) in cumulated.iter_mut().enumerate() {
+            let res = coefficients
+                .iter()
+                .zip(shares.iter())
+                .fold(0, |acc, (l, share)| (acc + l * share[idx] % q) % q);
+            *output += res;
+        }
+    }
     
     
     
-    pub fn secret_to_number(&self) -> RandomNum {
+    /// The beacon output at `(epoch, index)`. The full output is the SHA-256 hash of the secret.
+    pub fn derive_output(&self, derivation: &OutputDerivation, epoch: Epoch, index: usize) -> RandomNum {
         let hash = vec_to_sha256(&self.value);
-        let res = hash_to_u128(&hash);
-        res
+        let secret: Vec<u8> = self.value.iter().flat_map(|s| s.to_le_bytes()).collect();
+        derive_output(derivation, hash, &secret, epoch, index)
     }
     fn lagrange_interpolation_at_zero(points: &Vec<ZqMod>, values: &Vec<ZqMod>, q: ZqMod) -> ZqMod {
         let n = points.len();
@@ -111,9 +145,3 @@ fn vec_to_sha256(secrets: &Vec<Secret>) -> [u8; 32] {
 
     result.into()
 }
-
-fn hash_to_u128(hash: &[u8; 32]) -> u128 {
-    let mut bytes = [0u8; 16];
-    bytes.copy_from_slice(&hash[..16]);
-    u128::from_be_bytes(bytes)
-}
\ No newline at end of file
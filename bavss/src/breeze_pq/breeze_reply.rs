@@ -1,10 +1,13 @@
 use crate::breeze_pq::breeze_share_dealer::Shares;
-use crate::breeze_structs::{BreezeContent, BreezeMessage, PQCrs, Share};
+use crate::breeze_store::BreezeStore;
+use crate::breeze_structs::{Accusation, BreezeContent, BreezeMessage, PQCrs, Share};
 use bytes::Bytes;
-use config::Committee;
-use crypto::{Digest, PublicKey, SecretKey, Signature};
-use log::info;
-use model::types_and_const::{Epoch, Id};
+use config::SharedCommittee;
+use crypto::{Digest, Hash as _, PublicKey, Signature, SignatureService};
+use log::{info, warn};
+use model::breeze_universal::BreezeCertificate;
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::Epoch;
 use network::{CancelHandler, ReliableSender};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -12,25 +15,30 @@ use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{RwLock};
 
 pub struct BreezeReply {
-    node_id: (PublicKey, Id),
-    signing_key: SecretKey,
-    committee: Committee,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_share_receiver: Receiver<BreezeMessage>,
     breeze_merkle_roots_receiver: Receiver<BreezeMessage>,
     merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
     merkle_watch_sender:Sender<Epoch>,
-    shares_received: HashMap<Epoch, HashMap<PublicKey, Share>>,
+    /// The share received from each dealer, with the dealer's signature.
+    shares_received: HashMap<Epoch, HashMap<PublicKey, (Share, Signature)>>,
     network: ReliableSender,
     valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
     common_reference_string: Arc<PQCrs>,
+    breeze_store: BreezeStore,
+    /// Reports the dealers of invalid or conflicting shares.
+    tx_evidence: Sender<Evidence>,
     cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
 }
 
 impl BreezeReply {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        node_id: (PublicKey, Id),
-        signing_key: SecretKey,
-        committee: Committee,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_share_receiver: Receiver<BreezeMessage>,
         breeze_merkle_roots_receiver: Receiver<BreezeMessage>,
         merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
@@ -38,11 +46,13 @@ impl BreezeReply {
         network: ReliableSender,
         valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
         common_reference_string: Arc<PQCrs>,
+        breeze_store: BreezeStore,
+        tx_evidence: Sender<Evidence>,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
-                signing_key,
+                name,
+                signature_service,
                 committee,
                 breeze_share_receiver,
                 breeze_merkle_roots_receiver,
@@ -52,6 +62,8 @@ impl BreezeReply {
                 network,
                 valid_shares,
                 common_reference_string,
+                breeze_store,
+                tx_evidence,
 
                 cancel_handlers: HashMap::new(),
             }
@@ -64,26 +76,55 @@ impl BreezeReply {
         info!("Breeze reply start to listen");
         loop {
             tokio::select! {
+                biased;
                 Some(message) = self.breeze_share_receiver.recv() => {
-                    let my_share = match message.content {
-                        BreezeContent::Share(ref share) => share.clone(),
+                    let (my_share, dealer_signature) = match &message.content {
+                        BreezeContent::Share(share, signature) => (share.clone(), signature.clone()),
                         _ => {
                             continue;
                         }
                     };
 
-                    if message.sender != self.node_id.0 {
+                    // The share is evaluated at our id in the committee of its epoch.
+                    let epoch = my_share.epoch;
+                    let dealer = message.sender;
+                    let committee = self.committee.at(epoch);
+                    let id = match committee.get_id(&self.name) {
+                        Some(id) if committee.get_id(&dealer).is_some() => id,
+                        _ => continue,
+                    };
+
+                    // Only a share signed by its dealer can be held against it.
+                    let digest = my_share.digest();
+                    if dealer_signature.verify(&digest, &self.committee.signing_key(&dealer, epoch)).is_err() {
+                        continue;
+                    }
+                    if dealer != self.name {
                         if !Shares::verify_shares(
                             &self.common_reference_string,
                             &my_share,
-                            self.node_id.1,
+                            id,
                         ) {
+                            self.accuse(dealer, epoch, my_share.c).await;
+                            let dealing = SignedMessage::new(&my_share, digest, dealer_signature);
+                            self.report(Evidence::new(dealer, epoch, Misbehaviour::InvalidDealing(dealing))).await;
                             continue;
                         }
                     }
 
-                    let inner_map = self.shares_received.entry(my_share.epoch).or_insert_with(HashMap::new);
-                    inner_map.entry(message.sender).or_insert(my_share);
+                    let inner_map = self.shares_received.entry(epoch).or_insert_with(HashMap::new);
+                    if let Some((existing_share, existing_signature)) = inner_map.get(&dealer) {
+                        let existing_digest = existing_share.digest();
+                        if existing_digest != digest {
+                            let evidence = Evidence::new(dealer, epoch, Misbehaviour::Equivocation(
+                                SignedMessage::new(existing_share, existing_digest, existing_signature.clone()),
+                                SignedMessage::new(&my_share, digest, dealer_signature),
+                            ));
+                            self.report(evidence).await;
+                        }
+                        continue;
+                    }
+                    inner_map.insert(dealer, (my_share, dealer_signature));
                 },
                 Some(message) = self.breeze_merkle_roots_receiver.recv() => {
                     match message.content {
@@ -92,7 +133,10 @@ impl BreezeReply {
                             let inner_map = merkle_roots
                                 .entry(mr.epoch)
                                 .or_insert_with(HashMap::new);
-                            inner_map.entry(message.sender).or_insert(mr.roots);
+                            if !inner_map.contains_key(&message.sender) {
+                                inner_map.insert(message.sender, mr.roots);
+                                self.breeze_store.persist_merkle_roots(mr.epoch, inner_map).await;
+                            }
                             drop(merkle_roots);
                             self.merkle_watch_sender.send(mr.epoch).await.unwrap();
                         }
@@ -104,14 +148,18 @@ impl BreezeReply {
             let merkle_roots = self.merkle_roots_received.read().await;
             for (epoch, share_map) in &self.shares_received {
                 if let Some(merkle_map) = merkle_roots.get(epoch) {
-                    for (pk, share) in share_map {
+                    let id = match self.committee.at(*epoch).get_id(&self.name) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    for (pk, (share, _)) in share_map {
                         if let Some(digests) = merkle_map.get(pk) {
-                            if *pk == self.node_id.0 {
-                                let signature = Signature::new(&share.c, &self.signing_key);
+                            if *pk == self.name {
+                                let signature = self.signature_service.request_signature_at(*epoch, BreezeCertificate::signed_digest(&share.c, pk)).await;
                                 reply_msgs.push((*pk, share, signature, *epoch));
                             }
-                            else if Shares::verify_merkle_batch(self.node_id.1,share, digests) {
-                                let signature = Signature::new(&share.c, &self.signing_key);
+                            else if Shares::verify_merkle_batch(id,share, digests) {
+                                let signature = self.signature_service.request_signature_at(*epoch, BreezeCertificate::signed_digest(&share.c, pk)).await;
                                 reply_msgs.push((*pk, share, signature, *epoch));
                             }
                         }
@@ -119,12 +167,13 @@ impl BreezeReply {
                 }
             }
             let mut valid_shares = self.valid_shares.write().await;
+            let mut new_shares = Vec::new();
             for (dealer_pk, share, sig, epoch) in reply_msgs {
                 let reply =
-                    BreezeMessage::new_reply_message(dealer_pk, self.node_id.0, share.c, sig, epoch);
+                    BreezeMessage::new_reply_message(dealer_pk, self.name, share.c, sig, epoch);
                 let bytes =
                     bincode::serialize(&reply).expect("Failed to serialize reply in BreezeReply");
-                let address = self.committee.breeze_address(&dealer_pk).unwrap();
+                let address = self.committee.at(epoch).breeze_address(&dealer_pk).unwrap();
                 let handler = self.network.send(address, Bytes::from(bytes)).await;
                 self.cancel_handlers
                     .entry(epoch)
@@ -133,8 +182,47 @@ impl BreezeReply {
 
                 let inner_map =
                     valid_shares.entry(epoch).or_insert_with(HashMap::new);
-                inner_map.entry(dealer_pk).or_insert(share.clone());
+                if !inner_map.contains_key(&dealer_pk) {
+                    inner_map.insert(dealer_pk, share.clone());
+                    new_shares.push((epoch, dealer_pk, share.clone()));
+                }
+            }
+            drop(valid_shares);
+            for (epoch, dealer, share) in new_shares {
+                self.breeze_store.persist_share(epoch, &dealer, &share).await;
             }
         }
     }
+
+    /// Broadcasts a signed accusation against the dealing `c` of `dealer`, which must then reveal
+    /// our share publicly.
+    async fn accuse(&mut self, dealer: PublicKey, epoch: Epoch, c: Digest) {
+        let mut accusation = Accusation {
+            dealer,
+            epoch,
+            c,
+            signature: Signature::default(),
+        };
+        accusation.signature = self.signature_service.request_signature_at(epoch, accusation.digest()).await;
+        let message = BreezeMessage::new_accusation_message(self.name, accusation);
+        let bytes = bincode::serialize(&message).expect("Failed to serialize accusation in BreezeReply");
+        let addresses = self
+            .committee
+            .at(epoch)
+            .all_breeze_addresses()
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect();
+        let handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
+        self.cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(handlers);
+    }
+
+    async fn report(&self, evidence: Evidence) {
+        if let Err(e) = self.tx_evidence.send(evidence).await {
+            warn!("Failed to deliver evidence to the collector: {}", e);
+        }
+    }
 }
@@ -1,61 +1,83 @@
+use crate::beacon_proof::{BeaconProof, DealingWitness, ProofCache, ProofQuery};
 use crate::breeze_pq::breeze_reconstruct_dealer::BreezeReconResult;
-use crate::breeze_structs::{BreezeContent, BreezeMessage, PQCrs, SingleShare};
+use crate::breeze_structs::{BreezeContent, BreezeMessage, PQCrs, Share, SingleShare};
+use crate::reed_solomon::{decode, ZqField};
 use crate::Secret;
-use config::Committee;
-use crypto::{Digest, PublicKey};
-use log::{error, info};
-use model::types_and_const::{Epoch, RandomNum, MAX_INDEX};
-use std::collections::{HashMap, HashSet};
+use config::{Committee, SharedCommittee};
+use crypto::{Digest, Hash as _, PublicKey};
+use log::{error, info, warn};
+use model::beacon_output::OutputDerivation;
+use model::breeze_universal::BreezeReconRequest;
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::{Epoch, Id, RandomNum, ZqMod, MAX_INDEX};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{watch, RwLock};
-use crate::breeze_pq::breeze_share_dealer::Shares;
+use crate::breeze_pq::breeze_share_dealer::{hash_c, Shares};
+
+/// The verified shares of each dealing, by holder, for each `(epoch, index)` to reconstruct.
+type VerifiedShares = HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, SingleShare>>>;
+/// The signed message carrying the shares of each holder, for each `(epoch, index)` to reconstruct.
+type SignedShares = HashMap<(Epoch, usize), HashMap<PublicKey, Arc<SignedMessage>>>;
 
 pub struct BreezeResult {
     // committee: Arc<RwLock<Committee>>,
-    committee: Committee,
-    // breeze_recon_certificate_receiver: Receiver<(HashSet<Digest>,Epoch, usize)>,
+    committee: SharedCommittee,
+    breeze_recon_certificate_receiver: Receiver<BreezeReconRequest>,
     // breeze_reconstruct_secret_receiver: Receiver<BreezeMessage>,
     breeze_result_sender: Sender<(Epoch, usize, RandomNum)>,
-    // merkle_roots_received: Arc<RwLock<HashMap<Epoch,HashMap<PublicKey,Vec<Digest>>>>>,
-    // certificates_to_reconstruct_buffer: Vec<(HashSet<Digest>, Epoch, usize)>,
-    certificates_to_reconstruct_buffer: Arc<RwLock<Vec<(HashSet<Digest>, Epoch, usize)>>>,
+    /// Reports the holders of inconsistent reconstruction shares.
+    tx_evidence: Sender<Evidence>,
+    rx_proof_query: Receiver<ProofQuery>,
+    derivation: OutputDerivation,
+    /// Whether to wait for shares carrying 2f+1 of the weight and correct the wrong ones before
+    /// reconstructing.
+    robust_reconstruction: bool,
+    /// The merkle roots of each dealer, which witness its dealings in the proofs.
+    merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
+    /// The shares we hold, whose commitments witness the dealings in the proofs.
+    valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
+    certificates_to_reconstruct_buffer: Vec<(BTreeSet<Digest>, Epoch, usize)>,
     // shares_unverified_yet: Arc<RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashSet<(PublicKey, SingleShare)>>>>>,
-    shares_verified:
-        Arc<RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, Vec<Secret>>>>>>,
-    // reconstructed_epoch_wave: Vec<(Epoch, usize)>
-    reconstructed_epoch_wave: Arc<RwLock<Vec<(Epoch, usize)>>>,
+    shares_verified: Arc<RwLock<VerifiedShares>>,
+    /// Kept in robust mode to be held against the holders of inconsistent shares.
+    signed_shares: Arc<RwLock<SignedShares>>,
+    reconstructed_epoch_wave: HashSet<(Epoch, usize)>,
 
     shares_verified_watch_receiver: watch::Receiver<()>,
     common_reference_string: Arc<PQCrs>,
+    proofs: ProofCache,
 }
 
 impl BreezeResult {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         // committee: Arc<RwLock<Committee>>,
-        committee: Committee,
-        breeze_recon_certificate_receiver: Receiver<(HashSet<Digest>, Epoch, usize)>,
+        committee: SharedCommittee,
+        breeze_recon_certificate_receiver: Receiver<BreezeReconRequest>,
         breeze_reconstruct_secret_receiver: Receiver<BreezeMessage>,
         merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
         merkle_watch_receiver: Receiver<Epoch>,
         breeze_result_sender: Sender<(Epoch, usize, RandomNum)>,
+        tx_evidence: Sender<Evidence>,
+        rx_proof_query: Receiver<ProofQuery>,
+        valid_shares: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Share>>>>,
+        derivation: OutputDerivation,
+        robust_reconstruction: bool,
         common_reference_string: Arc<PQCrs>,
     ) {
         let shares_unverified_yet: Arc<
             RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, SingleShare>>>>,
         > = Arc::new(RwLock::new(HashMap::new()));
-        let shares_verified: Arc<
-            RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, Vec<Secret>>>>>,
-        > = Arc::new(RwLock::new(HashMap::new()));
-        let reconstructed_epoch_wave: Arc<RwLock<Vec<(Epoch, usize)>>> =
-            Arc::new(RwLock::new(Vec::new()));
-        let certificates_to_reconstruct_buffer: Arc<RwLock<Vec<(HashSet<Digest>, Epoch, usize)>>> =
-            Arc::new(RwLock::new(Vec::new()));
+        let shares_verified: Arc<RwLock<VerifiedShares>> = Arc::new(RwLock::new(HashMap::new()));
+        let signed_shares: Arc<RwLock<SignedShares>> = Arc::new(RwLock::new(HashMap::new()));
 
         let (shares_verified_watch_sender, shares_verified_watch_receiver) = watch::channel(());
 
         let g = common_reference_string.g;
         tokio::spawn(Self::merkle_watch_monitor(
+            committee.clone(),
             merkle_watch_receiver,
             Arc::clone(&merkle_roots_received),
             Arc::clone(&shares_unverified_yet),
@@ -64,33 +86,38 @@ impl BreezeResult {
             g
         ));
         tokio::spawn(Self::share_monitor(
+            committee.clone(),
             breeze_reconstruct_secret_receiver,
             Arc::clone(&merkle_roots_received),
             Arc::clone(&shares_verified),
             Arc::clone(&shares_unverified_yet),
             shares_verified_watch_sender.clone(),
+            tx_evidence.clone(),
+            robust_reconstruction.then(|| Arc::clone(&signed_shares)),
             g
         ));
-        tokio::spawn(Self::certificate_monitor(
-            breeze_recon_certificate_receiver,
-            Arc::clone(&reconstructed_epoch_wave),
-            Arc::clone(&certificates_to_reconstruct_buffer)
-        ));
 
         tokio::spawn(async move {
             Self {
                 committee,
-                // breeze_recon_certificate_receiver,
+                breeze_recon_certificate_receiver,
                 // breeze_reconstruct_secret_receiver,
                 breeze_result_sender,
-                // merkle_roots_received,
-                certificates_to_reconstruct_buffer: Arc::clone(&certificates_to_reconstruct_buffer),
+                tx_evidence,
+                rx_proof_query,
+                derivation,
+                robust_reconstruction,
+                merkle_roots_received,
+                valid_shares,
+                certificates_to_reconstruct_buffer: Vec::new(),
                 // shares_unverified_yet,
                 shares_verified,
-                reconstructed_epoch_wave: Arc::clone(&reconstructed_epoch_wave),
+                signed_shares,
+                reconstructed_epoch_wave: HashSet::new(),
 
                 shares_verified_watch_receiver,
-                common_reference_string
+                common_reference_string,
+                proofs: ProofCache::default(),
             }
             .run()
             .await;
@@ -99,86 +126,158 @@ impl BreezeResult {
 
     pub async fn run(&mut self) {
         info!("Breeze result start to listen");
-        let threshold = self.committee.authorities_fault_tolerance() + 1;
-        let q = self.common_reference_string.q;
         loop {
-            if self.shares_verified_watch_receiver.changed().await.is_ok() {
-                let mut secrets_to_reconstruct = Vec::new();
-                let mut certificates_to_reconstruct_buffer =
-                    self.certificates_to_reconstruct_buffer.write().await;
-                let shares_verified = self.shares_verified.read().await;
-
-                let mut key_changed = Vec::new();
-                certificates_to_reconstruct_buffer.retain(|(digests, epoch, index)| {
-                    let mut digest_can_be_reconstructed = HashSet::new();
-                    let mut secret_can_be_reconstructed = Vec::new();
-                    let key = (*epoch, *index);
-
-                    if let Some(shares) = shares_verified.get(&key) {
-                        for (c, s) in shares.iter() {
-                            if s.len() >= threshold {
-                                let s: Vec<_> = s.iter().map(|(pk, s)| (*pk,s.clone())).collect();
-                                secret_can_be_reconstructed.push((*c, s));
-                                digest_can_be_reconstructed.insert(*c);
-                            }
-                        }
-                        return if &digest_can_be_reconstructed == digests {
-                            secrets_to_reconstruct.push((
-                                *epoch,
-                                *index,
-                                secret_can_be_reconstructed,
-                            ));
-                            key_changed.push(key);
-                            false
-                        } else {
-                            true
-                        };
+            tokio::select! {
+                biased;
+                result = self.shares_verified_watch_receiver.changed() => {
+                    if result.is_err() {
+                        break;
                     }
-                    true
-                });
-                if !key_changed.is_empty() {
-                    drop(shares_verified);
-                    let mut reconstructed_epoch_wave = self.reconstructed_epoch_wave.write().await;
-                    let mut shares_verified = self.shares_verified.write().await;
-                    for key in key_changed {
-                        reconstructed_epoch_wave.push(key);
-                        shares_verified.remove(&key);
+                },
+                Some(request) = self.breeze_recon_certificate_receiver.recv() => {
+                    let (digests, epoch) = (request.digests(), request.epoch);
+                    self.proofs.insert_common_core(epoch, &request.common_core);
+                    for index in request.indices {
+                        if self.reconstructed_epoch_wave.contains(&(epoch, index)) {
+                            continue;
+                        }
+                        let exists = self.certificates_to_reconstruct_buffer.iter().any(|(_, e, w)| e == &epoch && w == &index);
+                        if !exists {
+                            self.certificates_to_reconstruct_buffer.push((digests.clone(), epoch, index));
+                        }
                     }
+                },
+                Some(((epoch, index), reply)) = self.rx_proof_query.recv() => {
+                    let proof = self.proof(epoch, index).await.map(|proof| {
+                        bincode::serialize(&proof).expect("Failed to serialize beacon proof")
+                    });
+                    let _ = reply.send(proof);
+                    continue;
                 }
+            }
+            self.reconstruct().await;
+        }
+    }
+
+    /// Reconstructs the beacons whose dealings all gathered enough verified shares.
+    async fn reconstruct(&mut self) {
+        let q = self.common_reference_string.q;
+        let mut secrets_to_reconstruct = Vec::new();
+        let shares_verified = self.shares_verified.read().await;
+
+        let mut key_changed = Vec::new();
+        self.certificates_to_reconstruct_buffer.retain(|(digests, epoch, index)| {
+            let mut digest_can_be_reconstructed = BTreeSet::new();
+            let mut secret_can_be_reconstructed = Vec::new();
+            let key = (*epoch, *index);
 
-                for (epoch, index, secret_set) in secrets_to_reconstruct {
-                    let mut cumulated_output = vec![0;self.common_reference_string.g];
-                    for (_, shares) in secret_set {
-                        let mut ids = Vec::new();
-                        let mut values = Vec::new();
-                        for (pk, value) in shares {
-                            let id = self.committee.get_id(&pk).unwrap();
-                            ids.push(id);
-                            values.push(value);
+            if let Some(shares) = shares_verified.get(&key) {
+                let committee = self.committee.at(*epoch);
+                for (c, s) in shares.iter() {
+                    let s = if self.robust_reconstruction {
+                        match consistent_shares(&committee, s, q) {
+                            Some(s) => s,
+                            None => continue,
                         }
-                        BreezeReconResult::interpolate(&ids, &values, q, &mut cumulated_output);
+                    } else if committee.can_reconstruct(s.keys()) {
+                        (s.iter().map(|(pk, s)| (*pk, s.clone())).collect(), Vec::new())
+                    } else {
+                        continue;
+                    };
+                    secret_can_be_reconstructed.push((*c, s));
+                    digest_can_be_reconstructed.insert(*c);
+                }
+                return if &digest_can_be_reconstructed == digests {
+                    secrets_to_reconstruct.push((
+                        *epoch,
+                        *index,
+                        secret_can_be_reconstructed,
+                    ));
+                    key_changed.push(key);
+                    false
+                } else {
+                    true
+                };
+            }
+            true
+        });
+        drop(shares_verified);
+        let mut signed = HashMap::new();
+        if !key_changed.is_empty() {
+            let mut shares_verified = self.shares_verified.write().await;
+            let mut signed_shares = self.signed_shares.write().await;
+            for key in key_changed {
+                self.reconstructed_epoch_wave.insert(key);
+                shares_verified.remove(&key);
+                signed.insert(key, signed_shares.remove(&key).unwrap_or_default());
+            }
+        }
+
+        // The indices of a batch are usually held by the same nodes: compute the Lagrange
+        // coefficients of each set of holders only once.
+        let mut coefficients: HashMap<Vec<Id>, Vec<ZqMod>> = HashMap::new();
+        for (epoch, index, secret_set) in secrets_to_reconstruct {
+            let committee = self.committee.at(epoch);
+            let mut cumulated_output = vec![0;self.common_reference_string.g];
+            let mut dealings = Vec::new();
+            for (c, (shares, faulty)) in secret_set {
+                for name in faulty {
+                    warn!("Inconsistent share of {} at index {} of epoch {} from {}", c, index, epoch, name);
+                    if let Some(message) = signed.get(&(epoch, index)).and_then(|signed| signed.get(&name)) {
+                        let misbehaviour = Misbehaviour::InconsistentShare(message.as_ref().clone());
+                        self.report(Evidence::new(name, epoch, misbehaviour)).await;
                     }
-                    let recon_output = BreezeReconResult::new(cumulated_output);
-                    self.breeze_result_sender
-                        .send((epoch, index, recon_output.secret_to_number()))
-                        .await
-                        .expect("breeze_result_sender error to send");
                 }
-            } else {
-                break;
+                let mut points: Vec<(Id, Vec<Secret>)> = shares
+                    .iter()
+                    .map(|(pk, share)| (committee.get_id(pk).unwrap(), share.y.clone()))
+                    .collect();
+                dealings.push((c, shares));
+                points.sort_by_key(|(id, _)| *id);
+                let (ids, values): (Vec<Id>, Vec<Vec<Secret>>) = points.into_iter().unzip();
+                let coefficients = coefficients
+                    .entry(ids)
+                    .or_insert_with_key(|ids| BreezeReconResult::lagrange_coefficients_at_zero(ids, q));
+                BreezeReconResult::interpolate_with(coefficients, &values, q, &mut cumulated_output);
             }
+            let value = BreezeReconResult::new(cumulated_output).derive_output(&self.derivation, epoch, index);
+            self.proofs.insert(&committee, epoch, index, value.clone(), dealings);
+            self.breeze_result_sender
+                .send((epoch, index, value))
+                .await
+                .expect("breeze_result_sender error to send");
         }
     }
 
+    async fn report(&self, evidence: Evidence) {
+        if let Err(e) = self.tx_evidence.send(evidence).await {
+            warn!("Failed to deliver evidence to the collector: {}", e);
+        }
+    }
+
+    /// Builds the proof of a beacon we reconstructed, each dealing witnessed by the commitment in
+    /// the share we hold and by the merkle roots of its dealer.
+    async fn proof(&self, epoch: Epoch, index: usize) -> Option<BeaconProof> {
+        let merkle_roots_received = self.merkle_roots_received.read().await;
+        let valid_shares = self.valid_shares.read().await;
+        let (roots, shares) = (merkle_roots_received.get(&epoch)?, valid_shares.get(&epoch)?);
+        self.proofs.proof(epoch, index, |c, _| {
+            let (dealer, share) = shares.iter().find(|(_, share)| share.c == *c)?;
+            Some(DealingWitness {
+                commitment: hash_c(&share.t),
+                merkle_roots: roots.get(dealer)?.clone(),
+            })
+        })
+    }
+
     async fn merkle_watch_monitor(
+        committee: SharedCommittee,
         mut merkle_watch_receiver: Receiver<Epoch>,
         merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
         shares_unverified_yet: Arc<
             RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, SingleShare>>>>,
         >,
-        shares_verified: Arc<
-            RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, Vec<Secret>>>>>,
-        >,
+        shares_verified: Arc<RwLock<VerifiedShares>>,
         shares_verified_watch_sender: watch::Sender<()>,
         g: usize,
     ) {
@@ -188,6 +287,7 @@ impl BreezeResult {
 
                     let merkle_roots_received = merkle_roots_received.read().await;
                     if let Some(roots) = merkle_roots_received.get(&epoch) {
+                        let committee = committee.at(epoch);
 
                         let mut shares_unverified_yet = shares_unverified_yet.write().await;
 
@@ -197,21 +297,15 @@ impl BreezeResult {
                         {
                             for (digest, set) in secrets.iter() {
                                 for (receiver_pk, ss) in set.iter() {
-                                    if roots.contains_key(&ss.dealer) {
-                                        let rs = roots.get(&ss.dealer).unwrap();
+                                    if let (Some(rs), Some(id)) = (roots.get(&ss.dealer), committee.get_id(receiver_pk)) {
                                         let idx = (index - 1) * g;
-                                        if Shares::verify_merkle(
-                                            &ss.y,
-                                            ss.merkle_proof.clone(),
-                                            rs[idx..idx + g].to_vec(),
-                                            ss.total_party_num,
-                                        ) {
+                                        if verify_share(ss, id, rs.get(idx..idx + g), committee.size()) {
                                             let mut write_lock = shares_verified.write().await;
                                             let temp = write_lock
                                                 .entry((*curr_epoch, *index))
                                                 .or_insert(HashMap::new());
                                             let temp2 = temp.entry(*digest).or_insert(HashMap::new());
-                                            temp2.insert(*receiver_pk, ss.y.clone());
+                                            temp2.insert(*receiver_pk, ss.clone());
 
                                             shares_verified_watch_sender.send(()).unwrap();
                                         }
@@ -230,60 +324,103 @@ impl BreezeResult {
     }
 
     async fn share_monitor(
+        committee: SharedCommittee,
         mut breeze_reconstruct_secret_receiver: Receiver<BreezeMessage>,
         merkle_roots_received: Arc<RwLock<HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>>>>,
-        shares_verified: Arc<
-            RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, Vec<Secret>>>>>,
-        >,
+        shares_verified: Arc<RwLock<VerifiedShares>>,
         shares_unverified_yet: Arc<
             RwLock<HashMap<(Epoch, usize), HashMap<Digest, HashMap<PublicKey, SingleShare>>>>,
         >,
 
         shares_verified_watch_sender: watch::Sender<()>,
+        tx_evidence: Sender<Evidence>,
+        signed_shares: Option<Arc<RwLock<SignedShares>>>,
         g: usize,
     ){
         loop{
             let shares_from_others = breeze_reconstruct_secret_receiver.recv().await.unwrap();
             match shares_from_others.content {
-                BreezeContent::Reconstruct(share) => {
-                    let max_index = *MAX_INDEX.get().unwrap();
-                    if share.index > max_index{
+                BreezeContent::Reconstruct(share, signature) => {
+                    // Only the members of the epoch's committee hold shares for it.
+                    let epoch_committee = committee.at(share.epoch);
+                    let id = match epoch_committee.get_id(&shares_from_others.sender) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    // Only shares signed by their holder can be held against it.
+                    let digest = share.digest();
+                    let signing_key = committee.signing_key(&shares_from_others.sender, share.epoch);
+                    if signature.verify(&digest, &signing_key).is_err() {
                         continue;
                     }
+                    let max_index = *MAX_INDEX.get().unwrap();
+                    if let Some(signed_shares) = &signed_shares {
+                        let signed = Arc::new(SignedMessage::new(&share, digest, signature.clone()));
+                        let mut signed_shares = signed_shares.write().await;
+                        for (index, _) in share.by_index().filter(|(index, _)| *index != 0 && *index <= max_index) {
+                            signed_shares
+                                .entry((share.epoch, index))
+                                .or_default()
+                                .entry(shares_from_others.sender)
+                                .or_insert_with(|| signed.clone());
+                        }
+                    }
                     let merkle_roots_received = merkle_roots_received.read().await;
-                    match merkle_roots_received.get(&share.epoch) {
-                        Some(roots) => {
-                            for ss in share.secrets.iter() {
-                                if roots.contains_key(&ss.dealer){
-                                    let rs = roots.get(&ss.dealer).unwrap();
-                                    let idx = (share.index - 1) * g;
-                                    if Shares::verify_merkle(&ss.y, ss.merkle_proof.clone(), rs[idx..idx+g].to_vec(), ss.total_party_num) {
-                                        let mut write_lock = shares_verified.write().await;
-                                        let temp = write_lock.entry((share.epoch,share.index)).or_insert(HashMap::new());
+                    let mut verified = false;
+                    let mut invalid = false;
+                    for (index, secrets) in share.by_index() {
+                        if index == 0 || index > max_index{
+                            continue;
+                        }
+                        match merkle_roots_received.get(&share.epoch) {
+                            Some(roots) => {
+                                for ss in secrets.iter() {
+                                    if let Some(rs) = roots.get(&ss.dealer) {
+                                        let idx = (index - 1) * g;
+                                        if verify_share(ss, id, rs.get(idx..idx + g), epoch_committee.size()) {
+                                            let mut write_lock = shares_verified.write().await;
+                                            let temp = write_lock.entry((share.epoch,index)).or_insert(HashMap::new());
+                                            let temp2 = temp.entry(ss.c).or_insert(HashMap::new());
+                                            temp2.insert(shares_from_others.sender,ss.clone());
+                                            drop(write_lock);
+                                            verified = true;
+                                        } else {
+                                            warn!("Invalid share of {} at index {} from {}", ss.c, index, shares_from_others.sender);
+                                            invalid = true;
+                                        }
+                                    }else {
+                                        let mut write_lock = shares_unverified_yet.write().await;
+                                        let temp = write_lock.entry((share.epoch,index)).or_insert(HashMap::new());
                                         let temp2 = temp.entry(ss.c).or_insert(HashMap::new());
-                                        temp2.insert(shares_from_others.sender,ss.y.clone());
+                                        temp2.insert(shares_from_others.sender,ss.clone());
                                         drop(write_lock);
-                                        shares_verified_watch_sender.send(()).unwrap();
-                                    }else {
                                     }
-                                }else {
+                                }
+
+                            }
+                            None => {
+                                for ss in secrets.iter() {
                                     let mut write_lock = shares_unverified_yet.write().await;
-                                    let temp = write_lock.entry((share.epoch,share.index)).or_insert(HashMap::new());
+                                    let temp = write_lock.entry((share.epoch,index)).or_insert(HashMap::new());
                                     let temp2 = temp.entry(ss.c).or_insert(HashMap::new());
                                     temp2.insert(shares_from_others.sender,ss.clone());
                                     drop(write_lock);
                                 }
                             }
-
                         }
-                        None => {
-                            for ss in share.secrets.iter() {
-                                let mut write_lock = shares_unverified_yet.write().await;
-                                let temp = write_lock.entry((share.epoch,share.index)).or_insert(HashMap::new());
-                                let temp2 = temp.entry(ss.c).or_insert(HashMap::new());
-                                temp2.insert(shares_from_others.sender,ss.clone());
-                                drop(write_lock);
-                            }
+                    }
+                    drop(merkle_roots_received);
+                    // Wake up the result once for the whole batch.
+                    if verified {
+                        shares_verified_watch_sender.send(()).unwrap();
+                    }
+                    // The shares checked only once the merkle roots arrive are not reported.
+                    if invalid {
+                        let epoch = share.epoch;
+                        let shares = SignedMessage::new(&share, digest, signature);
+                        let evidence = Evidence::new(shares_from_others.sender, epoch, Misbehaviour::InvalidShare(shares));
+                        if let Err(e) = tx_evidence.send(evidence).await {
+                            warn!("Failed to deliver evidence to the collector: {}", e);
                         }
                     }
                 }
@@ -291,23 +428,54 @@ impl BreezeResult {
             }
         }
     }
+}
 
-    async fn certificate_monitor(
-        mut breeze_recon_certificate_receiver: Receiver<(HashSet<Digest>, Epoch, usize)>,
-        reconstructed_epoch_wave: Arc<RwLock<Vec<(Epoch, usize)>>>,
-        certificates_to_reconstruct_buffer: Arc<RwLock<Vec<(HashSet<Digest>, Epoch, usize)>>>,
-    ) {
-        loop {
-            let certificates_to_reconstruct = breeze_recon_certificate_receiver.recv().await.unwrap();
-            let key = (certificates_to_reconstruct.1, certificates_to_reconstruct.2);
-            if !reconstructed_epoch_wave.read().await.contains(&key) {
-                let mut certificates_to_reconstruct_buffer = certificates_to_reconstruct_buffer.write().await;
-                let exists = certificates_to_reconstruct_buffer.iter().any(|(_, e, w)| e == &certificates_to_reconstruct.1 && w == &certificates_to_reconstruct.2);
-                if !exists {
-                    certificates_to_reconstruct_buffer.push(certificates_to_reconstruct);
-                }
-                drop(certificates_to_reconstruct_buffer);
-            }
-        }
+/// Checks the share of the holder `id` against the `roots` of its index, if they were received.
+fn verify_share(share: &SingleShare, id: Id, roots: Option<&[Digest]>, n: usize) -> bool {
+    roots.is_some_and(|roots| Shares::verify_merkle(id, &share.y, &share.merkle_proof.1, roots, n))
+}
+
+/// The holders of the shares not lying on the decoded polynomial.
+type Faulty = Vec<PublicKey>;
+
+/// Once the shares of a dealing carry 2f+1 of the weight, decodes each of their components with
+/// error correction. Returns the shares lying on all the decoded polynomials, along with the
+/// holders of the other ones.
+fn consistent_shares(
+    committee: &Committee,
+    shares: &HashMap<PublicKey, SingleShare>,
+    q: ZqMod,
+) -> Option<(Vec<(PublicKey, SingleShare)>, Faulty)> {
+    let t = committee.authorities_fault_tolerance();
+    if !committee.can_reconstruct_robustly(shares.keys()) {
+        return None;
+    }
+    let shares: Vec<(PublicKey, SingleShare)> =
+        shares.iter().map(|(pk, s)| (*pk, s.clone())).collect();
+    let ids: Vec<Id> = shares
+        .iter()
+        .map(|(pk, _)| committee.get_id(pk).unwrap())
+        .collect();
+    let field = ZqField { q };
+    let components = shares.iter().map(|(_, s)| s.y.len()).min().unwrap_or(0);
+    let mut errors = HashSet::new();
+    for k in 0..components {
+        let points: Vec<(Id, Secret)> = ids
+            .iter()
+            .zip(shares.iter())
+            .map(|(id, (_, s))| (*id, s.y[k]))
+            .collect();
+        errors.extend(decode(&field, &points, t)?.errors);
+    }
+    let (faulty, consistent): (Vec<_>, Vec<_>) = shares
+        .into_iter()
+        .enumerate()
+        .partition(|(i, _)| errors.contains(i));
+    // Up to f of the weight may come from faulty holders: only polynomials agreeing with shares
+    // carrying 2f+1 of it are the dealt ones.
+    let consistent: Vec<(PublicKey, SingleShare)> = consistent.into_iter().map(|(_, share)| share).collect();
+    if !committee.can_reconstruct_robustly(consistent.iter().map(|(pk, _)| pk)) {
+        return None;
     }
+    Some((consistent, faulty.into_iter().map(|(_, (pk, _))| pk).collect()))
 }
@@ -1,45 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use bytes::Bytes;
 use log::{info};
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::RwLock;
-use config::Committee;
-use crypto::{Digest, PublicKey};
-use model::types_and_const::{Epoch, Id, BEACON_PER_EPOCH, MAX_EPOCH};
+use config::SharedCommittee;
+use crypto::{Digest, Hash as _, PublicKey, SignatureService};
+use model::types_and_const::{Epoch, BEACON_PER_EPOCH, MAX_EPOCH};
 use network::{CancelHandler, ReliableSender};
+use rand::rngs::StdRng;
 use crate::breeze_pq::breeze_share_dealer::Shares;
-use crate::breeze_structs::{BreezeMessage, PQCrs};
+use crate::breeze_store::BreezeStore;
+use crate::breeze_structs::{BreezeMessage, Dealing, PQCrs};
 
 pub struct BreezeShare{
-    node_id: (PublicKey,Id),
-    committee: Committee,
+    name: PublicKey,
+    signature_service: SignatureService,
+    committee: SharedCommittee,
     breeze_share_cmd_receiver: Receiver<Epoch>,
     network: ReliableSender,
     common_reference_string: Arc<PQCrs>,
     my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+    my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+    breeze_store: BreezeStore,
+    /// Draws the polynomials of our dealings.
+    rng: StdRng,
     cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
     merkle_cancel_handlers: HashMap<Epoch, Vec<CancelHandler>>,
 }
 
 impl BreezeShare {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        node_id: (PublicKey,Id),
-        committee: Committee,
+        name: PublicKey,
+        signature_service: SignatureService,
+        committee: SharedCommittee,
         breeze_share_cmd_receiver: Receiver<Epoch>,
         network: ReliableSender,
         common_reference_string: Arc<PQCrs>,
-        my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>
+        my_dealer_shares: Arc<RwLock<HashMap<Epoch,Digest>>>,
+        my_dealings: Arc<RwLock<HashMap<Epoch, Dealing>>>,
+        breeze_store: BreezeStore,
+        rng: StdRng,
     ) {
         tokio::spawn(async move {
             Self {
-                node_id,
+                name,
+                signature_service,
                 committee,
                 breeze_share_cmd_receiver,
                 network,
                 common_reference_string,
                 my_dealer_shares,
+                my_dealings,
+                breeze_store,
+                rng,
                 cancel_handlers: HashMap::new(),
                 merkle_cancel_handlers: HashMap::new(),
             }
@@ -53,38 +69,72 @@ impl BreezeShare {
         loop {
             match self.breeze_share_cmd_receiver.recv().await.unwrap() {
                 epoch => {
-                    let ids = self.committee.get_all_ids();
-                    let fault_tolerance = self.committee.authorities_fault_tolerance();
+                    // Only the members of the epoch's committee deal for it.
+                    let committee = self.committee.at(epoch);
+                    if committee.get_id(&self.name).is_none() {
+                        continue;
+                    }
+                    // We already dealt this epoch before a restart, dealing again would
+                    // conflict with the shares the other nodes hold. Send the same shares and
+                    // roots again instead, in case the restart interrupted the dealing.
+                    let dealing = self.my_dealings.read().await.get(&epoch).cloned();
+                    if let Some(dealing) = dealing {
+                        if let Some(merkle_roots) = self.breeze_store.load_dealt_roots(epoch).await {
+                            self.send_dealing(epoch, dealing, merkle_roots).await;
+                        }
+                        continue;
+                    }
+                    if self.my_dealer_shares.read().await.contains_key(&epoch) {
+                        continue;
+                    }
+                    let ids = committee.get_all_ids();
+                    let fault_tolerance = committee.authorities_fault_tolerance();
                     let batch_size = *MAX_EPOCH.get().unwrap() + *BEACON_PER_EPOCH.get().unwrap();
-                    let (shares, merkle_roots) = Shares::new(batch_size as usize, epoch, ids, fault_tolerance, &self.common_reference_string);
+                    let (shares, merkle_roots) = Shares::new(batch_size as usize, epoch, ids, fault_tolerance, &self.common_reference_string, &mut self.rng);
                     let c = shares.get_c_ref().clone();
-                    let mut share_map_to_addresses: HashMap<SocketAddr, Bytes> = HashMap::new();
-                    let addresses = self.committee.all_breeze_addresses();
-
+                    let mut dealing = Dealing::new();
                     for (share, pk) in shares.get_shares_ref() {
-                        if let Some((_,addr)) = addresses.iter().find(|x|x.0 == *pk){
-                            let message = BreezeMessage::new_share_message(self.node_id.0, share.clone());
-                            let bytes = bincode::serialize(&message).expect("Failed to serialize shares in BreezeShare");
-                            share_map_to_addresses.insert(*addr, Bytes::from(bytes));
-                        }
+                        // Signing the share makes an invalid one a proof against us.
+                        let signature = self.signature_service.request_signature_at(epoch, share.digest()).await;
+                        dealing.insert(*pk, (share.clone(), signature));
                     }
+                    // The dealing is stored first, so that a restart finds it to send again.
+                    self.breeze_store.persist_dealing(epoch, &dealing).await;
+                    self.breeze_store.persist_dealt_roots(epoch, &merkle_roots).await;
+                    self.my_dealings.write().await.insert(epoch, dealing.clone());
                     let mut my_dealer_shares = self.my_dealer_shares.write().await;
                     my_dealer_shares.insert(epoch, c);
-                    let handlers = self.network.dispatch_to_addresses_compressed(share_map_to_addresses).await;
-                    self.cancel_handlers
-                        .entry(epoch)
-                        .or_insert_with(Vec::new)
-                        .extend(handlers);
-                    let merkle_roots_to_broadcast = BreezeMessage::new_merkle_message(self.node_id.0,merkle_roots,epoch);
-                    let bytes = bincode::serialize(&merkle_roots_to_broadcast).expect("Failed to serialize shares in BreezeShare");
-                    let addresses = addresses.iter().map(|x|x.1).collect();
-                    let merkle_handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
-                    self.merkle_cancel_handlers
-                        .entry(epoch)
-                        .or_insert_with(Vec::new)
-                        .extend(merkle_handlers);
+                    self.breeze_store.persist_dealer_shares(&my_dealer_shares).await;
+                    drop(my_dealer_shares);
+                    self.send_dealing(epoch, dealing, merkle_roots).await;
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Sends each holder its share of our dealing of `epoch`, and broadcasts the merkle roots.
+    async fn send_dealing(&mut self, epoch: Epoch, dealing: Dealing, merkle_roots: Vec<Digest>) {
+        let addresses = self.committee.at(epoch).all_breeze_addresses();
+        let mut share_map_to_addresses: BTreeMap<SocketAddr, Bytes> = BTreeMap::new();
+        for (pk, (share, signature)) in dealing {
+            if let Some((_,addr)) = addresses.iter().find(|x|x.0 == pk){
+                let message = BreezeMessage::new_share_message(self.name, share, signature);
+                let bytes = bincode::serialize(&message).expect("Failed to serialize shares in BreezeShare");
+                share_map_to_addresses.insert(*addr, Bytes::from(bytes));
+            }
+        }
+        let handlers = self.network.dispatch_to_addresses_compressed(share_map_to_addresses).await;
+        self.cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(handlers);
+        let merkle_roots_to_broadcast = BreezeMessage::new_merkle_message(self.name,merkle_roots,epoch);
+        let bytes = bincode::serialize(&merkle_roots_to_broadcast).expect("Failed to serialize shares in BreezeShare");
+        let addresses = addresses.iter().map(|x|x.1).collect();
+        let merkle_handlers = self.network.broadcast(addresses, Bytes::from(bytes)).await;
+        self.merkle_cancel_handlers
+            .entry(epoch)
+            .or_insert_with(Vec::new)
+            .extend(merkle_handlers);
+    }
+}
@@ -8,8 +8,9 @@ use crypto::{Digest, PublicKey};
 use log::error;
 use model::types_and_const::{Epoch, Id, ZqMod};
 use nalgebra::DVector;
+use rand::{CryptoRng, RngCore};
 use rayon::iter::ParallelIterator;
-use rayon::prelude::ParallelBridge;
+use rayon::slice::ParallelSlice;
 use sha2::{Digest as ShaDigest, Sha256};
 
 pub struct Shares(pub(crate) Vec<(Share, PublicKey)>);
@@ -45,51 +46,27 @@ impl Shares {
     }
 
 
-    pub fn verify_merkle_batch(id: usize, share: &Share, roots: &Vec<Digest>) -> bool {
-        let mut flag = true;
-        for (idx, x) in share.y_k.iter().enumerate() {
-            let mut hasher = Sha256::new();
-            hasher.update(x.to_be_bytes());
-            let leaf = hasher.finalize().to_vec();
-            match verify_merkle_proof(
-                &leaf,
-                (id - 1, share.merkle_proofs[idx].clone()),
-                roots[idx],
-                share.total_party_num,
-            ) {
-                Ok(_) => {}
-                Err(_) => {
-                    flag = false;
-                    break;
-                }
-            }
-        }
-        flag
+    /// Checks that `roots` are the merkle roots committed under the digest `c` of the share, and
+    /// that every component of the share is the leaf of the holder `id` under them.
+    pub fn verify_merkle_batch(id: Id, share: &Share, roots: &[Digest]) -> bool {
+        commitment_digest(&hash_c(&share.t), roots) == share.c
+            && Self::verify_merkle(id, &share.y_k, &share.merkle_proofs, roots, share.total_party_num)
     }
-    pub fn verify_merkle(y: &Vec<ZqMod>, proof: (usize,Vec<Vec<u8>>), root: Vec<Digest>, total_leaves_count: usize) -> bool {
-        if proof.1.len() != root.len() || y.len() != root.len() {
+
+    /// Checks that each component of `y` is the leaf of the holder `id` under the matching root,
+    /// in trees of `total_leaves_count` leaves.
+    pub fn verify_merkle(id: Id, y: &[ZqMod], branches: &[Vec<u8>], roots: &[Digest], total_leaves_count: usize) -> bool {
+        if id == 0 || id > total_leaves_count || branches.len() != roots.len() || y.len() != roots.len() {
             error!("proof length and roots length mismatch");
             return false;
         }
-
-        for (i,p) in proof.1.iter().enumerate() {
+        // The leaves are ordered by id, starting at 1.
+        y.iter().zip(branches.iter()).zip(roots.iter()).all(|((y, branch), root)| {
             let mut hasher = Sha256::new();
-            hasher.update(y[i].to_be_bytes());
+            hasher.update(y.to_be_bytes());
             let leaf = hasher.finalize().to_vec();
-            match verify_merkle_proof(
-                &leaf,
-                (proof.0,p.clone()),
-                root[i],
-                total_leaves_count
-            ) {
-                Ok(_) => {}
-                Err(_) => {
-                    return false;
-                }
-            }
-        }
-        true
-
+            matches!(verify_merkle_proof(&leaf, (id - 1, branch.clone()), *root, total_leaves_count), Ok(true))
+        })
     }
     pub fn new(
         batch_size: usize,
@@ -97,6 +74,7 @@ impl Shares {
         ids: Vec<(PublicKey, Id)>,
         ft: usize,
         crs: &PQCrs,
+        rng: &mut (impl RngCore + CryptoRng),
     ) -> (Self, Vec<Digest>) {
         let g = crs.g;
         let q = crs.q;
@@ -108,7 +86,7 @@ impl Shares {
         let mut polynomials = Vec::new();
         assert!(batch_size * g <= kappa * n, "batch size too large");
         for _ in 0..(batch_size * g) {
-            polynomials.push(Polynomial::new(ft, q));
+            polynomials.push(Polynomial::new(ft, q, rng));
         }
         let f = generate_f_vector(r, ell, kappa, n, q, polynomials);
         let a = &crs.a;
@@ -135,10 +113,10 @@ impl Shares {
         #[cfg(feature = "eval")]
         let start = Instant::now();
         let chunk_size = ids.len() / 10 + 1;
+        // The shares keep the order of `ids`, which sets the leaves of the merkle trees.
         let mut shares: Vec<_> = ids
-            .chunks(chunk_size)
-            .par_bridge()
-            .flat_map(|chunk| {
+            .par_chunks(chunk_size)
+            .flat_map_iter(|chunk| {
                 chunk
                     .iter()
                     .map(|&(pk, id)| {
@@ -177,7 +155,7 @@ impl Shares {
                         );
                         let share = Share {
                             t: t_vec.clone(),
-                            c: t_vec_hash.clone(),
+                            c: Digest::default(),
                             y_k: u_dvec_2_u_vec(&u, batch_size * g),
                             merkle_proofs: Vec::default(),
                             eval_proof: proof_unit_to_vec(&proof),
@@ -199,7 +177,10 @@ impl Shares {
         }
 
         let (roots, proofs) = generate_merkle_proofs(&shares);
+        // The merkle roots are bound to the commitment, so that the holders agree on them.
+        let c = commitment_digest(&t_vec_hash, &roots);
         for (share, proof) in shares.iter_mut().zip(proofs.into_iter()) {
+            share.0.c = c;
             share.0.merkle_proofs = proof.1;
         }
         (Shares(shares), roots)
@@ -254,7 +235,7 @@ fn hash_u128_vec_to_bytes_vec(inputs: Vec<ZqMod>) -> Vec<Vec<u8>> {
         .collect()
 }
 
-fn hash_c(t: &Vec<ZqMod>) -> Digest {
+pub(crate) fn hash_c(t: &Vec<ZqMod>) -> Digest {
     let mut hasher = Sha256::new();
     for num in t {
         hasher.update(num.to_be_bytes());
@@ -265,6 +246,20 @@ fn hash_c(t: &Vec<ZqMod>) -> Digest {
     Digest(output)
 }
 
+/// The digest `c` of a dealing: it binds the digest of the commitment `t` to the merkle roots of
+/// the evaluations.
+pub(crate) fn commitment_digest(t: &Digest, roots: &[Digest]) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(t.0);
+    for root in roots {
+        hasher.update(root.0);
+    }
+    let result = hasher.finalize();
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&result);
+    Digest(output)
+}
+
 fn transpose_merkle_proofs(matrix: Vec<Vec<(usize, Vec<u8>)>>) -> Vec<(usize, Vec<Vec<u8>>)> {
     if matrix.is_empty() || matrix[0].is_empty() {
         return Vec::new();
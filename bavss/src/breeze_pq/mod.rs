@@ -5,6 +5,7 @@ pub(crate) mod polynomial;
 pub(crate) mod breeze_reconstruct_dealer;
 mod calculation;
 pub(crate) mod breeze;
+pub(crate) mod breeze_complaint;
 pub(crate) mod breeze_confirm;
 pub(crate) mod breeze_message_handler;
 pub(crate) mod breeze_reconstruct;
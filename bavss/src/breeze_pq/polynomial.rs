@@ -1,6 +1,6 @@
 use nalgebra::DVector;
 use crate::breeze_pq::zq_int::ZqInt;
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use model::types_and_const::ZqMod;
 
 #[derive(Debug, Clone)]
@@ -10,10 +10,9 @@ pub struct Polynomial {
 }
 
 impl Polynomial {
-    pub fn new(degree: usize, modulus: ZqMod) -> Self {
+    pub fn new(degree: usize, modulus: ZqMod, rng: &mut (impl RngCore + CryptoRng)) -> Self {
         assert!(modulus > 0, "Modulus must be positive");
 
-        let mut rng = rand::thread_rng();
         let mut coeffs = Vec::with_capacity(degree + 1);
         
 
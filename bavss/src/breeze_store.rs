@@ -0,0 +1,196 @@
+use std::collections::{BTreeSet, HashMap};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crypto::{Digest, PublicKey};
+use model::types_and_const::Epoch;
+use store::Store;
+#[cfg(not(feature = "pq"))]
+use crate::breeze_structs::BreezeMessage;
+#[cfg(feature = "pq")]
+use crate::breeze_structs::Share;
+use crate::breeze_structs::Dealing;
+
+#[cfg(test)]
+#[cfg(not(feature = "pq"))]
+#[path = "tests/breeze_store_tests.rs"]
+pub mod breeze_store_tests;
+
+/// The column family of the primary's store holding the breeze state.
+const BREEZE: &str = "bavss";
+
+const DEALER_SHARES_KEY: &str = "dealer_shares";
+const SHARE_EPOCHS_KEY: &str = "share_epochs";
+const DEALING_EPOCHS_KEY: &str = "dealing_epochs";
+#[cfg(feature = "pq")]
+const MERKLE_EPOCHS_KEY: &str = "merkle_epochs";
+
+/// The share dealt to us by `dealer` in `epoch`, one key per dealer so that storing a share does
+/// not rewrite the others of the epoch.
+fn share_key(epoch: Epoch, dealer: &PublicKey) -> Vec<u8> {
+    let mut key = format!("shares/{}/", epoch).into_bytes();
+    key.extend(bincode::serialize(dealer).expect("Failed to serialize dealer"));
+    key
+}
+
+fn share_dealers_key(epoch: Epoch) -> String {
+    format!("share_dealers/{}", epoch)
+}
+
+fn dealing_key(epoch: Epoch) -> String {
+    format!("dealings/{}", epoch)
+}
+
+#[cfg(feature = "pq")]
+fn dealt_roots_key(epoch: Epoch) -> String {
+    format!("dealt_roots/{}", epoch)
+}
+
+#[cfg(feature = "pq")]
+fn merkle_roots_key(epoch: Epoch) -> String {
+    format!("merkle_roots/{}", epoch)
+}
+
+/// Persists the shares a node dealt and received, so that it can keep serving reconstruction
+/// requests after a restart. All keys live in the `bavss` column family of the primary's store.
+#[derive(Clone)]
+pub(crate) struct BreezeStore {
+    store: Store,
+}
+
+impl BreezeStore {
+    pub fn new(store: Store) -> Self {
+        Self {
+            store: store.family(BREEZE),
+        }
+    }
+
+    async fn read<T: DeserializeOwned>(&mut self, key: impl AsRef<[u8]>) -> Option<T> {
+        self.store
+            .read(key.as_ref().to_vec())
+            .await
+            .expect("Failed to read breeze state from store")
+            .map(|bytes| bincode::deserialize(&bytes).expect("Failed to deserialize breeze state"))
+    }
+
+    async fn write<T: Serialize>(&mut self, key: impl AsRef<[u8]>, value: &T) {
+        let bytes = bincode::serialize(value).expect("Failed to serialize breeze state");
+        self.store.write(key.as_ref().to_vec(), bytes).await;
+    }
+
+    /// Records `item` (an epoch or a dealer) in the index stored under `key`.
+    async fn index<T: Ord + Serialize + DeserializeOwned>(&mut self, key: &str, item: T) {
+        let mut items: BTreeSet<T> = self.read(key).await.unwrap_or_default();
+        if items.insert(item) {
+            self.write(key, &items).await;
+        }
+    }
+
+    /// Reads the shares dealt to us in `epoch`, by dealer.
+    async fn read_shares<T: DeserializeOwned>(&mut self, epoch: Epoch) -> Vec<(PublicKey, T)> {
+        let dealers: BTreeSet<PublicKey> = self.read(share_dealers_key(epoch)).await.unwrap_or_default();
+        let mut shares = Vec::new();
+        for dealer in dealers {
+            if let Some(share) = self.read(share_key(epoch, &dealer)).await {
+                shares.push((dealer, share));
+            }
+        }
+        shares
+    }
+
+    async fn write_share<T: Serialize>(&mut self, epoch: Epoch, dealer: &PublicKey, share: &T) {
+        self.write(share_key(epoch, dealer), share).await;
+        self.index(&share_dealers_key(epoch), *dealer).await;
+        self.index(SHARE_EPOCHS_KEY, epoch).await;
+    }
+
+    pub async fn load_dealer_shares(&mut self) -> HashMap<Epoch, Digest> {
+        self.read(DEALER_SHARES_KEY).await.unwrap_or_default()
+    }
+
+    pub async fn persist_dealer_shares(&mut self, dealer_shares: &HashMap<Epoch, Digest>) {
+        self.write(DEALER_SHARES_KEY, dealer_shares).await;
+    }
+
+    pub async fn load_dealings(&mut self) -> HashMap<Epoch, Dealing> {
+        let epochs: BTreeSet<Epoch> = self.read(DEALING_EPOCHS_KEY).await.unwrap_or_default();
+        let mut dealings = HashMap::new();
+        for epoch in epochs {
+            if let Some(dealing) = self.read(dealing_key(epoch)).await {
+                dealings.insert(epoch, dealing);
+            }
+        }
+        dealings
+    }
+
+    /// Persists the shares we dealt in `epoch`, to send them again after a restart and to reveal
+    /// them if a holder complains.
+    pub async fn persist_dealing(&mut self, epoch: Epoch, dealing: &Dealing) {
+        self.write(dealing_key(epoch), dealing).await;
+        self.index(DEALING_EPOCHS_KEY, epoch).await;
+    }
+
+    #[cfg(not(feature = "pq"))]
+    pub async fn load_shares(&mut self) -> Vec<BreezeMessage> {
+        let epochs: BTreeSet<Epoch> = self.read(SHARE_EPOCHS_KEY).await.unwrap_or_default();
+        let mut shares = Vec::new();
+        for epoch in epochs {
+            let messages = self.read_shares::<BreezeMessage>(epoch).await;
+            shares.extend(messages.into_iter().map(|(_, message)| message));
+        }
+        shares
+    }
+
+    /// Persists the share `message` received for `epoch`.
+    #[cfg(not(feature = "pq"))]
+    pub async fn persist_share(&mut self, epoch: Epoch, message: &BreezeMessage) {
+        self.write_share(epoch, &message.sender, message).await;
+    }
+
+    #[cfg(feature = "pq")]
+    pub async fn load_shares(&mut self) -> HashMap<Epoch, HashMap<PublicKey, Share>> {
+        let epochs: BTreeSet<Epoch> = self.read(SHARE_EPOCHS_KEY).await.unwrap_or_default();
+        let mut shares = HashMap::new();
+        for epoch in epochs {
+            let epoch_shares = self.read_shares(epoch).await;
+            shares.insert(epoch, epoch_shares.into_iter().collect());
+        }
+        shares
+    }
+
+    /// Persists the valid share dealt to us by `dealer` in `epoch`.
+    #[cfg(feature = "pq")]
+    pub async fn persist_share(&mut self, epoch: Epoch, dealer: &PublicKey, share: &Share) {
+        self.write_share(epoch, dealer, share).await;
+    }
+
+    #[cfg(feature = "pq")]
+    pub async fn load_dealt_roots(&mut self, epoch: Epoch) -> Option<Vec<Digest>> {
+        self.read(dealt_roots_key(epoch)).await
+    }
+
+    /// Persists the merkle roots of the shares we dealt in `epoch`, to broadcast them again after
+    /// a restart.
+    #[cfg(feature = "pq")]
+    pub async fn persist_dealt_roots(&mut self, epoch: Epoch, roots: &[Digest]) {
+        self.write(dealt_roots_key(epoch), &roots).await;
+    }
+
+    #[cfg(feature = "pq")]
+    pub async fn load_merkle_roots(&mut self) -> HashMap<Epoch, HashMap<PublicKey, Vec<Digest>>> {
+        let epochs: BTreeSet<Epoch> = self.read(MERKLE_EPOCHS_KEY).await.unwrap_or_default();
+        let mut roots = HashMap::new();
+        for epoch in epochs {
+            if let Some(epoch_roots) = self.read(merkle_roots_key(epoch)).await {
+                roots.insert(epoch, epoch_roots);
+            }
+        }
+        roots
+    }
+
+    /// Persists the merkle roots broadcast by the dealers of `epoch`.
+    #[cfg(feature = "pq")]
+    pub async fn persist_merkle_roots(&mut self, epoch: Epoch, roots: &HashMap<PublicKey, Vec<Digest>>) {
+        self.write(merkle_roots_key(epoch), roots).await;
+        self.index(MERKLE_EPOCHS_KEY, epoch).await;
+    }
+}
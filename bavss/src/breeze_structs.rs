@@ -4,11 +4,20 @@ use curve25519_dalek::{RistrettoPoint, Scalar};
 #[cfg(feature = "pq")]
 use nalgebra::DMatrix;
 use serde::{Deserialize, Serialize};
-use crypto::{Digest, PublicKey, Signature};
+use sha2::{Digest as ShaDigest, Sha256};
+use std::collections::HashMap;
+use std::ops::Range;
+use crypto::{Digest, Hash, PublicKey, Signature};
 #[cfg(feature = "pq")]
 use model::breeze_universal::CommonReferenceString;
 use model::breeze_universal::{BreezeCertificate};
 use model::types_and_const::Epoch;
+#[cfg(not(feature = "pq"))]
+use model::types_and_const::Id;
+#[cfg(not(feature = "pq"))]
+use crate::breeze_origin::breeze_share_dealer::{evaluation_leaf, merkle_leaf_count};
+#[cfg(not(feature = "pq"))]
+use crate::merkletree::verify_merkle_proof;
 
 #[cfg(feature = "pq")]
 use model::types_and_const::ZqMod;
@@ -69,6 +78,8 @@ pub struct Share{
     pub r_hat: Vec<RistrettoPoint>,
     pub r_witness: Vec<WitnessBreeze>,
     pub y_k:Vec<Scalar>,
+    /// The merkle branch of each value of `y_k` under `c`.
+    pub y_witness: Vec<(usize, Vec<u8>)>,
     pub phi_k: PhiElement,
     pub n: usize,
     pub epoch: Epoch,
@@ -89,6 +100,8 @@ pub struct Share{
 pub struct SingleShare{
     pub c: Digest,
     pub y: Scalar,
+    /// The merkle branch of `y` under `c`.
+    pub merkle_branch: (usize, Vec<u8>),
 }
 
 #[cfg(feature = "pq")]
@@ -102,18 +115,27 @@ pub struct SingleShare{
 }
 #[cfg(not(feature = "pq"))]
 impl SingleShare{
-    pub fn verify(&self) -> bool {
-        //TODO: The verification method in original Breeze seems to be unsafe
-        // So we do not implement the method mentioned in Rondo
-        // We just return true here
-        true
+    /// Checks that `y` is the evaluation the dealer committed under `c` for the holder `id`, at
+    /// `index` (starting at 1) of a batch of `batch_size` polynomials dealt to `n` holders.
+    pub fn verify(&self, id: Id, index: usize, batch_size: usize, n: usize) -> bool {
+        if id == 0 || id > n || index == 0 || index > batch_size
+            || self.merkle_branch.0 != evaluation_leaf(batch_size, n, index, id)
+        {
+            return false;
+        }
+        let leaf = self.y.to_bytes().to_vec();
+        matches!(
+            verify_merkle_proof(&leaf, self.merkle_branch.clone(), self.c, merkle_leaf_count(batch_size, n)),
+            Ok(true)
+        )
     }
 }
 #[derive(Clone, Serialize, Deserialize,Debug, PartialEq)]
 pub struct ReconstructShare {
-    pub secrets: Vec<SingleShare>,
+    /// The shares of each index of `indices`, in order.
+    pub secrets: Vec<Vec<SingleShare>>,
     pub epoch: Epoch,
-    pub index: usize
+    pub indices: Range<usize>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WitnessBreeze {
@@ -167,14 +189,49 @@ impl ProofUnit {
     }
 }
 
+/// The digest a dealer signs its share under.
+impl Hash for Share {
+    fn digest(&self) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(self).expect("Failed to serialize share"));
+        Digest(hasher.finalize().into())
+    }
+}
+
+/// The digest an accuser signs its accusation under.
+impl Hash for Accusation {
+    fn digest(&self) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(b"accusation");
+        hasher.update(self.dealer);
+        hasher.update(self.epoch.to_le_bytes());
+        hasher.update(self.c);
+        Digest(hasher.finalize().into())
+    }
+}
+
+/// The digest a holder signs its reconstruction shares under.
+impl Hash for ReconstructShare {
+    fn digest(&self) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(self).expect("Failed to serialize reconstruction shares"));
+        Digest(hasher.finalize().into())
+    }
+}
+
 impl ReconstructShare {
-    pub fn new(secrets:Vec<SingleShare>,epoch:Epoch,index:usize) -> Self {
+    pub fn new(secrets:Vec<Vec<SingleShare>>,epoch:Epoch,indices:Range<usize>) -> Self {
         ReconstructShare {
             secrets,
             epoch,
-            index
+            indices
         }
     }
+
+    /// The shares of each index. Indices beyond the shares provided are skipped.
+    pub fn by_index(&self) -> impl Iterator<Item = (usize, &Vec<SingleShare>)> {
+        self.indices.clone().zip(self.secrets.iter())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,10 +243,16 @@ pub struct MerkleRoots {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum BreezeContent {
-    Share(Share),
+    /// A share of a dealing, signed by the dealer.
+    Share(Share, Signature),
     Merkle(MerkleRoots),
     Reply(ReplyMessage),
-    Reconstruct(ReconstructShare),
+    /// Reconstruction shares, signed by their holder.
+    Reconstruct(ReconstructShare, Signature),
+    /// A holder complains that its share of a dealing is invalid.
+    Accusation(Accusation),
+    /// The dealer answers a complaint by revealing the share of the accuser.
+    Revelation(Revelation),
 }
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct BreezeMessage {
@@ -203,6 +266,27 @@ pub struct ReplyMessage{
     pub signature: Signature,
     pub dealer: PublicKey,
 }
+/// The complaint of a holder against the dealing of `dealer` of digest `c`, signed by the holder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Accusation {
+    pub dealer: PublicKey,
+    pub epoch: Epoch,
+    pub c: Digest,
+    pub signature: Signature,
+}
+
+/// The share the dealer dealt to `accuser`, with the signature it dealt it under. Anyone can check
+/// it against the commitment, the accuser adopts it if it is valid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revelation {
+    pub accuser: PublicKey,
+    pub share: Share,
+    pub signature: Signature,
+}
+
+/// The shares a node dealt in an epoch, by holder, with the signature it dealt each one under.
+pub type Dealing = HashMap<PublicKey, (Share, Signature)>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfirmMessage{
     pub epoch: Epoch,
@@ -210,10 +294,10 @@ pub struct ConfirmMessage{
 }
 
 impl BreezeMessage {
-    pub fn new_share_message(pk: PublicKey, share: Share) -> Self {
+    pub fn new_share_message(pk: PublicKey, share: Share, signature: Signature) -> Self {
         BreezeMessage {
             sender: pk,
-            content: BreezeContent::Share(share),
+            content: BreezeContent::Share(share, signature),
         }
     }
 
@@ -238,15 +322,27 @@ impl BreezeMessage {
             }),
         }
     }
-    pub fn new_reconstruct_message(pk: PublicKey, share: ReconstructShare) -> Self {
+    pub fn new_reconstruct_message(pk: PublicKey, share: ReconstructShare, signature: Signature) -> Self {
         BreezeMessage {
             sender: pk,
-            content: BreezeContent::Reconstruct(share),
+            content: BreezeContent::Reconstruct(share, signature),
+        }
+    }
+    pub fn new_accusation_message(accuser: PublicKey, accusation: Accusation) -> Self {
+        BreezeMessage {
+            sender: accuser,
+            content: BreezeContent::Accusation(accusation),
+        }
+    }
+    pub fn new_revelation_message(dealer: PublicKey, revelation: Revelation) -> Self {
+        BreezeMessage {
+            sender: dealer,
+            content: BreezeContent::Revelation(revelation),
         }
     }
     pub fn get_epoch(&self) -> Option<Epoch> {
         match &self.content {
-            BreezeContent::Share(share) => {
+            BreezeContent::Share(share, _) => {
                 Option::from(share.epoch)
             }
             BreezeContent::Reply(rm) => {
@@ -255,6 +351,12 @@ impl BreezeMessage {
             BreezeContent::Merkle(rm) => {
                 Option::from(rm.epoch)
             }
+            BreezeContent::Accusation(accusation) => {
+                Option::from(accusation.epoch)
+            }
+            BreezeContent::Revelation(revelation) => {
+                Option::from(revelation.share.epoch)
+            }
             _ => None,
         }
     }
@@ -13,12 +13,23 @@ pub use breeze_pq::breeze::Breeze;
 #[cfg(feature = "pq")]
 pub(crate) type Secret = ZqMod;
 mod merkletree;
+mod output_derivation;
+mod reed_solomon;
+mod breeze_store;
+pub mod beacon_proof;
+pub use beacon_proof::{verify_beacon, BeaconProof};
+
+#[cfg(test)]
+#[path = "tests/common.rs"]
+mod common;
+
 #[cfg(feature = "pq")]
 use model::types_and_const::ZqMod;
 
 
 
 mod breeze_structs;
+pub use breeze_structs::{Share, SingleShare, WitnessBreeze};
 #[cfg(feature = "pq")]
 #[cfg(test)]
 mod test {
@@ -70,7 +81,7 @@ mod test {
         let m = r * n * log_q;
         let crs = generate_crs_test(n, kappa, m, q, log_q, r, ell);
         let ids = generate_ids(nodes);
-        let shares = Shares::new(batch_size, 1, ids.clone(), 1, &crs);
+        let shares = Shares::new(batch_size, 1, ids.clone(), 1, &crs, &mut rand::thread_rng());
         let mut size_mb_proof = 0.0;
         let mut size_mb_t = 0.0;
         for share in shares.0.0.iter() {
@@ -0,0 +1,45 @@
+use model::beacon_output::{OutputDerivation, RandomNum};
+use model::types_and_const::Epoch;
+use sha2::Sha256;
+use sha3::digest::{ExtendableOutput, FixedOutput, Update, XofReader};
+use sha3::Shake256;
+
+#[cfg(test)]
+#[path = "tests/output_derivation_tests.rs"]
+pub mod output_derivation_tests;
+
+/// Separates the beacon outputs from any other use of the same hash functions.
+const DOMAIN: &[u8] = b"breeze/beacon-output/v1";
+
+/// Derives the beacon output at `(epoch, index)` (index in the avss batch). `full` is the
+/// 32-byte output of the reconstruction, `secret` the canonical encoding of the secret.
+pub(crate) fn derive_output(
+    derivation: &OutputDerivation,
+    full: [u8; 32],
+    secret: &[u8],
+    epoch: Epoch,
+    index: usize,
+) -> RandomNum {
+    match derivation {
+        OutputDerivation::Truncated => RandomNum(full[..16].to_vec()),
+        OutputDerivation::Full => RandomNum(full.to_vec()),
+        OutputDerivation::Hashed => {
+            let mut hasher = Sha256::default();
+            hasher.update(DOMAIN);
+            hasher.update(epoch.to_le_bytes());
+            hasher.update((index as u64).to_le_bytes());
+            hasher.update(secret);
+            RandomNum(hasher.finalize_fixed().to_vec())
+        }
+        OutputDerivation::Xof { length } => {
+            let mut hasher = Shake256::default();
+            hasher.update(DOMAIN);
+            hasher.update(epoch.to_le_bytes());
+            hasher.update((index as u64).to_le_bytes());
+            hasher.update(secret);
+            let mut output = vec![0u8; *length];
+            hasher.finalize_xof().read(&mut output);
+            RandomNum(output)
+        }
+    }
+}
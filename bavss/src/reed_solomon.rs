@@ -0,0 +1,236 @@
+//! Reed–Solomon decoding of Shamir shares with the Berlekamp–Welch algorithm: given the evaluations
+//! of a polynomial of degree at most `t` at `m` distinct points, it recovers the polynomial as long
+//! as at most `(m - t - 1) / 2` of them are wrong, and tells which ones.
+#[cfg(not(feature = "pq"))]
+use curve25519_dalek::Scalar;
+#[cfg(feature = "pq")]
+use model::types_and_const::ZqMod;
+use model::types_and_const::Id;
+
+#[cfg(test)]
+#[path = "tests/reed_solomon_tests.rs"]
+pub mod reed_solomon_tests;
+
+/// The arithmetic of the prime field the shares live in.
+pub trait PrimeField {
+    type Element: Copy + PartialEq;
+
+    fn zero(&self) -> Self::Element;
+    fn one(&self) -> Self::Element;
+    /// The evaluation point of the holder `id`.
+    fn point(&self, id: Id) -> Self::Element;
+    fn add(&self, a: Self::Element, b: Self::Element) -> Self::Element;
+    fn sub(&self, a: Self::Element, b: Self::Element) -> Self::Element;
+    fn mul(&self, a: Self::Element, b: Self::Element) -> Self::Element;
+    /// The inverse of a non-zero element.
+    fn inv(&self, a: Self::Element) -> Self::Element;
+}
+
+/// The scalar field of Ristretto.
+#[cfg(not(feature = "pq"))]
+pub struct ScalarField;
+
+#[cfg(not(feature = "pq"))]
+impl PrimeField for ScalarField {
+    type Element = Scalar;
+
+    fn zero(&self) -> Scalar {
+        Scalar::ZERO
+    }
+    fn one(&self) -> Scalar {
+        Scalar::ONE
+    }
+    fn point(&self, id: Id) -> Scalar {
+        Scalar::from(id as u64)
+    }
+    fn add(&self, a: Scalar, b: Scalar) -> Scalar {
+        a + b
+    }
+    fn sub(&self, a: Scalar, b: Scalar) -> Scalar {
+        a - b
+    }
+    fn mul(&self, a: Scalar, b: Scalar) -> Scalar {
+        a * b
+    }
+    fn inv(&self, a: Scalar) -> Scalar {
+        a.invert()
+    }
+}
+
+/// The integers modulo the prime `q` of the pq crs.
+#[cfg(feature = "pq")]
+pub struct ZqField {
+    pub q: ZqMod,
+}
+
+#[cfg(feature = "pq")]
+impl PrimeField for ZqField {
+    type Element = ZqMod;
+
+    fn zero(&self) -> ZqMod {
+        0
+    }
+    fn one(&self) -> ZqMod {
+        1 % self.q
+    }
+    fn point(&self, id: Id) -> ZqMod {
+        (id as ZqMod) % self.q
+    }
+    fn add(&self, a: ZqMod, b: ZqMod) -> ZqMod {
+        ((a as u128 + b as u128) % self.q as u128) as ZqMod
+    }
+    fn sub(&self, a: ZqMod, b: ZqMod) -> ZqMod {
+        ((a as u128 + self.q as u128 - b as u128 % self.q as u128) % self.q as u128) as ZqMod
+    }
+    fn mul(&self, a: ZqMod, b: ZqMod) -> ZqMod {
+        ((a as u128 * b as u128) % self.q as u128) as ZqMod
+    }
+    fn inv(&self, a: ZqMod) -> ZqMod {
+        // Fermat's little theorem: a^(q-2) is the inverse of a.
+        let (mut base, mut exp, mut result) = (a % self.q, self.q - 2, self.one());
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+/// A polynomial decoded from a set of shares.
+#[derive(Debug, PartialEq)]
+pub struct Decoded<E> {
+    /// The coefficients of the polynomial, starting with the constant one.
+    pub coefficients: Vec<E>,
+    /// The positions (in the input) of the shares that do not lie on the polynomial.
+    pub errors: Vec<usize>,
+}
+
+/// Decodes the polynomial of degree at most `degree` from the shares `(id, value)` of distinct
+/// holders. Returns `None` if no such polynomial agrees with all but `(m - degree - 1) / 2` of them.
+pub fn decode<F: PrimeField>(
+    field: &F,
+    shares: &[(Id, F::Element)],
+    degree: usize,
+) -> Option<Decoded<F::Element>> {
+    let m = shares.len();
+    if m < degree + 1 {
+        return None;
+    }
+    let max_errors = (m - degree - 1) / 2;
+    let points: Vec<F::Element> = shares.iter().map(|(id, _)| field.point(*id)).collect();
+
+    // Find E (monic, of degree `max_errors`) and Q (of degree `degree + max_errors`) such that
+    // Q(x_i) = y_i E(x_i) at every point. The unknowns are the coefficients of Q followed by the
+    // ones of E but the leading one.
+    let q_len = degree + max_errors + 1;
+    let unknowns = q_len + max_errors;
+    let mut system: Vec<Vec<F::Element>> = shares
+        .iter()
+        .zip(points.iter())
+        .map(|((_, y), x)| {
+            let powers = powers(field, *x, q_len);
+            let mut row = Vec::with_capacity(unknowns + 1);
+            row.extend(powers.iter().copied());
+            row.extend(powers[..max_errors].iter().map(|p| field.sub(field.zero(), field.mul(*y, *p))));
+            row.push(field.mul(*y, powers[max_errors]));
+            row
+        })
+        .collect();
+    let solution = solve(field, &mut system, unknowns)?;
+
+    let q_poly = &solution[..q_len];
+    let mut e_poly = solution[q_len..].to_vec();
+    e_poly.push(field.one());
+    let coefficients = divide(field, q_poly, &e_poly)?;
+
+    let errors: Vec<usize> = shares
+        .iter()
+        .zip(points.iter())
+        .enumerate()
+        .filter(|(_, ((_, y), x))| evaluate(field, &coefficients, **x) != *y)
+        .map(|(i, _)| i)
+        .collect();
+    if errors.len() > max_errors {
+        return None;
+    }
+    Some(Decoded { coefficients, errors })
+}
+
+/// Evaluates the polynomial of `coefficients` (starting with the constant one) at `x`.
+pub fn evaluate<F: PrimeField>(field: &F, coefficients: &[F::Element], x: F::Element) -> F::Element {
+    coefficients
+        .iter()
+        .rev()
+        .fold(field.zero(), |acc, c| field.add(field.mul(acc, x), *c))
+}
+
+/// Returns `1, x, ..., x^(count - 1)`.
+fn powers<F: PrimeField>(field: &F, x: F::Element, count: usize) -> Vec<F::Element> {
+    let mut powers = Vec::with_capacity(count);
+    let mut power = field.one();
+    for _ in 0..count {
+        powers.push(power);
+        power = field.mul(power, x);
+    }
+    powers
+}
+
+/// Solves the linear system of augmented `rows` by Gaussian elimination, setting the free unknowns
+/// to zero. Returns `None` if it has no solution.
+fn solve<F: PrimeField>(field: &F, rows: &mut [Vec<F::Element>], unknowns: usize) -> Option<Vec<F::Element>> {
+    let mut pivots = Vec::new();
+    let mut rank = 0;
+    for column in 0..unknowns {
+        let pivot = match (rank..rows.len()).find(|&i| rows[i][column] != field.zero()) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        rows.swap(rank, pivot);
+        let inverse = field.inv(rows[rank][column]);
+        for value in rows[rank].iter_mut() {
+            *value = field.mul(*value, inverse);
+        }
+        let pivot_row = rows[rank].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            let factor = row[column];
+            if i != rank && factor != field.zero() {
+                for (value, p) in row[column..].iter_mut().zip(pivot_row[column..].iter()) {
+                    *value = field.sub(*value, field.mul(factor, *p));
+                }
+            }
+        }
+        pivots.push(column);
+        rank += 1;
+    }
+
+    // The remaining equations read 0 = constant.
+    if rows[rank..].iter().any(|row| row[unknowns] != field.zero()) {
+        return None;
+    }
+    let mut solution = vec![field.zero(); unknowns];
+    for (row, column) in pivots.into_iter().enumerate() {
+        solution[column] = rows[row][unknowns];
+    }
+    Some(solution)
+}
+
+/// Divides `dividend` by the monic `divisor`. Returns `None` unless the division is exact.
+fn divide<F: PrimeField>(field: &F, dividend: &[F::Element], divisor: &[F::Element]) -> Option<Vec<F::Element>> {
+    let divisor_degree = divisor.len() - 1;
+    let mut remainder = dividend.to_vec();
+    let mut quotient = vec![field.zero(); dividend.len() - divisor_degree];
+    for i in (0..quotient.len()).rev() {
+        let factor = remainder[i + divisor_degree];
+        quotient[i] = factor;
+        for (j, d) in divisor.iter().enumerate() {
+            remainder[i + j] = field.sub(remainder[i + j], field.mul(factor, *d));
+        }
+    }
+    if remainder.iter().any(|r| *r != field.zero()) {
+        return None;
+    }
+    Some(quotient)
+}
@@ -0,0 +1,122 @@
+use super::*;
+use crate::breeze_pq::breeze_share_dealer::hash_c;
+use crate::common::{beacon_proof_tests, certificate, committee, keys};
+use rand::Rng as _;
+
+const BATCH_SIZE: usize = 3;
+/// A prime modulus of 31 bits.
+const Q: u64 = 2_147_483_647;
+
+// Fixture: small lattice parameters, just large enough for the batch.
+fn crs() -> CommonReferenceString {
+    let (n, r, ell, log_q) = (4, 2, 1, 31);
+    let mut rng = rand::thread_rng();
+    CommonReferenceString {
+        a: (0..n).map(|_| (0..r * n * log_q).map(|_| rng.gen_range(0..Q)).collect()).collect(),
+        q: Q,
+        log_q,
+        g: 4,
+        n,
+        kappa: 4,
+        r,
+        ell,
+    }
+}
+
+// Deals `dealers` batches and builds the proof of the beacon at `index` out of the shares of the
+// `holders` (by position in the committee).
+fn proof_with_holders(
+    dealers: usize,
+    index: usize,
+    holders: &[usize],
+) -> (Committee, CommonReferenceString, BeaconProof) {
+    let keys = keys();
+    let committee = committee(&keys);
+    let t = committee.authorities_fault_tolerance();
+    let crs = crs();
+    let pq_crs = PQCrs::from(&crs);
+    let g = crs.g;
+    let idx = (index - 1) * g;
+
+    let mut common_core = BTreeSet::new();
+    let mut dealings = Vec::new();
+    let mut cumulated_output = vec![0; g];
+    for (dealer, _) in keys.iter().take(dealers) {
+        let (shares, roots) = Shares::new(BATCH_SIZE, 1, committee.get_all_ids(), t, &pq_crs, &mut rand::thread_rng());
+        let c = *shares.get_c_ref();
+        common_core.insert(certificate(&keys, dealer, c));
+
+        let single_shares: Vec<_> = holders
+            .iter()
+            .map(|i| {
+                let (share, pk) = &shares.get_shares_ref()[*i];
+                let single_share = SingleShare {
+                    dealer: *dealer,
+                    c,
+                    y: share.y_k[idx..idx + g].to_vec(),
+                    merkle_proof: (i + 1, share.merkle_proofs[idx..idx + g].to_vec()),
+                    total_party_num: share.total_party_num,
+                };
+                (*pk, single_share)
+            })
+            .collect();
+        let ids: Vec<_> = single_shares.iter().map(|(pk, _)| committee.get_id(pk).unwrap()).collect();
+        let values: Vec<_> = single_shares.iter().map(|(_, s)| s.y.clone()).collect();
+        BreezeReconResult::interpolate(&ids, &values, crs.q, &mut cumulated_output);
+
+        dealings.push(DealingProof {
+            c,
+            shares: single_shares,
+            witness: DealingWitness {
+                commitment: hash_c(&shares.get_shares_ref()[0].0.t),
+                merkle_roots: roots,
+            },
+        });
+    }
+
+    let proof = BeaconProof {
+        epoch: 1,
+        index,
+        value: BreezeReconResult::new(cumulated_output).derive_output(&OutputDerivation::Hashed, 1, index),
+        common_core,
+        dealings,
+    };
+    (committee, crs, proof)
+}
+
+// Deals `dealers` batches and builds the proof of the beacon at `index` out of the shares of the
+// first t+1 holders.
+fn proof(dealers: usize, index: usize) -> (Committee, CommonReferenceString, BeaconProof) {
+    proof_with_holders(dealers, index, &[0, 1])
+}
+
+// Fixture
+fn forge_share(proof: &mut BeaconProof) {
+    let y = &mut proof.dealings[0].shares[0].1.y[0];
+    *y = (*y + 1) % Q;
+}
+
+beacon_proof_tests!();
+
+#[test]
+fn verify_shares_of_any_holders() {
+    // The merkle leaves follow the ids of the holders.
+    let (committee, crs, proof) = proof_with_holders(2, 2, &[3, 1, 2]);
+    assert!(verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+}
+
+#[test]
+fn verify_share_of_other_holder() {
+    // The second holder replays the share of the first one.
+    let (committee, crs, mut proof) = proof(2, 2);
+    let share = proof.dealings[0].shares[0].1.clone();
+    proof.dealings[0].shares[1].1 = share;
+    assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+}
+
+#[test]
+fn verify_roots_not_committed() {
+    let (committee, crs, mut proof) = proof(2, 2);
+    proof.dealings[0].witness.merkle_roots.swap(0, 1);
+    assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+}
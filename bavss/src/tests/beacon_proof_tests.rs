@@ -0,0 +1,64 @@
+use super::*;
+use crate::breeze_origin::breeze_share_dealer::Shares;
+use crate::common::{beacon_proof_tests, certificate, committee, crs, keys};
+
+const BATCH_SIZE: usize = 3;
+
+// Deals `dealers` batches and builds the proof of the beacon at `index` out of them.
+fn proof(dealers: usize, index: usize) -> (Committee, CommonReferenceString, BeaconProof) {
+    let keys = keys();
+    let committee = committee(&keys);
+    let t = committee.authorities_fault_tolerance();
+    let crs = crs(t);
+
+    let mut common_core = BTreeSet::new();
+    let mut dealings = Vec::new();
+    let mut cumulated_output = Scalar::ZERO;
+    for (dealer, _) in keys.iter().take(dealers) {
+        let shares = Shares::new(BATCH_SIZE, 1, committee.get_all_ids(), t, &crs, &mut rand::thread_rng());
+        let c = shares.get_c();
+        common_core.insert(certificate(&keys, dealer, c));
+
+        let holders: Vec<_> = shares.0.iter().take(t + 1).collect();
+        let single_shares: Vec<_> = holders
+            .iter()
+            .map(|(share, pk)| {
+                let single_share = SingleShare {
+                    c,
+                    y: share.y_k[index - 1],
+                    merkle_branch: share.y_witness[index - 1].clone(),
+                };
+                (*pk, single_share)
+            })
+            .collect();
+        let ids: Vec<_> = single_shares.iter().map(|(pk, _)| committee.get_id(pk).unwrap()).collect();
+        let values: Vec<_> = single_shares.iter().map(|(_, s)| s.y).collect();
+        let coefficients = BreezeReconResult::lagrange_coefficients_at_zero(&ids);
+        cumulated_output += BreezeReconResult::interpolate_with(&coefficients, &values);
+
+        dealings.push(DealingProof {
+            c,
+            shares: single_shares,
+            witness: DealingWitness {
+                commitment: holders[0].0.r_witness[index - 1].clone(),
+                batch_size: BATCH_SIZE,
+            },
+        });
+    }
+
+    let proof = BeaconProof {
+        epoch: 1,
+        index,
+        value: BreezeReconResult::new(cumulated_output).derive_output(&OutputDerivation::Hashed, 1, index),
+        common_core,
+        dealings,
+    };
+    (committee, crs, proof)
+}
+
+// Fixture
+fn forge_share(proof: &mut BeaconProof) {
+    proof.dealings[0].shares[0].1.y += Scalar::ONE;
+}
+
+beacon_proof_tests!();
@@ -0,0 +1,62 @@
+use super::*;
+use crate::common::{committee, crs, keys};
+use crypto::Digest;
+use curve25519_dalek::Scalar;
+
+const BATCH_SIZE: usize = 3;
+
+#[test]
+fn verify_revealed_shares() {
+    let committee = committee(&keys());
+    let t = committee.authorities_fault_tolerance();
+    let crs = crs(t);
+    let shares = Shares::new(BATCH_SIZE, 1, committee.get_all_ids(), t, &crs, &mut rand::thread_rng());
+    let (share, holder) = shares.0[0].clone();
+    let id = committee.get_id(&holder).unwrap();
+    assert!(verify_share(&committee, &crs, id, &share));
+
+    // The share of a holder is not the share of another one.
+    let other = committee.get_id(&shares.0[1].1).unwrap();
+    assert!(!verify_share(&committee, &crs, other, &share));
+
+    // Nor does an evaluation the dealer did not commit to check.
+    let mut tampered = share.clone();
+    tampered.y_k[0] += Scalar::ONE;
+    assert!(!verify_share(&committee, &crs, id, &tampered));
+
+    // The dealing must be for the whole committee.
+    let mut truncated = share;
+    truncated.n -= 1;
+    assert!(!verify_share(&committee, &crs, id, &truncated));
+}
+
+#[test]
+fn accused_dealings_are_blocked_until_resolved() {
+    let keys = keys();
+    let (first, second) = (keys[0].0, keys[1].0);
+    let c = Digest::default();
+    let mut complaints = Complaints::default();
+    assert!(!complaints.is_blocked(1, &c));
+
+    assert!(complaints.accuse(1, c, first));
+    assert!(!complaints.accuse(1, c, first));
+    assert!(complaints.accuse(1, c, second));
+    assert!(complaints.is_blocked(1, &c));
+    assert!(!complaints.is_blocked(2, &c));
+
+    // Every accuser must get its share revealed.
+    assert!(complaints.resolve(1, c, first));
+    assert!(complaints.is_blocked(1, &c));
+    assert!(complaints.resolve(1, c, second));
+    assert!(!complaints.is_blocked(1, &c));
+
+    // An accusation arriving after the revelation is already answered.
+    assert!(!complaints.accuse(1, c, first));
+    assert!(!complaints.is_blocked(1, &c));
+
+    // An exclusion is final.
+    assert!(complaints.update(&ComplaintStatus::Excluded { epoch: 2, c }));
+    assert!(complaints.is_excluded(2, &c));
+    assert!(!complaints.accuse(2, c, first));
+    assert!(complaints.is_blocked(2, &c));
+}
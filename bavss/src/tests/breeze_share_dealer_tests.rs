@@ -0,0 +1,64 @@
+use super::*;
+use crypto::generate_production_keypair;
+use rand::rngs::OsRng;
+
+const BATCH_SIZE: usize = 3;
+const NODES: usize = 4;
+const FAULT_TOLERANCE: usize = 1;
+
+// Fixture
+fn deal() -> (CommonReferenceString, Shares) {
+    let crs = CommonReferenceString {
+        g: (0..FAULT_TOLERANCE + 1).map(|_| RistrettoPoint::random(&mut OsRng)).collect(),
+        h: RistrettoPoint::random(&mut OsRng),
+    };
+    let ids = (1..=NODES).map(|id| (generate_production_keypair().0, id)).collect();
+    let shares = Shares::new(BATCH_SIZE, 1, ids, FAULT_TOLERANCE, &crs, &mut rand::thread_rng());
+    (crs, shares)
+}
+
+// Fixture
+fn single_share(share: &Share, index: usize) -> SingleShare {
+    SingleShare {
+        c: share.c,
+        y: share.y_k[index - 1],
+        merkle_branch: share.y_witness[index - 1].clone(),
+    }
+}
+
+#[test]
+fn verify_dealt_shares() {
+    let (crs, shares) = deal();
+    for (i, (share, _)) in shares.0.iter().enumerate() {
+        assert!(Shares::verify(&crs, i + 1, FAULT_TOLERANCE, share.clone()));
+        for index in 1..=BATCH_SIZE {
+            assert!(single_share(share, index).verify(i + 1, index, BATCH_SIZE, NODES));
+        }
+    }
+}
+
+#[test]
+fn reject_forged_single_share() {
+    let (_, shares) = deal();
+    let mut forged = single_share(&shares.0[0].0, 2);
+    forged.y += Scalar::ONE;
+    assert!(!forged.verify(1, 2, BATCH_SIZE, NODES));
+}
+
+#[test]
+fn reject_single_share_of_other_holder() {
+    // The evaluation of holder 2 is committed under `c`, but not as the one of holder 1.
+    let (_, shares) = deal();
+    let other = single_share(&shares.0[1].0, 2);
+    assert!(other.verify(2, 2, BATCH_SIZE, NODES));
+    assert!(!other.verify(1, 2, BATCH_SIZE, NODES));
+    assert!(!other.verify(2, 3, BATCH_SIZE, NODES));
+}
+
+#[test]
+fn reject_share_with_uncommitted_evaluation() {
+    let (crs, shares) = deal();
+    let mut share = shares.0[0].0.clone();
+    share.y_k[0] += Scalar::ONE;
+    assert!(!Shares::verify(&crs, 1, FAULT_TOLERANCE, share));
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::breeze_origin::breeze_share_dealer::Shares;
+use crate::breeze_structs::BreezeContent;
+use crypto::{generate_production_keypair, Hash as _, Signature};
+use curve25519_dalek::RistrettoPoint;
+use model::breeze_universal::CommonReferenceString;
+use rand::rngs::OsRng;
+
+// Fixture: the share message `dealer` sends to the first holder of a fresh dealing of `epoch`.
+fn share_message(dealer: PublicKey, epoch: Epoch) -> BreezeMessage {
+    let crs = CommonReferenceString {
+        g: (0..2).map(|_| RistrettoPoint::random(&mut OsRng)).collect(),
+        h: RistrettoPoint::random(&mut OsRng),
+    };
+    let ids = (1..=4).map(|id| (generate_production_keypair().0, id)).collect();
+    let shares = Shares::new(/* batch_size */ 2, epoch, ids, /* fault_tolerance */ 1, &crs, &mut rand::thread_rng());
+    let (share, _) = shares.0[0].clone();
+    BreezeMessage::new_share_message(dealer, share, Signature::default())
+}
+
+// Fixture
+fn digest(message: &BreezeMessage) -> (PublicKey, Epoch, Digest) {
+    match &message.content {
+        BreezeContent::Share(share, _) => (message.sender, share.epoch, share.digest()),
+        _ => panic!("Unexpected message"),
+    }
+}
+
+#[tokio::test]
+async fn persist_shares_by_dealer() {
+    let mut store = BreezeStore::new(Store::in_memory());
+    let (first, _) = generate_production_keypair();
+    let (second, _) = generate_production_keypair();
+    let messages = vec![share_message(first, 1), share_message(second, 1), share_message(first, 2)];
+    for message in &messages {
+        store.persist_share(digest(message).1, message).await;
+    }
+    // Storing a share again does not duplicate it.
+    store.persist_share(1, &messages[0]).await;
+
+    let mut expected: Vec<_> = messages.iter().map(digest).collect();
+    let mut loaded: Vec<_> = store.load_shares().await.iter().map(digest).collect();
+    expected.sort();
+    loaded.sort();
+    assert_eq!(loaded, expected);
+}
+
+#[tokio::test]
+async fn persist_dealing() {
+    let mut store = BreezeStore::new(Store::in_memory());
+    let (dealer, _) = generate_production_keypair();
+    let mut dealing = Dealing::new();
+    if let BreezeContent::Share(share, signature) = share_message(dealer, 3).content {
+        dealing.insert(dealer, (share, signature));
+    }
+    store.persist_dealing(3, &dealing).await;
+
+    // A restarted dealer finds the same shares to send again.
+    let dealings = store.load_dealings().await;
+    assert_eq!(dealings.len(), 1);
+    let (share, _) = &dealings[&3][&dealer];
+    assert_eq!(share.digest(), dealing[&dealer].0.digest());
+}
@@ -0,0 +1,128 @@
+use config::{Authority, Committee, PrimaryAddresses, Weighting};
+use crypto::{generate_production_keypair, Digest, PublicKey, SecretKey, Signature};
+use model::breeze_universal::BreezeCertificate;
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(not(feature = "pq"))]
+use curve25519_dalek::RistrettoPoint;
+#[cfg(not(feature = "pq"))]
+use model::breeze_universal::CommonReferenceString;
+#[cfg(not(feature = "pq"))]
+use rand::rngs::OsRng;
+
+// Fixture
+pub fn keys() -> Vec<(PublicKey, SecretKey)> {
+    (0..4).map(|_| generate_production_keypair()).collect()
+}
+
+// Fixture
+pub fn committee(keys: &[(PublicKey, SecretKey)]) -> Committee {
+    Committee {
+        authorities: keys
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let primary = PrimaryAddresses {
+                    primary_to_primary: format!("127.0.0.1:{}", 100 + i).parse().unwrap(),
+                    worker_to_primary: format!("127.0.0.1:{}", 200 + i).parse().unwrap(),
+                    breeze_addr: format!("127.0.0.1:{}", 600 + i).parse().unwrap(),
+                    init_bft_addr: format!("127.0.0.1:{}", 700 + i).parse().unwrap(),
+                    beacon_api_addr: Some(format!("127.0.0.1:{}", 800 + i).parse().unwrap()),
+                    evidence_api_addr: Some(format!("127.0.0.1:{}", 900 + i).parse().unwrap()),
+                    admin_api_addr: None,
+                };
+                let authority = Authority {
+                    stake: 1,
+                    primary,
+                    workers: BTreeMap::new(),
+                };
+                (*name, authority)
+            })
+            .collect(),
+        weighting: Weighting::Count,
+    }
+}
+
+// Fixture: random generators for polynomials of degree `t`.
+#[cfg(not(feature = "pq"))]
+pub fn crs(t: usize) -> CommonReferenceString {
+    CommonReferenceString {
+        g: (0..t + 1).map(|_| RistrettoPoint::random(&mut OsRng)).collect(),
+        h: RistrettoPoint::random(&mut OsRng),
+    }
+}
+
+// Fixture: the certificate of the dealing `c` of `dealer`, signed by all of `keys`.
+pub fn certificate(keys: &[(PublicKey, SecretKey)], dealer: &PublicKey, c: Digest) -> BreezeCertificate {
+    let mut certificate = BreezeCertificate {
+        c,
+        dealer: *dealer,
+        epoch: 1,
+        certificates: BTreeSet::new(),
+    };
+    let digest = BreezeCertificate::signed_digest(&c, dealer);
+    for (name, secret) in keys {
+        certificate.insert(*name, Signature::new(&digest, secret));
+    }
+    certificate
+}
+
+/// Expands the tests of `verify_beacon` that hold for both fields. The invoking module provides
+/// `proof(dealers, index)`, dealing `dealers` batches and proving the beacon at `index`, and
+/// `forge_share(&mut BeaconProof)`, tampering with the value of one share.
+macro_rules! beacon_proof_tests {
+    () => {
+        #[test]
+        fn verify_valid_beacon() {
+            let (committee, crs, proof) = proof(2, 2);
+            assert!(verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+
+        #[test]
+        fn verify_wrong_value() {
+            let (committee, crs, mut proof) = proof(2, 2);
+            proof.value.0[0] ^= 1;
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+
+        #[test]
+        fn verify_forged_share() {
+            let (committee, crs, mut proof) = proof(2, 2);
+            forge_share(&mut proof);
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+
+        #[test]
+        fn verify_commitment_of_other_index() {
+            let (committee, crs, mut proof) = proof(2, 2);
+            proof.index = 3;
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+
+        #[test]
+        fn verify_too_small_common_core() {
+            let (committee, crs, proof) = proof(1, 2);
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+
+        #[test]
+        fn verify_other_derivation() {
+            let (committee, crs, proof) = proof(2, 2);
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Full, &proof));
+        }
+
+        #[test]
+        fn verify_stake_weighted_common_core() {
+            // The two dealers are enough authorities, but not f+1 of the stake.
+            let (mut committee, crs, proof) = proof(2, 2);
+            let dealers: Vec<_> = proof.common_core.iter().map(|cer| cer.dealer).collect();
+            let other = *committee.authorities.keys().find(|name| !dealers.contains(name)).unwrap();
+            committee.authorities.get_mut(&other).unwrap().stake = 5;
+            committee.weighting = config::Weighting::Stake;
+            assert!(!verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+
+            committee.authorities.get_mut(&dealers[0]).unwrap().stake = 5;
+            assert!(verify_beacon(&committee.clone().into(), &crs, &OutputDerivation::Hashed, &proof));
+        }
+    };
+}
+pub(crate) use beacon_proof_tests;
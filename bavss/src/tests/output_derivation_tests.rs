@@ -0,0 +1,33 @@
+use super::*;
+
+const EPOCH: Epoch = 3;
+const INDEX: usize = 7;
+
+#[test]
+fn truncated_and_full_outputs() {
+    let full = [42u8; 32];
+    let truncated = derive_output(&OutputDerivation::Truncated, full, &full, EPOCH, INDEX);
+    assert_eq!(truncated.0, full[..16].to_vec());
+    let output = derive_output(&OutputDerivation::Full, full, &full, EPOCH, INDEX);
+    assert_eq!(output.0, full.to_vec());
+}
+
+#[test]
+fn hashed_outputs_bind_epoch_and_index() {
+    let full = [42u8; 32];
+    let output = derive_output(&OutputDerivation::Hashed, full, &full, EPOCH, INDEX);
+    assert_eq!(output.0.len(), 32);
+    assert_ne!(output, derive_output(&OutputDerivation::Hashed, full, &full, EPOCH + 1, INDEX));
+    assert_ne!(output, derive_output(&OutputDerivation::Hashed, full, &full, EPOCH, INDEX + 1));
+}
+
+#[test]
+fn xof_outputs_any_length() {
+    let full = [42u8; 32];
+    let short = derive_output(&OutputDerivation::Xof { length: 16 }, full, &full, EPOCH, INDEX);
+    let long = derive_output(&OutputDerivation::Xof { length: 1_000 }, full, &full, EPOCH, INDEX);
+    assert_eq!(short.0.len(), 16);
+    assert_eq!(long.0.len(), 1_000);
+    // The stream is the same, only longer.
+    assert_eq!(short.0[..], long.0[..16]);
+}
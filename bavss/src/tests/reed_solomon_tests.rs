@@ -0,0 +1,70 @@
+use super::*;
+
+// Fixture
+#[cfg(not(feature = "pq"))]
+fn field() -> ScalarField {
+    ScalarField
+}
+
+// Fixture
+#[cfg(feature = "pq")]
+fn field() -> ZqField {
+    ZqField { q: 2_147_483_647 }
+}
+
+// Fixture: the polynomial 7 + 3x + 11x^2.
+fn polynomial<F: PrimeField>(field: &F) -> Vec<F::Element> {
+    vec![field.point(7), field.point(3), field.point(11)]
+}
+
+// Fixture: the shares of `coefficients` at the holders 1..=n.
+fn shares<F: PrimeField>(field: &F, coefficients: &[F::Element], n: usize) -> Vec<(Id, F::Element)> {
+    (1..=n)
+        .map(|id| (id, evaluate(field, coefficients, field.point(id))))
+        .collect()
+}
+
+// Fixture
+fn corrupt<F: PrimeField>(field: &F, shares: &mut [(Id, F::Element)], positions: &[usize]) {
+    for position in positions {
+        let (_, value) = &mut shares[*position];
+        *value = field.add(*value, field.one());
+    }
+}
+
+#[test]
+fn decode_without_errors() {
+    let field = field();
+    let coefficients = polynomial(&field);
+    let shares = shares(&field, &coefficients, 7);
+    let decoded = decode(&field, &shares, 2).unwrap();
+    assert!(decoded.coefficients == coefficients);
+    assert!(decoded.errors.is_empty());
+}
+
+#[test]
+fn decode_with_errors() {
+    // 3t+1 shares correct up to t errors.
+    let field = field();
+    let coefficients = polynomial(&field);
+    let mut shares = shares(&field, &coefficients, 7);
+    corrupt(&field, &mut shares, &[1, 4]);
+    let decoded = decode(&field, &shares, 2).unwrap();
+    assert!(decoded.coefficients == coefficients);
+    assert_eq!(decoded.errors, vec![1, 4]);
+}
+
+#[test]
+fn decode_too_many_errors() {
+    let field = field();
+    let mut shares = shares(&field, &polynomial(&field), 6);
+    corrupt(&field, &mut shares, &[0, 2]);
+    assert!(decode(&field, &shares, 2).is_none());
+}
+
+#[test]
+fn decode_too_few_shares() {
+    let field = field();
+    let shares = shares(&field, &polynomial(&field), 2);
+    assert!(decode(&field, &shares, 2).is_none());
+}
@@ -23,10 +23,9 @@ class CommandMaker:
         return f'rm -r {PathMaker.logs_path()} ; mkdir -p {PathMaker.logs_path()}'
 
     @staticmethod
-    def compile(protocol='tusk', crypto='origin'):
-        protocol = '' if protocol == 'tusk' else protocol
+    def compile(crypto='origin'):
         crypto = '' if crypto == 'origin' else crypto
-        return f'cargo build --quiet --release --features "benchmark {crypto} {protocol}"'
+        return f'cargo build --quiet --release --features "benchmark {crypto}"'
 
     @staticmethod
     def compile_gen_files():
@@ -48,15 +47,22 @@ class CommandMaker:
         return f'./gen_files generate_crs --n {n} --log_q {log_q} --g {g} --kappa {kappa} --r {r} --ell {ell}'
 
     @staticmethod
-    def run_primary(keys, committee, store, crs, parameters, avss_batch_size, leader_per_epoch, debug=False):
+    def generate_coin(faults, key_files):
+        assert isinstance(key_files, list)
+        return f'./gen_files generate_coin --fault_tolerance {faults} --keys {" ".join(key_files)}'
+
+    @staticmethod
+    def run_primary(keys, committee, store, crs, coin, parameters, avss_batch_size, leader_per_epoch, debug=False):
         assert isinstance(keys, str)
         assert isinstance(committee, str)
         assert isinstance(parameters, str)
         assert isinstance(crs, str)
+        assert isinstance(coin, str)
         assert isinstance(debug, bool)
         v = '-vvv' if debug else '-vv'
         return (f'./node {v} run --keys {keys} --committee {committee} '
-                f'--store {store} --parameters {parameters} primary --crs {crs} --bs {avss_batch_size} --le {leader_per_epoch}')
+                f'--store {store} --parameters {parameters} primary --crs {crs} --coin {coin} '
+                f'--bs {avss_batch_size} --le {leader_per_epoch}')
 
     @staticmethod
     def run_worker(keys, committee, store, parameters, id, debug=False):
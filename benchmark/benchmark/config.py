@@ -69,8 +69,11 @@ class Committee:
                 'worker_to_primary': f'{host}:{port + 1}',
                 'breeze_addr': f'{host}:{port+2}',
                 'init_bft_addr': f'{host}:{port + 3}',
+                'beacon_api_addr': f'{host}:{port + 4}',
+                'evidence_api_addr': f'{host}:{port + 5}',
+                'admin_api_addr': f'{host}:{port + 6}',
             }
-            port += 4
+            port += 7
 
             workers_addr = OrderedDict()
             for j, host in enumerate(hosts):
@@ -225,7 +228,7 @@ class BenchParameters:
 
             if 'protocol' not in json:
                 self.protocol = 'tusk'
-            elif json['protocol'] == 'tusk' or json['protocol'] == 'dolphin':
+            elif json['protocol'] in ['tusk', 'dolphin', 'bullshark']:
                 self.protocol = json['protocol']
             else:
                 protocol = json['protocol']
@@ -17,6 +17,8 @@ class LocalBench:
         try:
             self.bench_parameters = BenchParameters(bench_parameters_dict)
             self.node_parameters = NodeParameters(node_parameters_dict)
+            # The nodes select the consensus protocol at runtime.
+            self.node_parameters.json['protocol'] = self.bench_parameters.protocol
         except ConfigError as e:
             raise BenchError('Invalid nodes or bench parameters', e)
 
@@ -53,7 +55,7 @@ class LocalBench:
             sleep(0.5)  # Removing the store may take time.
 
             # Recompile the latest code.
-            cmd = CommandMaker.compile(self.protocol, self.crypto)
+            cmd = CommandMaker.compile(self.crypto)
             subprocess.run(
                 [cmd], shell=True, check=True, cwd=PathMaker.node_crate_path()
             )
@@ -95,6 +97,10 @@ class LocalBench:
                 cmd = CommandMaker.generate_crs(fault_tolerance).split()
                 subprocess.run(cmd, check=True)
 
+            # Deal the keys of the threshold coin of the secondary BFT.
+            fault_tolerance = (nodes - 1) // 3
+            cmd = CommandMaker.generate_coin(fault_tolerance, key_files).split()
+            subprocess.run(cmd, check=True)
 
             self.node_parameters.print(PathMaker.parameters_file())
 
@@ -137,6 +143,7 @@ class LocalBench:
                     PathMaker.committee_file(),
                     PathMaker.db_path(i),
                     PathMaker.crs_file(),
+                    PathMaker.coin_file(i),
                     PathMaker.parameters_file(),
                     self.avss_batch_size,
                     self.leader_per_epoch,
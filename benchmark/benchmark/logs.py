@@ -144,14 +144,14 @@ class LogParser:
 
         ip = search(r'booted on (\d+.\d+.\d+.\d+)', log).group(1)
         # add beacon
-        beacon_pattern = r'\[(.*Z) .* Beacon output for epoch:(\d+) index:(\d+) is (\d+)'
+        beacon_pattern = r'\[(.*Z) .* Beacon output for epoch:(\d+) index:(\d+) is ([0-9a-f]+)'
         tmp = findall(beacon_pattern, log)
         beacons = [
             {
                 'timestamp': self._to_posix(t),
                 'epoch': int(e),
                 'index': int(i),
-                'value': int(v)
+                'value': v
             }
             for t, e, i, v in tmp
         ]
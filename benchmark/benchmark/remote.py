@@ -140,7 +140,7 @@ class Bench:
         output = c.run(cmd, hide=True)
         self._check_stderr(output)
 
-    def _update(self, hosts, collocate, protocol, crypto):
+    def _update(self, hosts, collocate, crypto):
         if collocate:
             ips = list(set(hosts))
         else:
@@ -185,7 +185,7 @@ class Bench:
         try:
             # Compile locally
             Print.info('Compiling locally in ~/narwhal/node...')
-            compile_cmd = CommandMaker.compile(protocol, crypto)
+            compile_cmd = CommandMaker.compile(crypto)
             subprocess.run(
                 [compile_cmd], shell=True, check=True, cwd=node_path
             )
@@ -292,6 +292,11 @@ class Bench:
                 cmd = CommandMaker.generate_crs(fault_tolerance).split()
                 subprocess.run(cmd, check=True)
 
+        # Deal the keys of the threshold coin of the secondary BFT.
+        fault_tolerance = (len(hosts) - 1) // 3
+        cmd = CommandMaker.generate_coin(fault_tolerance, key_files).split()
+        subprocess.run(cmd, check=True)
+
         node_parameters.print(PathMaker.parameters_file())
 
         # Cleanup all nodes and upload configuration files in parallel.
@@ -307,6 +312,7 @@ class Bench:
                         c.run(f'{CommandMaker.cleanup_exp_crs()} || true', hide=True)
                     c.put(PathMaker.committee_file(), '.')
                     c.put(PathMaker.key_file(i), '.')
+                    c.put(PathMaker.coin_file(i), '.')
                     c.put(PathMaker.parameters_file(), '.')
                     if update_crs:
                         c.put(PathMaker.crs_file(), '.')
@@ -380,6 +386,7 @@ class Bench:
                 PathMaker.committee_file(),
                 PathMaker.db_path(i),
                 PathMaker.crs_file(),
+                PathMaker.coin_file(i),
                 PathMaker.parameters_file(),
                 bench_parameters.avss_batch_size,
                 bench_parameters.leader_per_epoch,
@@ -489,6 +496,8 @@ class Bench:
         try:
             bench_parameters = BenchParameters(bench_parameters_dict)
             node_parameters = NodeParameters(node_parameters_dict)
+            # The nodes select the consensus protocol at runtime.
+            node_parameters.json['protocol'] = bench_parameters.protocol
         except ConfigError as e:
             raise BenchError('Invalid nodes or bench parameters', e)
 
@@ -504,7 +513,6 @@ class Bench:
                 self._update(
                     selected_hosts,
                     bench_parameters.collocate,
-                    bench_parameters.protocol,
                     bench_parameters.crypto
                 )
             except (GroupException, ExecutionError) as e:
@@ -27,6 +27,11 @@ class PathMaker:
     def crs_file():
         return '.crs.json'
 
+    @staticmethod
+    def coin_file(i):
+        assert isinstance(i, int) and i >= 0
+        return f'.coin-{i}.json'
+
     @staticmethod
     def parameters_file():
         return '.parameters.json'
@@ -72,6 +77,8 @@ class PathMaker:
     def result_file(faults, nodes, workers, collocate, rate, tx_size, protocol ,crypto, test_beacon):
         if protocol == 'dolphin':
             protocol = 'bullshark'
+        elif protocol == 'bullshark':
+            protocol = 'bullshark_psync'
         if crypto != 'pq':
             crypto = 'npq'
         return join(
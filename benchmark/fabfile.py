@@ -19,7 +19,7 @@ def local(ctx, debug=False):
         'rate': 50_000,
         'tx_size': 512,
         'duration': 20,
-        'protocol': 'dolphin', # dolphin or tusk
+        'protocol': 'dolphin', # dolphin, bullshark or tusk
         'crypto': 'origin', # origin or post_quantum (need extra params)
         'avss_batch_size': 256,
         'leader_per_epoch': 40
@@ -220,7 +220,7 @@ def plot(ctx):
         'workers': [1],
         'collocate': True,
         'tx_size': 512,
-        'protocol': ['bullshark', 'tusk'],
+        'protocol': ['dolphin', 'tusk'],
         'crypto': ['pq', 'npq'],
         'rate': 200000,
         'eval_beacon': True  # Set to False to skip Beacon plots
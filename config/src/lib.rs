@@ -1,11 +1,13 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crypto::{generate_production_keypair, PublicKey, SecretKey};
-use log::info;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use model::beacon_output::OutputDerivation;
 use model::file_io::*;
-use model::types_and_const::{Id, Stake, WorkerId};
+use model::types_and_const::{round_epoch, Epoch, Id, Round, Stake, WorkerId};
 
 #[derive(Deserialize, Clone)]
 pub struct Parameters {
@@ -17,6 +19,11 @@ pub struct Parameters {
     pub max_header_delay: u64,
     /// The depth of the garbage collection (Denominated in number of rounds).
     pub gc_depth: u64,
+    /// How many rounds the primary and workers keep in store beyond the garbage collection depth.
+    /// Older certificates, payload digests and batches are deleted (Denominated in number of rounds).
+    /// The store is never pruned if unset.
+    #[serde(default)]
+    pub store_retention: Option<u64>,
     /// The delay after which the synchronizer retries to send sync requests. Denominated in ms.
     pub sync_retry_delay: u64,
     /// Determine with how many nodes to sync when re-trying to send sync-request. These nodes
@@ -28,14 +35,40 @@ pub struct Parameters {
     /// The delay after which the workers seal a batch of transactions, even if `max_batch_size`
     /// is not reached. Denominated in ms.
     pub max_batch_delay: u64,
-    #[cfg(feature = "dolphin")]
-    /// The leader timeout value. Denominated in ms.
+    /// The consensus protocol ordering the dag.
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// The leader timeout value (only used by Dolphin). Denominated in ms.
+    #[serde(default = "Parameters::default_timeout")]
     pub timeout: u64,
+    /// Whether Bullshark schedules its leaders by reputation, rather than in turn.
+    #[serde(default)]
+    pub leader_reputation: bool,
+    /// How many commits Tusk and Bullshark leave a leader whose round was skipped (e.g. because it
+    /// crashed) out of the leaders elected by the global coin. Zero disables the exclusion.
+    #[serde(default)]
+    pub leader_exclusion: usize,
     
     pub beacon_req_delay: u64,
     pub breeze_epoch_limit: u64,
     
     pub eval_beacon: bool,
+    /// How the beacon outputs are derived from the reconstructed secrets.
+    #[serde(default)]
+    pub beacon_output: OutputDerivation,
+    /// Whether to reconstruct the beacons from shares carrying 2f+1 of the weight with error
+    /// correction (reporting the holders of inconsistent shares), rather than from the first f+1
+    /// shares.
+    #[serde(default)]
+    pub robust_reconstruction: bool,
+    /// The delay after which the consensus asks the coordinator again for a global coin it did not
+    /// receive. Denominated in ms.
+    #[serde(default = "Parameters::default_coin_timeout")]
+    pub coin_timeout: u64,
+    /// How many leader coins the coordinator reconstructs ahead of the consensus, once their common
+    /// core is decided. Revealing a coin early reveals its leader early too.
+    #[serde(default = "Parameters::default_coin_lookahead")]
+    pub coin_lookahead: usize,
 }
 
 impl Default for Parameters {
@@ -44,37 +77,86 @@ impl Default for Parameters {
             header_size: 1_000,
             max_header_delay: 100,
             gc_depth: 50,
+            store_retention: None,
             sync_retry_delay: 5_000,
             sync_retry_nodes: 3,
             batch_size: 500_000,
             max_batch_delay: 100,
-            #[cfg(feature = "dolphin")]
-            timeout: 5_000,
+            protocol: Protocol::default(),
+            timeout: Self::default_timeout(),
+            leader_reputation: false,
+            leader_exclusion: 0,
             beacon_req_delay: 0,
             breeze_epoch_limit: 20,
             eval_beacon: true,
+            beacon_output: OutputDerivation::default(),
+            robust_reconstruction: false,
+            coin_timeout: Self::default_coin_timeout(),
+            coin_lookahead: Self::default_coin_lookahead(),
         }
     }
 }
 
-impl Import for Parameters {}
+impl Import for Parameters {
+    fn validate(&self) -> Result<(), String> {
+        if !self.beacon_output.is_valid() {
+            return Err(format!(
+                "beacon output {:?} is not {} to {} bytes long",
+                self.beacon_output,
+                OutputDerivation::MIN_XOF_LENGTH,
+                OutputDerivation::MAX_XOF_LENGTH
+            ));
+        }
+        Ok(())
+    }
+}
 
 impl Parameters {
+    fn default_timeout() -> u64 {
+        5_000
+    }
+
+    fn default_coin_timeout() -> u64 {
+        5_000
+    }
+
+    fn default_coin_lookahead() -> usize {
+        2
+    }
+
     pub fn log(&self) {
         info!("Header size set to {} B", self.header_size);
         info!("Max header delay set to {} ms", self.max_header_delay);
         info!("Garbage collection depth set to {} rounds", self.gc_depth);
+        match self.store_retention {
+            Some(retention) => info!("Store retention set to {} rounds", retention),
+            None => info!("Store pruning disabled"),
+        }
         info!("Sync retry delay set to {} ms", self.sync_retry_delay);
         info!("Sync retry nodes set to {} nodes", self.sync_retry_nodes);
         info!("Batch size set to {} B", self.batch_size);
         info!("Max batch delay set to {} ms", self.max_batch_delay);
-        #[cfg(feature = "dolphin")]
-        info!("Leader timeout set to {} ms", self.timeout);
+        info!("Consensus protocol set to {:?}", self.protocol);
+        match self.protocol {
+            Protocol::Dolphin => info!("Leader timeout set to {} ms", self.timeout),
+            Protocol::Bullshark => info!("Leader reputation set to {}", self.leader_reputation),
+            Protocol::Tusk => (),
+        }
+        if self.protocol != Protocol::Dolphin {
+            info!("Leader exclusion set to {} commits", self.leader_exclusion);
+        }
         info!("Beacon request delay set to {} ms", self.beacon_req_delay);
+        info!("Beacon output derivation set to {:?}", self.beacon_output);
+        if !self.beacon_output.is_valid() {
+            warn!("Beacon output derivation {:?} has an unsupported length", self.beacon_output);
+        }
+        info!("Robust reconstruction set to {}", self.robust_reconstruction);
+        info!("Global coin timeout set to {} ms", self.coin_timeout);
+        info!("Global coin lookahead set to {} coins", self.coin_lookahead);
     }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct PrimaryAddresses {
     /// Address to receive messages from other primaries (WAN).
     pub primary_to_primary: SocketAddr,
@@ -83,9 +165,20 @@ pub struct PrimaryAddresses {
 
     pub breeze_addr: SocketAddr,
     pub init_bft_addr: SocketAddr,
+    /// Address to serve the beacon output to applications (the beacon is not served if absent).
+    #[serde(default)]
+    pub beacon_api_addr: Option<SocketAddr>,
+    /// Address to serve the evidence of misbehaviour to operators (the evidence is only recorded if
+    /// absent).
+    #[serde(default)]
+    pub evidence_api_addr: Option<SocketAddr>,
+    /// Port to receive the commands of our operator (reconfigurations and key rotations) on the
+    /// loopback interface only (the commands are disabled if absent).
+    #[serde(default)]
+    pub admin_api_addr: Option<SocketAddr>,
 }
 
-#[derive(Clone, Deserialize, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
 pub struct WorkerAddresses {
     /// Address to receive client transactions (WAN).
     pub transactions: SocketAddr,
@@ -95,19 +188,47 @@ pub struct WorkerAddresses {
     pub primary_to_worker: SocketAddr,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Authority {
     /// The voting power of this authority.
     pub stake: Stake,
     /// The network addresses of the primary.
     pub primary: PrimaryAddresses,
     /// Map of workers' id and their network addresses.
-    pub workers: HashMap<WorkerId, WorkerAddresses>,
+    pub workers: BTreeMap<WorkerId, WorkerAddresses>,
+}
+
+/// The consensus protocol ordering the dag, which every node of a committee must run.
+#[derive(Clone, Copy, Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tusk,
+    /// The asynchronous version of Bullshark.
+    Dolphin,
+    /// The partially-synchronous version of Bullshark.
+    Bullshark,
 }
 
-#[derive(Clone, Deserialize)]
+/// How the beacon and the secondary BFT weigh the authorities when counting quorums. The DAG
+/// layer always counts stake.
+#[derive(Clone, Copy, Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    /// Every authority counts for one.
+    #[default]
+    Count,
+    /// Every authority counts for its stake. The threshold coin of the secondary BFT is dealt by
+    /// count, so the committee of epoch 0 cannot be weighted by stake.
+    Stake,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Committee {
     pub authorities: BTreeMap<PublicKey, Authority>,
+    /// The weighting of the beacon and secondary BFT quorums.
+    #[serde(default)]
+    pub weighting: Weighting,
 }
 
 impl Import for Committee {}
@@ -158,6 +279,66 @@ impl Committee {
         (total_votes + 2) / 3
     }
 
+    /// Returns the weight of a specific authority in the beacon and the secondary BFT (0 if it is
+    /// not a member of the committee).
+    pub fn weight(&self, name: &PublicKey) -> Stake {
+        match self.weighting {
+            Weighting::Count => Stake::from(self.authorities.contains_key(name)),
+            Weighting::Stake => self.stake(name),
+        }
+    }
+
+    /// Returns the total weight of a set of authorities, each one counted once.
+    pub fn weight_of<'a, I>(&self, names: I) -> Stake
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let names: BTreeSet<_> = names.into_iter().collect();
+        names.into_iter().map(|name| self.weight(name)).sum()
+    }
+
+    /// Returns the weight required to reach a quorum (2f+1) in the beacon and the secondary BFT.
+    pub fn weighted_quorum_threshold(&self) -> Stake {
+        match self.weighting {
+            Weighting::Count => self.authorities_quorum_threshold() as Stake,
+            Weighting::Stake => self.quorum_threshold(),
+        }
+    }
+
+    /// Returns the weight required to reach availability (f+1) in the beacon and the secondary BFT.
+    pub fn weighted_validity_threshold(&self) -> Stake {
+        match self.weighting {
+            Weighting::Count => self.authorities_fault_tolerance() as Stake + 1,
+            Weighting::Stake => self.validity_threshold(),
+        }
+    }
+
+    /// Returns true if the shares of `holders` are enough to reconstruct a secret: they must
+    /// carry f+1 of the weight and be at least f+1 points of the degree-f polynomial.
+    pub fn can_reconstruct<'a, I>(&self, holders: I) -> bool
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let holders: BTreeSet<_> = holders.into_iter().collect();
+        holders.len() > self.authorities_fault_tolerance()
+            && self.weight_of(holders) >= self.weighted_validity_threshold()
+    }
+
+    /// Returns true if the shares of `holders` are enough to reconstruct a secret despite f wrong
+    /// ones: they must carry 2f+1 of the weight and be at least f+1 points of the degree-f
+    /// polynomial.
+    pub fn can_reconstruct_robustly<'a, I>(&self, holders: I) -> bool
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let holders: BTreeSet<_> = holders.into_iter().collect();
+        let threshold = match self.weighting {
+            Weighting::Count => 2 * self.authorities_fault_tolerance() as Stake + 1,
+            Weighting::Stake => self.quorum_threshold(),
+        };
+        holders.len() > self.authorities_fault_tolerance() && self.weight_of(holders) >= threshold
+    }
+
     pub fn get_id(&self, key: &PublicKey) -> Option<Id> {
         self.authorities
             .keys()
@@ -196,6 +377,30 @@ impl Committee {
             .ok_or_else(|| ConfigError::NotInCommittee(*to))
     }
 
+    /// Returns the address of the public beacon service of the target primary (if it has one).
+    pub fn beacon_api_address(&self, to: &PublicKey) -> Result<Option<SocketAddr>, ConfigError> {
+        self.authorities
+            .get(to)
+            .map(|x| x.primary.beacon_api_addr)
+            .ok_or_else(|| ConfigError::NotInCommittee(*to))
+    }
+
+    /// Returns the address of the evidence service of the target primary (if it has one).
+    pub fn evidence_api_address(&self, to: &PublicKey) -> Result<Option<SocketAddr>, ConfigError> {
+        self.authorities
+            .get(to)
+            .map(|x| x.primary.evidence_api_addr)
+            .ok_or_else(|| ConfigError::NotInCommittee(*to))
+    }
+
+    /// Returns the address of the operator commands of the target primary (if it takes any).
+    pub fn admin_api_address(&self, to: &PublicKey) -> Result<Option<SocketAddr>, ConfigError> {
+        self.authorities
+            .get(to)
+            .map(|x| x.primary.admin_api_addr)
+            .ok_or_else(|| ConfigError::NotInCommittee(*to))
+    }
+
     /// Returns the addresses of all primaries except `myself`.
     pub fn others_primaries(&self, myself: &PublicKey) -> Vec<(PublicKey, PrimaryAddresses)> {
         self.authorities
@@ -267,6 +472,114 @@ impl Committee {
     }
 }
 
+/// A committee taking over from a Breeze epoch on. Authorities propose it in their headers, and it
+/// is installed once authorities holding f+1 stake proposed it in committed headers. Authorities
+/// leaving the committee hold shares of the epochs before it, so they must keep running until the
+/// beacons of those epochs are reconstructed.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Reconfiguration {
+    /// The first epoch of the new committee, for the beacon as well as for the DAG.
+    pub epoch: Epoch,
+    pub committee: Committee,
+}
+
+impl Import for Reconfiguration {}
+impl Export for Reconfiguration {}
+
+/// The committees of the successive epochs, shared by all the tasks of a node. A committee stays in
+/// effect until the epoch of the next one, so installing a reconfiguration is a single write and
+/// every task looks the committee up by the epoch (or DAG round) of the message it handles.
+///
+/// It also tracks the signing keys of the authorities. An authority is identified by its public
+/// key in the committee and signs with it until it rotates to a new signing key.
+#[derive(Clone)]
+pub struct SharedCommittee {
+    committees: Arc<RwLock<BTreeMap<Epoch, Arc<Committee>>>>,
+    /// The rotated signing keys of each authority, indexed by the first epoch they sign for.
+    signing_keys: Arc<RwLock<HashMap<PublicKey, BTreeMap<Epoch, PublicKey>>>>,
+}
+
+impl SharedCommittee {
+    /// Creates a schedule holding the committee of epoch 0.
+    pub fn new(committee: Committee) -> Self {
+        let mut committees = BTreeMap::new();
+        committees.insert(0, Arc::new(committee));
+        Self {
+            committees: Arc::new(RwLock::new(committees)),
+            signing_keys: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the committee in effect at `epoch`.
+    pub fn at(&self, epoch: Epoch) -> Arc<Committee> {
+        let committees = self.committees.read().unwrap();
+        let (_, committee) = committees
+            .range(..=epoch)
+            .next_back()
+            .expect("The schedule starts at epoch 0");
+        committee.clone()
+    }
+
+    /// Returns the committee in effect at the DAG round `round`.
+    pub fn at_round(&self, round: Round) -> Arc<Committee> {
+        self.at(round_epoch(round))
+    }
+
+    /// Returns the last committee installed, and the epoch it takes over from.
+    pub fn latest(&self) -> (Epoch, Arc<Committee>) {
+        let committees = self.committees.read().unwrap();
+        let (epoch, committee) = committees
+            .iter()
+            .next_back()
+            .expect("The schedule starts at epoch 0");
+        (*epoch, committee.clone())
+    }
+
+    /// Installs a reconfiguration. It must take over after the last committee installed.
+    pub fn install(&self, reconfiguration: Reconfiguration) -> Result<(), ConfigError> {
+        let Reconfiguration { epoch, committee } = reconfiguration;
+        if committee.size() == 0 {
+            return Err(ConfigError::EmptyCommittee(epoch));
+        }
+        let mut committees = self.committees.write().unwrap();
+        let last = *committees.keys().next_back().expect("The schedule starts at epoch 0");
+        if epoch <= last {
+            return Err(ConfigError::StaleReconfiguration(epoch, last));
+        }
+        committees.insert(epoch, Arc::new(committee));
+        Ok(())
+    }
+
+    /// Returns the key `name` signs with at `epoch`.
+    pub fn signing_key(&self, name: &PublicKey, epoch: Epoch) -> PublicKey {
+        self.signing_keys
+            .read()
+            .unwrap()
+            .get(name)
+            .and_then(|keys| keys.range(..=epoch).next_back())
+            .map_or(*name, |(_, key)| *key)
+    }
+
+    /// Makes `name` sign with `key` from `epoch` on. It must be after its last rotation.
+    pub fn rotate(&self, name: PublicKey, epoch: Epoch, key: PublicKey) -> Result<(), ConfigError> {
+        let mut signing_keys = self.signing_keys.write().unwrap();
+        let keys = signing_keys.entry(name).or_insert_with(BTreeMap::new);
+        if let Some(last) = keys.keys().next_back() {
+            if epoch <= *last {
+                return Err(ConfigError::StaleKeyRotation(name, epoch, *last));
+            }
+        }
+        keys.insert(epoch, key);
+        Ok(())
+    }
+}
+
+impl From<Committee> for SharedCommittee {
+    fn from(committee: Committee) -> Self {
+        Self::new(committee)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct KeyPair {
     /// The node's public key (and identifier).
@@ -0,0 +1,291 @@
+use crate::consensus_store::ConsensusStore;
+use crate::global_coin::GlobalCoin;
+use crate::protocol::ConsensusProtocol;
+use crate::state::{Dag, State};
+use async_trait::async_trait;
+use config::SharedCommittee;
+use crypto::{Digest, PublicKey};
+use log::{debug, info, log_enabled, warn};
+use model::types_and_const::{Round, Stake};
+use primary::Certificate;
+use store::Store;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+pub(crate) mod schedule;
+
+use schedule::LeaderSchedule;
+
+#[cfg(test)]
+#[path = "tests/bullshark_tests.rs"]
+pub mod bullshark_tests;
+
+/// The partially-synchronous version of Bullshark. The leaders of the even rounds are scheduled
+/// ahead of time, and a leader is committed as soon as `f+1` certificates of the next round refer
+/// to it. After a commit skipping some leaders (their rounds timed out), the next leaders are
+/// elected by the global coin until a commit skips none.
+pub struct Bullshark {
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
+    /// The depth of the garbage collector.
+    gc_depth: Round,
+
+    /// Receives new certificates from the primary. The primary should send us new certificates only
+    /// if it already sent us its whole history.
+    rx_primary: Receiver<Certificate>,
+    /// Outputs the sequence of ordered certificates to the primary (for cleanup and feedback).
+    tx_primary: Sender<Certificate>,
+    /// Outputs the sequence of ordered certificates to the application layer.
+    tx_output: Sender<Certificate>,
+
+    /// The genesis certificates.
+    genesis: Vec<Certificate>,
+    /// Checkpoints the consensus state for crash-recovery.
+    consensus_store: ConsensusStore,
+
+    /// Delivers the global coins electing the leaders after a timeout.
+    global_coin: GlobalCoin,
+    /// The leaders of the rounds following the last committed anchor.
+    schedule: LeaderSchedule,
+}
+
+impl Bullshark {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        committee: SharedCommittee,
+        gc_depth: Round,
+        leader_reputation: bool,
+        leader_exclusion: usize,
+        rx_primary: Receiver<Certificate>,
+        tx_primary: Sender<Certificate>,
+        tx_output: Sender<Certificate>,
+
+        global_coin: GlobalCoin,
+        store: Store,
+    ) -> Self {
+        let genesis = Certificate::genesis(&committee.at(0));
+        Self {
+            consensus_store: ConsensusStore::new(store, &genesis),
+            genesis,
+            committee,
+            gc_depth,
+            rx_primary,
+            tx_primary,
+            tx_output,
+            global_coin,
+            schedule: LeaderSchedule::new(leader_reputation, leader_exclusion),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        committee: SharedCommittee,
+        gc_depth: Round,
+        leader_reputation: bool,
+        leader_exclusion: usize,
+        rx_primary: Receiver<Certificate>,
+        tx_primary: Sender<Certificate>,
+        tx_output: Sender<Certificate>,
+
+        global_coin: GlobalCoin,
+        store: Store,
+    ) {
+        let mut bullshark = Self::new(
+            committee,
+            gc_depth,
+            leader_reputation,
+            leader_exclusion,
+            rx_primary,
+            tx_primary,
+            tx_output,
+            global_coin,
+            store,
+        );
+        tokio::spawn(async move {
+            bullshark.run().await;
+        });
+    }
+
+    /// Outputs the committed `sequence` in order, recording each certificate before sending it so
+    /// that a restart resumes the output after it.
+    async fn output(&mut self, sequence: Vec<Certificate>) {
+        for certificate in sequence {
+            #[cfg(not(feature = "benchmark"))]
+            info!("Committed {}", certificate.header);
+
+            #[cfg(feature = "benchmark")]
+            for digest in certificate.header.payload.keys() {
+                // NOTE: This log entry is used to compute performance.
+                info!("Committed {} -> {:?}", certificate.header, digest);
+            }
+
+            self.consensus_store.persist_output(&certificate).await;
+            self.tx_primary
+                .send(certificate.clone())
+                .await
+                .expect("Failed to send certificate to primary");
+
+            if let Err(e) = self.tx_output.send(certificate).await {
+                warn!("Failed to output certificate: {}", e);
+            }
+        }
+    }
+
+    /// Returns the leader of the specified round: the scheduled one, or the one elected by the
+    /// global coin after a timeout.
+    async fn elect(&self, round: Round) -> PublicKey {
+        let committee = self.committee.at_round(round);
+        if !self.schedule.fallback {
+            return self.schedule.leader(round, &committee);
+        }
+
+        debug!("Electing the leader of round {} with the global coin", round);
+        let coin = self.global_coin.coin(round).await;
+        self.schedule.elect(&coin, &committee)
+    }
+
+    /// Returns the certificate (and the certificate's digest) originated by the leader of the
+    /// specified round (if any).
+    async fn leader<'a>(&self, round: Round, dag: &'a Dag) -> Option<&'a (Digest, Certificate)> {
+        let leader = self.elect(round).await;
+        dag.get(&round).and_then(|x| x.get(&leader))
+    }
+
+    /// Returns the oldest leader linked to `leader` that we did not commit yet, along with the
+    /// leaders of the rounds we skip before it.
+    async fn next_anchor(&self, leader: &Certificate, state: &State) -> (Certificate, Vec<PublicKey>) {
+        let mut anchor = leader;
+        let mut skipped = Vec::new();
+        for r in (state.last_committed_round + 2..=leader.round() - 2)
+            .rev()
+            .step_by(2)
+        {
+            let name = self.elect(r).await;
+            match state.dag.get(&r).and_then(|x| x.get(&name)) {
+                Some((_, prev_leader)) if state.linked(anchor, prev_leader) => {
+                    // Whether the leaders above it are skipped is decided once it is committed.
+                    anchor = prev_leader;
+                    skipped.clear();
+                }
+                _ => skipped.push(name),
+            }
+        }
+        skipped.reverse();
+        (anchor.clone(), skipped)
+    }
+
+    /// Returns the round of the leader a certificate of round `round` may commit, if any. A
+    /// certificate may vote for the leader of the previous round. After a timeout, we only consider
+    /// the leader of the round before the previous 2f+1 certificates (as Tusk does), which we need
+    /// to reveal the coin.
+    fn leader_round(&self, round: Round, state: &State) -> Option<Round> {
+        let leader_round = match self.schedule.fallback {
+            false => round - 1,
+            true => round.saturating_sub(3),
+        };
+        (leader_round % 2 == 0 && leader_round >= 2 && leader_round > state.last_committed_round)
+            .then_some(leader_round)
+    }
+
+    /// Commits the leader a certificate of round `round` may commit if `f+1` certificates of the
+    /// next round refer to it, along with the preceding leaders linked to it. The anchors are
+    /// committed one at a time, as each one may change the leaders of the rounds after it.
+    async fn try_commit(&mut self, round: Round, state: &mut State) -> Vec<Certificate> {
+        let mut sequence = Vec::new();
+        while let Some(leader_round) = self.leader_round(round, state) {
+            let (leader_digest, leader) = match self.leader(leader_round, &state.dag).await {
+                Some((digest, certificate)) => (*digest, certificate.clone()),
+                None => break,
+            };
+
+            // Check if the leader has f+1 support from its children.
+            let committee = self.committee.at_round(leader_round + 1);
+            let stake: Stake = state
+                .dag
+                .get(&(leader_round + 1))
+                .map_or(0, |certificates| {
+                    certificates
+                        .values()
+                        .filter(|(_, x)| x.header.parents.contains(&leader_digest))
+                        .map(|(_, x)| committee.stake(&x.origin()))
+                        .sum()
+                });
+            if stake < committee.validity_threshold() {
+                debug!("Leader {:?} does not have enough support", leader);
+                break;
+            }
+
+            // Commit the oldest leader we did not commit yet, flattening the sub-dag it references.
+            debug!("Leader {:?} has enough support", leader);
+            let (anchor, skipped) = self.next_anchor(&leader, state).await;
+            if !skipped.is_empty() {
+                warn!(
+                    "Skipping {} leader(s) before round {}, falling back to the global coin",
+                    skipped.len(),
+                    anchor.round()
+                );
+            }
+            let sub_dag = state.flatten(&anchor);
+            for x in &sub_dag {
+                // Update and clean up internal state.
+                state.update(x);
+            }
+            let committee = self.committee.at_round(anchor.round());
+            self.schedule.commit(&sub_dag, skipped, &committee);
+            sequence.extend(sub_dag);
+        }
+        sequence
+    }
+}
+
+#[async_trait]
+impl ConsensusProtocol for Bullshark {
+    async fn run(&mut self) {
+        info!("Starting Consensus...");
+        // The consensus state (everything else is immutable).
+        let mut state = State::new(self.gc_depth, self.genesis.clone());
+        if self.consensus_store.restore_state(&mut state).await {
+            info!("Consensus recovered up to round {}", state.last_committed_round);
+        }
+        if let Some(schedule) = self.consensus_store.restore_schedule().await {
+            self.schedule = schedule;
+        }
+
+        // Finish the output interrupted by the restart (if any).
+        let sequence = self.consensus_store.restore_output().await;
+        self.output(sequence).await;
+
+        // Listen to incoming certificates.
+        while let Some(certificate) = self.rx_primary.recv().await {
+            debug!("Processing {:?}", certificate);
+            let round = certificate.round();
+
+            // Add the new certificate to the local storage.
+            self.consensus_store.persist_certificate(&certificate).await;
+            state.add(certificate);
+
+            // Try to order the dag to commit.
+            let sequence = self.try_commit(round, &mut state).await;
+            let committed = !sequence.is_empty();
+
+            // Log the latest committed round of every authority (for debug).
+            if log_enabled!(log::Level::Debug) && committed {
+                for (name, round) in &state.last_committed {
+                    debug!("Latest commit of {}: Round {}", name, round);
+                }
+            }
+
+            if !committed {
+                continue;
+            }
+
+            // Checkpoint the commit along with the sequence it outputs, then output the sequence.
+            self.consensus_store
+                .persist_schedule(&state, &self.schedule, &sequence)
+                .await;
+            self.output(sequence).await;
+
+            // The coins of the committed rounds are not needed anymore.
+            self.global_coin.cleanup(state.last_committed_round).await;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::leader_election::LeaderElection;
+use config::Committee;
+use crypto::PublicKey;
+use model::types_and_const::{RandomNum, Round, Stake};
+use primary::Certificate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(test)]
+#[path = "tests/schedule_tests.rs"]
+pub mod schedule_tests;
+
+/// The number of committed anchors the reputation of the authorities is computed over.
+const REPUTATION_WINDOW: usize = 10;
+
+/// What the committee did between two committed anchors.
+#[derive(Clone, Serialize, Deserialize)]
+struct Commit {
+    /// The authors of the certificates of the committed sub-dag (one entry per certificate).
+    authors: Vec<PublicKey>,
+    /// The leaders skipped before the anchor.
+    skipped: Vec<PublicKey>,
+}
+
+/// The leaders of the rounds following the last committed anchor. The schedule only changes when
+/// an anchor is committed, so every node derives the same leaders from the same commits.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LeaderSchedule {
+    /// Whether the leaders are elected by the global coin rather than scheduled. This is the case
+    /// after a commit skipping some leaders (the rounds of these leaders timed out).
+    pub fallback: bool,
+    /// Whether the leaders are scheduled by reputation.
+    reputation: bool,
+    /// The authorities taking turns as leaders (the whole committee, in key order, if empty).
+    leaders: Vec<PublicKey>,
+    /// The latest commits (the most recent last), up to `REPUTATION_WINDOW` of them.
+    history: VecDeque<Commit>,
+    /// Elects the leaders from the global coins, after a timeout.
+    election: LeaderElection,
+}
+
+impl LeaderSchedule {
+    pub fn new(reputation: bool, exclusion: usize) -> Self {
+        Self {
+            reputation,
+            election: LeaderElection::new(exclusion),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the scheduled leader of round `round`, among its committee. The leaders take turns
+    /// in proportion to their stake, as they do when elected by the coin: each one leads as many
+    /// consecutive leader rounds as it has stake.
+    pub fn leader(&self, round: Round, committee: &Committee) -> PublicKey {
+        let mut leaders: Vec<_> = self
+            .leaders
+            .iter()
+            .map(|name| (*name, committee.stake(name)))
+            .filter(|(_, stake)| *stake > 0)
+            .collect();
+        if leaders.is_empty() {
+            leaders = committee
+                .authorities
+                .iter()
+                .map(|(name, authority)| (*name, authority.stake.max(1)))
+                .collect();
+        }
+
+        let total: Stake = leaders.iter().map(|(_, stake)| stake).sum();
+        let mut slot = (round / 2) % total as u64;
+        for (name, stake) in &leaders {
+            match slot.checked_sub(*stake as u64) {
+                Some(rest) => slot = rest,
+                None => return *name,
+            }
+        }
+        unreachable!("The slot is below the total stake");
+    }
+
+    /// Returns the leader elected by `coin` among `committee`, after a timeout.
+    pub fn elect(&self, coin: &RandomNum, committee: &Committee) -> PublicKey {
+        self.election.elect(coin, committee)
+    }
+
+    /// Updates the schedule with a committed anchor: `sub_dag` holds the certificates committed
+    /// along with it, and `skipped` the leaders of the rounds since the previous anchor.
+    pub fn commit(&mut self, sub_dag: &[Certificate], skipped: Vec<PublicKey>, committee: &Committee) {
+        self.fallback = !skipped.is_empty();
+        self.election.commit(&skipped);
+        if !self.reputation {
+            return;
+        }
+
+        self.history.push_back(Commit {
+            authors: sub_dag.iter().map(|x| x.origin()).collect(),
+            skipped,
+        });
+        if self.history.len() > REPUTATION_WINDOW {
+            self.history.pop_front();
+        }
+
+        // Rank the authorities by the leader rounds they missed, then by the certificates they got
+        // committed: the best ones holding a quorum of stake take turns as leaders.
+        let mut scores: HashMap<_, (usize, usize)> = HashMap::new();
+        for commit in &self.history {
+            for name in &commit.skipped {
+                scores.entry(*name).or_default().0 += 1;
+            }
+            for name in &commit.authors {
+                scores.entry(*name).or_default().1 += 1;
+            }
+        }
+        let mut ranking: Vec<_> = committee.authorities.keys().cloned().collect();
+        ranking.sort_by_key(|name| {
+            let (missed, committed) = scores.get(name).cloned().unwrap_or_default();
+            (missed, std::cmp::Reverse(committed), *name)
+        });
+
+        let mut stake: Stake = 0;
+        self.leaders = ranking
+            .into_iter()
+            .take_while(|name| {
+                let enough = stake >= committee.quorum_threshold();
+                stake += committee.stake(name);
+                !enough
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,180 @@
+use super::*;
+use crate::common::{committee, keys};
+use crypto::Hash as _;
+use model::types_and_const::RandomNum;
+use primary::Header;
+use std::collections::{BTreeSet, VecDeque};
+use tokio::sync::mpsc::channel;
+
+// Fixture: the coins of a coordinator electing the first authority (in key order) every round.
+// Also returns the rounds of the coins the consensus asked for.
+fn global_coin() -> (GlobalCoin, Receiver<Round>) {
+    let (tx_request, mut rx_request) = channel(1);
+    let (tx_coin, rx_coin) = channel(1);
+    let (tx_asked, rx_asked) = channel(10);
+    tokio::spawn(async move {
+        while let Some(round) = rx_request.recv().await {
+            let _ = tx_asked.send(round).await;
+            let _ = tx_coin.send((round, Ok(RandomNum(vec![0; 32])))).await;
+        }
+    });
+    (GlobalCoin::spawn(tx_request, rx_coin, 5_000), rx_asked)
+}
+
+// Fixture
+fn mock_certificate(
+    origin: PublicKey,
+    round: Round,
+    parents: BTreeSet<Digest>,
+) -> (Digest, Certificate) {
+    let certificate = Certificate {
+        header: Header {
+            author: origin,
+            round,
+            parents,
+            ..Header::default()
+        },
+        ..Certificate::default()
+    };
+    (certificate.digest(), certificate)
+}
+
+// Creates one certificate per authority starting and finishing at the specified rounds (inclusive).
+// Outputs a VecDeque of certificates (the certificate with higher round is on the front) and a set
+// of digests to be used as parents for the certificates of the next round.
+fn make_certificates(
+    start: Round,
+    stop: Round,
+    initial_parents: &BTreeSet<Digest>,
+    keys: &[PublicKey],
+) -> (VecDeque<Certificate>, BTreeSet<Digest>) {
+    let mut certificates = VecDeque::new();
+    let mut parents = initial_parents.iter().cloned().collect::<BTreeSet<_>>();
+    let mut next_parents = BTreeSet::new();
+
+    for round in start..=stop {
+        next_parents.clear();
+        for name in keys {
+            let (digest, certificate) = mock_certificate(*name, round, parents.clone());
+            certificates.push_back(certificate);
+            next_parents.insert(digest);
+        }
+        parents = next_parents.clone();
+    }
+    (certificates, next_parents)
+}
+
+// Spawns the consensus engine and sinks the primary channel.
+fn spawn_bullshark(global_coin: GlobalCoin) -> (Sender<Certificate>, Receiver<Certificate>) {
+    let (tx_waiter, rx_waiter) = channel(1);
+    let (tx_primary, mut rx_primary) = channel(1);
+    let (tx_output, rx_output) = channel(100);
+    Bullshark::spawn(
+        committee().into(),
+        /* gc_depth */ 50,
+        /* leader_reputation */ false,
+        /* leader_exclusion */ 0,
+        rx_waiter,
+        tx_primary,
+        tx_output,
+        global_coin,
+        Store::in_memory(),
+    );
+    tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
+    (tx_waiter, rx_output)
+}
+
+// Run for 3 dag rounds in ideal conditions (all nodes reference all other nodes). We should commit
+// the scheduled leader of round 2 as soon as it has f+1 votes, without any coin.
+#[tokio::test]
+async fn commit_one() {
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+    let genesis = Certificate::genesis(&committee())
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+    let (mut certificates, _) = make_certificates(1, 3, &genesis, &keys);
+
+    let (global_coin, mut rx_asked) = global_coin();
+    let (tx_waiter, mut rx_output) = spawn_bullshark(global_coin);
+    while let Some(certificate) = certificates.pop_front() {
+        tx_waiter.send(certificate).await.unwrap();
+    }
+
+    // Ensure the first 4 ordered certificates are from round 1 (they are the parents of the committed
+    // leader); then the leader's certificate should be committed.
+    for _ in 1..=4 {
+        let certificate = rx_output.recv().await.unwrap();
+        assert_eq!(certificate.round(), 1);
+    }
+    let certificate = rx_output.recv().await.unwrap();
+    assert_eq!(certificate.round(), 2);
+    assert_eq!(certificate.origin(), keys[1]);
+    assert!(rx_asked.try_recv().is_err());
+}
+
+// Run for 9 dag rounds. The scheduled leader of round 2 is missing, so that the leader of round 4
+// is committed after a timeout. The leader of round 6 is then elected by the coin, and the one of
+// round 8 is scheduled again.
+#[tokio::test]
+async fn fallback_after_timeout() {
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+    let genesis = Certificate::genesis(&committee())
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+
+    let mut certificates = VecDeque::new();
+
+    // Round 1: Fully connected graph.
+    let (out, parents) = make_certificates(1, 1, &genesis, &keys);
+    certificates.extend(out);
+
+    // Round 2: The scheduled leader (node 1) is missing.
+    let nodes: Vec<_> = keys.iter().cloned().filter(|x| x != &keys[1]).collect();
+    let (out, parents) = make_certificates(2, 2, &parents, &nodes);
+    certificates.extend(out);
+
+    // Rounds 3 to 9: Fully connected graph.
+    let (out, _) = make_certificates(3, 9, &parents, &keys);
+    certificates.extend(out);
+
+    let (global_coin, mut rx_asked) = global_coin();
+    let (tx_waiter, mut rx_output) = spawn_bullshark(global_coin);
+    while let Some(certificate) = certificates.pop_front() {
+        tx_waiter.send(certificate).await.unwrap();
+    }
+
+    // The leader of round 4 (node 2) commits rounds 1 to 3.
+    let mut committed = Vec::new();
+    for _ in 0..12 {
+        committed.push(rx_output.recv().await.unwrap());
+    }
+    let rounds: Vec<_> = committed.iter().map(|x| x.round()).collect();
+    assert_eq!(rounds, vec![1, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 4]);
+    assert_eq!(committed[11].origin(), keys[2]);
+
+    // The leader of round 6 (elected by the coin) then commits round 5.
+    let mut committed = Vec::new();
+    for _ in 0..8 {
+        committed.push(rx_output.recv().await.unwrap());
+    }
+    let rounds: Vec<_> = committed.iter().map(|x| x.round()).collect();
+    assert_eq!(rounds, vec![4, 4, 4, 5, 5, 5, 5, 6]);
+    assert_eq!(committed[7].origin(), keys[0]);
+
+    // Finally the scheduled leader of round 8 (node 0) commits round 7.
+    let mut committed = Vec::new();
+    for _ in 0..8 {
+        committed.push(rx_output.recv().await.unwrap());
+    }
+    let rounds: Vec<_> = committed.iter().map(|x| x.round()).collect();
+    assert_eq!(rounds, vec![6, 6, 6, 7, 7, 7, 7, 8]);
+    assert_eq!(committed[7].origin(), keys[0]);
+
+    // Only the leader of round 6 needed a coin.
+    assert_eq!(rx_asked.recv().await, Some(6));
+    assert!(rx_asked.try_recv().is_err());
+}
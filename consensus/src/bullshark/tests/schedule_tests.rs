@@ -0,0 +1,66 @@
+use super::*;
+use crate::common::{committee, keys, weighted_committee};
+use primary::Header;
+use std::collections::HashSet;
+
+// Fixture: a sub-dag holding one certificate of each of `authors`.
+fn sub_dag(authors: &[PublicKey]) -> Vec<Certificate> {
+    authors
+        .iter()
+        .map(|author| Certificate {
+            header: Header {
+                author: *author,
+                ..Header::default()
+            },
+            ..Certificate::default()
+        })
+        .collect()
+}
+
+#[test]
+fn round_robin() {
+    let committee = committee();
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+
+    let mut schedule = LeaderSchedule::new(false, 0);
+    let leaders: Vec<_> = (1..=4).map(|i| schedule.leader(2 * i, &committee)).collect();
+    assert_eq!(leaders, vec![keys[1], keys[2], keys[3], keys[0]]);
+
+    // Skipping a leader falls back to the coin, but does not change the schedule.
+    schedule.commit(&sub_dag(&keys[..3]), vec![keys[3]], &committee);
+    assert!(schedule.fallback);
+    assert_eq!(schedule.leader(6, &committee), keys[3]);
+
+    schedule.commit(&sub_dag(&keys), Vec::new(), &committee);
+    assert!(!schedule.fallback);
+}
+
+#[test]
+fn round_robin_weighted_by_stake() {
+    // The sorted keys have stakes 1 to 4.
+    let committee = weighted_committee();
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+
+    let schedule = LeaderSchedule::new(false, 0);
+    let leaders: Vec<_> = (0..10).map(|i| schedule.leader(2 * i, &committee)).collect();
+    for (i, name) in keys.iter().enumerate() {
+        assert_eq!(leaders.iter().filter(|leader| *leader == name).count(), i + 1);
+    }
+}
+
+#[test]
+fn reputation_excludes_failed_leaders() {
+    let committee = committee();
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+
+    // Node 3 crashed: it missed its leader round and has no certificate committed.
+    let mut schedule = LeaderSchedule::new(true, 0);
+    schedule.commit(&sub_dag(&keys[..3]), vec![keys[3]], &committee);
+
+    // The others take turns as leaders.
+    let leaders: HashSet<_> = (1..=12).map(|i| schedule.leader(2 * i, &committee)).collect();
+    assert_eq!(leaders, keys[..3].iter().cloned().collect());
+}
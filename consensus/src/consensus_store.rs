@@ -0,0 +1,309 @@
+use crate::bullshark::schedule::LeaderSchedule;
+use crate::dolphin::virtual_state::VirtualState;
+use crate::leader_election::LeaderElection;
+use crate::state::{Dag, State};
+use crypto::{Digest, Hash as _, PublicKey};
+use futures::stream::StreamExt as _;
+use log::debug;
+use model::types_and_const::Round;
+use primary::{Certificate, CERTIFICATES};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use store::{Store, TypedStore};
+
+#[cfg(test)]
+#[path = "tests/consensus_store_tests.rs"]
+pub mod consensus_store_tests;
+
+/// The column family of the primary's store holding the consensus checkpoints.
+const CONSENSUS: &str = "consensus";
+/// The column family of the primary's store holding the digests of the certificates of the dag,
+/// by round and author.
+const CONSENSUS_DAG: &str = "consensus_dag";
+
+const STATE_KEY: &str = "state";
+const VIRTUAL_STATE_KEY: &str = "virtual_state";
+const SCHEDULE_KEY: &str = "schedule";
+const ELECTION_KEY: &str = "election";
+const OUTPUT_KEY: &str = "output";
+const OUTPUT_CURSOR_KEY: &str = "output_cursor";
+
+/// A dag whose certificates are referred to by digest.
+type DagIndex = HashMap<Round, HashMap<PublicKey, Digest>>;
+
+/// The key of the certificate of `name` at `round` in the dag family. The round comes first, in
+/// big-endian, so that the keys are sorted by round.
+fn dag_key(round: Round, name: &PublicKey) -> Vec<u8> {
+    let mut key = round.to_be_bytes().to_vec();
+    key.extend(bincode::serialize(name).expect("Failed to serialize author"));
+    key
+}
+
+/// The commits of the state. Its dag is checkpointed one certificate at a time, in the dag family.
+#[derive(Serialize, Deserialize)]
+struct StateCheckpoint {
+    last_committed_round: Round,
+    last_committed: HashMap<PublicKey, Round>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VirtualStateCheckpoint {
+    dag: DagIndex,
+    steady_authorities_sets: HashMap<Round, HashSet<PublicKey>>,
+    fallback_authorities_sets: HashMap<Round, HashSet<PublicKey>>,
+    steady_state: bool,
+    /// The last wave committed by the committer.
+    last_committed: Round,
+}
+
+/// Checkpoints the consensus state in the `consensus` column family of the primary's store, and
+/// the dag of the state in the `consensus_dag` family. The dags only keep the digests of their
+/// certificates: the primary stores every certificate (under its digest) before handing it over to
+/// the consensus.
+pub struct ConsensusStore {
+    store: Store,
+    dag: Store,
+    certificates: TypedStore<Digest, Certificate>,
+    /// The genesis certificates, which the primary does not store.
+    genesis: HashMap<Digest, Certificate>,
+}
+
+impl ConsensusStore {
+    pub fn new(store: Store, genesis: &[Certificate]) -> Self {
+        Self {
+            store: store.family(CONSENSUS),
+            dag: store.family(CONSENSUS_DAG),
+            certificates: TypedStore::new(&store, CERTIFICATES),
+            genesis: genesis.iter().map(|x| (x.digest(), x.clone())).collect(),
+        }
+    }
+
+    async fn read<T: DeserializeOwned>(&mut self, key: &[u8]) -> Option<T> {
+        self.store
+            .read(key.to_vec())
+            .await
+            .expect("Failed to read consensus state from store")
+            .map(|bytes| {
+                bincode::deserialize(&bytes).expect("Failed to deserialize consensus state")
+            })
+    }
+
+    fn entry<T: Serialize>(key: &str, value: &T) -> (Vec<u8>, Vec<u8>) {
+        let bytes = bincode::serialize(value).expect("Failed to serialize consensus state");
+        (key.as_bytes().to_vec(), bytes)
+    }
+
+    fn index(dag: &Dag) -> DagIndex {
+        dag.iter()
+            .map(|(round, certificates)| {
+                let digests = certificates
+                    .iter()
+                    .map(|(name, (digest, _))| (*name, *digest))
+                    .collect();
+                (*round, digests)
+            })
+            .collect()
+    }
+
+    /// Reads back the certificate `digest`, unless the primary already pruned it from the store
+    /// (past its garbage collection horizon).
+    async fn certificate(&mut self, digest: &Digest) -> Option<Certificate> {
+        if let Some(certificate) = self.genesis.get(digest) {
+            return Some(certificate.clone());
+        }
+        let certificate = self
+            .certificates
+            .read(digest)
+            .await
+            .expect("Failed to read certificate from store");
+        if certificate.is_none() {
+            debug!(
+                "Certificate {} of the consensus checkpoint was pruned",
+                digest
+            );
+        }
+        certificate
+    }
+
+    /// Reads back the certificates of a checkpointed dag. The certificates the primary already
+    /// pruned from the store are left out.
+    async fn resolve(&mut self, index: DagIndex) -> Dag {
+        let mut dag = Dag::new();
+        for (round, digests) in index {
+            let mut certificates = HashMap::new();
+            for (name, digest) in digests {
+                if let Some(certificate) = self.certificate(&digest).await {
+                    certificates.insert(name, (digest, certificate));
+                }
+            }
+            dag.insert(round, certificates);
+        }
+        dag
+    }
+
+    fn state_entry(state: &State) -> (Vec<u8>, Vec<u8>) {
+        let checkpoint = StateCheckpoint {
+            last_committed_round: state.last_committed_round,
+            last_committed: state.last_committed.clone(),
+        };
+        Self::entry(STATE_KEY, &checkpoint)
+    }
+
+    /// Records a certificate added to the dag of the state. Each certificate has a key of its own,
+    /// so that the cost of recording it does not grow with the dag.
+    pub async fn persist_certificate(&mut self, certificate: &Certificate) {
+        let value = bincode::serialize(&certificate.digest()).expect("Failed to serialize digest");
+        self.dag
+            .write(dag_key(certificate.round(), &certificate.origin()), value)
+            .await;
+    }
+
+    /// Writes a checkpoint of the commits of `state`, along with the `sequence` of certificates
+    /// they output (none of which is output yet) and the other `entries`, atomically. Then
+    /// forgets the certificates of the dag below its garbage collection horizon.
+    async fn checkpoint(
+        &mut self,
+        state: &State,
+        sequence: &[Certificate],
+        mut entries: Vec<(Vec<u8>, Vec<u8>)>,
+    ) {
+        let output: Vec<Digest> = sequence.iter().map(|x| x.digest()).collect();
+        entries.push(Self::state_entry(state));
+        entries.push(Self::entry(OUTPUT_KEY, &output));
+        entries.push(Self::entry(OUTPUT_CURSOR_KEY, &None::<Digest>));
+        self.store.write_batch(entries).await;
+        if let Some(horizon) = state.dag.keys().min() {
+            self.dag
+                .delete_range(0u64.to_be_bytes().to_vec(), horizon.to_be_bytes().to_vec())
+                .await;
+        }
+    }
+
+    /// Restores the state checkpointed before a restart (if any) into `state`. Returns whether
+    /// there was one.
+    pub async fn restore_state(&mut self, state: &mut State) -> bool {
+        let checkpoint: Option<StateCheckpoint> = self.read(STATE_KEY.as_bytes()).await;
+        if let Some(checkpoint) = &checkpoint {
+            state.last_committed_round = checkpoint.last_committed_round;
+            state.last_committed = checkpoint.last_committed.clone();
+            // The genesis of the fresh state may be obsolete already.
+            state.cleanup();
+        }
+
+        // Add the certificates back in round order: the ones the checkpointed commits make
+        // obsolete are left out.
+        let entries: Vec<_> = self.dag.iter().collect().await;
+        let restored = checkpoint.is_some() || !entries.is_empty();
+        for (_, value) in entries {
+            let digest: Digest =
+                bincode::deserialize(&value).expect("Failed to deserialize digest");
+            if let Some(certificate) = self.certificate(&digest).await {
+                state.add(certificate);
+            }
+        }
+        restored
+    }
+
+    /// Records that `certificate`, of the checkpointed sequence, is about to be output. It is
+    /// recorded before it is sent, so that a restarted node never outputs it twice.
+    pub async fn persist_output(&mut self, certificate: &Certificate) {
+        let (key, value) = Self::entry(OUTPUT_CURSOR_KEY, &Some(certificate.digest()));
+        self.store.write(key, value).await;
+    }
+
+    /// Returns the certificates of the checkpointed sequence that were not output before a
+    /// restart, in order.
+    pub async fn restore_output(&mut self) -> Vec<Certificate> {
+        let output: Vec<Digest> = self.read(OUTPUT_KEY.as_bytes()).await.unwrap_or_default();
+        let cursor: Option<Digest> = self.read(OUTPUT_CURSOR_KEY.as_bytes()).await.flatten();
+        let start = match cursor {
+            Some(cursor) => output
+                .iter()
+                .position(|x| *x == cursor)
+                .map_or(0, |i| i + 1),
+            None => 0,
+        };
+        let mut sequence = Vec::new();
+        for digest in &output[start..] {
+            if let Some(certificate) = self.certificate(digest).await {
+                sequence.push(certificate);
+            }
+        }
+        sequence
+    }
+
+    /// Checkpoints the state along with the virtual state and the `sequence` the last commit
+    /// outputs, atomically: a restarted node never resumes from a state and a virtual state taken
+    /// at different times.
+    pub async fn persist_states(
+        &mut self,
+        state: &State,
+        virtual_state: &VirtualState,
+        last_committed: Round,
+        sequence: &[Certificate],
+    ) {
+        let checkpoint = VirtualStateCheckpoint {
+            dag: Self::index(&virtual_state.dag),
+            steady_authorities_sets: virtual_state.steady_authorities_sets.clone(),
+            fallback_authorities_sets: virtual_state.fallback_authorities_sets.clone(),
+            steady_state: virtual_state._steady_state,
+            last_committed,
+        };
+        self.checkpoint(
+            state,
+            sequence,
+            vec![Self::entry(VIRTUAL_STATE_KEY, &checkpoint)],
+        )
+        .await;
+    }
+
+    /// Restores the virtual state checkpointed before a restart (if any) into `virtual_state`.
+    /// Returns the last wave the committer committed.
+    pub async fn restore_virtual_state(
+        &mut self,
+        virtual_state: &mut VirtualState,
+    ) -> Option<Round> {
+        let checkpoint: VirtualStateCheckpoint = self.read(VIRTUAL_STATE_KEY.as_bytes()).await?;
+        virtual_state.dag = self.resolve(checkpoint.dag).await;
+        virtual_state.steady_authorities_sets = checkpoint.steady_authorities_sets;
+        virtual_state.fallback_authorities_sets = checkpoint.fallback_authorities_sets;
+        virtual_state._steady_state = checkpoint.steady_state;
+        Some(checkpoint.last_committed)
+    }
+
+    /// Checkpoints the state along with the leader schedule and the `sequence` its last commit
+    /// outputs, atomically: the schedule follows the commits of the state.
+    pub async fn persist_schedule(
+        &mut self,
+        state: &State,
+        schedule: &LeaderSchedule,
+        sequence: &[Certificate],
+    ) {
+        self.checkpoint(state, sequence, vec![Self::entry(SCHEDULE_KEY, schedule)])
+            .await;
+    }
+
+    /// Returns the leader schedule checkpointed before a restart (if any).
+    pub async fn restore_schedule(&mut self) -> Option<LeaderSchedule> {
+        self.read(SCHEDULE_KEY.as_bytes()).await
+    }
+
+    /// Checkpoints the state along with the leader election and the `sequence` its last commit
+    /// outputs, atomically: the leaders excluded from the election follow the commits of the
+    /// state.
+    pub async fn persist_election(
+        &mut self,
+        state: &State,
+        election: &LeaderElection,
+        sequence: &[Certificate],
+    ) {
+        self.checkpoint(state, sequence, vec![Self::entry(ELECTION_KEY, election)])
+            .await;
+    }
+
+    /// Returns the leader election checkpointed before a restart (if any).
+    pub async fn restore_election(&mut self) -> Option<LeaderElection> {
+        self.read(ELECTION_KEY.as_bytes()).await
+    }
+}
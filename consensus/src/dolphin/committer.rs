@@ -1,23 +1,24 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::dolphin::virtual_state::VirtualState;
 use crate::state::{Dag, State};
-use config::{Committee};
+use config::SharedCommittee;
 use log::{debug, log_enabled};
 use primary::{Certificate};
 use std::collections::HashSet;
 use model::types_and_const::{Round, Stake};
 
 pub struct Committer {
-    /// The committee information.
-    committee: Committee,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
     /// The depth of the garbage collection.
     gc_depth: Round,
 
-    last_committed: Round,
+    /// The last committed wave.
+    pub last_committed: Round,
 }
 
 impl Committer {
-    pub fn new(committee: Committee, gc_depth: Round) -> Self {
+    pub fn new(committee: SharedCommittee, gc_depth: Round) -> Self {
         Self {
             committee,
             gc_depth,
@@ -166,6 +167,7 @@ impl Committer {
         state: &VirtualState,
     ) -> Option<Certificate> {
         debug!("Checking steady commit");
+        let committee = self.committee.at_round(certificate.virtual_round());
         state
             .steady_leader(wave)
             .map(|(_, leader)| {
@@ -186,9 +188,9 @@ impl Committer {
                         debug!("Link between {:?} <- {:?}: {}", leader, parent, is_linked);
                         is_parent && is_steady && is_linked
                     })
-                    .map(|(_, certificate)| self.committee.stake(&certificate.origin()))
+                    .map(|(_, certificate)| committee.stake(&certificate.origin()))
                     .sum::<Stake>()
-                    >= self.committee.quorum_threshold())
+                    >= committee.quorum_threshold())
                 .then(|| leader.clone())
             })
             .flatten()
@@ -201,6 +203,7 @@ impl Committer {
         state: &VirtualState,
     ) -> Option<Certificate> {
         debug!("Checking fallback commit");
+        let committee = self.committee.at_round(certificate.virtual_round());
         state
             .fallback_leader(wave)
             .await.map(|(_, leader)| {
@@ -221,9 +224,9 @@ impl Committer {
                         debug!("Link between {:?} <- {:?}: {}", leader, parent, is_linked);
                         is_parent && is_fallback && is_linked
                     })
-                    .map(|(_, certificate)| self.committee.stake(&certificate.origin()))
+                    .map(|(_, certificate)| committee.stake(&certificate.origin()))
                     .sum::<Stake>()
-                    >= self.committee.quorum_threshold())
+                    >= committee.quorum_threshold())
                 .then(|| leader.clone())
             })
             .flatten()
@@ -259,6 +262,7 @@ impl Committer {
         let steady_wave = (leader.virtual_round() + 1) / 2;
         let mut leader = leader;
         for w in (last_committed_wave + 1..steady_wave).rev() {
+            let committee = self.committee.at_round(2 * w - 1);
             let (_, v) = state
                 .dag
                 .get(&(2 * w - 1))
@@ -289,7 +293,7 @@ impl Committer {
                                 },
                             )
                         })
-                        .map(|voter| self.committee.stake(&voter.origin()))
+                        .map(|voter| committee.stake(&voter.origin()))
                         .sum()
                 },
             );
@@ -309,7 +313,7 @@ impl Committer {
                                 },
                             )
                         })
-                        .map(|voter| self.committee.stake(&voter.origin()))
+                        .map(|voter| committee.stake(&voter.origin()))
                         .sum()
                 },
             );
@@ -318,8 +322,8 @@ impl Committer {
             }
 
             if let Some(steady_leader) = steady_leader {
-                if steady_votes >= self.committee.validity_threshold()
-                    && fallback_votes < self.committee.validity_threshold()
+                if steady_votes >= committee.validity_threshold()
+                    && fallback_votes < committee.validity_threshold()
                 {
                     to_commit.push(steady_leader.clone());
                     leader = steady_leader
@@ -327,8 +331,8 @@ impl Committer {
             }
 
             if let Some(fallback_leader) = fallback_leader {
-                if fallback_votes >= self.committee.validity_threshold()
-                    && steady_votes < self.committee.validity_threshold()
+                if fallback_votes >= committee.validity_threshold()
+                    && steady_votes < committee.validity_threshold()
                 {
                     to_commit.push(fallback_leader.clone());
                     leader = fallback_leader
@@ -1,20 +1,24 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::consensus_store::ConsensusStore;
 use crate::dolphin::committer::Committer;
 use crate::dolphin::virtual_state::VirtualState;
+use crate::global_coin::GlobalCoin;
+use crate::protocol::ConsensusProtocol;
 use crate::state::State;
-use config::{Committee};
-use crypto::Hash as _;
+use async_trait::async_trait;
+use config::SharedCommittee;
+use crypto::{Digest, Hash as _};
 use log::{debug, info, log_enabled, warn};
 use primary::{Certificate, Metadata};
 use std::collections::BTreeSet;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::{sleep, Duration, Instant};
-use drb_coordinator::error::DrbError;
-use model::types_and_const::{RandomNum, Round, Stake};
+use store::Store;
+use model::types_and_const::{Round, Stake};
 
 pub struct Dolphin {
-    /// The committee information.
-    committee: Committee,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
     /// The leader timeout value.
     timeout: u64,
     /// The garbage collection depth.
@@ -36,12 +40,47 @@ pub struct Dolphin {
     virtual_round: Round,
     /// Implements the commit logic and returns an ordered list of certificates.
     committer: Committer,
+    /// Checkpoints the consensus state for crash-recovery.
+    consensus_store: ConsensusStore,
+
+    /// Delivers the global coins electing the fallback leaders.
+    global_coin: GlobalCoin,
 }
 
 impl Dolphin {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        committee: SharedCommittee,
+        timeout: u64,
+        gc_depth: Round,
+        rx_certificate: Receiver<Certificate>,
+        tx_commit: Sender<Certificate>,
+        tx_parents: Sender<Metadata>,
+        tx_output: Sender<Certificate>,
+
+        global_coin: GlobalCoin,
+        store: Store,
+    ) -> Self {
+        let genesis = Certificate::genesis(&committee.at(0));
+        Self {
+            committee: committee.clone(),
+            timeout,
+            gc_depth,
+            rx_certificate,
+            tx_commit,
+            tx_parents,
+            tx_output,
+            consensus_store: ConsensusStore::new(store, &genesis),
+            genesis,
+            virtual_round: 0,
+            committer: Committer::new(committee, gc_depth),
+            global_coin,
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        committee: Committee,
+        committee: SharedCommittee,
         timeout: u64,
         gc_depth: Round,
         rx_certificate: Receiver<Certificate>,
@@ -49,32 +88,111 @@ impl Dolphin {
         tx_parents: Sender<Metadata>,
         tx_output: Sender<Certificate>,
 
-        global_coin_recon_req_sender: Sender<Round>,
-        global_coin_res_receiver: Receiver<(Round,Result<RandomNum, DrbError>)>
+        global_coin: GlobalCoin,
+        store: Store,
     ) {
+        let mut dolphin = Self::new(
+            committee,
+            timeout,
+            gc_depth,
+            rx_certificate,
+            tx_commit,
+            tx_parents,
+            tx_output,
+            global_coin,
+            store,
+        );
         tokio::spawn(async move {
-            Self {
-                committee: committee.clone(),
-                timeout,
-                gc_depth,
-                rx_certificate,
-                tx_commit,
-                tx_parents,
-                tx_output,
-                genesis: Certificate::genesis(&committee),
-                virtual_round: 0,
-                committer: Committer::new(committee, gc_depth),
-            }
-            .run(global_coin_recon_req_sender,global_coin_res_receiver)
-            .await;
+            dolphin.run().await;
         });
     }
 
-    async fn run(&mut self, global_coin_recon_req_sender: Sender<Round>,global_coin_res_receiver: Receiver<(Round,Result<RandomNum, DrbError>)>) {
+    /// Outputs the committed `sequence` in the right order. Each certificate is recorded as output
+    /// before it is sent: a restarted node resumes the output after it, and never outputs it twice.
+    async fn output(&mut self, sequence: Vec<Certificate>) {
+        for certificate in sequence {
+            #[cfg(not(feature = "benchmark"))]
+            info!("Committed {}", certificate.header);
+
+            #[cfg(feature = "benchmark")]
+            for digest in certificate.header.payload.keys() {
+                // NOTE: This log entry is used to compute performance.
+                info!("Committed {} -> {:?}", certificate.header, digest);
+            }
+
+            self.consensus_store.persist_output(&certificate).await;
+            self.tx_commit
+                .send(certificate.clone())
+                .await
+                .expect("Failed to send committed certificate to primary");
+
+            if let Err(e) = self.tx_output.send(certificate).await {
+                warn!("Failed to output certificate: {}", e);
+            }
+        }
+    }
+
+    /// Returns the certificates of the virtual round to use as virtual parents, if they come from a
+    /// quorum.
+    fn quorum(&self, virtual_round: Round, virtual_state: &VirtualState) -> Option<BTreeSet<(Digest, Round)>> {
+        let (parents, authors): (BTreeSet<_>, Vec<_>) = virtual_state
+            .dag
+            .get(&virtual_round)?
+            .values()
+            .map(|(digest, x)| ((*digest, x.virtual_round()), x.origin()))
+            .collect::<Vec<_>>()
+            .iter()
+            .cloned()
+            .unzip();
+
+        let committee = self.committee.at_round(virtual_round);
+        (authors
+            .iter()
+            .map(|x| committee.stake(x))
+            .sum::<Stake>() >= committee.quorum_threshold())
+            .then_some(parents)
+    }
+
+    /// Check if we gathered a quorum of votes for the leader.
+    fn enough_votes(&mut self, virtual_round: Round, virtual_state: &VirtualState) -> bool {
+        let wave = (virtual_round + 1) / 2;
+        let committee = self.committee.at_round(virtual_round);
+        virtual_state.steady_leader(wave - 1).map_or_else(
+            || false,
+            |(leader_digest, _)| {
+                // Either we got 2f+1 votes for the leader.
+                virtual_state
+                    .dag
+                    .get(&virtual_round)
+                    .expect("We just added a certificate with this round")
+                    .values()
+                    .filter(|(_, x)| x.virtual_parents().contains(&leader_digest))
+                    .map(|(_, x)| committee.stake(&x.origin()))
+                    .sum::<Stake>()
+                    >= committee.quorum_threshold()
+
+                // Or we go f+1 votes that are not for the leader.
+                    || virtual_state
+                        .dag
+                        .get(&virtual_round)
+                        .expect("We just added a certificate with this round")
+                        .values()
+                        .filter(|(_, x)| !x.virtual_parents().contains(&leader_digest))
+                        .map(|(_, x)| committee.stake(&x.origin()))
+                        .sum::<Stake>()
+                        >= committee.validity_threshold()
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl ConsensusProtocol for Dolphin {
+    async fn run(&mut self) {
         info!("Starting Consensus...");
         // The consensus state (everything else is immutable).
         let mut state = State::new(self.gc_depth, self.genesis.clone());
-        let mut virtual_state = VirtualState::new(self.committee.clone(), self.genesis.clone(), global_coin_recon_req_sender, global_coin_res_receiver);
+        let mut virtual_state = VirtualState::new(self.committee.clone(), self.genesis.clone(), self.global_coin.clone());
 
         // The timer keeping track of the leader timeout.
         let timer = sleep(Duration::from_millis(self.timeout));
@@ -82,6 +200,21 @@ impl Dolphin {
 
         let mut quorum = Some(self.genesis.iter().map(|x| (x.digest(), 0)).collect());
         let mut advance_early = true;
+
+        // Resume from the state checkpointed before a restart, at the highest virtual round we hold.
+        if self.consensus_store.restore_state(&mut state).await {
+            info!("Consensus recovered up to round {}", state.last_committed_round);
+        }
+        if let Some(last_committed) = self.consensus_store.restore_virtual_state(&mut virtual_state).await {
+            self.committer.last_committed = last_committed;
+            self.virtual_round = virtual_state.dag.keys().max().cloned().unwrap_or_default();
+            quorum = self.quorum(self.virtual_round, &virtual_state);
+        }
+
+        // Finish the output interrupted by the restart (if any).
+        let sequence = self.consensus_store.restore_output().await;
+        self.output(sequence).await;
+
         loop {
             if (timer.is_elapsed() || advance_early) && quorum.is_some() {
                 if !advance_early {
@@ -110,11 +243,13 @@ impl Dolphin {
             }
 
             tokio::select! {
+                biased;
                 Some(certificate) = self.rx_certificate.recv() => {
                     debug!("Processing {:?}", certificate);
                     let virtual_round = certificate.virtual_round();
 
                     // Add the new certificate to the local storage.
+                    self.consensus_store.persist_certificate(&certificate).await;
                     state.add(certificate.clone());
 
                     // Try adding the certificate to the virtual dag.
@@ -125,6 +260,7 @@ impl Dolphin {
 
                     // Try to commit.
                     let sequence = self.committer.try_commit(&certificate, &mut state, &mut virtual_state).await;
+                    let committed = !sequence.is_empty();
 
                     // Log the latest committed round of every authority (for debug).
                     if log_enabled!(log::Level::Debug) {
@@ -133,25 +269,16 @@ impl Dolphin {
                         }
                     }
 
-                    // Output the sequence in the right order.
-                    for certificate in sequence {
-                        #[cfg(not(feature = "benchmark"))]
-                        info!("Committed {}", certificate.header);
+                    // Checkpoint the commit along with the sequence it outputs, then output the
+                    // sequence.
+                    self.consensus_store
+                        .persist_states(&state, &virtual_state, self.committer.last_committed, &sequence)
+                        .await;
+                    self.output(sequence).await;
 
-                        #[cfg(feature = "benchmark")]
-                        for digest in certificate.header.payload.keys() {
-                            // NOTE: This log entry is used to compute performance.
-                            info!("Committed {} -> {:?}", certificate.header, digest);
-                        }
-
-                        self.tx_commit
-                            .send(certificate.clone())
-                            .await
-                            .expect("Failed to send committed certificate to primary");
-
-                        if let Err(e) = self.tx_output.send(certificate).await {
-                            warn!("Failed to output certificate: {}", e);
-                        }
+                    // The coins of the committed waves are not needed anymore.
+                    if committed {
+                        self.global_coin.cleanup(self.committer.last_committed).await;
                     }
 
                     // If the certificate is not from our virtual round, it cannot help us advance round.
@@ -161,23 +288,7 @@ impl Dolphin {
                     debug!("Trying to advance round");
 
                     // Try to advance to the next (virtual) round.
-                    let (parents, authors): (BTreeSet<_>, Vec<_>) = virtual_state
-                        .dag
-                        .get(&virtual_round)
-                        .expect("We just added a certificate with this round")
-                        .values()
-                        .map(|(digest, x)| ((digest.clone(), x.virtual_round()), x.origin()))
-                        .collect::<Vec<_>>()
-                        .iter()
-                        .cloned()
-                        .unzip();
-
-                    //if authors.iter().any(|x| x == &self.name) {
-                    quorum = (authors
-                        .iter()
-                        .map(|x| self.committee.stake(x))
-                        .sum::<Stake>() >= self.committee.quorum_threshold())
-                        .then(|| parents);
+                    quorum = self.quorum(virtual_round, &virtual_state);
                     debug!("Got quorum for round {}: {}", self.virtual_round, quorum.is_some());
 
                     advance_early = match virtual_round % 2 {
@@ -185,7 +296,6 @@ impl Dolphin {
                         _ => virtual_state.steady_leader((virtual_round+1)/2).is_some(),
                     };
                     debug!("Can early advance for round {}: {}", self.virtual_round, advance_early);
-                    //}
                 },
                 () = &mut timer => {
                     // Nothing to do.
@@ -193,35 +303,4 @@ impl Dolphin {
             }
         }
     }
-
-    /// Check if we gathered a quorum of votes for the leader.
-    fn enough_votes(&mut self, virtual_round: Round, virtual_state: &VirtualState) -> bool {
-        let wave = (virtual_round + 1) / 2;
-        virtual_state.steady_leader(wave - 1).map_or_else(
-            || false,
-            |(leader_digest, _)| {
-                // Either we got 2f+1 votes for the leader.
-                virtual_state
-                    .dag
-                    .get(&virtual_round)
-                    .expect("We just added a certificate with this round")
-                    .values()
-                    .filter(|(_, x)| x.virtual_parents().contains(&leader_digest))
-                    .map(|(_, x)| self.committee.stake(&x.origin()))
-                    .sum::<Stake>()
-                    >= self.committee.quorum_threshold()
-
-                // Or we go f+1 votes that are not for the leader.
-                    || virtual_state
-                        .dag
-                        .get(&virtual_round)
-                        .expect("We just added a certificate with this round")
-                        .values()
-                        .filter(|(_, x)| !x.virtual_parents().contains(&leader_digest))
-                        .map(|(_, x)| self.committee.stake(&x.origin()))
-                        .sum::<Stake>()
-                        >= self.committee.validity_threshold()
-            },
-        )
-    }
 }
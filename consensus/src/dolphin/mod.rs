@@ -1,3 +1,3 @@
 mod committer;
 pub(crate) mod core;
-mod virtual_state;
+pub(crate) mod virtual_state;
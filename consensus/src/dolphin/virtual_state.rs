@@ -1,22 +1,19 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::global_coin::GlobalCoin;
+use crate::leader_election;
 use crate::state::Dag;
-use config::Committee;
+use config::SharedCommittee;
 use crypto::{Digest, Hash as _, PublicKey};
 use log::{debug};
 use primary::{Certificate};
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::RwLock;
-use drb_coordinator::error::DrbError;
-use model::types_and_const::{RandomNum, Round};
+use model::types_and_const::Round;
 
 /// The virtual consensus state. This state is interpreted from metadata included in the certificates
 /// and can be derived from the real state (`State`).
 pub struct VirtualState {
-    /// The committee information.
-    committee: Committee,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
     /// Keeps the latest committed certificate (and its children) for every authority. Anything older
     /// must be regularly cleaned up through the function `update`.
     pub dag: Dag,
@@ -27,47 +24,28 @@ pub struct VirtualState {
 
     pub _steady_state: bool,
 
-    pub global_coin_recon_req_sender: Sender<Round>,
-    pub global_coin_buffer: Arc<RwLock<HashMap<Round, RandomNum>>>,
+    /// Delivers the global coins electing the fallback leaders.
+    global_coin: GlobalCoin,
 }
 
 impl VirtualState {
     /// Create a new (empty) virtual state.
-    pub fn new(committee: Committee, genesis: Vec<Certificate>, global_coin_recon_req_sender: Sender<Round>, mut global_coin_res_receiver: Receiver<(Round,Result<RandomNum, DrbError>)>) -> Self {
+    pub fn new(committee: SharedCommittee, genesis: Vec<Certificate>, global_coin: GlobalCoin) -> Self {
         let genesis = genesis
             .into_iter()
             .map(|x| (x.origin(), (x.digest(), x)))
             .collect::<HashMap<_, _>>();
 
-        let global_coin_buffer= Arc::new(RwLock::new(HashMap::new()));
-        let buffer_ref = Arc::clone(&global_coin_buffer);
-        let s = global_coin_recon_req_sender.clone();
-        tokio::spawn(async move {
-            loop{
-                let res = global_coin_res_receiver.recv().await.unwrap();
-                if let Ok(random_num) = res.1 {
-                    let mut write_lock = buffer_ref.write().await;
-                    write_lock.insert(res.0, random_num);
-                    drop(write_lock);
-                }else {
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                    s.send(res.0).await.unwrap();
-                }
-            }
-        });
-
         Self {
             committee: committee.clone(),
             dag: [(0, genesis)].iter().cloned().collect(),
-            steady_authorities_sets: [(1, committee.authorities.keys().cloned().collect())]
+            steady_authorities_sets: [(1, committee.at(0).authorities.keys().cloned().collect())]
                 .iter()
                 .cloned()
                 .collect(),
             fallback_authorities_sets: HashMap::new(),
             _steady_state: true,
-
-            global_coin_recon_req_sender,
-            global_coin_buffer:Arc::clone(&global_coin_buffer)
+            global_coin,
         }
     }
 
@@ -124,16 +102,17 @@ impl VirtualState {
         #[cfg(not(test))]
         let seed = wave;
 
-        // Elect the leader.
-        let mut keys: Vec<_> = self.committee.authorities.keys().cloned().collect();
-        keys.sort();
-        let leader = keys[seed as usize % self.committee.size()];
-
-        // Return its certificate and the certificate's digest.
+        // Elect the leader among the committee of its round.
         let round = match wave {
             0 => 0,
             _ => wave * 2 - 1,
         };
+        let committee = self.committee.at_round(round);
+        let mut keys: Vec<_> = committee.authorities.keys().cloned().collect();
+        keys.sort();
+        let leader = keys[seed as usize % committee.size()];
+
+        // Return its certificate and the certificate's digest.
         self.dag.get(&round).map(|x| x.get(&leader)).flatten()
     }
 
@@ -144,33 +123,23 @@ impl VirtualState {
         // We use randomness beacon to get global coin.
         let wave = if wave % 2 == 0 {wave -1 } else { wave };
 
-        let coin;
-        self.global_coin_recon_req_sender.send(wave).await.unwrap();
-        loop {
-            match self.global_coin_buffer.read().await.get(&wave) {
-                Some(r) => {
-                    coin = *r;
-                    break;
-                }
-                _ => {}
-            }
-        }
+        let coin = self.global_coin.coin(wave).await;
 
-        // Elect the leader.
-        let mut keys: Vec<_> = self.committee.authorities.keys().cloned().collect();
-        keys.sort();
-        let leader = keys[coin as usize % self.committee.size()];
-
-        // Return its certificate and the certificate's digest.
+        // Elect the leader among the committee of its round.
         let round = match wave {
             0 => 0,
             _ => wave * 2 - 1,
         };
+        let committee = self.committee.at_round(round);
+        let leader = leader_election::elect(&coin, &committee, |_| false);
+
+        // Return its certificate and the certificate's digest.
         self.dag.get(&round).map(|x| x.get(&leader)).flatten()
     }
 
     /// Print the mode and latest waves of each authority.
     pub fn print_status(&self, certificate: &Certificate) {
+        let size = self.committee.at_round(certificate.virtual_round()).size();
         let mut seen = HashSet::new();
         let steady_wave = (certificate.virtual_round() + 1) / 2;
         for w in (1..=steady_wave).rev() {
@@ -181,7 +150,7 @@ impl VirtualState {
                     }
                 }
             }
-            if seen.len() == self.committee.size() {
+            if seen.len() == size {
                 break;
             }
         }
@@ -196,7 +165,7 @@ impl VirtualState {
                     }
                 }
             }
-            if seen.len() == self.committee.size() {
+            if seen.len() == size {
                 break;
             }
         }
@@ -0,0 +1,182 @@
+use drb_coordinator::error::DrbError;
+use log::debug;
+use model::types_and_const::{RandomNum, Round};
+use std::collections::HashMap;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::time::{sleep, Duration, Instant};
+
+#[cfg(test)]
+#[path = "tests/global_coin_tests.rs"]
+pub mod global_coin_tests;
+
+/// Resolution of the timer managing the retrials of coin requests (in ms).
+const TIMER_RESOLUTION: u64 = 100;
+
+/// The delay after which we ask again for a coin the coordinator could not serve yet (in ms).
+const RETRY_DELAY: u64 = 500;
+
+/// A message to the task managing the global coins.
+enum CoinMessage {
+    /// Waits for the coin of a leader round.
+    Coin(Round, oneshot::Sender<RandomNum>),
+    /// Forgets the coins of the rounds up to this one (included).
+    Cleanup(Round),
+}
+
+/// A handle to the global coins of the leader rounds, which the DRB coordinator reconstructs on
+/// request. Each coin is requested once, however many tasks wait for it, and kept once received.
+#[derive(Clone)]
+pub struct GlobalCoin {
+    channel: Sender<CoinMessage>,
+}
+
+impl GlobalCoin {
+    /// Spawns the task requesting the coins from the coordinator. A request neither served nor
+    /// denied within `timeout` ms is sent again.
+    pub fn spawn(
+        tx_request: Sender<Round>,
+        rx_coin: Receiver<(Round, Result<RandomNum, DrbError>)>,
+        timeout: u64,
+    ) -> Self {
+        let (tx_waiter, rx_waiter) = channel(100);
+        tokio::spawn(async move {
+            CoinWaiter {
+                tx_request,
+                rx_coin,
+                rx_waiter,
+                timeout,
+                coins: HashMap::new(),
+                pending: HashMap::new(),
+                gc_round: 0,
+            }
+            .run()
+            .await;
+        });
+        Self { channel: tx_waiter }
+    }
+
+    /// Returns the coin of the leader round `round`, once the coordinator reconstructed it.
+    pub async fn coin(&self, round: Round) -> RandomNum {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(CoinMessage::Coin(round, sender)).await {
+            panic!("Failed to request global coin: {}", e);
+        }
+        receiver.await.expect("Failed to receive global coin")
+    }
+
+    /// Forgets the coins of the rounds up to `round` (included). The consensus calls it with its
+    /// last committed round, since it never elects the leaders of the rounds below it again.
+    pub async fn cleanup(&self, round: Round) {
+        if let Err(e) = self.channel.send(CoinMessage::Cleanup(round)).await {
+            panic!("Failed to clean up global coins: {}", e);
+        }
+    }
+}
+
+struct CoinWaiter {
+    /// Asks the coordinator for the coin of a leader round.
+    tx_request: Sender<Round>,
+    /// Receives the coins (or the reason why they cannot be served yet) from the coordinator.
+    rx_coin: Receiver<(Round, Result<RandomNum, DrbError>)>,
+    /// Receives the requests of the consensus.
+    rx_waiter: Receiver<CoinMessage>,
+    /// The delay after which we ask again for a coin (in ms).
+    timeout: u64,
+    /// The coins received so far.
+    coins: HashMap<Round, RandomNum>,
+    /// The tasks waiting for a coin, along with the time at which to ask for it again.
+    pending: HashMap<Round, (Vec<oneshot::Sender<RandomNum>>, Instant)>,
+    /// The coins of the rounds up to this one (included) are not kept anymore.
+    gc_round: Round,
+}
+
+impl CoinWaiter {
+    async fn request(&mut self, round: Round) {
+        self.tx_request
+            .send(round)
+            .await
+            .expect("Failed to send coin request to coordinator");
+    }
+
+    async fn run(&mut self) {
+        let timer = sleep(Duration::from_millis(TIMER_RESOLUTION));
+        tokio::pin!(timer);
+
+        loop {
+            tokio::select! {
+                biased;
+                Some(message) = self.rx_waiter.recv() => match message {
+                    CoinMessage::Coin(round, sender) => {
+                        if let Some(coin) = self.coins.get(&round) {
+                            let _ = sender.send(coin.clone());
+                            continue;
+                        }
+
+                        // Ensure we do not send twice the same request.
+                        let deadline = Instant::now() + Duration::from_millis(self.timeout);
+                        let first = !self.pending.contains_key(&round);
+                        self.pending
+                            .entry(round)
+                            .or_insert_with(|| (Vec::new(), deadline))
+                            .0
+                            .push(sender);
+                        if first {
+                            debug!("Requesting global coin of round {}", round);
+                            self.request(round).await;
+                        }
+                    },
+                    CoinMessage::Cleanup(round) => {
+                        self.gc_round = self.gc_round.max(round);
+                        let gc_round = self.gc_round;
+                        self.coins.retain(|r, _| *r > gc_round);
+                    }
+                },
+
+                Some((round, result)) = self.rx_coin.recv() => match result {
+                    Ok(coin) => {
+                        if let Some((senders, _)) = self.pending.remove(&round) {
+                            for sender in senders {
+                                let _ = sender.send(coin.clone());
+                            }
+                        }
+                        if round > self.gc_round {
+                            self.coins.insert(round, coin);
+                        }
+                    },
+                    Err(e) => {
+                        // The coordinator cannot serve the coin yet (e.g. the common core of its
+                        // epoch is not decided): ask again shortly.
+                        debug!("Global coin of round {} not available yet: {}", round, e);
+                        if let Some((_, deadline)) = self.pending.get_mut(&round) {
+                            *deadline = Instant::now() + Duration::from_millis(RETRY_DELAY);
+                        }
+                    }
+                },
+
+                // Triggers on timer's expiration.
+                () = &mut timer => {
+                    let now = Instant::now();
+                    let timeout = Duration::from_millis(self.timeout);
+                    let mut retry: Vec<_> = self
+                        .pending
+                        .iter_mut()
+                        .filter(|(_, (_, deadline))| *deadline <= now)
+                        .map(|(round, (_, deadline))| {
+                            *deadline = now + timeout;
+                            *round
+                        })
+                        .collect();
+                    retry.sort_unstable();
+                    for round in retry {
+                        debug!("Requesting global coin of round {} (retry)", round);
+                        self.request(round).await;
+                    }
+
+                    // Reschedule the timer.
+                    timer.as_mut().reset(now + Duration::from_millis(TIMER_RESOLUTION));
+                },
+            }
+        }
+    }
+}
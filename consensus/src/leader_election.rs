@@ -0,0 +1,79 @@
+use config::Committee;
+use crypto::PublicKey;
+use model::types_and_const::{RandomNum, Stake};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+#[path = "tests/leader_election_tests.rs"]
+pub mod leader_election_tests;
+
+/// Maps the global coin `coin` to an authority of `committee`, with a probability proportional to
+/// its stake. The authorities for which `excluded` holds are left out, unless that leaves nobody.
+pub fn elect<F>(coin: &RandomNum, committee: &Committee, excluded: F) -> PublicKey
+where
+    F: Fn(&PublicKey) -> bool,
+{
+    let mut candidates: Vec<_> = committee
+        .authorities
+        .iter()
+        .filter(|(name, authority)| !excluded(name) && authority.stake > 0)
+        .map(|(name, authority)| (*name, authority.stake))
+        .collect();
+    if candidates.is_empty() {
+        candidates = committee
+            .authorities
+            .iter()
+            .map(|(name, authority)| (*name, authority.stake.max(1)))
+            .collect();
+    }
+
+    // Every node walks the candidates in key order, so they all elect the same one.
+    let total: Stake = candidates.iter().map(|(_, stake)| stake).sum();
+    let mut target = coin.to_u64() % total as u64;
+    for (name, stake) in &candidates {
+        match target.checked_sub(*stake as u64) {
+            Some(rest) => target = rest,
+            None => return *name,
+        }
+    }
+    unreachable!("The target is below the total stake");
+}
+
+/// Elects the leaders from the global coins, leaving out the leaders that recently crashed: the
+/// leaders whose rounds were skipped by a commit are not elected for the next `exclusion` commits.
+/// The exclusions only change with the commits, so every node elects the same leaders.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LeaderElection {
+    /// How many commits a crashed leader is excluded for.
+    exclusion: usize,
+    /// The crashed leaders, along with the number of commits they remain excluded for.
+    excluded: BTreeMap<PublicKey, usize>,
+}
+
+impl LeaderElection {
+    pub fn new(exclusion: usize) -> Self {
+        Self {
+            exclusion,
+            excluded: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the leader elected by `coin` among `committee`.
+    pub fn elect(&self, coin: &RandomNum, committee: &Committee) -> PublicKey {
+        elect(coin, committee, |name| self.excluded.contains_key(name))
+    }
+
+    /// Records a commit, which skipped the rounds of the leaders `crashed`.
+    pub fn commit(&mut self, crashed: &[PublicKey]) {
+        self.excluded.retain(|_, commits| {
+            *commits -= 1;
+            *commits > 0
+        });
+        if self.exclusion > 0 {
+            for name in crashed {
+                self.excluded.insert(*name, self.exclusion);
+            }
+        }
+    }
+}
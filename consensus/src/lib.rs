@@ -1,12 +1,19 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
-#[cfg(feature = "dolphin")]
+mod bullshark;
+mod consensus_store;
 mod dolphin;
+mod global_coin;
+mod leader_election;
+mod protocol;
 mod state;
-#[cfg(not(feature = "dolphin"))]
 mod tusk;
 
-#[cfg(not(feature = "dolphin"))]
-pub use crate::tusk::Tusk;
+#[cfg(test)]
+#[path = "tests/common.rs"]
+mod common;
 
-#[cfg(feature = "dolphin")]
+pub use crate::bullshark::Bullshark;
 pub use crate::dolphin::core::Dolphin;
+pub use crate::global_coin::GlobalCoin;
+pub use crate::protocol::{Consensus, ConsensusProtocol};
+pub use crate::tusk::Tusk;
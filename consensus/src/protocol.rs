@@ -0,0 +1,72 @@
+use crate::bullshark::Bullshark;
+use crate::dolphin::core::Dolphin;
+use crate::global_coin::GlobalCoin;
+use crate::tusk::Tusk;
+use async_trait::async_trait;
+use config::{Parameters, Protocol, SharedCommittee};
+use primary::{Certificate, Metadata};
+use store::Store;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// A consensus protocol ordering the certificates of the dag.
+#[async_trait]
+pub trait ConsensusProtocol: Send {
+    /// Orders the certificates received from the primary, until the primary hangs up.
+    async fn run(&mut self);
+}
+
+/// Runs the consensus protocol selected by the parameters.
+pub struct Consensus;
+
+impl Consensus {
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        committee: SharedCommittee,
+        parameters: &Parameters,
+        rx_primary: Receiver<Certificate>,
+        tx_primary: Sender<Certificate>,
+        tx_parents: Sender<Metadata>,
+        tx_output: Sender<Certificate>,
+
+        global_coin: GlobalCoin,
+        store: Store,
+    ) {
+        let mut consensus: Box<dyn ConsensusProtocol> = match parameters.protocol {
+            Protocol::Tusk => Box::new(Tusk::new(
+                committee,
+                parameters.gc_depth,
+                parameters.leader_exclusion,
+                rx_primary,
+                tx_primary,
+                tx_output,
+                global_coin,
+                store,
+            )),
+            Protocol::Dolphin => Box::new(Dolphin::new(
+                committee,
+                parameters.timeout,
+                parameters.gc_depth,
+                rx_primary,
+                tx_primary,
+                tx_parents,
+                tx_output,
+                global_coin,
+                store,
+            )),
+            Protocol::Bullshark => Box::new(Bullshark::new(
+                committee,
+                parameters.gc_depth,
+                parameters.leader_reputation,
+                parameters.leader_exclusion,
+                rx_primary,
+                tx_primary,
+                tx_output,
+                global_coin,
+                store,
+            )),
+        };
+        tokio::spawn(async move {
+            consensus.run().await;
+        });
+    }
+}
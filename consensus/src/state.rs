@@ -38,8 +38,18 @@ impl State {
         }
     }
 
-    /// Add a certificate to the dag.
+    /// Add a certificate to the dag. Certificates older than the last one committed for their
+    /// author, or below the garbage collection horizon, are ignored: they may come back from the
+    /// checkpoint of the dag after a restart.
     pub fn add(&mut self, certificate: Certificate) {
+        if self
+            .last_committed
+            .get(&certificate.origin())
+            .is_some_and(|r| certificate.round() < *r)
+            || certificate.round() + self.gc_depth < self.last_committed_round
+        {
+            return;
+        }
         self.dag
             .entry(certificate.round())
             .or_insert_with(HashMap::new)
@@ -53,9 +63,14 @@ impl State {
             .and_modify(|r| *r = max(*r, certificate.round()))
             .or_insert_with(|| certificate.round());
 
-        let last_committed_round = *self.last_committed.values().max().unwrap();
-        self.last_committed_round = last_committed_round;
+        self.last_committed_round = *self.last_committed.values().max().unwrap();
+        self.cleanup();
+    }
 
+    /// Removes the certificates older than the last one committed for their author, or below the
+    /// garbage collection horizon.
+    pub fn cleanup(&mut self) {
+        let last_committed_round = self.last_committed_round;
         let gc_depth = self.gc_depth;
         for (name, round) in &self.last_committed {
             self.dag.retain(|r, authorities| {
@@ -65,6 +80,22 @@ impl State {
         }
     }
 
+    /// Checks if there is a path between two leaders.
+    pub fn linked(&self, leader: &Certificate, prev_leader: &Certificate) -> bool {
+        let mut parents = vec![leader];
+        for r in (prev_leader.round()..leader.round()).rev() {
+            parents = self
+                .dag
+                .get(&(r))
+                .expect("We should have the whole history by now")
+                .values()
+                .filter(|(digest, _)| parents.iter().any(|x| x.header.parents.contains(digest)))
+                .map(|(_, certificate)| certificate)
+                .collect();
+        }
+        parents.contains(&prev_leader)
+    }
+
     /// Flatten the dag referenced by the input certificate. This is a classic depth-first search (pre-order):
     /// https://en.wikipedia.org/wiki/Tree_traversal#Pre-order
     pub fn flatten(&self, vertex: &Certificate) -> Vec<Certificate> {
@@ -0,0 +1,54 @@
+use config::{Authority, Committee, PrimaryAddresses, Weighting};
+use crypto::{generate_keypair, PublicKey, SecretKey};
+use model::types_and_const::Stake;
+use rand::rngs::StdRng;
+use rand::SeedableRng as _;
+use std::collections::BTreeMap;
+
+// Fixture
+pub fn keys() -> Vec<(PublicKey, SecretKey)> {
+    let mut rng = StdRng::from_seed([0; 32]);
+    (0..4).map(|_| generate_keypair(&mut rng)).collect()
+}
+
+// Fixture: an authority with `stake` and no workers. The consensus never dials its addresses.
+fn authority(stake: Stake) -> Authority {
+    Authority {
+        stake,
+        primary: PrimaryAddresses {
+            primary_to_primary: "0.0.0.0:0".parse().unwrap(),
+            worker_to_primary: "0.0.0.0:0".parse().unwrap(),
+            breeze_addr: "0.0.0.0:0".parse().unwrap(),
+            init_bft_addr: "0.0.0.0:0".parse().unwrap(),
+            beacon_api_addr: Some("0.0.0.0:0".parse().unwrap()),
+            evidence_api_addr: Some("0.0.0.0:0".parse().unwrap()),
+            admin_api_addr: None,
+        },
+        workers: BTreeMap::default(),
+    }
+}
+
+// Fixture
+pub fn committee() -> Committee {
+    Committee {
+        authorities: keys()
+            .iter()
+            .map(|(name, _)| (*name, authority(1)))
+            .collect(),
+        weighting: Weighting::Count,
+    }
+}
+
+// Fixture: a committee in which the authorities have stakes 1, 2, 3 and 4 (in key order).
+pub fn weighted_committee() -> Committee {
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+    Committee {
+        authorities: keys
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, authority(i as Stake + 1)))
+            .collect(),
+        weighting: Weighting::Stake,
+    }
+}
@@ -0,0 +1,167 @@
+use super::*;
+use crate::common::{committee, keys};
+use crate::global_coin::GlobalCoin;
+use primary::Header;
+use std::collections::BTreeSet;
+use tokio::sync::mpsc::channel;
+
+// Fixture: a certificate of `round` for every authority, stored the way the primary does.
+async fn certificates(store: &Store, round: Round, parents: &BTreeSet<Digest>) -> Vec<Certificate> {
+    let mut certificate_store = TypedStore::new(store, CERTIFICATES);
+    let mut certificates = Vec::new();
+    for (author, _) in keys() {
+        let certificate = Certificate {
+            header: Header {
+                author,
+                round,
+                parents: parents.clone(),
+                ..Header::default()
+            },
+            ..Certificate::default()
+        };
+        certificate_store
+            .write(&certificate.digest(), &certificate)
+            .await;
+        certificates.push(certificate);
+    }
+    certificates
+}
+
+#[tokio::test]
+async fn restore_without_checkpoint() {
+    let store = Store::in_memory();
+    let genesis = Certificate::genesis(&committee());
+
+    let mut state = State::new(50, genesis.clone());
+    assert!(
+        !ConsensusStore::new(store, &genesis)
+            .restore_state(&mut state)
+            .await
+    );
+    assert_eq!(state.last_committed_round, 0);
+    assert_eq!(state.dag.len(), 1);
+}
+
+#[tokio::test]
+async fn restore_checkpointed_state() {
+    let store = Store::in_memory();
+    let genesis = Certificate::genesis(&committee());
+
+    // Commit the first certificate of round 1, the others stay in the dag.
+    let mut state = State::new(50, genesis.clone());
+    let parents = genesis.iter().map(|x| x.digest()).collect();
+    let round_1 = certificates(&store, 1, &parents).await;
+    let mut consensus_store = ConsensusStore::new(store.clone(), &genesis);
+    for certificate in &round_1 {
+        consensus_store.persist_certificate(certificate).await;
+        state.add(certificate.clone());
+    }
+    state.update(&round_1[0]);
+    let election = LeaderElection::new(0);
+    consensus_store
+        .persist_election(&state, &election, &[])
+        .await;
+
+    // A restarted node resumes from the same state.
+    let mut restored = State::new(50, genesis.clone());
+    assert!(
+        ConsensusStore::new(store, &genesis)
+            .restore_state(&mut restored)
+            .await
+    );
+    assert_eq!(restored.last_committed_round, 1);
+    assert_eq!(restored.last_committed, state.last_committed);
+    assert!(restored.dag == state.dag);
+
+    // Certificates older than the last commit of their author are not added back.
+    let mut stale = genesis[0].clone();
+    stale.header.author = round_1[0].origin();
+    restored.add(stale);
+    assert!(restored.dag == state.dag);
+}
+
+#[tokio::test]
+async fn restart_between_output_and_checkpoint() {
+    let store = Store::in_memory();
+    let genesis = Certificate::genesis(&committee());
+
+    // Commit round 1, and crash after outputting the first two certificates of the sequence.
+    let mut state = State::new(50, genesis.clone());
+    let parents = genesis.iter().map(|x| x.digest()).collect();
+    let sequence = certificates(&store, 1, &parents).await;
+    let mut consensus_store = ConsensusStore::new(store.clone(), &genesis);
+    for certificate in &sequence {
+        consensus_store.persist_certificate(certificate).await;
+        state.add(certificate.clone());
+    }
+    state.update(&sequence[0]);
+    consensus_store
+        .persist_election(&state, &LeaderElection::new(0), &sequence)
+        .await;
+    for certificate in &sequence[..2] {
+        consensus_store.persist_output(certificate).await;
+    }
+
+    // A restarted node outputs the rest of the sequence only, and does not commit it again.
+    let mut consensus_store = ConsensusStore::new(store.clone(), &genesis);
+    let mut restored = State::new(50, genesis.clone());
+    assert!(consensus_store.restore_state(&mut restored).await);
+    assert_eq!(restored.last_committed_round, 1);
+    let output: Vec<_> = consensus_store
+        .restore_output()
+        .await
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    let rest: Vec<_> = sequence[2..].iter().map(|x| x.digest()).collect();
+    assert_eq!(output, rest);
+
+    // Once the rest is output, a second restart outputs nothing.
+    for certificate in &sequence[2..] {
+        consensus_store.persist_output(certificate).await;
+    }
+    let mut consensus_store = ConsensusStore::new(store, &genesis);
+    assert!(consensus_store.restore_output().await.is_empty());
+}
+
+#[tokio::test]
+async fn restart_between_virtual_output_and_checkpoint() {
+    let store = Store::in_memory();
+    let genesis = Certificate::genesis(&committee());
+    let (tx_request, _rx_request) = channel(1);
+    let (_tx_coin, rx_coin) = channel(1);
+    let global_coin = GlobalCoin::spawn(tx_request, rx_coin, 5_000);
+
+    // Commit the first wave, and crash after outputting the first certificate of the sequence.
+    let mut state = State::new(50, genesis.clone());
+    let virtual_state = VirtualState::new(committee().into(), genesis.clone(), global_coin.clone());
+    let parents = genesis.iter().map(|x| x.digest()).collect();
+    let sequence = certificates(&store, 1, &parents).await;
+    let mut consensus_store = ConsensusStore::new(store.clone(), &genesis);
+    for certificate in &sequence {
+        consensus_store.persist_certificate(certificate).await;
+        state.add(certificate.clone());
+    }
+    state.update(&sequence[0]);
+    consensus_store
+        .persist_states(&state, &virtual_state, 1, &sequence)
+        .await;
+    consensus_store.persist_output(&sequence[0]).await;
+
+    // A restarted node resumes the committer after the wave, and outputs the rest of the sequence
+    // only.
+    let mut consensus_store = ConsensusStore::new(store, &genesis);
+    let mut restored = VirtualState::new(committee().into(), genesis.clone(), global_coin);
+    assert_eq!(
+        consensus_store.restore_virtual_state(&mut restored).await,
+        Some(1)
+    );
+    let output: Vec<_> = consensus_store
+        .restore_output()
+        .await
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    let rest: Vec<_> = sequence[1..].iter().map(|x| x.digest()).collect();
+    assert_eq!(output, rest);
+}
@@ -0,0 +1,87 @@
+use super::*;
+use tokio::time::timeout;
+
+// Fixture
+fn coin() -> RandomNum {
+    RandomNum(vec![1; 32])
+}
+
+#[tokio::test]
+async fn deduplicate_requests() {
+    let (tx_request, mut rx_request) = channel(10);
+    let (tx_coin, rx_coin) = channel(10);
+    let global_coin = GlobalCoin::spawn(tx_request, rx_coin, 5_000);
+
+    // Wait twice for the same coin: it is only requested once.
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let global_coin = global_coin.clone();
+            tokio::spawn(async move { global_coin.coin(2).await })
+        })
+        .collect();
+    assert_eq!(rx_request.recv().await, Some(2));
+    assert!(timeout(Duration::from_millis(200), rx_request.recv()).await.is_err());
+
+    // Both waiters get the coin, and later ones get it without a new request.
+    tx_coin.send((2, Ok(coin()))).await.unwrap();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), coin());
+    }
+    assert_eq!(global_coin.coin(2).await, coin());
+    assert!(rx_request.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn retry_unavailable_coin() {
+    let (tx_request, mut rx_request) = channel(10);
+    let (tx_coin, rx_coin) = channel(10);
+    let global_coin = GlobalCoin::spawn(tx_request, rx_coin, 5_000);
+
+    let handle = tokio::spawn(async move { global_coin.coin(2).await });
+    assert_eq!(rx_request.recv().await, Some(2));
+
+    // The coordinator cannot serve the coin yet: it is requested again shortly after.
+    tx_coin.send((2, Err(DrbError::NoCommonCore))).await.unwrap();
+    assert_eq!(rx_request.recv().await, Some(2));
+    tx_coin.send((2, Ok(coin()))).await.unwrap();
+    assert_eq!(handle.await.unwrap(), coin());
+}
+
+#[tokio::test]
+async fn retry_after_timeout() {
+    let (tx_request, mut rx_request) = channel(10);
+    let (_tx_coin, rx_coin) = channel(10);
+    let global_coin = GlobalCoin::spawn(tx_request, rx_coin, 300);
+
+    // The coordinator does not answer: the coin is requested again once the timeout expires.
+    tokio::spawn(async move { global_coin.coin(2).await });
+    assert_eq!(rx_request.recv().await, Some(2));
+    let retry = timeout(Duration::from_millis(1_000), rx_request.recv()).await;
+    assert_eq!(retry.unwrap(), Some(2));
+}
+
+#[tokio::test]
+async fn cleanup_committed_rounds() {
+    let (tx_request, mut rx_request) = channel(10);
+    let (tx_coin, rx_coin) = channel(10);
+    let global_coin = GlobalCoin::spawn(tx_request, rx_coin, 5_000);
+
+    for round in [2, 4] {
+        let handle = {
+            let global_coin = global_coin.clone();
+            tokio::spawn(async move { global_coin.coin(round).await })
+        };
+        assert_eq!(rx_request.recv().await, Some(round));
+        tx_coin.send((round, Ok(coin()))).await.unwrap();
+        assert_eq!(handle.await.unwrap(), coin());
+    }
+
+    // The coin of the committed round is forgotten, the later one is still kept.
+    global_coin.cleanup(2).await;
+    assert_eq!(global_coin.coin(4).await, coin());
+    assert!(rx_request.try_recv().is_err());
+    let handle = tokio::spawn(async move { global_coin.coin(2).await });
+    assert_eq!(rx_request.recv().await, Some(2));
+    tx_coin.send((2, Ok(coin()))).await.unwrap();
+    assert_eq!(handle.await.unwrap(), coin());
+}
@@ -0,0 +1,50 @@
+use super::*;
+use crate::common::weighted_committee;
+
+// Fixture
+fn coin(value: u64) -> RandomNum {
+    RandomNum(value.to_le_bytes().to_vec())
+}
+
+#[test]
+fn stake_weighted() {
+    let committee = weighted_committee();
+    let keys: Vec<_> = committee.authorities.keys().cloned().collect();
+
+    let election = LeaderElection::new(0);
+    let mut elected = BTreeMap::new();
+    for value in 0..100 {
+        *elected.entry(election.elect(&coin(value), &committee)).or_insert(0) += 1;
+    }
+    let counts: Vec<_> = keys.iter().map(|name| elected[name]).collect();
+    assert_eq!(counts, vec![10, 20, 30, 40]);
+}
+
+#[test]
+fn exclude_crashed_leaders() {
+    let committee = weighted_committee();
+    let keys: Vec<_> = committee.authorities.keys().cloned().collect();
+
+    // The coin 9 elects the last authority.
+    let mut election = LeaderElection::new(2);
+    assert_eq!(election.elect(&coin(9), &committee), keys[3]);
+
+    // It is not elected for the 2 commits following the one skipping its round.
+    election.commit(&[keys[3]]);
+    assert_ne!(election.elect(&coin(9), &committee), keys[3]);
+    election.commit(&[]);
+    assert_ne!(election.elect(&coin(9), &committee), keys[3]);
+    election.commit(&[]);
+    assert_eq!(election.elect(&coin(9), &committee), keys[3]);
+}
+
+#[test]
+fn exclusions_leave_somebody() {
+    let committee = weighted_committee();
+    let keys: Vec<_> = committee.authorities.keys().cloned().collect();
+
+    // Even with everybody excluded, the coin still elects a leader.
+    let mut election = LeaderElection::new(1);
+    election.commit(&keys);
+    assert_eq!(election.elect(&coin(9), &committee), keys[3]);
+}
@@ -1,24 +1,25 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::consensus_store::ConsensusStore;
+use crate::global_coin::GlobalCoin;
+use crate::leader_election::LeaderElection;
+use crate::protocol::ConsensusProtocol;
 use crate::state::{Dag, State};
-use config::{Committee};
-use crypto::Digest;
+use config::SharedCommittee;
+use crypto::{Digest, PublicKey};
 use log::{debug, info, log_enabled, warn};
+use async_trait::async_trait;
 use primary::{Certificate};
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::RwLock;
-use drb_coordinator::error::DrbError;
-use model::types_and_const::{RandomNum, Round, Stake};
+use store::Store;
+use model::types_and_const::{Round, Stake};
 
 #[cfg(test)]
 #[path = "tests/tusk_tests.rs"]
 pub mod tusk_tests;
 
 pub struct Tusk {
-    /// The committee information.
-    committee: Committee,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
     /// The depth of the garbage collector.
     gc_depth: Round,
 
@@ -32,61 +33,201 @@ pub struct Tusk {
 
     /// The genesis certificates.
     genesis: Vec<Certificate>,
+    /// Checkpoints the consensus state for crash-recovery.
+    consensus_store: ConsensusStore,
 
-    global_coin_recon_req_sender: Sender<Round>,
-    global_coin_buffer: Arc<RwLock<HashMap<Round, RandomNum>>>,
+    /// Delivers the global coins electing the leaders.
+    global_coin: GlobalCoin,
+    /// Elects the leaders from the global coins.
+    election: LeaderElection,
 }
 
 impl Tusk {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        committee: SharedCommittee,
+        gc_depth: Round,
+        leader_exclusion: usize,
+        rx_primary: Receiver<Certificate>,
+        tx_primary: Sender<Certificate>,
+        tx_output: Sender<Certificate>,
+
+        global_coin: GlobalCoin,
+        store: Store,
+    ) -> Self {
+        let genesis = Certificate::genesis(&committee.at(0));
+        Self {
+            consensus_store: ConsensusStore::new(store, &genesis),
+            genesis,
+            committee,
+            gc_depth,
+            rx_primary,
+            tx_primary,
+            tx_output,
+            global_coin,
+            election: LeaderElection::new(leader_exclusion),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        committee: Committee,
+        committee: SharedCommittee,
         gc_depth: Round,
+        leader_exclusion: usize,
         rx_primary: Receiver<Certificate>,
         tx_primary: Sender<Certificate>,
         tx_output: Sender<Certificate>,
 
-        global_coin_recon_req_sender: Sender<Round>,
-        mut global_coin_res_receiver: Receiver<(Round, Result<RandomNum, DrbError>)>
+        global_coin: GlobalCoin,
+        store: Store,
     ) {
-        let global_coin_buffer= Arc::new(RwLock::new(HashMap::new()));
-        let buffer_ref = Arc::clone(&global_coin_buffer);
-        let s = global_coin_recon_req_sender.clone();
-
+        let mut tusk = Self::new(
+            committee,
+            gc_depth,
+            leader_exclusion,
+            rx_primary,
+            tx_primary,
+            tx_output,
+            global_coin,
+            store,
+        );
         tokio::spawn(async move {
-            loop{
-                let res = global_coin_res_receiver.recv().await.unwrap();
-                if let Ok(random_num) = res.1 {
-                    let mut write_lock = buffer_ref.write().await;
-                    write_lock.insert(res.0, random_num);
-                    drop(write_lock);
-                } else {
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                    s.send(res.0).await.unwrap();
+            tusk.run().await;
+        });
+    }
+
+    /// Outputs the committed `sequence` in the right order. Each certificate is recorded as output
+    /// before it is sent: a restarted node resumes the output after it, and never outputs it twice.
+    async fn output(&mut self, sequence: Vec<Certificate>) {
+        for certificate in sequence {
+            #[cfg(not(feature = "benchmark"))]
+            info!("Committed {}", certificate.header);
+
+            #[cfg(feature = "benchmark")]
+            for digest in certificate.header.payload.keys() {
+                // NOTE: This log entry is used to compute performance.
+                info!("Committed {} -> {:?}", certificate.header, digest);
+            }
+
+            self.consensus_store.persist_output(&certificate).await;
+            self.tx_primary
+                .send(certificate.clone())
+                .await
+                .expect("Failed to send certificate to primary");
+
+            if let Err(e) = self.tx_output.send(certificate).await {
+                warn!("Failed to output certificate: {}", e);
+            }
+        }
+    }
+
+    /// Returns the leader of the specified round, elected by the global coin.
+    async fn elect(&self, round: Round) -> PublicKey {
+        info!("start to elect leader for round:{}", round);
+        let coin = self.global_coin.coin(round).await;
+
+        // Elect the leader among the committee of its round.
+        let committee = self.committee.at_round(round);
+        self.election.elect(&coin, &committee)
+    }
+
+    /// Returns the certificate (and the certificate's digest) originated by the leader of the
+    /// specified round (if any).
+    async fn leader<'a>(&self, round: Round, dag: &'a Dag) -> Option<&'a (Digest, Certificate)> {
+        let leader = self.elect(round).await;
+
+        // Return its certificate and the certificate's digest.
+        dag.get(&round).and_then(|x| x.get(&leader))
+    }
+
+    /// Returns the oldest past leader linked to `leader` that we didn't already commit, along with
+    /// the leaders of the rounds we skip before it (they crashed, or were too slow).
+    async fn next_anchor(&self, leader: &Certificate, state: &State) -> (Certificate, Vec<PublicKey>) {
+        let mut anchor = leader;
+        let mut crashed = Vec::new();
+        // Start below the leader itself, which is the anchor to begin with.
+        for r in (state.last_committed_round + 2..=leader.round() - 2)
+            .rev()
+            .step_by(2)
+        {
+            // Get the certificate proposed by the previous leader, and check whether there is a
+            // path between the last two leaders.
+            let name = self.elect(r).await;
+            match state.dag.get(&r).and_then(|x| x.get(&name)) {
+                Some((_, prev_leader)) if state.linked(anchor, prev_leader) => {
+                    // Whether the leaders above it crashed is decided once it is committed.
+                    anchor = prev_leader;
+                    crashed.clear();
                 }
+                _ => crashed.push(name),
             }
-        });
+        }
+        crashed.reverse();
+        (anchor.clone(), crashed)
+    }
 
-        tokio::spawn(async move {
-            Self {
-                committee: committee.clone(),
-                gc_depth,
-                rx_primary,
-                tx_primary,
-                tx_output,
-                genesis: Certificate::genesis(&committee),
-
-                global_coin_recon_req_sender,
-                global_coin_buffer:Arc::clone(&global_coin_buffer)
+    /// Commits the leader of `leader_round` if it has f+1 support from its children, along with
+    /// the past leaders linked to it.
+    async fn try_commit(&mut self, leader_round: Round, state: &mut State) -> Vec<Certificate> {
+        let mut sequence = Vec::new();
+        while leader_round > state.last_committed_round {
+            let (leader_digest, leader) = match self.leader(leader_round, &state.dag).await {
+                Some((digest, certificate)) => (*digest, certificate.clone()),
+                None => break,
+            };
+
+            // Check if the leader has f+1 support from its children (ie. round r-1).
+            let committee = self.committee.at_round(leader_round + 1);
+            let stake: Stake = state
+                .dag
+                .get(&(leader_round + 1))
+                .expect("We should have the whole history by now")
+                .values()
+                .filter(|(_, x)| x.header.parents.contains(&leader_digest))
+                .map(|(_, x)| committee.stake(&x.origin()))
+                .sum();
+
+            // If it is the case, we can commit the leader. But first, we need to recursively go back to
+            // the last committed leader, and commit all preceding leaders in the right order. Committing
+            // a leader block means committing all its dependencies. The leaders are committed one at a
+            // time, as each commit may exclude some leaders from the next elections.
+            if stake < committee.validity_threshold() {
+                debug!("Leader {:?} does not have enough support", leader);
+                break;
             }
-            .run()
-            .await;
-        });
+            debug!("Leader {:?} has enough support", leader);
+            let (anchor, crashed) = self.next_anchor(&leader, state).await;
+
+            // Starting from the oldest leader, flatten the sub-dag referenced by the leader.
+            for x in state.flatten(&anchor) {
+                // Update and clean up internal state.
+                state.update(&x);
+
+                // Add the certificate to the sequence.
+                sequence.push(x);
+            }
+            self.election.commit(&crashed);
+        }
+        sequence
     }
+}
 
+#[async_trait]
+impl ConsensusProtocol for Tusk {
     async fn run(&mut self) {
         info!("Starting Consensus...");
         // The consensus state (everything else is immutable).
         let mut state = State::new(self.gc_depth, self.genesis.clone());
+        if self.consensus_store.restore_state(&mut state).await {
+            info!("Consensus recovered up to round {}", state.last_committed_round);
+        }
+        if let Some(election) = self.consensus_store.restore_election().await {
+            self.election = election;
+        }
+
+        // Finish the output interrupted by the restart (if any).
+        let sequence = self.consensus_store.restore_output().await;
+        self.output(sequence).await;
 
         // Listen to incoming certificates.
         while let Some(certificate) = self.rx_primary.recv().await {
@@ -94,6 +235,7 @@ impl Tusk {
             let round = certificate.round();
 
             // Add the new certificate to the local storage.
+            self.consensus_store.persist_certificate(&certificate).await;
             state.add(certificate);
 
             // Try to order the dag to commit. Start from the highest round for which we have at least
@@ -105,49 +247,17 @@ impl Tusk {
                 continue;
             }
 
-            // Get the certificate's digest of the leader of round r-2. If we already ordered this leader,
-            // there is nothing to do.
+            // Try to commit the leader of round r-2. If we already ordered this leader, there is nothing
+            // to do.
             let leader_round = r - 2;
             if leader_round <= state.last_committed_round {
                 continue;
             }
-            let (leader_digest, leader) = match self.leader(leader_round, &state.dag).await {
-                Some(x) => x,
-                None => continue,
-            };
-
-            // Check if the leader has f+1 support from its children (ie. round r-1).
-            let stake: Stake = state
-                .dag
-                .get(&(r - 1))
-                .expect("We should have the whole history by now")
-                .values()
-                .filter(|(_, x)| x.header.parents.contains(&leader_digest))
-                .map(|(_, x)| self.committee.stake(&x.origin()))
-                .sum();
-
-            // If it is the case, we can commit the leader. But first, we need to recursively go back to
-            // the last committed leader, and commit all preceding leaders in the right order. Committing
-            // a leader block means committing all its dependencies.
-            if stake < self.committee.validity_threshold() {
-                debug!("Leader {:?} does not have enough support", leader);
+            let sequence = self.try_commit(leader_round, &mut state).await;
+            if sequence.is_empty() {
                 continue;
             }
 
-            // Get an ordered list of past leaders that are linked to the current leader.
-            debug!("Leader {:?} has enough support", leader);
-            let mut sequence = Vec::new();
-            for leader in self.order_leaders(leader, &state).await.iter().rev() {
-                // Starting from the oldest leader, flatten the sub-dag referenced by the leader.
-                for x in state.flatten(leader) {
-                    // Update and clean up internal state.
-                    state.update(&x);
-
-                    // Add the certificate to the sequence.
-                    sequence.push(x);
-                }
-            }
-
             // Log the latest committed round of every authority (for debug).
             if log_enabled!(log::Level::Debug) {
                 for (name, round) in &state.last_committed {
@@ -155,94 +265,14 @@ impl Tusk {
                 }
             }
 
-            // Output the sequence in the right order.
-            for certificate in sequence {
-                #[cfg(not(feature = "benchmark"))]
-                info!("Committed {}", certificate.header);
-
-                #[cfg(feature = "benchmark")]
-                for digest in certificate.header.payload.keys() {
-                    // NOTE: This log entry is used to compute performance.
-                    info!("Committed {} -> {:?}", certificate.header, digest);
-                }
-
-                self.tx_primary
-                    .send(certificate.clone())
-                    .await
-                    .expect("Failed to send certificate to primary");
-
-                if let Err(e) = self.tx_output.send(certificate).await {
-                    warn!("Failed to output certificate: {}", e);
-                }
-            }
-        }
-    }
-
-    /// Returns the certificate (and the certificate's digest) originated by the leader of the
-    /// specified round (if any).
-    async fn leader<'a>(&self, round: Round, dag: &'a Dag) -> Option<&'a (Digest, Certificate)> {
-
-        info!("start to elect leader for round:{}", round);
-        let coin;
-        self.global_coin_recon_req_sender.send(round).await.unwrap();
-        loop {
-            match self.global_coin_buffer.read().await.get(&round) {
-                Some(r) => {
-                    coin = *r;
-                    break;
-                }
-                _ => {}
-            }
-        }
-
-        // Elect the leader.
-        let mut keys: Vec<_> = self.committee.authorities.keys().cloned().collect();
-        keys.sort();
-        let leader = keys[coin as usize % self.committee.size()];
-
-        // Return its certificate and the certificate's digest.
-        dag.get(&round).map(|x| x.get(&leader)).flatten()
-    }
-
-    /// Order the past leaders that we didn't already commit.
-    async fn order_leaders(&self, leader: &Certificate, state: &State) -> Vec<Certificate> {
-        let mut to_commit = vec![leader.clone()];
-        let mut leader = leader;
-        // for r in (state.last_committed_round + 2..leader.round()) //original: r-sequence is odd numbers
-        for r in (state.last_committed_round + 2..=leader.round())
-            .rev()
-            .step_by(2)
-        {
-            if r % 2 != 0 {
-                panic!("r: {} error", r);
-            }
-            // Get the certificate proposed by the previous leader.
-            let (_, prev_leader) = match self.leader(r, &state.dag).await {
-                Some(x) => x,
-                None => continue,
-            };
-
-            // Check whether there is a path between the last two leaders.
-            if self.linked(leader, prev_leader, &state.dag) {
-                to_commit.push(prev_leader.clone());
-                leader = prev_leader;
-            }
-        }
-        to_commit
-    }
+            // Checkpoint the commit along with the sequence it outputs, then output the sequence.
+            self.consensus_store
+                .persist_election(&state, &self.election, &sequence)
+                .await;
+            self.output(sequence).await;
 
-    /// Checks if there is a path between two leaders.
-    fn linked(&self, leader: &Certificate, prev_leader: &Certificate, dag: &Dag) -> bool {
-        let mut parents = vec![leader];
-        for r in (prev_leader.round()..leader.round()).rev() {
-            parents = dag
-                .get(&(r))
-                .expect("We should have the whole history by now")
-                .values()
-                .filter(|(digest, _)| parents.iter().any(|x| x.header.parents.contains(digest)))
-                .map(|(_, certificate)| certificate)
-                .collect();
+            // The coins of the committed rounds are not needed anymore.
+            self.global_coin.cleanup(state.last_committed_round).await;
         }
-        parents.contains(&prev_leader)
     }
 }
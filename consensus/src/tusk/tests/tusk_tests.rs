@@ -1,41 +1,23 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
-use config::{Authority, PrimaryAddresses};
-use crypto::{generate_keypair, Digest, Hash as _, PublicKey, SecretKey};
+use crate::common::{committee, keys};
+use crypto::{Digest, Hash as _, PublicKey};
+use model::types_and_const::RandomNum;
 use primary::Header;
-use rand::rngs::StdRng;
-use rand::SeedableRng as _;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeSet, VecDeque};
 use tokio::sync::mpsc::channel;
+use tokio::time::{timeout, Duration};
 
-// Fixture
-fn keys() -> Vec<(PublicKey, SecretKey)> {
-    let mut rng = StdRng::from_seed([0; 32]);
-    (0..4).map(|_| generate_keypair(&mut rng)).collect()
-}
-
-// Fixture
-pub fn mock_committee() -> Committee {
-    Committee {
-        authorities: keys()
-            .iter()
-            .map(|(id, _)| {
-                (
-                    *id,
-                    Authority {
-                        stake: 1,
-                        primary: PrimaryAddresses {
-                            primary_to_primary: "0.0.0.0:0".parse().unwrap(),
-                            worker_to_primary: "0.0.0.0:0".parse().unwrap(),
-                            breeze_addr:"0.0.0.0:0".parse().unwrap(),
-                            init_bft_addr:"0.0.0.0:0".parse().unwrap(),
-                        },
-                        workers: HashMap::default(),
-                    },
-                )
-            })
-            .collect(),
-    }
+// Fixture: the coins of a coordinator electing the first authority (in key order) every round.
+fn global_coin() -> GlobalCoin {
+    let (tx_request, mut rx_request) = channel(1);
+    let (tx_coin, rx_coin) = channel(1);
+    tokio::spawn(async move {
+        while let Some(round) = rx_request.recv().await {
+            let _ = tx_coin.send((round, Ok(RandomNum(vec![0; 32])))).await;
+        }
+    });
+    GlobalCoin::spawn(tx_request, rx_coin, 5_000)
 }
 
 // Fixture
@@ -87,7 +69,7 @@ fn make_certificates(
 async fn commit_one() {
     // Make certificates for rounds 1 to 4.
     let keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
-    let genesis = Certificate::genesis(&mock_committee())
+    let genesis = Certificate::genesis(&committee())
         .iter()
         .map(|x| x.digest())
         .collect::<BTreeSet<_>>();
@@ -102,18 +84,15 @@ async fn commit_one() {
     let (tx_primary, mut rx_primary) = channel(1);
     let (tx_output, mut rx_output) = channel(1);
 
-    let (global_coin_recon_req_sender, _global_coin_recon_req_receiver) =
-        channel(1);
-    let (_global_coin_res_sender, global_coin_res_receiver) =
-        channel(1);
     Tusk::spawn(
-        mock_committee(),
+        committee().into(),
         /* gc_depth */ 50,
+        /* leader_exclusion */ 0,
         rx_waiter,
         tx_primary,
         tx_output,
-        global_coin_recon_req_sender,
-        global_coin_res_receiver
+        global_coin(),
+        Store::in_memory(),
     );
     tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
 
@@ -142,7 +121,7 @@ async fn dead_node() {
     keys.sort(); // Ensure we don't remove one of the leaders.
     let _ = keys.pop().unwrap();
 
-    let genesis = Certificate::genesis(&mock_committee())
+    let genesis = Certificate::genesis(&committee())
         .iter()
         .map(|x| x.digest())
         .collect::<BTreeSet<_>>();
@@ -153,18 +132,15 @@ async fn dead_node() {
     let (tx_waiter, rx_waiter) = channel(1);
     let (tx_primary, mut rx_primary) = channel(1);
     let (tx_output, mut rx_output) = channel(1);
-    let (global_coin_recon_req_sender, _global_coin_recon_req_receiver) =
-        channel(1);
-    let (_global_coin_res_sender, global_coin_res_receiver) =
-        channel(1);
     Tusk::spawn(
-        mock_committee(),
+        committee().into(),
         /* gc_depth */ 50,
+        /* leader_exclusion */ 0,
         rx_waiter,
         tx_primary,
         tx_output,
-        global_coin_recon_req_sender,
-        global_coin_res_receiver
+        global_coin(),
+        Store::in_memory(),
     );
     tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
 
@@ -192,7 +168,7 @@ async fn not_enough_support() {
     let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
     keys.sort();
 
-    let genesis = Certificate::genesis(&mock_committee())
+    let genesis = Certificate::genesis(&committee())
         .iter()
         .map(|x| x.digest())
         .collect::<BTreeSet<_>>();
@@ -248,18 +224,15 @@ async fn not_enough_support() {
     let (tx_primary, mut rx_primary) = channel(1);
     let (tx_output, mut rx_output) = channel(1);
 
-    let (global_coin_recon_req_sender, _global_coin_recon_req_receiver) =
-        channel(1);
-    let (_global_coin_res_sender, global_coin_res_receiver) =
-        channel(1);
     Tusk::spawn(
-        mock_committee(),
+        committee().into(),
         /* gc_depth */ 50,
+        /* leader_exclusion */ 0,
         rx_waiter,
         tx_primary,
         tx_output,
-        global_coin_recon_req_sender,
-        global_coin_res_receiver
+        global_coin(),
+        Store::in_memory(),
     );
     tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
 
@@ -293,7 +266,7 @@ async fn missing_leader() {
     let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
     keys.sort();
 
-    let genesis = Certificate::genesis(&mock_committee())
+    let genesis = Certificate::genesis(&committee())
         .iter()
         .map(|x| x.digest())
         .collect::<BTreeSet<_>>();
@@ -318,18 +291,15 @@ async fn missing_leader() {
     let (tx_primary, mut rx_primary) = channel(1);
     let (tx_output, mut rx_output) = channel(1);
 
-    let (global_coin_recon_req_sender, _global_coin_recon_req_receiver) =
-        channel(1);
-    let (_global_coin_res_sender, global_coin_res_receiver) =
-        channel(1);
     Tusk::spawn(
-        mock_committee(),
+        committee().into(),
         /* gc_depth */ 50,
+        /* leader_exclusion */ 0,
         rx_waiter,
         tx_primary,
         tx_output,
-        global_coin_recon_req_sender,
-        global_coin_res_receiver
+        global_coin(),
+        Store::in_memory(),
     );
     tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
 
@@ -355,3 +325,84 @@ async fn missing_leader() {
     let certificate = rx_output.recv().await.unwrap();
     assert_eq!(certificate.round(), 4);
 }
+
+// Run for 8 dag rounds. The leader of round 2 only gets support once the leader of round 4
+// commits, so both commit together: the walk back to the leader of round 2 must not order the
+// leader of round 4 a second time.
+#[tokio::test]
+async fn commit_linked_leaders_once() {
+    let mut keys: Vec<_> = keys().into_iter().map(|(x, _)| x).collect();
+    keys.sort();
+
+    let genesis = Certificate::genesis(&committee())
+        .iter()
+        .map(|x| x.digest())
+        .collect::<BTreeSet<_>>();
+
+    let mut certificates = VecDeque::new();
+
+    // Rounds 1 and 2: Fully connected graph.
+    let (out, parents) = make_certificates(1, 2, &genesis, &keys);
+    certificates.extend(out);
+
+    // Round 3: Only node 0 links to the leader of round 2 (node 0).
+    let leader_2_digest = certificates
+        .iter()
+        .find(|x| x.round() == 2 && x.origin() == keys[0])
+        .map(|x| x.digest())
+        .unwrap();
+    let mut next_parents = BTreeSet::new();
+    for name in &keys[..3] {
+        let mut parents = parents.clone();
+        if *name != keys[0] {
+            parents.remove(&leader_2_digest);
+        }
+        let (digest, certificate) = mock_certificate(*name, 3, parents);
+        certificates.push_back(certificate);
+        next_parents.insert(digest);
+    }
+
+    // Rounds 4 to 8: Fully connected graph.
+    let nodes: Vec<_> = keys.iter().cloned().take(3).collect();
+    let (out, parents) = make_certificates(4, 8, &next_parents, &nodes);
+    certificates.extend(out);
+
+    // Round 9: Send a single certificate to trigger the commits.
+    let (_, certificate) = mock_certificate(keys[0], 9, parents);
+    certificates.push_back(certificate);
+
+    // Spawn the consensus engine and sink the primary channel.
+    let (tx_waiter, rx_waiter) = channel(1);
+    let (tx_primary, mut rx_primary) = channel(1);
+    let (tx_output, mut rx_output) = channel(1);
+
+    Tusk::spawn(
+        committee().into(),
+        /* gc_depth */ 50,
+        /* leader_exclusion */ 0,
+        rx_waiter,
+        tx_primary,
+        tx_output,
+        global_coin(),
+        Store::in_memory(),
+    );
+    tokio::spawn(async move { while rx_primary.recv().await.is_some() {} });
+    tokio::spawn(async move {
+        while let Some(certificate) = certificates.pop_front() {
+            tx_waiter.send(certificate).await.unwrap();
+        }
+    });
+
+    // The leaders of rounds 2, 4 and 6 commit once each, and so does everything below them.
+    let mut committed = Vec::new();
+    while let Ok(Some(certificate)) = timeout(Duration::from_millis(500), rx_output.recv()).await {
+        committed.push(certificate);
+    }
+    let digests: BTreeSet<_> = committed.iter().map(|x| x.digest()).collect();
+    assert_eq!(digests.len(), committed.len());
+    for round in [2, 4, 6] {
+        let leaders = committed.iter().filter(|x| x.round() == round && x.origin() == keys[0]);
+        assert_eq!(leaders.count(), 1);
+    }
+    assert_eq!(committed.last().unwrap().round(), 6);
+}
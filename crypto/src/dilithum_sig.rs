@@ -210,7 +210,7 @@ impl Signature {
         Signature(sign(digest.as_ref(), &secret.0))
     }
     pub fn verify(&self, digest: &Digest, public_key: &PublicKey) -> Result<(), CryptoError> {
-        let opened_msg = open(&self.0, &public_key.0).unwrap();
+        let opened_msg = open(&self.0, &public_key.0).map_err(|_| CryptoError::InvalidSignature)?;
         if digest.to_vec() == opened_msg {
             return Ok(());
         }
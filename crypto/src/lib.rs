@@ -1,5 +1,6 @@
 
 use std::array::TryFromSliceError;
+use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use serde::{Deserialize, Serialize};
@@ -84,31 +85,74 @@ pub trait Hash {
     fn digest(&self) -> Digest;
 }
 
-/// This service holds the node's private key. It takes digests as input and returns a signature
-/// over the digest (through a oneshot channel).
+/// A request to the `SignatureService`.
+enum SignatureRequest {
+    /// Sign a digest with the key in effect at an epoch.
+    Sign(u64, Digest, oneshot::Sender<Signature>),
+    /// Sign with a new key from an epoch on.
+    Rotate(u64, SecretKey),
+}
+
+/// This service holds the node's private keys. It takes digests as input and returns a signature
+/// over the digest (through a oneshot channel). Each key signs from its epoch until the next one.
 #[derive(Clone)]
 pub struct SignatureService {
-    channel: Sender<(Digest, oneshot::Sender<Signature>)>,
+    channel: Sender<SignatureRequest>,
 }
+
 impl SignatureService {
     pub fn new(secret: SecretKey) -> Self {
-        let (tx, mut rx): (Sender<(_, oneshot::Sender<_>)>, _) = channel(100);
+        let mut schedule = BTreeMap::new();
+        schedule.insert(0, secret);
+        Self::with_schedule(schedule)
+    }
+
+    /// Creates a service signing with the keys of `schedule`, each one from its epoch on. The
+    /// schedule must hold a key for epoch 0.
+    pub fn with_schedule(mut schedule: BTreeMap<u64, SecretKey>) -> Self {
+        assert!(schedule.contains_key(&0), "The signing schedule starts at epoch 0");
+        let (tx, mut rx): (Sender<SignatureRequest>, _) = channel(100);
         tokio::spawn(async move {
-            while let Some((digest, sender)) = rx.recv().await {
-                let signature = Signature::new(&digest, &secret);
-                let _ = sender.send(signature);
+            while let Some(request) = rx.recv().await {
+                match request {
+                    SignatureRequest::Sign(epoch, digest, sender) => {
+                        let (_, secret) = schedule.range(..=epoch).next_back().unwrap();
+                        let signature = Signature::new(&digest, secret);
+                        let _ = sender.send(signature);
+                    }
+                    SignatureRequest::Rotate(epoch, secret) => {
+                        schedule.insert(epoch, secret);
+                    }
+                }
             }
         });
         Self { channel: tx }
     }
 
+    /// Signs `digest` with the latest key.
     pub async fn request_signature(&mut self, digest: Digest) -> Signature {
+        self.request_signature_at(u64::MAX, digest).await
+    }
+
+    /// Signs `digest` with the key in effect at `epoch`.
+    pub async fn request_signature_at(&mut self, epoch: u64, digest: Digest) -> Signature {
         let (sender, receiver): (oneshot::Sender<_>, oneshot::Receiver<_>) = oneshot::channel();
-        if let Err(e) = self.channel.send((digest, sender)).await {
+        if let Err(e) = self
+            .channel
+            .send(SignatureRequest::Sign(epoch, digest, sender))
+            .await
+        {
             panic!("Failed to send message Signature Service: {}", e);
         }
         receiver
             .await
             .expect("Failed to receive signature from Signature Service")
     }
-}
\ No newline at end of file
+
+    /// Signs with `secret` from `epoch` on.
+    pub async fn rotate(&mut self, epoch: u64, secret: SecretKey) {
+        if let Err(e) = self.channel.send(SignatureRequest::Rotate(epoch, secret)).await {
+            panic!("Failed to send message Signature Service: {}", e);
+        }
+    }
+}
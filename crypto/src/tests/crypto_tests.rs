@@ -133,3 +133,24 @@ async fn signature_service() {
     // Verify the signature we received.
     assert!(signature.verify(&digest, &public_key).is_ok());
 }
+
+#[tokio::test]
+async fn signature_service_rotation() {
+    let mut keys = keys();
+    let (old_public_key, old_secret_key) = keys.pop().unwrap();
+    let (new_public_key, new_secret_key) = keys.pop().unwrap();
+
+    // Spawn the signature service and rotate its key from epoch 5 on.
+    let mut service = SignatureService::new(old_secret_key);
+    service.rotate(5, new_secret_key).await;
+
+    // Earlier epochs are still signed with the old key.
+    let message: &[u8] = b"Hello, world!";
+    let digest = message.digest();
+    let signature = service.request_signature_at(4, digest.clone()).await;
+    assert!(signature.verify(&digest, &old_public_key).is_ok());
+
+    let signature = service.request_signature_at(5, digest.clone()).await;
+    assert!(signature.verify(&digest, &new_public_key).is_ok());
+    assert!(signature.verify(&digest, &old_public_key).is_err());
+}
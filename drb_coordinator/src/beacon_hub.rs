@@ -1,43 +1,334 @@
 use crate::error::DrbError;
-use log::info;
-use model::types_and_const::{Epoch, RandomNum, BEACON_PER_EPOCH};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::SinkExt;
+use log::{info, warn};
+use model::beacon_message::{BeaconRequest, BeaconResponse};
+use model::types_and_const::{Epoch, RandomNum, Round, BEACON_PER_EPOCH, CHANNEL_CAPACITY, MAX_EPOCH};
+use network::{MessageHandler, Receiver as NetworkReceiver, Transport, Writer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::time::sleep;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::{sleep, Instant};
 
-pub struct BeaconHub;
+#[cfg(test)]
+#[path = "tests/beacon_hub_tests.rs"]
+pub mod beacon_hub_tests;
+
+type Beacon = (Epoch, usize, RandomNum);
+
+/// How long a client waits for a specific beacon before the hub gives up on it (in ms).
+const QUERY_TIMEOUT: u64 = 10_000;
+
+/// Resolution of the timer expiring the queries (in ms).
+const TIMER_RESOLUTION: u64 = 1_000;
+
+/// Checks that `(epoch, index)` may name a beacon: its index is within the epoch, and its epoch
+/// starts at a round the dag can reach.
+fn check_beacon(epoch: Epoch, index: usize) -> Result<(), DrbError> {
+    if index == 0 || index as u64 > *BEACON_PER_EPOCH.get().unwrap() {
+        return Err(DrbError::InvalidIndex);
+    }
+    if epoch >= Round::MAX / (2 * *MAX_EPOCH.get().unwrap()) {
+        return Err(DrbError::InvalidEpoch);
+    }
+    Ok(())
+}
+
+/// Asks Breeze for the proof of the beacon at `(epoch, index)` of its avss batch, serialized with
+/// bincode. The reply is `None` if Breeze did not reconstruct the beacon lately.
+pub type ProofQuery = ((Epoch, usize), oneshot::Sender<Option<Vec<u8>>>);
+
+/// Queries forwarded by the network handler to the hub.
+#[derive(Debug)]
+enum BeaconQuery {
+    Get(Epoch, usize),
+    Latest,
+}
+
+/// The `BeaconHub` serves the beacon output of the primary to applications over the network.
+/// Clients may ask for a specific `(epoch, index)`, for the latest output, or subscribe to the
+/// stream of new outputs, and for the proof of an output. With `eval_beacon`, the hub also drives the whole beacon sequence (one
+/// value after the other, every `beacon_req_delay` ms, requesting the rest of the epoch in one go);
+/// otherwise beacons are only reconstructed when clients ask for them, and the newest of them is
+/// the latest output.
+pub struct BeaconHub {
+    beacon_recon_req_sender: Sender<(Epoch, Range<usize>)>,
+    beacon_res_receiver: Receiver<((Epoch, usize), Result<RandomNum, DrbError>)>,
+    beacon_req_delay: u64,
+    eval_beacon: bool,
+    rx_query: Receiver<(BeaconQuery, oneshot::Sender<BeaconResponse>)>,
+    tx_stream: broadcast::Sender<Beacon>,
+    /// Clients waiting for the output of a specific beacon, along with the time at which they give
+    /// up on it.
+    pending: HashMap<(Epoch, usize), Vec<(oneshot::Sender<BeaconResponse>, Instant)>>,
+    latest: Option<Beacon>,
+    /// The epoch whose remaining beacons have been requested at once.
+    requested: Option<Epoch>,
+    /// Beacons of the sequence reconstructed ahead of their turn.
+    prefetched: HashMap<(Epoch, usize), RandomNum>,
+}
 
 impl BeaconHub {
     pub fn spawn(
-        beacon_recon_req_sender: Sender<(Epoch, usize)>,
-        mut beacon_res_receiver: Receiver<((Epoch, usize), Result<RandomNum, DrbError>)>,
-        beacon_req_delay: u64
+        transport: Arc<dyn Transport>,
+        address: Option<SocketAddr>,
+        beacon_recon_req_sender: Sender<(Epoch, Range<usize>)>,
+        beacon_res_receiver: Receiver<((Epoch, usize), Result<RandomNum, DrbError>)>,
+        tx_proof_query: Sender<ProofQuery>,
+        beacon_req_delay: u64,
+        eval_beacon: bool,
     ) {
-        let beacon_per_epoch = *BEACON_PER_EPOCH.get().unwrap();
+        let (tx_query, rx_query) = channel(CHANNEL_CAPACITY);
+        let (tx_stream, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        match address {
+            Some(address) => {
+                NetworkReceiver::spawn_with_transport(
+                    transport,
+                    address,
+                    BeaconReceiverHandler {
+                        tx_query,
+                        tx_stream: tx_stream.clone(),
+                        tx_proof_query,
+                    },
+                );
+                info!("Beacon service listening on {}", address);
+            }
+            None => info!("No beacon service address, the beacon is not served"),
+        }
+
         tokio::spawn(async move {
-            let mut current_epoch = 0;
-            let mut current_index = 1;
-            loop {
-                beacon_recon_req_sender
-                    .send((current_epoch, current_index as usize))
-                    .await
-                    .unwrap();
-                match beacon_res_receiver.recv().await.unwrap() {
-                    ((e, i), Ok(random)) => {
-                        info!("Beacon output for epoch:{} index:{} is {}", e, i, random);
+            Self {
+                beacon_recon_req_sender,
+                beacon_res_receiver,
+                beacon_req_delay,
+                eval_beacon,
+                rx_query,
+                tx_stream,
+                pending: HashMap::new(),
+                latest: None,
+                requested: None,
+                prefetched: HashMap::new(),
+            }
+            .run()
+            .await;
+        });
+    }
+
+    async fn run(&mut self) {
+        let beacon_per_epoch = *BEACON_PER_EPOCH.get().unwrap();
+        let mut current_epoch = 0;
+        let mut current_index = 1;
+
+        // The next beacon of the sequence is output once this timer fires.
+        let timer = sleep(Duration::from_millis(0));
+        tokio::pin!(timer);
+        let mut waiting = false;
+
+        // The queries of the clients are expired once this timer fires.
+        let query_timer = sleep(Duration::from_millis(TIMER_RESOLUTION));
+        tokio::pin!(query_timer);
+
+        loop {
+            // The queries of the clients come last, so that they cannot hold the beacons back.
+            tokio::select! {
+                biased;
+                () = &mut timer, if self.eval_beacon && !waiting => {
+                    if let Some(random) = self.prefetched.remove(&(current_epoch, current_index as usize)) {
+                        self.output(current_epoch, current_index as usize, random);
                         if current_index < beacon_per_epoch {
                             current_index += 1;
                         }else {
                             current_epoch += 1;
                             current_index = 1;
                         }
-                        sleep(Duration::from_millis(beacon_req_delay)).await;
+                        timer.as_mut().reset(tokio::time::Instant::now() + Duration::from_millis(self.beacon_req_delay));
+                        continue;
+                    }
+                    if self.requested != Some(current_epoch) {
+                        // Ask for the rest of the epoch at once, the beacons are then output one by one.
+                        let indices = current_index as usize..beacon_per_epoch as usize + 1;
+                        self.beacon_recon_req_sender.send((current_epoch, indices)).await.unwrap();
+                        self.requested = Some(current_epoch);
                     }
-                    (_, Err(_)) => {
-                        sleep(Duration::from_millis(200)).await;
+                    waiting = true;
+                }
+                Some(((e, i), result)) = self.beacon_res_receiver.recv() => {
+                    let is_next = waiting && (e, i) == (current_epoch, current_index as usize);
+                    match result {
+                        Ok(random) => {
+                            for (reply, _) in self.pending.remove(&(e, i)).unwrap_or_default() {
+                                let _ = reply.send(BeaconResponse::Beacon { epoch: e, index: i, value: random.clone() });
+                            }
+                            if !self.eval_beacon {
+                                // Without a sequence to follow, the newest beacon is the latest output.
+                                if self.latest.as_ref().is_none_or(|(epoch, index, _)| (*epoch, *index) < (e, i)) {
+                                    self.output(e, i, random);
+                                }
+                                continue;
+                            }
+                            if !is_next {
+                                // Keep the beacons requested ahead of their turn (including the next
+                                // one, if it arrives before its timer fires).
+                                if self.requested == Some(e) && i >= current_index as usize {
+                                    self.prefetched.insert((e, i), random);
+                                }
+                                continue;
+                            }
+                            self.output(e, i, random);
+                            if current_index < beacon_per_epoch {
+                                current_index += 1;
+                            }else {
+                                current_epoch += 1;
+                                current_index = 1;
+                            }
+                            waiting = false;
+                            timer.as_mut().reset(tokio::time::Instant::now() + Duration::from_millis(self.beacon_req_delay));
+                        }
+                        Err(error) => {
+                            for (reply, _) in self.pending.remove(&(e, i)).unwrap_or_default() {
+                                let _ = reply.send(BeaconResponse::Error(error.to_string()));
+                            }
+                            if is_next {
+                                // The beacon is not ready yet, retry shortly.
+                                waiting = false;
+                                self.requested = None;
+                                timer.as_mut().reset(tokio::time::Instant::now() + Duration::from_millis(200));
+                            }
+                        }
                     }
                 }
+                Some((query, reply)) = self.rx_query.recv() => match query {
+                    BeaconQuery::Get(epoch, index) => {
+                        let deadline = Instant::now() + Duration::from_millis(QUERY_TIMEOUT);
+                        self.pending.entry((epoch, index)).or_default().push((reply, deadline));
+                        self.beacon_recon_req_sender.send((epoch, index..index + 1)).await.unwrap();
+                    }
+                    BeaconQuery::Latest => {
+                        let response = match &self.latest {
+                            Some((epoch, index, value)) => BeaconResponse::Beacon { epoch: *epoch, index: *index, value: value.clone() },
+                            None => BeaconResponse::Error("No beacon output yet".to_string()),
+                        };
+                        let _ = reply.send(response);
+                    }
+                },
+                () = &mut query_timer => {
+                    // Forget the clients that left, and tell the others when their beacon is
+                    // taking too long.
+                    let now = Instant::now();
+                    self.pending.retain(|_, replies| {
+                        let (expired, waiting): (Vec<_>, Vec<_>) = replies
+                            .drain(..)
+                            .filter(|(reply, _)| !reply.is_closed())
+                            .partition(|(_, deadline)| *deadline <= now);
+                        for (reply, _) in expired {
+                            let _ = reply.send(BeaconResponse::Error("Timed out waiting for the beacon".to_string()));
+                        }
+                        *replies = waiting;
+                        !replies.is_empty()
+                    });
+                    query_timer.as_mut().reset(now + Duration::from_millis(TIMER_RESOLUTION));
+                }
             }
-        });
+        }
+    }
+
+    /// Publish the next beacon of the sequence.
+    fn output(&mut self, epoch: Epoch, index: usize, random: RandomNum) {
+        // NOTE: This log entry is used to compute performance.
+        info!("Beacon output for epoch:{} index:{} is {}", epoch, index, random);
+        self.latest = Some((epoch, index, random.clone()));
+        // An error only means that nobody subscribed.
+        let _ = self.tx_stream.send((epoch, index, random));
+    }
+}
+
+/// Defines how the network receiver handles incoming beacon requests.
+#[derive(Clone)]
+struct BeaconReceiverHandler {
+    tx_query: Sender<(BeaconQuery, oneshot::Sender<BeaconResponse>)>,
+    tx_stream: broadcast::Sender<Beacon>,
+    tx_proof_query: Sender<ProofQuery>,
+}
+
+impl BeaconReceiverHandler {
+    async fn query(&self, query: BeaconQuery) -> BeaconResponse {
+        let (sender, receiver) = oneshot::channel();
+        self.tx_query
+            .send((query, sender))
+            .await
+            .expect("Failed to send beacon query");
+        receiver
+            .await
+            .unwrap_or_else(|_| BeaconResponse::Error("Beacon hub dropped the query".to_string()))
+    }
+
+    /// Asks Breeze for the proof of a beacon, which must have been output already.
+    async fn proof(&self, epoch: Epoch, index: usize) -> BeaconResponse {
+        if let Err(e) = check_beacon(epoch, index) {
+            return BeaconResponse::Error(e.to_string());
+        }
+        // The beacons follow the coins in the avss batch.
+        let batch_index = *MAX_EPOCH.get().unwrap() as usize + index;
+        let (sender, receiver) = oneshot::channel();
+        self.tx_proof_query
+            .send(((epoch, batch_index), sender))
+            .await
+            .expect("Failed to send proof query");
+        match receiver.await {
+            Ok(Some(proof)) => BeaconResponse::Proof(proof),
+            _ => BeaconResponse::Error("No proof of this beacon, ask for the beacon first".to_string()),
+        }
+    }
+
+    async fn reply(writer: &mut Writer, response: &BeaconResponse) -> Result<(), Box<dyn Error>> {
+        let bytes = bincode::serialize(response)?;
+        writer.send(Bytes::from(bytes)).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageHandler for BeaconReceiverHandler {
+    async fn dispatch(&self, writer: &mut Writer, serialized: Bytes) -> Result<(), Box<dyn Error>> {
+        match bincode::deserialize(&serialized)? {
+            BeaconRequest::Get { epoch, index } => {
+                let response = match check_beacon(epoch, index) {
+                    Ok(()) => self.query(BeaconQuery::Get(epoch, index)).await,
+                    Err(e) => BeaconResponse::Error(e.to_string()),
+                };
+                Self::reply(writer, &response).await
+            }
+            BeaconRequest::Latest => {
+                let response = self.query(BeaconQuery::Latest).await;
+                Self::reply(writer, &response).await
+            }
+            BeaconRequest::Proof { epoch, index } => {
+                let response = self.proof(epoch, index).await;
+                Self::reply(writer, &response).await
+            }
+            BeaconRequest::Subscribe => {
+                // The connection is dedicated to the stream from now on.
+                let mut rx_stream = self.tx_stream.subscribe();
+                loop {
+                    match rx_stream.recv().await {
+                        Ok((epoch, index, value)) => {
+                            let response = BeaconResponse::Beacon { epoch, index, value };
+                            Self::reply(writer, &response).await?;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Beacon subscriber lagging behind, skipped {} beacons", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                    }
+                }
+            }
+        }
     }
 }
@@ -1,15 +1,21 @@
+use crate::coordinator_store::CoordinatorStore;
 use crate::error::DrbError;
-use config::Committee;
-use model::breeze_universal::{BreezeCertificate, BreezeReconRequest};
+use config::{Protocol, SharedCommittee};
+use model::breeze_universal::{BreezeCertificate, BreezeReconRequest, Committed, Complaint, ComplaintQuery, Complaints};
 use model::types_and_const::*;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use log::info;
+use std::ops::Range;
+use log::{debug, info, warn};
+use store::Store;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::{watch,RwLock};
+use tokio::sync::{oneshot, watch};
+
+#[cfg(test)]
+#[path = "tests/coordinator_tests.rs"]
+pub mod coordinator_tests;
 
 pub struct Coordinator {
-    committee: Arc<RwLock<Committee>>,
+    committee: SharedCommittee,
     // share phase
     b_share_cmd_sender: Sender<Epoch>,
     certificate_from_breeze: Receiver<BreezeCertificate>,
@@ -17,13 +23,16 @@ pub struct Coordinator {
     // propose certificate to the consensus
     certificate_to_consensus: Sender<BreezeCertificate>,
     certificate_to_init_consensus: Sender<BreezeCertificate>,
-    cer_decided_from_consensus: Receiver<BreezeCertificate>,
+    // certificates and complaints, in the order the consensus committed them
+    cer_decided_from_consensus: Receiver<Committed>,
     cc_decided_from_init_consensus: Receiver<HashSet<BreezeCertificate>>,
+    // asks breeze about the committed complaints against the dealings
+    complaint_query_sender: Sender<ComplaintQuery>,
     // common core get
     // recon request from consensus
     global_coin_recon_req_receiver: Receiver<Round>,
     // recon request from beacon consumer
-    beacon_recon_req_receiver: Receiver<(Epoch, usize)>,
+    beacon_recon_req_receiver: Receiver<(Epoch, Range<usize>)>,
     // relay recon request to breeze
     b_recon_req_sender: Sender<BreezeReconRequest>,
     b_recon_res_receiver: Receiver<(Epoch, usize, RandomNum)>,
@@ -35,25 +44,38 @@ pub struct Coordinator {
     certificate_buffer: HashMap<Epoch, HashSet<BreezeCertificate>>,
 
     decided_common_core: HashSet<Epoch>,
+    /// The committed complaints, which every node applies in the same order.
+    complaints: Complaints,
+    /// Decided certificates of dealings accused and not yet resolved, kept out of the common core.
+    parked: HashMap<Epoch, Vec<BreezeCertificate>>,
     beacon_reconstructed: HashMap<(Epoch, usize), RandomNum>,
+    /// How many leader coins to reconstruct ahead of the consensus.
+    coin_lookahead: usize,
+    /// The latest coin the consensus asked for, as the epoch of its common core and its index.
+    last_coin: Option<(Epoch, usize)>,
+    /// The coins being reconstructed ahead of the consensus.
+    prefetching: HashSet<(Epoch, usize)>,
+    coordinator_store: CoordinatorStore,
     
     recover_signal_sender: watch::Sender<()>,
 
-    eval_beacon: bool
+    eval_beacon: bool,
+    /// The consensus protocol, which determines the leader rounds.
+    protocol: Protocol,
 }
 
 impl Coordinator {
     pub async fn spawn(
-        // committee: Arc<RwLock<Committee>>,
-        committee: Committee,
+        committee: SharedCommittee,
         b_share_cmd_sender: Sender<Epoch>,
         certificate_from_breeze: Receiver<BreezeCertificate>,
         certificate_to_consensus: Sender<BreezeCertificate>,
         certificate_to_init_consensus: Sender<BreezeCertificate>,
-        cer_decided_from_consensus: Receiver<BreezeCertificate>,
+        cer_decided_from_consensus: Receiver<Committed>,
         cc_decided_from_init_consensus: Receiver<HashSet<BreezeCertificate>>,
+        complaint_query_sender: Sender<ComplaintQuery>,
         global_coin_recon_req_receiver: Receiver<Round>,
-        beacon_recon_req_receiver: Receiver<(Epoch, usize)>,
+        beacon_recon_req_receiver: Receiver<(Epoch, Range<usize>)>,
         b_recon_req_sender: Sender<BreezeReconRequest>,
         b_recon_res_receiver: Receiver<(Epoch, usize, RandomNum)>,
         global_coin_res_sender: Sender<(Round, Result<RandomNum, DrbError>)>,
@@ -62,11 +84,18 @@ impl Coordinator {
         recover_signal_sender: watch::Sender<()>,
 
         eval_beacon: bool,
+        protocol: Protocol,
+        coin_lookahead: usize,
+        store: Store,
     ) {
-        let certificate_buffer = HashMap::new();
-        let decided_common_core = HashSet::new();
-        let beacon_reconstructed = HashMap::new();
-        let committee = Arc::new(RwLock::new(committee));
+        // Reload the state persisted before a restart.
+        let mut coordinator_store = CoordinatorStore::new(store);
+        let state = coordinator_store.load().await;
+        let certificate_buffer = state.certificate_buffer;
+        let decided_common_core = state.decided_common_core;
+        let beacon_reconstructed = state.beacon_reconstructed;
+        let complaints = state.complaints;
+        let parked = state.parked;
         tokio::spawn(async move {
             Self {
                 committee,
@@ -76,6 +105,7 @@ impl Coordinator {
                 certificate_to_init_consensus,
                 cer_decided_from_consensus,
                 cc_decided_from_init_consensus,
+                complaint_query_sender,
                 global_coin_recon_req_receiver,
                 beacon_recon_req_receiver,
                 b_recon_req_sender,
@@ -85,18 +115,38 @@ impl Coordinator {
 
                 certificate_buffer,
                 decided_common_core,
+                complaints,
+                parked,
                 beacon_reconstructed,
+                coin_lookahead,
+                last_coin: None,
+                prefetching: HashSet::new(),
+                coordinator_store,
                 recover_signal_sender,
 
-                eval_beacon
+                eval_beacon,
+                protocol,
             }
             .run()
             .await;
         });
     }
     async fn run(&mut self) {
-        self.b_share_cmd_sender.send(0).await.unwrap();
-        info!("Share command send for epoch:{}", 0);
+        match self.decided_common_core.iter().max() {
+            // Resume after a restart: the initial common core is already decided, so the
+            // consensus can start right away. Breeze skips the epochs it already dealt.
+            Some(last_decided) => {
+                let epoch = last_decided + 1;
+                info!("Coordinator recovered up to epoch:{}", last_decided);
+                self.b_share_cmd_sender.send(epoch).await.unwrap();
+                info!("Share command send for epoch:{}", epoch);
+                self.recover_signal_sender.send(()).unwrap();
+            }
+            None => {
+                self.b_share_cmd_sender.send(0).await.unwrap();
+                info!("Share command send for epoch:{}", 0);
+            }
+        }
         let max_epoch = *MAX_EPOCH.get().unwrap();
         let beacon_per_epoch = *BEACON_PER_EPOCH.get().unwrap();
         info!("Beacons for leader election per epoch:{}", max_epoch);
@@ -105,8 +155,10 @@ impl Coordinator {
         }else {
             info!("Beacons for output per epoch:{}", 0);
         }
+        self.prefetch_coins().await;
         loop {
             tokio::select! {
+                biased;
                 Some(cer) = self.certificate_from_breeze.recv() => {
                     info!("Breeze Certificate received for epoch:{}", cer.epoch);
                     if cer.epoch == 0{
@@ -116,94 +168,112 @@ impl Coordinator {
                     }
                 }
                 Some(cc) = self.cc_decided_from_init_consensus.recv()=>{
+                    if self.decided_common_core.contains(&0) {
+                        continue;
+                    }
+                    // The secondary BFT decides this core as a whole, it is not checked against
+                    // the complaints.
+                    self.coordinator_store.persist_certificates(0, &cc).await;
                     self.certificate_buffer.insert(0, cc);
                     self.decided_common_core.insert(0);
+                    self.coordinator_store.persist_decided_common_core(&self.decided_common_core).await;
                     info!("Common core for epoch:{} decided. Beacon resource add:{}", 0, beacon_per_epoch);
                     self.b_share_cmd_sender.send(1).await.unwrap();
                     info!("Share command send for epoch:{}", 1);
                     self.recover_signal_sender.send(()).unwrap();
+                    self.prefetch_coins().await;
                 }
-                Some(cer) = self.cer_decided_from_consensus.recv() =>{
+                Some(committed) = self.cer_decided_from_consensus.recv() =>{
+                    let cer = match committed {
+                        Committed::Complaint(complaint) => {
+                            self.handle_complaint(complaint).await;
+                            continue;
+                        }
+                        Committed::Certificate(cer) => cer,
+                    };
                     let epoch = cer.epoch;
                     if self.decided_common_core.contains(&epoch) {
                         continue;
                     }
-                    let inner_map = self.certificate_buffer
-                        .entry(epoch)
-                        .or_insert_with(HashSet::new);
-                    inner_map.insert(cer);
-                    let committee = self.committee.read().await;
-                    let fault_tolerance = committee.authorities_fault_tolerance();
-                    if inner_map.len() >= fault_tolerance + 1{
-                        self.decided_common_core.insert(epoch);
-                        info!("Common core for epoch:{} decided. Beacon resource add:{}", epoch, beacon_per_epoch);
-                        self.b_share_cmd_sender.send(epoch + 1).await.unwrap();
-                        info!("Share command send for epoch:{}", epoch + 1);
+                    if self.complaints.is_blocked(epoch, &cer.c) {
+                        warn!("Dealing {} of epoch:{} is accused, kept out of the common core", cer.c, epoch);
+                        self.parked.entry(epoch).or_insert_with(Vec::new).push(cer);
+                        self.coordinator_store.persist_parked(&self.parked).await;
+                        continue;
                     }
+                    self.add_to_common_core(cer).await;
                 }
 
                 Some(round) = self.global_coin_recon_req_receiver.recv() =>{
-                    #[cfg(feature = "dolphin")]
-                    let (mut epoch, index) = leader_round_to_epoch_index(round, max_epoch);
-                    #[cfg(not(feature = "dolphin"))]
-                    let (mut epoch, index) = leader_round_to_epoch_index_tusk(round, max_epoch);
+                    let (mut epoch, index) = match self.protocol {
+                        Protocol::Tusk | Protocol::Bullshark => leader_round_to_epoch_index_tusk(round, max_epoch),
+                        Protocol::Dolphin => leader_round_to_epoch_index(round, max_epoch),
+                    };
                     if index > max_epoch as usize {
                         self.global_coin_res_sender.send((round,Err(DrbError::InvalidIndex))).await.unwrap();
                         continue;
                     }
                     epoch -= 1;
+                    if self.last_coin.is_none_or(|last| last < (epoch, index)) {
+                        self.last_coin = Some((epoch, index));
+                    }
                     if !self.decided_common_core.contains(&epoch){
                         self.global_coin_res_sender.send((round,Err(DrbError::NoCommonCore))).await.unwrap();
                         continue;
                     }
                     let mut flag = true;
                     if let Some(v) = self.beacon_reconstructed.get(&(epoch,index)){
-                        self.global_coin_res_sender.send((round,Ok(*v))).await.unwrap();
+                        self.global_coin_res_sender.send((round,Ok(v.clone()))).await.unwrap();
                         flag = false;
                     }
-                    if flag{
-                        let certificates = &self.certificate_buffer[&epoch];
-                        let recon_req = BreezeReconRequest{
-                            c: certificates.iter().map(|x| x.c).collect(),
-                            epoch,
-                            index
-                        };
-                        self.b_recon_req_sender.send(recon_req).await.unwrap();
+                    // A coin already being reconstructed is served once it is (this request is not
+                    // repeated, but the next one for the same coin is).
+                    if flag && !self.prefetching.remove(&(epoch, index)) {
+                        self.request_reconstruction(epoch, index..index + 1).await;
                     }
+                    self.prefetch_coins().await;
                 }
 
-                Some((epoch,index)) = self.beacon_recon_req_receiver.recv() =>{
+                Some((epoch,indices)) = self.beacon_recon_req_receiver.recv() =>{
                     if !self.decided_common_core.contains(&epoch){
-                        self.beacon_res_sender.send(((epoch,index),Err(DrbError::NoCommonCore))).await.unwrap();
-                        continue;
-                    }
-                    if index > beacon_per_epoch as usize{
-                        self.beacon_res_sender.send(((epoch,index),Err(DrbError::InvalidIndex))).await.unwrap();
+                        for index in indices {
+                            self.beacon_res_sender.send(((epoch,index),Err(DrbError::NoCommonCore))).await.unwrap();
+                        }
                         continue;
                     }
-                    let mut flag = true;
-                    if let Some(v) = self.beacon_reconstructed.get(&(epoch,index)){
-                        self.beacon_res_sender.send(((epoch,index),Ok(*v))).await.unwrap();
-                        flag = false;
+                    // Serve the beacons already reconstructed, and ask Breeze for the others in as
+                    // few ranges as possible.
+                    let mut missing: Vec<Range<usize>> = Vec::new();
+                    for index in indices {
+                        if index == 0 || index > beacon_per_epoch as usize{
+                            self.beacon_res_sender.send(((epoch,index),Err(DrbError::InvalidIndex))).await.unwrap();
+                            continue;
+                        }
+                        // Beacons follow the coins of the leaders in the avss batch.
+                        let batch_index = index + max_epoch as usize;
+                        if let Some(v) = self.beacon_reconstructed.get(&(epoch,batch_index)){
+                            self.beacon_res_sender.send(((epoch,index),Ok(v.clone()))).await.unwrap();
+                            continue;
+                        }
+                        match missing.last_mut() {
+                            Some(range) if range.end == batch_index => range.end += 1,
+                            _ => missing.push(batch_index..batch_index + 1),
+                        }
                     }
-                    if flag{
-                        let certificates = &self.certificate_buffer[&epoch];
-                        let recon_req = BreezeReconRequest{
-                            c: certificates.iter().map(|x| x.c).collect(),
-                            epoch,
-                            index:index + max_epoch as usize
-                        };
-                        self.b_recon_req_sender.send(recon_req).await.unwrap();
+                    for range in missing {
+                        self.request_reconstruction(epoch, range).await;
                     }
                 }
 
                 Some((epoch,index,value)) = self.b_recon_res_receiver.recv() =>{
-                    self.beacon_reconstructed.insert((epoch,index),value);
+                    self.prefetching.remove(&(epoch, index));
+                    self.beacon_reconstructed.insert((epoch,index),value.clone());
+                    self.coordinator_store.persist_beacon(epoch, index, value.clone()).await;
                     if index <= max_epoch as usize{
-                        #[cfg(feature = "dolphin")]
-                        let round = epoch_index_to_leader_round(epoch +1,index,max_epoch);
-                        #[cfg(not(feature = "dolphin"))]
-                        let round = epoch_index_to_leader_round_tusk(epoch +1,index,max_epoch);
+                        let round = match self.protocol {
+                            Protocol::Tusk | Protocol::Bullshark => epoch_index_to_leader_round_tusk(epoch + 1, index, max_epoch),
+                            Protocol::Dolphin => epoch_index_to_leader_round(epoch + 1, index, max_epoch),
+                        };
                         self.global_coin_res_sender.send((round,Ok(value))).await.unwrap();
                     } else if index <= (max_epoch+ beacon_per_epoch) as usize{
                         self.beacon_res_sender.send(((epoch,index - max_epoch as usize),Ok(value))).await.unwrap();
@@ -213,4 +283,107 @@ impl Coordinator {
             }
         }
     }
+
+    /// Applies a committed complaint, as checked by Breeze: admits the parked dealings whose
+    /// complaints are all resolved, and drops the excluded ones.
+    async fn handle_complaint(&mut self, complaint: Complaint) {
+        let (sender, receiver) = oneshot::channel();
+        self.complaint_query_sender
+            .send((complaint, sender))
+            .await
+            .expect("Failed to send complaint query to breeze");
+        let status = match receiver.await.expect("Breeze dropped the complaint query") {
+            Some(status) => status,
+            None => return,
+        };
+        if !self.complaints.update(&status) {
+            return;
+        }
+        self.coordinator_store.persist_complaints(&self.complaints).await;
+
+        let parked = std::mem::take(&mut self.parked);
+        for (epoch, certificates) in parked {
+            for cer in certificates {
+                if self.complaints.is_excluded(epoch, &cer.c) || self.decided_common_core.contains(&epoch) {
+                    continue;
+                }
+                if self.complaints.is_blocked(epoch, &cer.c) {
+                    self.parked.entry(epoch).or_insert_with(Vec::new).push(cer);
+                } else {
+                    self.add_to_common_core(cer).await;
+                }
+            }
+        }
+        self.coordinator_store.persist_parked(&self.parked).await;
+    }
+
+    /// Adds a decided certificate to the common core of its epoch, which is decided once its
+    /// dealers carry `f + 1` of the weight.
+    async fn add_to_common_core(&mut self, cer: BreezeCertificate) {
+        let epoch = cer.epoch;
+        let inner_map = self.certificate_buffer
+            .entry(epoch)
+            .or_insert_with(HashSet::new);
+        inner_map.insert(cer);
+        self.coordinator_store.persist_certificates(epoch, inner_map).await;
+        let committee = self.committee.at(epoch);
+        if committee.weight_of(inner_map.iter().map(|cer| &cer.dealer)) >= committee.weighted_validity_threshold() {
+            self.decided_common_core.insert(epoch);
+            self.coordinator_store.persist_decided_common_core(&self.decided_common_core).await;
+            info!("Common core for epoch:{} decided. Beacon resource add:{}", epoch, *BEACON_PER_EPOCH.get().unwrap());
+            self.b_share_cmd_sender.send(epoch + 1).await.unwrap();
+            info!("Share command send for epoch:{}", epoch + 1);
+            if self.parked.remove(&epoch).is_some() {
+                self.coordinator_store.persist_parked(&self.parked).await;
+            }
+            self.prefetch_coins().await;
+        }
+    }
+
+    /// Reconstructs ahead of time the coins of the `coin_lookahead` leader rounds following the
+    /// latest one the consensus asked for, as far as their common cores are decided. They are
+    /// cached in `beacon_reconstructed`, so the consensus usually gets its coins right away.
+    /// Bullshark only asks for coins after timeouts, which prefetching cannot anticipate.
+    async fn prefetch_coins(&mut self) {
+        if self.protocol == Protocol::Bullshark {
+            return;
+        }
+        let max_epoch = *MAX_EPOCH.get().unwrap() as usize;
+        let (mut epoch, mut index) = self.last_coin.unwrap_or((0, 0));
+        let mut missing: Vec<(Epoch, Range<usize>)> = Vec::new();
+        for _ in 0..self.coin_lookahead {
+            index += 1;
+            if index > max_epoch {
+                epoch += 1;
+                index = 1;
+            }
+            if !self.decided_common_core.contains(&epoch) {
+                break;
+            }
+            if self.beacon_reconstructed.contains_key(&(epoch, index))
+                || !self.prefetching.insert((epoch, index))
+            {
+                continue;
+            }
+            match missing.last_mut() {
+                Some((e, range)) if *e == epoch && range.end == index => range.end += 1,
+                _ => missing.push((epoch, index..index + 1)),
+            }
+        }
+        for (epoch, range) in missing {
+            debug!("Prefetching the coins {:?} of epoch:{}", range, epoch);
+            self.request_reconstruction(epoch, range).await;
+        }
+    }
+
+    /// Asks Breeze to reconstruct the outputs at `indices` of the avss batch of `epoch`.
+    async fn request_reconstruction(&self, epoch: Epoch, indices: Range<usize>) {
+        let certificates = &self.certificate_buffer[&epoch];
+        let recon_req = BreezeReconRequest{
+            common_core: certificates.iter().cloned().collect(),
+            epoch,
+            indices
+        };
+        self.b_recon_req_sender.send(recon_req).await.unwrap();
+    }
 }
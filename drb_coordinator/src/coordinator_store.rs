@@ -0,0 +1,133 @@
+use futures::stream::StreamExt as _;
+use model::breeze_universal::{BreezeCertificate, Complaints};
+use model::types_and_const::{Epoch, RandomNum};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use store::Store;
+
+#[cfg(test)]
+#[path = "tests/coordinator_store_tests.rs"]
+pub mod coordinator_store_tests;
+
+/// The column family of the primary's store holding the coordinator state.
+const COORDINATOR: &str = "drb";
+
+const DECIDED_COMMON_CORE_KEY: &str = "decided_common_core";
+const BUFFERED_EPOCHS_KEY: &str = "buffered_epochs";
+const COMPLAINTS_KEY: &str = "complaints";
+const PARKED_KEY: &str = "parked";
+
+fn certificate_buffer_key(epoch: Epoch) -> String {
+    format!("certificate_buffer/{}", epoch)
+}
+
+fn beacons_prefix(epoch: Epoch) -> String {
+    format!("beacons/{}/", epoch)
+}
+
+/// Each beacon has a key of its own, so that persisting one does not rewrite the others.
+fn beacon_key(epoch: Epoch, index: usize) -> String {
+    format!("{}{}", beacons_prefix(epoch), index)
+}
+
+/// The part of the coordinator state that survives a restart.
+#[derive(Default)]
+pub struct CoordinatorState {
+    pub certificate_buffer: HashMap<Epoch, HashSet<BreezeCertificate>>,
+    pub decided_common_core: HashSet<Epoch>,
+    pub beacon_reconstructed: HashMap<(Epoch, usize), RandomNum>,
+    pub complaints: Complaints,
+    pub parked: HashMap<Epoch, Vec<BreezeCertificate>>,
+}
+
+/// Persists the coordinator state in the `drb` column family of the primary's store.
+pub struct CoordinatorStore {
+    store: Store,
+    /// The epochs whose certificate buffer is in the store. It is read back by `load`, which must
+    /// run before anything is persisted.
+    buffered_epochs: BTreeSet<Epoch>,
+}
+
+impl CoordinatorStore {
+    pub fn new(store: Store) -> Self {
+        Self {
+            store: store.family(COORDINATOR),
+            buffered_epochs: BTreeSet::new(),
+        }
+    }
+
+    async fn read<T: DeserializeOwned>(&mut self, key: &str) -> Option<T> {
+        self.store
+            .read(key.as_bytes().to_vec())
+            .await
+            .expect("Failed to read coordinator state from store")
+            .map(|bytes| {
+                bincode::deserialize(&bytes).expect("Failed to deserialize coordinator state")
+            })
+    }
+
+    async fn write<T: Serialize>(&mut self, key: &str, value: &T) {
+        let bytes = bincode::serialize(value).expect("Failed to serialize coordinator state");
+        self.store.write(key.as_bytes().to_vec(), bytes).await;
+    }
+
+    pub async fn load(&mut self) -> CoordinatorState {
+        let mut state = CoordinatorState::default();
+        self.buffered_epochs = self.read(BUFFERED_EPOCHS_KEY).await.unwrap_or_default();
+        for epoch in self.buffered_epochs.clone() {
+            if let Some(certificates) = self.read(&certificate_buffer_key(epoch)).await {
+                state.certificate_buffer.insert(epoch, certificates);
+            }
+        }
+        state.decided_common_core = self.read(DECIDED_COMMON_CORE_KEY).await.unwrap_or_default();
+        state.complaints = self.read(COMPLAINTS_KEY).await.unwrap_or_default();
+        state.parked = self.read(PARKED_KEY).await.unwrap_or_default();
+        // Beacons are only reconstructed for epochs whose common core is decided.
+        for epoch in state.decided_common_core.iter() {
+            let prefix = beacons_prefix(*epoch);
+            let beacons: Vec<_> = self
+                .store
+                .prefix_iter(prefix.as_bytes().to_vec())
+                .collect()
+                .await;
+            for (key, value) in beacons {
+                let index = String::from_utf8(key[prefix.len()..].to_vec())
+                    .ok()
+                    .and_then(|index| index.parse().ok())
+                    .expect("Failed to parse beacon key");
+                let value = bincode::deserialize(&value).expect("Failed to deserialize beacon");
+                state.beacon_reconstructed.insert((*epoch, index), value);
+            }
+        }
+        state
+    }
+
+    pub async fn persist_certificates(
+        &mut self,
+        epoch: Epoch,
+        certificates: &HashSet<BreezeCertificate>,
+    ) {
+        self.write(&certificate_buffer_key(epoch), certificates).await;
+        if self.buffered_epochs.insert(epoch) {
+            let buffered_epochs = self.buffered_epochs.clone();
+            self.write(BUFFERED_EPOCHS_KEY, &buffered_epochs).await;
+        }
+    }
+
+    pub async fn persist_decided_common_core(&mut self, decided: &HashSet<Epoch>) {
+        self.write(DECIDED_COMMON_CORE_KEY, decided).await;
+    }
+
+    pub async fn persist_complaints(&mut self, complaints: &Complaints) {
+        self.write(COMPLAINTS_KEY, complaints).await;
+    }
+
+    pub async fn persist_parked(&mut self, parked: &HashMap<Epoch, Vec<BreezeCertificate>>) {
+        self.write(PARKED_KEY, parked).await;
+    }
+
+    pub async fn persist_beacon(&mut self, epoch: Epoch, index: usize, value: RandomNum) {
+        self.write(&beacon_key(epoch, index), &value).await;
+    }
+}
@@ -6,4 +6,6 @@ pub enum DrbError {
     NoCommonCore,
     #[error("Index out of bound")]
     InvalidIndex,
+    #[error("Epoch out of bound")]
+    InvalidEpoch,
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 
 pub mod coordinator;
 pub mod error;
+pub mod coordinator_store;
 pub mod beacon_hub;
@@ -0,0 +1,139 @@
+use super::*;
+use futures::stream::StreamExt as _;
+use network::TcpTransport;
+use tokio::net::TcpStream;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+// Fixture: the value of the beacon `(epoch, index)`.
+fn beacon(epoch: Epoch, index: usize) -> RandomNum {
+    RandomNum(vec![epoch as u8, index as u8])
+}
+
+// Fixture: a coordinator reconstructing every beacon it is asked for, and a Breeze proving the
+// beacons of epoch 1 (the proof being the epoch and the index in the avss batch).
+fn spawn_hub(address: SocketAddr, eval_beacon: bool) {
+    BEACON_PER_EPOCH.get_or_init(|| 10);
+    MAX_EPOCH.get_or_init(|| 4);
+    let (tx_request, mut rx_request) = channel::<(Epoch, Range<usize>)>(10);
+    let (tx_beacon, rx_beacon) = channel(100);
+    tokio::spawn(async move {
+        while let Some((epoch, indices)) = rx_request.recv().await {
+            for index in indices {
+                let _ = tx_beacon.send(((epoch, index), Ok(beacon(epoch, index)))).await;
+            }
+        }
+    });
+    let (tx_proof_query, mut rx_proof_query) = channel::<ProofQuery>(10);
+    tokio::spawn(async move {
+        while let Some(((epoch, index), reply)) = rx_proof_query.recv().await {
+            let _ = reply.send((epoch == 1).then(|| vec![epoch as u8, index as u8]));
+        }
+    });
+    BeaconHub::spawn(Arc::new(TcpTransport), Some(address), tx_request, rx_beacon, tx_proof_query, /* beacon_req_delay */ 10, eval_beacon);
+}
+
+// Fixture: a connection to the hub, on which `request` was sent.
+async fn connect(address: SocketAddr, request: BeaconRequest) -> Framed<TcpStream, LengthDelimitedCodec> {
+    let stream = TcpStream::connect(address).await.unwrap();
+    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
+    // The receiver expects the flag of an uncompressed message first.
+    let mut bytes = vec![0x00];
+    bytes.extend(bincode::serialize(&request).unwrap());
+    transport.send(Bytes::from(bytes)).await.unwrap();
+    transport
+}
+
+// Fixture
+async fn response(transport: &mut Framed<TcpStream, LengthDelimitedCodec>) -> BeaconResponse {
+    let reply = transport.next().await.unwrap().unwrap();
+    bincode::deserialize(&reply).unwrap()
+}
+
+// Fixture
+async fn next(transport: &mut Framed<TcpStream, LengthDelimitedCodec>) -> (Epoch, usize, RandomNum) {
+    match response(transport).await {
+        BeaconResponse::Beacon { epoch, index, value } => (epoch, index, value),
+        response => panic!("Unexpected response: {:?}", response),
+    }
+}
+
+#[tokio::test]
+async fn get_and_latest() {
+    let address = "127.0.0.1:13100".parse().unwrap();
+    spawn_hub(address, /* eval_beacon */ false);
+    sleep(Duration::from_millis(50)).await;
+
+    // Nothing is output until somebody asks for a beacon.
+    let mut transport = connect(address, BeaconRequest::Latest).await;
+    assert!(matches!(response(&mut transport).await, BeaconResponse::Error(_)));
+
+    let mut transport = connect(address, BeaconRequest::Get { epoch: 1, index: 3 }).await;
+    assert_eq!(next(&mut transport).await, (1, 3, beacon(1, 3)));
+
+    // An older beacon does not replace the latest one.
+    let mut transport = connect(address, BeaconRequest::Get { epoch: 0, index: 5 }).await;
+    assert_eq!(next(&mut transport).await, (0, 5, beacon(0, 5)));
+
+    let mut transport = connect(address, BeaconRequest::Latest).await;
+    assert_eq!(next(&mut transport).await, (1, 3, beacon(1, 3)));
+}
+
+#[tokio::test]
+async fn subscribe_to_sequence() {
+    let address = "127.0.0.1:13101".parse().unwrap();
+    spawn_hub(address, /* eval_beacon */ true);
+    sleep(Duration::from_millis(50)).await;
+
+    // Subscribers receive the beacons in order, from wherever the sequence is when they join.
+    let mut transport = connect(address, BeaconRequest::Subscribe).await;
+    let (mut epoch, mut index, value) = next(&mut transport).await;
+    assert_eq!(value, beacon(epoch, index));
+    for _ in 0..15 {
+        if index < 10 {
+            index += 1;
+        } else {
+            epoch += 1;
+            index = 1;
+        }
+        assert_eq!(next(&mut transport).await, (epoch, index, beacon(epoch, index)));
+    }
+}
+
+#[tokio::test]
+async fn serve_proof() {
+    let address = "127.0.0.1:13102".parse().unwrap();
+    spawn_hub(address, /* eval_beacon */ false);
+    sleep(Duration::from_millis(50)).await;
+
+    // Breeze looks the beacon up after the coins of the avss batch.
+    let mut transport = connect(address, BeaconRequest::Proof { epoch: 1, index: 3 }).await;
+    match response(&mut transport).await {
+        BeaconResponse::Proof(proof) => assert_eq!(proof, vec![1, 4 + 3]),
+        response => panic!("Unexpected response: {:?}", response),
+    }
+
+    // Breeze has no proof of this beacon.
+    let mut transport = connect(address, BeaconRequest::Proof { epoch: 0, index: 3 }).await;
+    assert!(matches!(response(&mut transport).await, BeaconResponse::Error(_)));
+
+    // There is no such beacon.
+    let mut transport = connect(address, BeaconRequest::Proof { epoch: 1, index: 11 }).await;
+    assert!(matches!(response(&mut transport).await, BeaconResponse::Error(_)));
+}
+
+#[tokio::test]
+async fn reject_invalid_beacon() {
+    let address = "127.0.0.1:13103".parse().unwrap();
+    spawn_hub(address, /* eval_beacon */ false);
+    sleep(Duration::from_millis(50)).await;
+
+    // The hub turns these down without asking the coordinator.
+    for (epoch, index) in [(1, 0), (1, 11), (1, usize::MAX), (Epoch::MAX, 1)] {
+        let mut transport = connect(address, BeaconRequest::Get { epoch, index }).await;
+        assert!(matches!(response(&mut transport).await, BeaconResponse::Error(_)));
+    }
+
+    // Nothing was output.
+    let mut transport = connect(address, BeaconRequest::Latest).await;
+    assert!(matches!(response(&mut transport).await, BeaconResponse::Error(_)));
+}
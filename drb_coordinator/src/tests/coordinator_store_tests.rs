@@ -0,0 +1,80 @@
+use super::*;
+use crypto::{Digest, PublicKey};
+
+fn certificate(epoch: Epoch, tag: u8) -> BreezeCertificate {
+    BreezeCertificate {
+        c: Digest([tag; 32]),
+        epoch,
+        ..BreezeCertificate::default()
+    }
+}
+
+#[tokio::test]
+async fn load_empty_state() {
+    let store = Store::in_memory();
+
+    let state = CoordinatorStore::new(store).load().await;
+    assert!(state.certificate_buffer.is_empty());
+    assert!(state.decided_common_core.is_empty());
+    assert!(state.beacon_reconstructed.is_empty());
+}
+
+#[tokio::test]
+async fn reload_persisted_state() {
+    let store = Store::in_memory();
+
+    let mut coordinator_store = CoordinatorStore::new(store.clone());
+    let common_core: HashSet<_> = (0..2).map(|i| certificate(0, i)).collect();
+    let pending: HashSet<_> = (0..1).map(|i| certificate(1, i)).collect();
+    coordinator_store.persist_certificates(0, &common_core).await;
+    coordinator_store.persist_certificates(1, &pending).await;
+    coordinator_store
+        .persist_decided_common_core(&[0].into_iter().collect())
+        .await;
+    coordinator_store.persist_beacon(0, 1, RandomNum(vec![7])).await;
+    coordinator_store.persist_beacon(0, 3, RandomNum(vec![9; 32])).await;
+    let mut complaints = Complaints::default();
+    complaints.accuse(1, Digest([1; 32]), PublicKey::default());
+    coordinator_store.persist_complaints(&complaints).await;
+    let parked: HashMap<_, _> = [(1, vec![certificate(1, 1)])].into_iter().collect();
+    coordinator_store.persist_parked(&parked).await;
+
+    // A fresh handle on the same store sees everything.
+    let state = CoordinatorStore::new(store).load().await;
+    assert_eq!(state.certificate_buffer.get(&0), Some(&common_core));
+    assert_eq!(state.certificate_buffer.get(&1), Some(&pending));
+    assert_eq!(state.decided_common_core, [0].into_iter().collect());
+    assert_eq!(state.beacon_reconstructed.len(), 2);
+    assert_eq!(state.beacon_reconstructed.get(&(0, 1)), Some(&RandomNum(vec![7])));
+    assert_eq!(state.beacon_reconstructed.get(&(0, 3)), Some(&RandomNum(vec![9; 32])));
+    assert!(state.complaints.is_blocked(1, &Digest([1; 32])));
+    assert_eq!(state.parked, parked);
+}
+
+#[tokio::test]
+async fn persist_after_restart() {
+    let store = Store::in_memory();
+
+    let first: HashSet<_> = (0..1).map(|i| certificate(1, i)).collect();
+    CoordinatorStore::new(store.clone())
+        .persist_certificates(1, &first)
+        .await;
+
+    // A restarted coordinator keeps the epochs buffered before the restart.
+    let mut coordinator_store = CoordinatorStore::new(store.clone());
+    coordinator_store.load().await;
+    let second: HashSet<_> = (0..1).map(|i| certificate(10, i)).collect();
+    coordinator_store.persist_certificates(10, &second).await;
+    coordinator_store
+        .persist_decided_common_core(&[1, 10].into_iter().collect())
+        .await;
+    coordinator_store.persist_beacon(1, 2, RandomNum(vec![1])).await;
+    coordinator_store.persist_beacon(10, 2, RandomNum(vec![10])).await;
+
+    let state = CoordinatorStore::new(store).load().await;
+    assert_eq!(state.certificate_buffer.get(&1), Some(&first));
+    assert_eq!(state.certificate_buffer.get(&10), Some(&second));
+    assert_eq!(state.beacon_reconstructed.len(), 2);
+    assert_eq!(state.beacon_reconstructed.get(&(1, 2)), Some(&RandomNum(vec![1])));
+    assert_eq!(state.beacon_reconstructed.get(&(10, 2)), Some(&RandomNum(vec![10])));
+}
@@ -0,0 +1,266 @@
+use super::*;
+use crate::coordinator_store::CoordinatorStore;
+use config::{Authority, Committee, PrimaryAddresses, Weighting};
+use crypto::{generate_production_keypair, Digest, PublicKey};
+use model::breeze_universal::ComplaintStatus;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc::channel;
+use tokio::time::{sleep, Duration};
+
+/// A committee of `names`, the authority `i` holding `stake(i)`.
+fn committee(names: &[PublicKey], stake: impl Fn(usize) -> Stake, weighting: Weighting) -> Committee {
+    Committee {
+        authorities: names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let primary = PrimaryAddresses {
+                    primary_to_primary: "0.0.0.0:0".parse().unwrap(),
+                    worker_to_primary: "0.0.0.0:0".parse().unwrap(),
+                    breeze_addr: "0.0.0.0:0".parse().unwrap(),
+                    init_bft_addr: "0.0.0.0:0".parse().unwrap(),
+                    beacon_api_addr: None,
+                    evidence_api_addr: None,
+                    admin_api_addr: None,
+                };
+                (*name, Authority { stake: stake(i), primary, workers: BTreeMap::new() })
+            })
+            .collect(),
+        weighting,
+    }
+}
+
+#[tokio::test]
+async fn prefetch_coins() {
+    MAX_EPOCH.get_or_init(|| 10);
+    BEACON_PER_EPOCH.get_or_init(|| 10);
+    let committee = Committee {
+        authorities: BTreeMap::new(),
+        weighting: Weighting::default(),
+    };
+
+    // Resume with the common core of epoch 0 decided.
+    let store = Store::in_memory();
+    let mut coordinator_store = CoordinatorStore::new(store.clone());
+    let certificate = BreezeCertificate {
+        c: Digest([1; 32]),
+        epoch: 0,
+        ..BreezeCertificate::default()
+    };
+    coordinator_store
+        .persist_certificates(0, &[certificate].iter().cloned().collect())
+        .await;
+    coordinator_store
+        .persist_decided_common_core(&[0].iter().cloned().collect())
+        .await;
+
+    let (b_share_cmd_sender, _b_share_cmd_receiver) = channel(10);
+    let (_certificate_from_breeze_sender, certificate_from_breeze) = channel(10);
+    let (certificate_to_consensus, _certificate_to_consensus_receiver) = channel(10);
+    let (certificate_to_init_consensus, _certificate_to_init_consensus_receiver) = channel(10);
+    let (_cer_decided_sender, cer_decided_from_consensus) = channel(10);
+    let (_cc_decided_sender, cc_decided_from_init_consensus) = channel(10);
+    let (complaint_query_sender, _complaint_query_receiver) = channel(10);
+    let (global_coin_recon_req_sender, global_coin_recon_req_receiver) = channel(10);
+    let (_beacon_recon_req_sender, beacon_recon_req_receiver) = channel(10);
+    let (b_recon_req_sender, mut b_recon_req_receiver) = channel(10);
+    let (b_recon_res_sender, b_recon_res_receiver) = channel(10);
+    let (global_coin_res_sender, mut global_coin_res_receiver) = channel(10);
+    let (beacon_res_sender, _beacon_res_receiver) = channel(10);
+    let (recover_signal_sender, _recover_signal_receiver) = watch::channel(());
+    Coordinator::spawn(
+        committee.into(),
+        b_share_cmd_sender,
+        certificate_from_breeze,
+        certificate_to_consensus,
+        certificate_to_init_consensus,
+        cer_decided_from_consensus,
+        cc_decided_from_init_consensus,
+        complaint_query_sender,
+        global_coin_recon_req_receiver,
+        beacon_recon_req_receiver,
+        b_recon_req_sender,
+        b_recon_res_receiver,
+        global_coin_res_sender,
+        beacon_res_sender,
+        recover_signal_sender,
+        /* eval_beacon */ false,
+        Protocol::Tusk,
+        /* coin_lookahead */ 2,
+        store,
+    )
+    .await;
+
+    // The first two coins are reconstructed before the consensus asks for them.
+    let request = b_recon_req_receiver.recv().await.unwrap();
+    assert_eq!((request.epoch, request.indices), (0, 1..3));
+
+    // Asking for the first one does not reconstruct it again, but moves the window forward.
+    global_coin_recon_req_sender.send(2).await.unwrap();
+    let request = b_recon_req_receiver.recv().await.unwrap();
+    assert_eq!((request.epoch, request.indices), (0, 3..4));
+
+    // The coin is served once reconstructed.
+    let coin = RandomNum(vec![7; 32]);
+    b_recon_res_sender.send((0, 1, coin.clone())).await.unwrap();
+    let (round, result) = global_coin_res_receiver.recv().await.unwrap();
+    assert_eq!(round, 2);
+    assert_eq!(result.unwrap(), coin);
+}
+
+#[tokio::test]
+async fn stake_weighted_common_core() {
+    MAX_EPOCH.get_or_init(|| 10);
+    BEACON_PER_EPOCH.get_or_init(|| 10);
+    // The last authority holds more stake than the three others together.
+    let names: Vec<_> = (0..4).map(|_| generate_production_keypair().0).collect();
+    let committee = committee(&names, |i| if i == 3 { 5 } else { 1 }, Weighting::Stake);
+
+    // Resume with the common core of epoch 0 decided.
+    let store = Store::in_memory();
+    let mut coordinator_store = CoordinatorStore::new(store.clone());
+    coordinator_store
+        .persist_decided_common_core(&[0].iter().cloned().collect())
+        .await;
+
+    let (b_share_cmd_sender, mut b_share_cmd_receiver) = channel(10);
+    let (_certificate_from_breeze_sender, certificate_from_breeze) = channel(10);
+    let (certificate_to_consensus, _certificate_to_consensus_receiver) = channel(10);
+    let (certificate_to_init_consensus, _certificate_to_init_consensus_receiver) = channel(10);
+    let (cer_decided_sender, cer_decided_from_consensus) = channel(10);
+    let (_cc_decided_sender, cc_decided_from_init_consensus) = channel(10);
+    let (complaint_query_sender, _complaint_query_receiver) = channel(10);
+    let (_global_coin_recon_req_sender, global_coin_recon_req_receiver) = channel(10);
+    let (_beacon_recon_req_sender, beacon_recon_req_receiver) = channel(10);
+    let (b_recon_req_sender, _b_recon_req_receiver) = channel(10);
+    let (_b_recon_res_sender, b_recon_res_receiver) = channel(10);
+    let (global_coin_res_sender, _global_coin_res_receiver) = channel(10);
+    let (beacon_res_sender, _beacon_res_receiver) = channel(10);
+    let (recover_signal_sender, _recover_signal_receiver) = watch::channel(());
+    Coordinator::spawn(
+        committee.into(),
+        b_share_cmd_sender,
+        certificate_from_breeze,
+        certificate_to_consensus,
+        certificate_to_init_consensus,
+        cer_decided_from_consensus,
+        cc_decided_from_init_consensus,
+        complaint_query_sender,
+        global_coin_recon_req_receiver,
+        beacon_recon_req_receiver,
+        b_recon_req_sender,
+        b_recon_res_receiver,
+        global_coin_res_sender,
+        beacon_res_sender,
+        recover_signal_sender,
+        /* eval_beacon */ false,
+        Protocol::Tusk,
+        /* coin_lookahead */ 0,
+        store,
+    )
+    .await;
+    assert_eq!(b_share_cmd_receiver.recv().await, Some(1));
+
+    let certificate = |i: usize| BreezeCertificate {
+        c: Digest([i as u8; 32]),
+        dealer: names[i],
+        epoch: 1,
+        ..BreezeCertificate::default()
+    };
+
+    // The dealings of two light authorities are f+1 of the authorities, but not of the stake.
+    cer_decided_sender.send(Committed::Certificate(certificate(0))).await.unwrap();
+    cer_decided_sender.send(Committed::Certificate(certificate(1))).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(b_share_cmd_receiver.try_recv().is_err());
+
+    // The dealing of the heavy authority decides the common core.
+    cer_decided_sender.send(Committed::Certificate(certificate(3))).await.unwrap();
+    assert_eq!(b_share_cmd_receiver.recv().await, Some(2));
+}
+
+#[tokio::test]
+async fn committed_complaints_park_dealings() {
+    MAX_EPOCH.get_or_init(|| 10);
+    BEACON_PER_EPOCH.get_or_init(|| 10);
+    let names: Vec<_> = (0..4).map(|_| generate_production_keypair().0).collect();
+    let committee = committee(&names, |_| 1, Weighting::Count);
+
+    // Resume with the common core of epoch 0 decided.
+    let store = Store::in_memory();
+    let mut coordinator_store = CoordinatorStore::new(store.clone());
+    coordinator_store
+        .persist_decided_common_core(&[0].iter().cloned().collect())
+        .await;
+
+    let (b_share_cmd_sender, mut b_share_cmd_receiver) = channel(10);
+    let (_certificate_from_breeze_sender, certificate_from_breeze) = channel(10);
+    let (certificate_to_consensus, _certificate_to_consensus_receiver) = channel(10);
+    let (certificate_to_init_consensus, _certificate_to_init_consensus_receiver) = channel(10);
+    let (cer_decided_sender, cer_decided_from_consensus) = channel(10);
+    let (_cc_decided_sender, cc_decided_from_init_consensus) = channel(10);
+    let (complaint_query_sender, mut complaint_query_receiver) = channel::<ComplaintQuery>(10);
+    let (_global_coin_recon_req_sender, global_coin_recon_req_receiver) = channel(10);
+    let (_beacon_recon_req_sender, beacon_recon_req_receiver) = channel(10);
+    let (b_recon_req_sender, _b_recon_req_receiver) = channel(10);
+    let (_b_recon_res_sender, b_recon_res_receiver) = channel(10);
+    let (global_coin_res_sender, _global_coin_res_receiver) = channel(10);
+    let (beacon_res_sender, _beacon_res_receiver) = channel(10);
+    let (recover_signal_sender, _recover_signal_receiver) = watch::channel(());
+    Coordinator::spawn(
+        committee.into(),
+        b_share_cmd_sender,
+        certificate_from_breeze,
+        certificate_to_consensus,
+        certificate_to_init_consensus,
+        cer_decided_from_consensus,
+        cc_decided_from_init_consensus,
+        complaint_query_sender,
+        global_coin_recon_req_receiver,
+        beacon_recon_req_receiver,
+        b_recon_req_sender,
+        b_recon_res_receiver,
+        global_coin_res_sender,
+        beacon_res_sender,
+        recover_signal_sender,
+        /* eval_beacon */ false,
+        Protocol::Tusk,
+        /* coin_lookahead */ 0,
+        store,
+    )
+    .await;
+    assert_eq!(b_share_cmd_receiver.recv().await, Some(1));
+
+    let certificate = |i: usize| BreezeCertificate {
+        c: Digest([i as u8; 32]),
+        dealer: names[i],
+        epoch: 1,
+        ..BreezeCertificate::default()
+    };
+
+    // Breeze checks the committed complaints: the second holder accuses the first dealing, which
+    // its dealer later answers. Anything else is not a valid complaint.
+    let (c, accuser) = (certificate(0).c, names[1]);
+    tokio::spawn(async move {
+        while let Some((complaint, reply)) = complaint_query_receiver.recv().await {
+            let status = match complaint.0[..] {
+                [0] => Some(ComplaintStatus::Accused { epoch: 1, c, accuser }),
+                [1] => Some(ComplaintStatus::Resolved { epoch: 1, c, accuser }),
+                _ => None,
+            };
+            let _ = reply.send(status);
+        }
+    });
+
+    // The accused dealing is kept out of the common core.
+    cer_decided_sender.send(Committed::Complaint(Complaint(vec![0]))).await.unwrap();
+    cer_decided_sender.send(Committed::Certificate(certificate(0))).await.unwrap();
+    cer_decided_sender.send(Committed::Certificate(certificate(1))).await.unwrap();
+    cer_decided_sender.send(Committed::Complaint(Complaint(vec![2]))).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(b_share_cmd_receiver.try_recv().is_err());
+
+    // It joins the common core once the complaint is resolved.
+    cer_decided_sender.send(Committed::Complaint(Complaint(vec![1]))).await.unwrap();
+    assert_eq!(b_share_cmd_receiver.recv().await, Some(2));
+}
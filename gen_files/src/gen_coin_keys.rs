@@ -0,0 +1,34 @@
+use crypto::PublicKey;
+use curve25519_dalek::Scalar;
+use model::file_io::Export;
+use model::threshold_coin::CoinKey;
+use rand::rngs::OsRng;
+use serde::Deserialize;
+
+/// The public part of the key file of a node.
+#[derive(Deserialize)]
+struct NodeKey {
+    name: PublicKey,
+}
+
+/// Deals the keys of the threshold coin of the secondary BFT among the nodes of `key_files`, so
+/// that `t + 1` of them toss a coin. The key of the node of the `i`-th file goes to
+/// `.coin-{i}.json`.
+pub fn generate_coin_keys(key_files: &[&str], t: usize) {
+    let names: Vec<PublicKey> = key_files
+        .iter()
+        .map(|file| {
+            let data = std::fs::read(file).expect("Failed to read key file");
+            let key: NodeKey = serde_json::from_slice(&data).expect("Failed to parse key file");
+            key.name
+        })
+        .collect();
+    let mut rng = OsRng;
+    for (i, key) in CoinKey::deal(&names, t + 1, || Scalar::random(&mut rng)).iter().enumerate() {
+        #[cfg(feature = "benchmark")]
+        let path = format!("./.coin-{}.json", i);
+        #[cfg(not(feature = "benchmark"))]
+        let path = format!("benchmark/.coin-{}.json", i);
+        key.export(&path).expect("Failed to write coin key to json");
+    }
+}
@@ -1,7 +1,9 @@
-use clap::{crate_name, crate_version, App, AppSettings, SubCommand};
+use clap::{crate_name, crate_version, App, AppSettings, ArgMatches, SubCommand};
 use crate::gen_breeze_crs::generate_crs;
+use crate::gen_coin_keys::generate_coin_keys;
 
 mod gen_breeze_crs;
+mod gen_coin_keys;
 
 #[cfg(not(feature = "pq"))]
 fn main() {
@@ -13,6 +15,7 @@ fn main() {
                 .about("Generate CRS with specified faults")
                 .args_from_usage("--fault_tolerance=[NUMBER] 'Sets the fault tolerance [default: 1]'")
         )
+        .subcommand(coin_subcommand())
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .get_matches();
 
@@ -27,6 +30,9 @@ fn main() {
         #[cfg(not(feature = "pq"))]
         generate_crs(faults);
     }
+    if let Some(sub_matches) = matches.subcommand_matches("generate_coin") {
+        generate_coin(sub_matches);
+    }
 }
 
 #[cfg(feature = "pq")]
@@ -46,6 +52,7 @@ fn main() {
                      --ell=[NUMBER] 'Sets the number of nested G^-1(.) [default: 1]'"
                 )
         )
+        .subcommand(coin_subcommand())
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .get_matches();
 
@@ -89,4 +96,26 @@ fn main() {
 
         generate_crs(n, log_q, g, kappa, r, ell);
     }
+    if let Some(sub_matches) = matches.subcommand_matches("generate_coin") {
+        generate_coin(sub_matches);
+    }
+}
+
+fn coin_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("generate_coin")
+        .about("Deal the keys of the threshold coin of the secondary BFT")
+        .args_from_usage(
+            "--fault_tolerance=[NUMBER] 'Sets the fault tolerance [default: 1]'
+             --keys=<FILE>... 'The key files of the nodes'"
+        )
+}
+
+fn generate_coin(sub_matches: &ArgMatches) {
+    let faults = sub_matches
+        .value_of("fault_tolerance")
+        .unwrap_or("1")
+        .parse::<usize>()
+        .expect("Fault tolerance must be a valid number");
+    let key_files: Vec<_> = sub_matches.values_of("keys").unwrap().collect();
+    generate_coin_keys(&key_files, faults);
 }
\ No newline at end of file
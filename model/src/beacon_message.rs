@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use crate::types_and_const::{Epoch, RandomNum};
+
+/// Requests accepted by the public beacon service of a primary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BeaconRequest {
+    /// Ask for the beacon output at `(epoch, index)`.
+    Get { epoch: Epoch, index: usize },
+    /// Ask for the most recent beacon output known to the primary.
+    Latest,
+    /// Keep the connection open and receive every new beacon output.
+    Subscribe,
+    /// Ask for a transferable proof of the beacon output at `(epoch, index)`, once it is output.
+    Proof { epoch: Epoch, index: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BeaconResponse {
+    Beacon {
+        epoch: Epoch,
+        index: usize,
+        value: RandomNum,
+    },
+    /// A `bavss::BeaconProof` of the requested beacon, serialized with bincode.
+    Proof(Vec<u8>),
+    /// The requested beacon is not (yet) available.
+    Error(String),
+}
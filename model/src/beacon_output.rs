@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A beacon output. Its length depends on the `OutputDerivation` of the committee.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RandomNum(pub Vec<u8>);
+
+impl RandomNum {
+    /// The first 8 bytes read as a little-endian integer (zero-padded), e.g. to elect a leader.
+    pub fn to_u64(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        let len = self.0.len().min(8);
+        bytes[..len].copy_from_slice(&self.0[..len]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+impl fmt::Display for RandomNum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a beacon output is derived from the reconstructed secret. All the nodes of a committee
+/// must use the same derivation, as the consensus elects its leaders from the outputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputDerivation {
+    /// The first 16 bytes of the full output (the original 128-bit output).
+    #[default]
+    Truncated,
+    /// The full 32 bytes: the secret itself for Ristretto, its SHA-256 hash for the pq variant.
+    Full,
+    /// SHA-256 of the secret, prefixed by a domain tag and the epoch and index of the output.
+    Hashed,
+    /// `length` bytes of SHAKE256 over the same input as `Hashed`, between `MIN_XOF_LENGTH` and
+    /// `MAX_XOF_LENGTH`.
+    Xof { length: usize },
+}
+
+impl OutputDerivation {
+    /// The shortest `Xof` output: the 8 bytes the consensus elects its leaders from.
+    pub const MIN_XOF_LENGTH: usize = 8;
+    /// The longest `Xof` output, so that a misconfigured length does not blow up every beacon.
+    pub const MAX_XOF_LENGTH: usize = 1_024;
+
+    /// Whether the derivation yields outputs of a supported length.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Xof { length } => (Self::MIN_XOF_LENGTH..=Self::MAX_XOF_LENGTH).contains(length),
+            _ => true,
+        }
+    }
+}
@@ -1,16 +1,58 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::BTreeSet;
 use serde::{Deserialize, Serialize};
-use crypto::{PublicKey, Signature};
+use crypto::{Digest, PublicKey, Signature};
 use crate::breeze_universal::BreezeCertificate;
+use crate::threshold_coin::CoinShare;
+
+pub type View = u64;
+
+/// A message of the MVBA deciding the common core of epoch 0. The sender signs the content, so
+/// that the protocol does not rely on authenticated channels.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DumboMessage{
     pub sender: PublicKey,
-    pub content: DumboContent
+    pub content: DumboContent,
+    pub signature: Signature,
 }
+
+/// Transferable proof that a quorum echoed the proposal of `proposer` whose digest is `digest`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FinishProof {
+    pub proposer: PublicKey,
+    pub digest: Digest,
+    pub echoes: BTreeSet<(PublicKey, Signature)>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DumboContent {
+    /// A dealer disseminates the certificate of its epoch-0 dealing.
     Certificate(BreezeCertificate),
-    // Propose(HashSet<BreezeCertificate>),
-    Vote((BTreeSet<BreezeCertificate>,Signature)),
-    Decided((BTreeSet<BreezeCertificate>,HashSet<(PublicKey,Signature)>)),
-}
\ No newline at end of file
+    /// Provable broadcast of the candidate common core of the sender.
+    Proposal(BTreeSet<BreezeCertificate>),
+    /// Echo of the proposal of `proposer`, sent back to it.
+    Echo {
+        proposer: PublicKey,
+        digest: Digest,
+        signature: Signature,
+    },
+    /// The proposal of the sender gathered a quorum of echoes.
+    Finish(FinishProof),
+    /// Vote on the leader elected for `view`, with its proposal if the sender knows it finished.
+    Vote {
+        view: View,
+        leader: PublicKey,
+        proposal: Option<(BTreeSet<BreezeCertificate>, FinishProof)>,
+    },
+    /// Binary agreement of `view` on whether to decide the proposal of its leader.
+    BVal { view: View, round: u64, value: bool },
+    Aux { view: View, round: u64, value: bool },
+    /// The sender decided `value` in the binary agreement of `view`.
+    Term { view: View, value: bool },
+    /// Share of the coin of `view`: the one electing its leader if `round` is `None`, the one
+    /// tossed in `round` of its binary agreement otherwise.
+    Coin {
+        view: View,
+        round: Option<u64>,
+        share: CoinShare,
+    },
+}
@@ -1,10 +1,14 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
 #[cfg(not(feature = "pq"))]
 use curve25519_dalek::{RistrettoPoint};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use thiserror::Error;
+use tokio::sync::oneshot;
 use crypto::{Digest, PublicKey, Signature};
 use crate::file_io::Import;
-use crate::types_and_const::Epoch;
+use crate::types_and_const::{Epoch, Stake};
 #[cfg(feature = "pq")]
 use crate::types_and_const::ZqMod;
 #[cfg(feature = "pq")]
@@ -34,21 +38,33 @@ impl Import for CommonReferenceString {}
 #[derive(Clone, Serialize, Deserialize, Default, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct BreezeCertificate {
     pub c: Digest,
+    /// The author of the dealing, whose weight the certificate counts for in a common core.
+    pub dealer: PublicKey,
     pub epoch: Epoch,
     pub certificates: BTreeSet<(PublicKey,Signature)>,
 }
 
 impl BreezeCertificate {
-    pub fn new(c:Digest, pk:PublicKey, epoch: Epoch, signature: Signature) -> Self {
+    pub fn new(c:Digest, dealer: PublicKey, pk:PublicKey, epoch: Epoch, signature: Signature) -> Self {
         let mut certificates = BTreeSet::new();
         certificates.insert((pk,signature));
         BreezeCertificate {
             c,
+            dealer,
             epoch,
             certificates,
         }
     }
 
+    /// The digest the holders sign to acknowledge the dealing `c` of `dealer`. It binds the
+    /// dealer, so that nobody can claim the certificate of another dealer's dealing.
+    pub fn signed_digest(c: &Digest, dealer: &PublicKey) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(c);
+        hasher.update(dealer);
+        Digest(hasher.finalize().into())
+    }
+
     pub fn insert(&mut self, pk:PublicKey,signature: Signature) {
         self.certificates.insert((pk,signature)); // HashSet 自动去重
     }
@@ -60,24 +76,150 @@ impl BreezeCertificate {
         self.certificates.is_empty()
     }
 
-    pub fn verify(&self, quorum_threshold: usize) -> bool {
-        if self.certificates.is_empty()
-            || self.certificates.len() < quorum_threshold{
-            return false;
-        }
+    /// Checks that the certificate carries valid signatures of its `signed_digest` from signers
+    /// whose total `weight` reaches `quorum_threshold`, each one made with the signer's
+    /// `signing_key` (at the epoch of the certificate). Signers with no weight (i.e. non-members)
+    /// or signing twice invalidate the certificate.
+    pub fn verify<F, K>(&self, weight: F, signing_key: K, quorum_threshold: Stake) -> Result<(), CertificateError>
+    where
+        F: Fn(&PublicKey) -> Stake,
+        K: Fn(&PublicKey) -> PublicKey,
+    {
+        let digest = Self::signed_digest(&self.c, &self.dealer);
+        let mut used = HashSet::new();
+        let mut total = 0;
         for (pk, signature) in self.certificates.iter() {
-            match signature.verify(&self.c, pk) {
-                Ok(()) => {},
-                Err(_) => { return false; }
+            if !used.insert(pk) {
+                return Err(CertificateError::AuthorityReuse(*pk));
+            }
+            let w = weight(pk);
+            if w == 0 {
+                return Err(CertificateError::UnknownAuthority(*pk));
+            }
+            if signature.verify(&digest, &signing_key(pk)).is_err() {
+                return Err(CertificateError::InvalidSignature(*pk));
             }
+            total += w;
         }
-        true
+        if self.certificates.is_empty() || total < quorum_threshold {
+            return Err(CertificateError::RequiresQuorum);
+        }
+        Ok(())
     }
 }
 
+/// Why a `BreezeCertificate` does not verify.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    #[error("The signers do not reach a quorum")]
+    RequiresQuorum,
+
+    #[error("Invalid signature from {0}")]
+    InvalidSignature(PublicKey),
+
+    #[error("Signed by unknown authority {0}")]
+    UnknownAuthority(PublicKey),
+
+    #[error("Signed more than once by {0}")]
+    AuthorityReuse(PublicKey),
+}
+
+/// Asks Breeze to reconstruct the outputs at `indices` of `epoch`, from the dealings certified
+/// by the certificates of the common core.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct BreezeReconRequest {
-    pub c: HashSet<Digest>,
+    pub common_core: BTreeSet<BreezeCertificate>,
     pub epoch: Epoch,
-    pub index: usize,
-}
\ No newline at end of file
+    pub indices: Range<usize>,
+}
+
+impl BreezeReconRequest {
+    /// The digests of the dealings to reconstruct, in a deterministic order.
+    pub fn digests(&self) -> BTreeSet<Digest> {
+        self.common_core.iter().map(|cer| cer.c).collect()
+    }
+}
+/// An accusation or a revelation of the complaint phase, as the serialized breeze message that
+/// carried it. Headers carry them, so that every node weighs the complaints against a dealing in
+/// the order the consensus commits them. They are only checked once committed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Complaint(pub Vec<u8>);
+
+/// Asks Breeze for the change a committed complaint makes. The reply is `None` if the complaint
+/// is malformed or not signed by its author.
+pub type ComplaintQuery = (Complaint, oneshot::Sender<Option<ComplaintStatus>>);
+
+/// What the committed headers bring to the coordinator, in commit order.
+#[derive(Clone, Debug)]
+pub enum Committed {
+    Complaint(Complaint),
+    Certificate(BreezeCertificate),
+}
+
+/// A change in the complaints against the dealing of digest `c` of an epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComplaintStatus {
+    /// A holder accused the dealer of dealing it an invalid share.
+    Accused { epoch: Epoch, c: Digest, accuser: PublicKey },
+    /// The dealer publicly revealed a valid share for the accuser.
+    Resolved { epoch: Epoch, c: Digest, accuser: PublicKey },
+    /// The dealer revealed an invalid share: the dealing is excluded for good.
+    Excluded { epoch: Epoch, c: Digest },
+}
+
+/// The complaints against the dealings, as seen by a node. A dealing accused by some holder may
+/// only go into a common core once its dealer revealed a valid share for every accuser.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Complaints {
+    /// The accusers still waiting for the dealer to reveal their share.
+    unresolved: HashMap<(Epoch, Digest), HashSet<PublicKey>>,
+    /// The accusers whose share the dealer revealed, in case the revelation arrives first.
+    revealed: HashMap<(Epoch, Digest), HashSet<PublicKey>>,
+    excluded: HashSet<(Epoch, Digest)>,
+}
+
+impl Complaints {
+    /// Records an accusation. Returns whether it is new and still needs an answer.
+    pub fn accuse(&mut self, epoch: Epoch, c: Digest, accuser: PublicKey) -> bool {
+        let key = (epoch, c);
+        if self.excluded.contains(&key) || self.revealed.get(&key).is_some_and(|r| r.contains(&accuser)) {
+            return false;
+        }
+        self.unresolved.entry(key).or_default().insert(accuser)
+    }
+
+    /// Records that the dealer revealed a valid share for `accuser`. Returns whether it is new.
+    pub fn resolve(&mut self, epoch: Epoch, c: Digest, accuser: PublicKey) -> bool {
+        let key = (epoch, c);
+        if let Some(accusers) = self.unresolved.get_mut(&key) {
+            accusers.remove(&accuser);
+            if accusers.is_empty() {
+                self.unresolved.remove(&key);
+            }
+        }
+        self.revealed.entry(key).or_default().insert(accuser)
+    }
+
+    /// Excludes the dealing for good. Returns whether it is new.
+    pub fn exclude(&mut self, epoch: Epoch, c: Digest) -> bool {
+        self.unresolved.remove(&(epoch, c));
+        self.excluded.insert((epoch, c))
+    }
+
+    pub fn update(&mut self, status: &ComplaintStatus) -> bool {
+        match status {
+            ComplaintStatus::Accused { epoch, c, accuser } => self.accuse(*epoch, *c, *accuser),
+            ComplaintStatus::Resolved { epoch, c, accuser } => self.resolve(*epoch, *c, *accuser),
+            ComplaintStatus::Excluded { epoch, c } => self.exclude(*epoch, *c),
+        }
+    }
+
+    pub fn is_excluded(&self, epoch: Epoch, c: &Digest) -> bool {
+        self.excluded.contains(&(epoch, *c))
+    }
+
+    /// Whether the dealing may not go into a common core (yet).
+    pub fn is_blocked(&self, epoch: Epoch, c: &Digest) -> bool {
+        self.is_excluded(epoch, c) || self.unresolved.contains_key(&(epoch, *c))
+    }
+}
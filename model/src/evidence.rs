@@ -0,0 +1,111 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crypto::{Digest, PublicKey, Signature};
+use crate::types_and_const::Epoch;
+
+/// A message as its author signed it: the serialized message, the digest the author signed (as
+/// computed by the component the message belongs to), and the signature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedMessage {
+    pub message: Vec<u8>,
+    pub digest: Digest,
+    pub signature: Signature,
+}
+
+impl SignedMessage {
+    pub fn new<T: Serialize>(message: &T, digest: Digest, signature: Signature) -> Self {
+        Self {
+            message: bincode::serialize(message).expect("Failed to serialize signed message"),
+            digest,
+            signature,
+        }
+    }
+}
+
+/// The ways a validator can provably misbehave.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Misbehaviour {
+    /// Two different messages for the same slot, e.g. two headers of the same round.
+    Equivocation(SignedMessage, SignedMessage),
+    /// A share of a dealing that does not verify against the dealer's commitment.
+    InvalidDealing(SignedMessage),
+    /// Reconstruction shares that do not verify against the dealer's commitment.
+    InvalidShare(SignedMessage),
+    /// Reconstruction shares off the polynomial decoded from a quorum of shares of the same
+    /// dealing.
+    InconsistentShare(SignedMessage),
+    /// A vote of the secondary BFT that no honest node casts.
+    InvalidVote(SignedMessage),
+}
+
+/// Proof that `culprit` misbehaved in `epoch`, made of messages it signed with its signing key of
+/// that epoch. Anyone holding the committee can check the signatures; the messages themselves are
+/// checked by the component they belong to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Evidence {
+    pub culprit: PublicKey,
+    pub epoch: Epoch,
+    pub misbehaviour: Misbehaviour,
+}
+
+impl Evidence {
+    pub fn new(culprit: PublicKey, epoch: Epoch, misbehaviour: Misbehaviour) -> Self {
+        Self {
+            culprit,
+            epoch,
+            misbehaviour,
+        }
+    }
+
+    /// The messages signed by the culprit.
+    pub fn messages(&self) -> Vec<&SignedMessage> {
+        match &self.misbehaviour {
+            Misbehaviour::Equivocation(first, second) => vec![first, second],
+            Misbehaviour::InvalidDealing(message)
+            | Misbehaviour::InvalidShare(message)
+            | Misbehaviour::InconsistentShare(message)
+            | Misbehaviour::InvalidVote(message) => vec![message],
+        }
+    }
+
+    /// Checks that every message is signed with `signing_key`, the key of the culprit at `epoch`,
+    /// and that equivocating messages differ.
+    pub fn verify(&self, signing_key: &PublicKey) -> bool {
+        if let Misbehaviour::Equivocation(first, second) = &self.misbehaviour {
+            if first.digest == second.digest {
+                return false;
+            }
+        }
+        self.messages()
+            .iter()
+            .all(|message| message.signature.verify(&message.digest, signing_key).is_ok())
+    }
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match &self.misbehaviour {
+            Misbehaviour::Equivocation(..) => "equivocation",
+            Misbehaviour::InvalidDealing(..) => "invalid dealing",
+            Misbehaviour::InvalidShare(..) => "invalid reconstruction share",
+            Misbehaviour::InconsistentShare(..) => "inconsistent reconstruction share",
+            Misbehaviour::InvalidVote(..) => "invalid vote",
+        };
+        write!(f, "{} of {} in epoch {}", kind, self.culprit, self.epoch)
+    }
+}
+
+/// Requests accepted by the evidence service of a primary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EvidenceRequest {
+    /// Ask for all the evidence gathered so far.
+    All,
+    /// Ask for the evidence against a single validator.
+    Against(PublicKey),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EvidenceResponse {
+    Evidence(Vec<Evidence>),
+    Error(String),
+}
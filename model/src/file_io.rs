@@ -4,7 +4,7 @@ use std::io::{BufWriter, Write};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use crypto::PublicKey;
-use crate::types_and_const::WorkerId;
+use crate::types_and_const::{Epoch, WorkerId};
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -19,6 +19,18 @@ pub enum ConfigError {
 
     #[error("Failed to write config file '{file}': {message}")]
     ExportError { file: String, message: String },
+
+    #[error("Committee of epoch {0} does not take over after the one of epoch {1}")]
+    StaleReconfiguration(Epoch, Epoch),
+
+    #[error("Committee of epoch {0} has no authority")]
+    EmptyCommittee(Epoch),
+
+    #[error("Signing key of {0} for epoch {1} does not take over after the one of epoch {2}")]
+    StaleKeyRotation(PublicKey, Epoch, Epoch),
+
+    #[error("Invalid config file '{file}': {message}")]
+    InvalidConfig { file: String, message: String },
 }
 
 pub trait Import: DeserializeOwned {
@@ -27,10 +39,20 @@ pub trait Import: DeserializeOwned {
             let data = fs::read(path)?;
             Ok(serde_json::from_slice(data.as_slice())?)
         };
-        reader().map_err(|e| ConfigError::ImportError {
+        let imported = reader().map_err(|e| ConfigError::ImportError {
             file: path.to_string(),
             message: e.to_string(),
-        })
+        })?;
+        imported.validate().map_err(|message| ConfigError::InvalidConfig {
+            file: path.to_string(),
+            message,
+        })?;
+        Ok(imported)
+    }
+
+    /// Checks the imported values, beyond their types.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
     }
 }
 
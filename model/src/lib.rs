@@ -1,4 +1,8 @@
 pub mod breeze_universal;
 pub mod types_and_const;
 pub mod bft_message;
+pub mod beacon_message;
+pub mod beacon_output;
+pub mod evidence;
 pub mod file_io;
+pub mod threshold_coin;
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::{RistrettoPoint, Scalar};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256, Sha512};
+use crypto::PublicKey;
+use crate::file_io::{Export, Import};
+
+/// The share of a threshold coin computed by one holder, with a proof that it used the secret
+/// matching its verification key (a Chaum-Pedersen proof of equal discrete logarithms).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinShare {
+    pub value: RistrettoPoint,
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+/// The key of a holder of the threshold coin of Cachin, Kursawe and Shoup. A dealer shares a
+/// secret `x` among the holders with a polynomial of degree `threshold - 1`: the coin named `m` is
+/// the hash of `x·H(m)`, which any `threshold` shares `x_i·H(m)` interpolate to, and which
+/// nobody can compute out of fewer shares.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinKey {
+    /// The number of shares needed to toss a coin.
+    pub threshold: usize,
+    /// The verification key `x_i·G` of each holder. The secret of a holder is the evaluation of the
+    /// polynomial at its rank in this map, counting from 1.
+    pub holders: BTreeMap<PublicKey, RistrettoPoint>,
+    /// Our own secret `x_i`.
+    pub secret: Scalar,
+}
+
+impl Import for CoinKey {}
+impl Export for CoinKey {}
+
+impl CoinKey {
+    /// Deals the keys of the `holders`, in the same order. `random` draws the coefficients of the
+    /// polynomial, the first one being the shared secret.
+    pub fn deal(holders: &[PublicKey], threshold: usize, mut random: impl FnMut() -> Scalar) -> Vec<Self> {
+        let coefficients: Vec<Scalar> = (0..threshold).map(|_| random()).collect();
+        let mut names = holders.to_vec();
+        names.sort();
+        let secrets: BTreeMap<_, _> = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let x = Scalar::from(i as u64 + 1);
+                let secret = coefficients.iter().rev().fold(Scalar::ZERO, |acc, a| acc * x + a);
+                (name, secret)
+            })
+            .collect();
+        let verification_keys: BTreeMap<_, _> = secrets
+            .iter()
+            .map(|(name, secret)| (*name, secret * RISTRETTO_BASEPOINT_POINT))
+            .collect();
+        holders
+            .iter()
+            .map(|name| CoinKey {
+                threshold,
+                holders: verification_keys.clone(),
+                secret: secrets[name],
+            })
+            .collect()
+    }
+
+    /// Our share of the coin named `name`. The proof uses a nonce derived from the secret and the
+    /// coin, as in ed25519, so that tossing the same coin twice gives the same share.
+    pub fn share(&self, name: &[u8]) -> CoinShare {
+        let base = Self::base(name);
+        let value = self.secret * base;
+        let nonce = Scalar::from_bytes_mod_order_wide(&wide(
+            Sha512::new()
+                .chain_update(b"coin-nonce")
+                .chain_update(self.secret.as_bytes())
+                .chain_update(base.compress().as_bytes()),
+        ));
+        let verification_key = self.secret * RISTRETTO_BASEPOINT_POINT;
+        let challenge = Self::challenge(
+            &base,
+            &verification_key,
+            &value,
+            &(nonce * RISTRETTO_BASEPOINT_POINT),
+            &(nonce * base),
+        );
+        CoinShare {
+            value,
+            challenge,
+            response: nonce + challenge * self.secret,
+        }
+    }
+
+    /// Checks that `share` is the share of `holder` for the coin named `name`.
+    pub fn verify(&self, holder: &PublicKey, name: &[u8], share: &CoinShare) -> bool {
+        let verification_key = match self.holders.get(holder) {
+            Some(key) => key,
+            None => return false,
+        };
+        let base = Self::base(name);
+        let commitment_g = share.response * RISTRETTO_BASEPOINT_POINT - share.challenge * verification_key;
+        let commitment_h = share.response * base - share.challenge * share.value;
+        Self::challenge(&base, verification_key, &share.value, &commitment_g, &commitment_h) == share.challenge
+    }
+
+    /// The value of the coin, interpolated out of the first `threshold` shares. The shares must
+    /// have been verified, and come from distinct holders.
+    pub fn combine<'a>(&self, shares: impl IntoIterator<Item = (&'a PublicKey, &'a CoinShare)>) -> Option<[u8; 32]> {
+        let points: Vec<(Scalar, RistrettoPoint)> = shares
+            .into_iter()
+            .filter_map(|(holder, share)| {
+                let rank = self.holders.keys().position(|name| name == holder)?;
+                Some((Scalar::from(rank as u64 + 1), share.value))
+            })
+            .take(self.threshold)
+            .collect();
+        if points.len() < self.threshold {
+            return None;
+        }
+        // Lagrange interpolation at 0, in the exponent.
+        let value: RistrettoPoint = points
+            .iter()
+            .map(|(i, share)| {
+                let lambda = points
+                    .iter()
+                    .filter(|(j, _)| j != i)
+                    .fold(Scalar::ONE, |acc, (j, _)| acc * j * (j - i).invert());
+                lambda * share
+            })
+            .sum();
+        Some(Sha256::digest(value.compress().as_bytes()).into())
+    }
+
+    fn base(name: &[u8]) -> RistrettoPoint {
+        RistrettoPoint::from_uniform_bytes(&wide(Sha512::new().chain_update(b"coin").chain_update(name)))
+    }
+
+    fn challenge(
+        base: &RistrettoPoint,
+        verification_key: &RistrettoPoint,
+        value: &RistrettoPoint,
+        commitment_g: &RistrettoPoint,
+        commitment_h: &RistrettoPoint,
+    ) -> Scalar {
+        let mut hasher = Sha512::new();
+        for point in [base, verification_key, value, commitment_g, commitment_h] {
+            hasher.update(point.compress().as_bytes());
+        }
+        Scalar::from_bytes_mod_order_wide(&wide(hasher))
+    }
+}
+
+fn wide(hasher: Sha512) -> [u8; 64] {
+    let mut bytes = [0; 64];
+    bytes.copy_from_slice(&hasher.finalize());
+    bytes
+}
@@ -7,7 +7,7 @@ pub type Id = usize;
 pub type Stake = u32;
 pub type WorkerId = u32;
 
-pub type RandomNum = u128;
+pub use crate::beacon_output::RandomNum;
 #[cfg(feature = "pq")]
 pub type ZqMod = u64;
 
@@ -72,6 +72,15 @@ pub fn round_to_epoch_index(round: Round, max_epoch: u64) -> (Epoch, usize) {
     (epoch, index as usize)
 }
 
+/// Returns the epoch of a DAG round. The genesis, and every round of a process that did not set
+/// `MAX_EPOCH` (the workers), belong to epoch 0.
+pub fn round_epoch(round: Round) -> Epoch {
+    match MAX_EPOCH.get() {
+        Some(max_epoch) if round > 0 => round_to_epoch_index(round, *max_epoch).0,
+        _ => 0,
+    }
+}
+
 
 pub fn leader_round_to_epoch_index(round: Round, max_epoch: u64) -> (Epoch, usize) {
     assert_eq!(round % 2, 1, "round must be an odd number");
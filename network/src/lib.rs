@@ -3,11 +3,15 @@ mod error;
 mod receiver;
 mod reliable_sender;
 mod simple_sender;
+mod simulator;
+mod transport;
 
 #[cfg(test)]
 #[path = "tests/common.rs"]
 pub mod common;
 
-pub use crate::receiver::{MessageHandler, Receiver, Writer};
+pub use crate::receiver::{MessageHandler, Receiver};
 pub use crate::reliable_sender::{CancelHandler, ReliableSender};
 pub use crate::simple_sender::SimpleSender;
+pub use crate::simulator::{Delivery, NodeId, SimulatedNetwork, SimulationParameters};
+pub use crate::transport::{Listener, Reader, TcpTransport, Transport, Writer};
@@ -1,25 +1,20 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::NetworkError;
+use crate::transport::{Reader, TcpTransport, Transport, Writer};
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::stream::SplitSink;
 use futures::stream::StreamExt as _;
 use log::{debug, info, warn};
 use std::error::Error;
 use std::io::Read;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use flate2::read::ZlibDecoder;
-use tokio::net::{TcpListener, TcpStream};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
-use model::types_and_const::MAX_FRAME_SIZE;
 
 #[cfg(test)]
 #[path = "tests/receiver_tests.rs"]
 pub mod receiver_tests;
 
-/// Convenient alias for the writer end of the TCP channel.
-pub type Writer = SplitSink<Framed<TcpStream, LengthDelimitedCodec>, Bytes>;
-
 #[async_trait]
 pub trait MessageHandler: Clone + Send + Sync + 'static {
     /// Defines how to handle an incoming message. A typical usage is to define a `MessageHandler` with a
@@ -36,25 +31,40 @@ pub struct Receiver<Handler: MessageHandler> {
     address: SocketAddr,
     /// Struct responsible to define how to handle received messages.
     handler: Handler,
+    /// The transport accepting the connections.
+    transport: Arc<dyn Transport>,
 }
 
 impl<Handler: MessageHandler> Receiver<Handler> {
-    /// Spawn a new network receiver handling connections from any incoming peer.
+    /// Spawn a new network receiver handling connections from any incoming peer, over TCP.
     pub fn spawn(address: SocketAddr, handler: Handler) {
+        Self::spawn_with_transport(Arc::new(TcpTransport), address, handler);
+    }
+
+    /// Spawn a new network receiver accepting its connections through the provided transport.
+    pub fn spawn_with_transport(transport: Arc<dyn Transport>, address: SocketAddr, handler: Handler) {
         tokio::spawn(async move {
-            Self { address, handler }.run().await;
+            Self {
+                address,
+                handler,
+                transport,
+            }
+            .run()
+            .await;
         });
     }
 
     /// Main loop responsible to accept incoming connections and spawn a new runner to handle it.
     async fn run(&self) {
-        let listener = TcpListener::bind(&self.address)
+        let mut listener = self
+            .transport
+            .bind(self.address)
             .await
-            .expect("Failed to bind TCP port");
+            .expect("Failed to bind address");
 
         debug!("Listening on {}", self.address);
         loop {
-            let (socket, peer) = match listener.accept().await {
+            let (writer, reader, peer) = match listener.accept().await {
                 Ok(value) => value,
                 Err(e) => {
                     warn!("{}", NetworkError::FailedToListen(e));
@@ -62,11 +72,11 @@ impl<Handler: MessageHandler> Receiver<Handler> {
                 }
             };
             info!("Incoming connection established with {}", peer);
-            Self::spawn_runner(socket, peer, self.handler.clone()).await;
+            Self::spawn_runner(writer, reader, peer, self.handler.clone()).await;
         }
     }
 
-    /// Spawn a new runner to handle a specific TCP connection. It receives messages and process them
+    /// Spawn a new runner to handle a specific connection. It receives messages and process them
     /// using the provided handler.
     // async fn spawn_runner(socket: TcpStream, peer: SocketAddr, handler: Handler) {
     //     tokio::spawn(async move {
@@ -92,17 +102,11 @@ impl<Handler: MessageHandler> Receiver<Handler> {
     //         warn!("Connection closed by peer {}", peer);
     //     });
     // }
-    async fn spawn_runner(socket: TcpStream, peer: SocketAddr, handler: Handler) {
+    async fn spawn_runner(mut writer: Writer, mut reader: Reader, peer: SocketAddr, handler: Handler) {
         tokio::spawn(async move {
-            let codec = LengthDelimitedCodec::builder()
-                .max_frame_length(MAX_FRAME_SIZE)
-                .new_codec();
-            let transport = Framed::new(socket, codec);
-            let (mut writer, mut reader) = transport.split();
             while let Some(frame) = reader.next().await {
                 match frame.map_err(|e| NetworkError::FailedToReceiveMessage(peer, e)) {
                     Ok(message) => {
-                        let message = message.freeze();
                         // 检查标志位
                         if message.is_empty() {
                             warn!("Received empty message from {}", peer);
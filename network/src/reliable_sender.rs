@@ -1,25 +1,23 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::NetworkError;
+use crate::transport::{Reader, TcpTransport, Transport, Writer};
 use bytes::{Bytes, BytesMut};
 use futures::sink::SinkExt as _;
 use futures::stream::StreamExt as _;
 use log::{info, warn};
 use rand::prelude::SliceRandom as _;
 use rand::rngs::SmallRng;
-use rand::SeedableRng as _;
 use std::cmp::min;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt::Debug;
 use std::io::Write;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use flate2::Compression;
 use flate2::write::ZlibEncoder;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::oneshot;
 use tokio::time::{sleep, Duration};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
-use model::types_and_const::MAX_FRAME_SIZE;
 
 #[cfg(test)]
 #[path = "tests/reliable_sender_tests.rs"]
@@ -37,6 +35,8 @@ pub struct ReliableSender {
     connections: HashMap<SocketAddr, Sender<InnerMessage>>,
     /// Small RNG just used to shuffle nodes and randomize connections (not crypto related).
     rng: SmallRng,
+    /// The transport opening our connections.
+    transport: Arc<dyn Transport>,
 }
 
 impl std::default::Default for ReliableSender {
@@ -46,17 +46,24 @@ impl std::default::Default for ReliableSender {
 }
 
 impl ReliableSender {
+    /// Make a sender opening its connections over TCP.
     pub fn new() -> Self {
+        Self::with_transport(Arc::new(TcpTransport))
+    }
+
+    /// Make a sender opening its connections through the provided transport.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
         Self {
             connections: HashMap::new(),
-            rng: SmallRng::from_entropy(),
+            rng: transport.rng(),
+            transport,
         }
     }
 
     /// Helper function to spawn a new connection.
-    fn spawn_connection(address: SocketAddr) -> Sender<InnerMessage> {
+    fn spawn_connection(transport: &Arc<dyn Transport>, address: SocketAddr) -> Sender<InnerMessage> {
         let (tx, rx) = channel(1_000);
-        Connection::spawn(address, rx);
+        Connection::spawn(transport.clone(), address, rx);
         tx
     }
 
@@ -79,9 +86,10 @@ impl ReliableSender {
         let mut prefixed_data = BytesMut::with_capacity(data.len() + 1);
         prefixed_data.extend_from_slice(&[0x00]);
         prefixed_data.extend_from_slice(&data);
+        let transport = &self.transport;
         self.connections
             .entry(address)
-            .or_insert_with(|| Self::spawn_connection(address))
+            .or_insert_with(|| Self::spawn_connection(transport, address))
             .send(InnerMessage {
                 data: prefixed_data.freeze(),
                 cancel_handler: sender,
@@ -98,9 +106,10 @@ impl ReliableSender {
         let mut prefixed_data = BytesMut::with_capacity(compressed_data.len() + 1);
         prefixed_data.extend_from_slice(&[0x01]);
         prefixed_data.extend_from_slice(&compressed_data);
+        let transport = &self.transport;
         self.connections
             .entry(address)
-            .or_insert_with(|| Self::spawn_connection(address))
+            .or_insert_with(|| Self::spawn_connection(transport, address))
             .send(InnerMessage {
                 data: prefixed_data.freeze(),
                 cancel_handler: sender,
@@ -138,11 +147,11 @@ impl ReliableSender {
         self.broadcast(addresses, data).await
     }
 
-    /// Dispatch the message in the HashMap to all the addresses in a reliable manner. It returns a vector of
-    /// cancel handlers ordered as the input `addresses` vector.
+    /// Dispatch the message of each address of the map in a reliable manner, in the order of the
+    /// addresses. It returns a vector of cancel handlers in the same order.
     pub async fn dispatch_to_addresses(
         &mut self,
-        map: HashMap<SocketAddr, Bytes>,
+        map: BTreeMap<SocketAddr, Bytes>,
     ) -> Vec<CancelHandler> {
         let mut handlers = Vec::new();
 
@@ -155,7 +164,7 @@ impl ReliableSender {
 
     pub async fn dispatch_to_addresses_compressed(
         &mut self,
-        map: HashMap<SocketAddr, Bytes>,
+        map: BTreeMap<SocketAddr, Bytes>,
     ) -> Vec<CancelHandler> {
         let mut handlers = Vec::new();
 
@@ -179,6 +188,8 @@ struct InnerMessage {
 
 /// A connection is responsible to reliably establish (and keep alive) a connection with a single peer.
 struct Connection {
+    /// The transport opening the connection.
+    transport: Arc<dyn Transport>,
     /// The destination address.
     address: SocketAddr,
     /// Channel from which the connection receives its commands.
@@ -190,9 +201,10 @@ struct Connection {
 }
 
 impl Connection {
-    fn spawn(address: SocketAddr, receiver: Receiver<InnerMessage>) {
+    fn spawn(transport: Arc<dyn Transport>, address: SocketAddr, receiver: Receiver<InnerMessage>) {
         tokio::spawn(async move {
             Self {
+                transport,
                 address,
                 receiver,
                 retry_delay: 200,
@@ -208,8 +220,8 @@ impl Connection {
         let mut delay = self.retry_delay;
         let mut retry = 0;
         loop {
-            match TcpStream::connect(self.address).await {
-                Ok(stream) => {
+            match self.transport.connect(self.address).await {
+                Ok((writer, reader)) => {
                     info!("Outgoing connection established with {}", self.address);
 
                     // Reset the delay.
@@ -218,7 +230,7 @@ impl Connection {
 
                     // Try to transmit all messages in the buffer and keep transmitting incoming messages.
                     // The following function only returns if there is an error.
-                    let error = self.keep_alive(stream).await;
+                    let error = self.keep_alive(writer, reader).await;
                     warn!("{}", error);
                 }
                 Err(e) => {
@@ -228,6 +240,8 @@ impl Connection {
 
                     'waiter: loop {
                         tokio::select! {
+                            biased;
+
                             // Wait an increasing delay before attempting to reconnect.
                             () = &mut timer => {
                                 delay = min(2*delay, 60_000);
@@ -249,14 +263,10 @@ impl Connection {
     }
 
     /// Transmit messages once we have established a connection.
-    async fn keep_alive(&mut self, stream: TcpStream) -> NetworkError {
+    async fn keep_alive(&mut self, mut writer: Writer, mut reader: Reader) -> NetworkError {
         // This buffer keeps all messages and handlers that we have successfully transmitted but for
         // which we are still waiting to receive an ACK.
         let mut pending_replies = VecDeque::new();
-        let codec = LengthDelimitedCodec::builder()
-            .max_frame_length(MAX_FRAME_SIZE)
-            .new_codec();
-        let (mut writer, mut reader) = Framed::new(stream, codec).split();
         let error = 'connection: loop {
             // Try to send all messages of the buffer.
             while let Some((data, handler)) = self.buffer.pop_front() {
@@ -280,12 +290,10 @@ impl Connection {
                 }
             }
 
-            // Check if there are any new messages to send or if we get an ACK for messages we already sent.
+            // Check if we get an ACK for messages we already sent or if there are any new messages to send.
+            // The ACKs come first: they are bounded by what we sent, while new messages may keep coming.
             tokio::select! {
-                Some(InnerMessage{data, cancel_handler}) = self.receiver.recv() => {
-                    // Add the message to the buffer of messages to send.
-                    self.buffer.push_back((data, cancel_handler));
-                },
+                biased;
                 response = reader.next() => {
                     let (data, handler) = match pending_replies.pop_front() {
                         Some(message) => message,
@@ -294,7 +302,7 @@ impl Connection {
                     match response {
                         Some(Ok(bytes)) => {
                             // Notify the handler that the message has been successfully sent.
-                            let _ = handler.send(bytes.freeze());
+                            let _ = handler.send(bytes);
                         },
                         _ => {
                             // Something has gone wrong (either the channel dropped or we failed to read from it).
@@ -304,6 +312,10 @@ impl Connection {
                         }
                     }
                 },
+                Some(InnerMessage{data, cancel_handler}) = self.receiver.recv() => {
+                    // Add the message to the buffer of messages to send.
+                    self.buffer.push_back((data, cancel_handler));
+                },
             }
         };
 
@@ -1,18 +1,16 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::NetworkError;
+use crate::transport::{TcpTransport, Transport};
 use bytes::{Bytes, BytesMut};
 use futures::sink::SinkExt as _;
 use futures::stream::StreamExt as _;
 use log::{info, warn};
 use rand::prelude::SliceRandom as _;
 use rand::rngs::SmallRng;
-use rand::SeedableRng as _;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
+use std::sync::Arc;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
-use model::types_and_const::{MAX_FRAME_SIZE};
 
 #[cfg(test)]
 #[path = "tests/simple_sender_tests.rs"]
@@ -25,6 +23,8 @@ pub struct SimpleSender {
     connections: HashMap<SocketAddr, Sender<Bytes>>,
     /// Small RNG just used to shuffle nodes and randomize connections (not crypto related).
     rng: SmallRng,
+    /// The transport opening our connections.
+    transport: Arc<dyn Transport>,
 }
 
 impl std::default::Default for SimpleSender {
@@ -34,17 +34,24 @@ impl std::default::Default for SimpleSender {
 }
 
 impl SimpleSender {
+    /// Make a sender opening its connections over TCP.
     pub fn new() -> Self {
+        Self::with_transport(Arc::new(TcpTransport))
+    }
+
+    /// Make a sender opening its connections through the provided transport.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
         Self {
             connections: HashMap::new(),
-            rng: SmallRng::from_entropy(),
+            rng: transport.rng(),
+            transport,
         }
     }
 
     /// Helper function to spawn a new connection.
-    fn spawn_connection(address: SocketAddr) -> Sender<Bytes> {
+    fn spawn_connection(transport: &Arc<dyn Transport>, address: SocketAddr) -> Sender<Bytes> {
         let (tx, rx) = channel(1_000);
-        Connection::spawn(address, rx);
+        Connection::spawn(transport.clone(), address, rx);
         tx
     }
 
@@ -63,7 +70,7 @@ impl SimpleSender {
         }
 
         // Otherwise make a new connection.
-        let tx = Self::spawn_connection(address);
+        let tx = Self::spawn_connection(&self.transport, address);
         if tx.send(prefixed_data).await.is_ok() {
             self.connections.insert(address, tx);
         }
@@ -92,6 +99,8 @@ impl SimpleSender {
 
 /// A connection is responsible to establish and keep alive (if possible) a connection with a single peer.
 struct Connection {
+    /// The transport opening the connection.
+    transport: Arc<dyn Transport>,
     /// The destination address.
     address: SocketAddr,
     /// Channel from which the connection receives its commands.
@@ -99,20 +108,23 @@ struct Connection {
 }
 
 impl Connection {
-    fn spawn(address: SocketAddr, receiver: Receiver<Bytes>) {
+    fn spawn(transport: Arc<dyn Transport>, address: SocketAddr, receiver: Receiver<Bytes>) {
         tokio::spawn(async move {
-            Self { address, receiver }.run().await;
+            Self {
+                transport,
+                address,
+                receiver,
+            }
+            .run()
+            .await;
         });
     }
 
     /// Main loop trying to connect to the peer and transmit messages.
     async fn run(&mut self) {
-        let codec = LengthDelimitedCodec::builder()
-            .max_frame_length(MAX_FRAME_SIZE)
-            .new_codec();
         // Try to connect to the peer.
-        let (mut writer, mut reader) = match TcpStream::connect(self.address).await {
-            Ok(stream) => Framed::new(stream, codec).split(),
+        let (mut writer, mut reader) = match self.transport.connect(self.address).await {
+            Ok(connection) => connection,
             Err(e) => {
                 warn!(
                     "{}",
@@ -125,14 +137,9 @@ impl Connection {
 
         // Transmit messages once we have established a connection.
         loop {
-            // Check if there are any new messages to send or if we get an ACK for messages we already sent.
+            // Check if we get an ACK for messages we already sent or if there are any new messages to send.
             tokio::select! {
-                Some(data) = self.receiver.recv() => {
-                    if let Err(e) = writer.send(data).await {
-                        warn!("{}", NetworkError::FailedToSendMessage(self.address, e));
-                        return;
-                    }
-                },
+                biased;
                 response = reader.next() => {
                     match response {
                         Some(Ok(_)) => {
@@ -145,6 +152,12 @@ impl Connection {
                         }
                     }
                 },
+                Some(data) = self.receiver.recv() => {
+                    if let Err(e) = writer.send(data).await {
+                        warn!("{}", NetworkError::FailedToSendMessage(self.address, e));
+                        return;
+                    }
+                },
             }
         }
     }
@@ -0,0 +1,346 @@
+use crate::transport::{Listener, Reader, Transport, Writer};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::channel::mpsc as futures_mpsc;
+use futures::sink::Sink;
+use futures::stream::StreamExt as _;
+use rand::rngs::{SmallRng, StdRng};
+use rand::{Rng as _, SeedableRng as _};
+use std::cmp::max;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash as _, Hasher as _};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep_until, Duration, Instant};
+
+#[cfg(test)]
+#[path = "tests/simulator_tests.rs"]
+pub mod simulator_tests;
+
+/// Identifies a node of the simulation.
+pub type NodeId = usize;
+
+/// The behaviour of the simulated links.
+#[derive(Clone, Debug)]
+pub struct SimulationParameters {
+    /// The minimum delay of a message (in ms).
+    pub min_delay: u64,
+    /// The maximum delay of a message (in ms).
+    pub max_delay: u64,
+    /// The probability that a message is lost. As TCP would after a timeout, a loss resets the
+    /// connection that carried the message, so that reliable senders transmit it again.
+    pub drop_rate: f64,
+    /// The probability that a message may overtake the messages written before it on the same
+    /// connection (it then only waits for its own delay).
+    pub reorder_rate: f64,
+}
+
+impl Default for SimulationParameters {
+    fn default() -> Self {
+        Self {
+            min_delay: 1,
+            max_delay: 50,
+            drop_rate: 0.0,
+            reorder_rate: 0.0,
+        }
+    }
+}
+
+type Incoming = (Writer, Reader, SocketAddr);
+
+/// The stream of draws of the senders of a node (the other streams are the links between nodes).
+const SENDERS: u64 = u64::MAX;
+
+/// A frame delivered by the simulated network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delivery {
+    /// The time of the delivery, since the network was made.
+    pub time: Duration,
+    pub from: NodeId,
+    pub to: NodeId,
+    /// A hash of the frame.
+    pub digest: u64,
+}
+
+struct State {
+    parameters: SimulationParameters,
+    seed: u64,
+    /// The number of RNGs handed out so far to each stream of draws.
+    streams: HashMap<(u64, u64), u64>,
+    start: Instant,
+    /// The frames delivered so far.
+    trace: Vec<Delivery>,
+    /// The listeners of each bound address, along with the node that bound it.
+    listeners: HashMap<SocketAddr, (NodeId, UnboundedSender<Incoming>)>,
+    /// The side of the partition of each node. Nodes that are not listed are all on the same side.
+    partition: HashMap<NodeId, usize>,
+    /// Port of the next (fake) address handed out to a connecting peer.
+    next_port: u16,
+}
+
+/// An in-memory network connecting the nodes of a simulation. Every message gets a delay drawn
+/// uniformly between `min_delay` and `max_delay`, may be lost with probability `drop_rate`, and
+/// may overtake the messages written before it on its connection with probability `reorder_rate`.
+/// Each link draws from its own RNG, seeded by the seed of the caller, the two nodes and the number
+/// of links they opened before: the fate of a message does not depend on the traffic of the other
+/// links. The senders of the nodes shuffle their peers with seeded RNGs as well. With a paused tokio
+/// clock and a single-threaded runtime, a seed reproduces the same run, as its `trace` shows: the
+/// tasks of a node poll their inputs in a fixed order (`biased` selects), and send in an order that
+/// does not depend on the iteration order of a `HashMap`.
+#[derive(Clone)]
+pub struct SimulatedNetwork {
+    state: Arc<Mutex<State>>,
+}
+
+impl SimulatedNetwork {
+    pub fn new(seed: u64, parameters: SimulationParameters) -> Self {
+        assert!(parameters.min_delay <= parameters.max_delay);
+        Self {
+            state: Arc::new(Mutex::new(State {
+                parameters,
+                seed,
+                streams: HashMap::new(),
+                start: Instant::now(),
+                trace: Vec::new(),
+                listeners: HashMap::new(),
+                partition: HashMap::new(),
+                next_port: 1,
+            })),
+        }
+    }
+
+    /// The transport of `node`: its listeners and connections are subject to the partitions
+    /// involving it.
+    pub fn endpoint(&self, node: NodeId) -> Arc<dyn Transport> {
+        Arc::new(Endpoint {
+            node,
+            network: self.clone(),
+        })
+    }
+
+    /// The frames delivered so far, in their order of delivery.
+    pub fn trace(&self) -> Vec<Delivery> {
+        self.state.lock().unwrap().trace.clone()
+    }
+
+    /// Split the nodes into the provided groups (the nodes not listed form one more group). Nodes
+    /// of different groups can no longer connect, and their connections break on the next message.
+    pub fn partition(&self, groups: &[Vec<NodeId>]) {
+        let mut state = self.state.lock().unwrap();
+        state.partition = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| group.iter().map(move |node| (*node, i + 1)))
+            .collect();
+    }
+
+    /// Cut `node` from all the others, as if it crashed.
+    pub fn isolate(&self, node: NodeId) {
+        self.partition(&[vec![node]]);
+    }
+
+    /// Reconnect all the nodes.
+    pub fn heal(&self) {
+        self.state.lock().unwrap().partition.clear();
+    }
+
+    fn connected(&self, a: NodeId, b: NodeId) -> bool {
+        let state = self.state.lock().unwrap();
+        state.partition.get(&a).unwrap_or(&0) == state.partition.get(&b).unwrap_or(&0)
+    }
+
+    /// The RNG of the next member of `stream`, seeded by our seed, the stream and the number of
+    /// RNGs it got before.
+    fn rng(&self, stream: (u64, u64)) -> StdRng {
+        let mut state = self.state.lock().unwrap();
+        let seed = state.seed;
+        let count = state.streams.entry(stream).or_insert(0);
+        *count += 1;
+        let mut bytes = [0u8; 32];
+        for (chunk, value) in bytes.chunks_mut(8).zip([seed, stream.0, stream.1, *count]) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        StdRng::from_seed(bytes)
+    }
+
+    fn record(&self, from: NodeId, to: NodeId, frame: &Bytes) {
+        let mut hasher = DefaultHasher::new();
+        frame.hash(&mut hasher);
+        let mut state = self.state.lock().unwrap();
+        let time = Instant::now() - state.start;
+        state.trace.push(Delivery {
+            time,
+            from,
+            to,
+            digest: hasher.finish(),
+        });
+    }
+
+    /// Make a one-way link carrying frames from `from` to `to`. A frame written while the nodes are
+    /// apart is lost. A loss resets the link once the frames written before it are delivered:
+    /// a reliable sender matches acknowledgements to its messages in order, so a frame overtaken by
+    /// an acknowledged one must still arrive.
+    fn link(&self, from: NodeId, to: NodeId) -> (Writer, Reader) {
+        let (tx_frame, mut rx_frame) = futures_mpsc::unbounded::<Bytes>();
+        let (tx_delivery, rx_delivery) = futures_mpsc::unbounded::<io::Result<Bytes>>();
+
+        let network = self.clone();
+        let mut rng = self.rng((from as u64, to as u64));
+        let parameters = self.state.lock().unwrap().parameters.clone();
+        tokio::spawn(async move {
+            // The frames in flight by delivery time (and write order), `None` standing for a loss.
+            let mut scheduled: BTreeMap<(Instant, u64), Option<Bytes>> = BTreeMap::new();
+            let mut latest = Instant::now();
+            let mut written = 0;
+            let mut open = true;
+            loop {
+                let next = scheduled.keys().next().map(|(deadline, _)| *deadline);
+                tokio::select! {
+                    biased;
+
+                    () = sleep_until(next.unwrap_or_else(Instant::now)), if next.is_some() => {
+                        let (_, frame) = scheduled.pop_first().unwrap();
+                        if let Some(frame) = &frame {
+                            network.record(from, to, frame);
+                        }
+                        let delivery = frame.ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionReset));
+                        let reset = delivery.is_err();
+                        if tx_delivery.unbounded_send(delivery).is_err() || reset {
+                            return;
+                        }
+                    },
+                    frame = rx_frame.next(), if open => {
+                        let frame = match frame {
+                            Some(frame) => frame,
+                            None => {
+                                open = false;
+                                continue;
+                            }
+                        };
+                        let delay = Duration::from_millis(
+                            rng.gen_range(parameters.min_delay, parameters.max_delay + 1),
+                        );
+                        let lost = parameters.drop_rate > 0.0 && rng.gen_bool(parameters.drop_rate);
+                        let reordered = parameters.reorder_rate > 0.0 && rng.gen_bool(parameters.reorder_rate);
+                        let lost = lost || !network.connected(from, to);
+
+                        let deadline = match reordered && !lost {
+                            true => Instant::now() + delay,
+                            false => max(latest, Instant::now() + delay),
+                        };
+                        latest = max(latest, deadline);
+                        written += 1;
+                        if lost {
+                            scheduled.insert((deadline, written), None);
+                            rx_frame.close();
+                            open = false;
+                        } else {
+                            scheduled.insert((deadline, written), Some(frame));
+                        }
+                    },
+                    else => return,
+                }
+            }
+        });
+
+        (Box::pin(LinkWriter(tx_frame)), Box::pin(rx_delivery))
+    }
+}
+
+/// The writer end of a link. Writing fails once the link is broken.
+struct LinkWriter(futures_mpsc::UnboundedSender<Bytes>);
+
+impl Sink<Bytes> for LinkWriter {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.0.is_closed() {
+            true => Poll::Ready(Err(io::Error::from(io::ErrorKind::BrokenPipe))),
+            false => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: Bytes) -> io::Result<()> {
+        self.0
+            .unbounded_send(frame)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.0.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The transport of one node of a `SimulatedNetwork`.
+struct Endpoint {
+    node: NodeId,
+    network: SimulatedNetwork,
+}
+
+#[async_trait]
+impl Transport for Endpoint {
+    fn rng(&self) -> SmallRng {
+        SmallRng::from_rng(self.network.rng((self.node as u64, SENDERS))).unwrap()
+    }
+
+    async fn bind(&self, address: SocketAddr) -> io::Result<Box<dyn Listener>> {
+        let mut state = self.network.state.lock().unwrap();
+        if matches!(state.listeners.get(&address), Some((_, tx)) if !tx.is_closed()) {
+            return Err(io::Error::from(io::ErrorKind::AddrInUse));
+        }
+        let (tx_incoming, rx_incoming) = unbounded_channel();
+        state.listeners.insert(address, (self.node, tx_incoming));
+        Ok(Box::new(SimulatedListener { rx_incoming }))
+    }
+
+    async fn connect(&self, address: SocketAddr) -> io::Result<(Writer, Reader)> {
+        // Like TCP, a listener bound to the unspecified address accepts connections to any IP.
+        let wildcard = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), address.port());
+        let (node, tx_incoming, peer) = {
+            let mut state = self.network.state.lock().unwrap();
+            let (node, tx_incoming) = state
+                .listeners
+                .get(&address)
+                .or_else(|| state.listeners.get(&wildcard))
+                .cloned()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+            let peer = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), state.next_port);
+            state.next_port = state.next_port.wrapping_add(1);
+            (node, tx_incoming, peer)
+        };
+        if !self.network.connected(self.node, node) {
+            return Err(io::Error::from(io::ErrorKind::ConnectionRefused));
+        }
+
+        let (writer, remote_reader) = self.network.link(self.node, node);
+        let (remote_writer, reader) = self.network.link(node, self.node);
+        tx_incoming
+            .send((remote_writer, remote_reader, peer))
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+        Ok((writer, reader))
+    }
+}
+
+struct SimulatedListener {
+    rx_incoming: UnboundedReceiver<Incoming>,
+}
+
+#[async_trait]
+impl Listener for SimulatedListener {
+    async fn accept(&mut self) -> io::Result<Incoming> {
+        self.rx_incoming
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))
+    }
+}
@@ -1,9 +1,11 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
 use futures::sink::SinkExt as _;
+use tokio::net::TcpStream;
 use tokio::sync::mpsc::channel;
 use tokio::sync::mpsc::Sender;
 use tokio::time::{sleep, Duration};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
 #[derive(Clone)]
 struct TestHandler {
@@ -35,7 +37,8 @@ async fn receive() {
 
     // Send a message.
     let sent = "Hello, world!";
-    let bytes = Bytes::from(bincode::serialize(sent).unwrap());
+    // The first byte flags an uncompressed message.
+    let bytes = Bytes::from([&[0x00], bincode::serialize(sent).unwrap().as_slice()].concat());
     let stream = TcpStream::connect(address).await.unwrap();
     let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
     transport.send(bytes.clone()).await.unwrap();
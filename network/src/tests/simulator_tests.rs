@@ -0,0 +1,141 @@
+use super::*;
+use crate::{MessageHandler, Receiver, ReliableSender};
+use futures::future::try_join_all;
+use futures::sink::SinkExt as _;
+use std::collections::BTreeSet;
+use std::error::Error;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::time::timeout;
+
+#[derive(Clone)]
+struct TestHandler {
+    deliver: Sender<(String, Instant)>,
+}
+
+#[async_trait]
+impl MessageHandler for TestHandler {
+    async fn dispatch(&self, writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>> {
+        // Reply with an ACK.
+        let _ = writer.send(Bytes::from("Ack")).await;
+
+        // Deliver the message to the application, along with its arrival time.
+        let message = bincode::deserialize(&message).unwrap();
+        self.deliver.send((message, Instant::now())).await.unwrap();
+        Ok(())
+    }
+}
+
+fn address(node: NodeId) -> SocketAddr {
+    format!("127.0.0.1:{}", 7_000 + node).parse().unwrap()
+}
+
+// Node 0 sends `messages` messages to node 1, and returns them in their order of arrival.
+async fn run(network: &SimulatedNetwork, messages: usize) -> Vec<(String, Instant)> {
+    let (tx, mut rx) = channel(1_000);
+    Receiver::spawn_with_transport(network.endpoint(1), address(1), TestHandler { deliver: tx });
+
+    let mut sender = ReliableSender::with_transport(network.endpoint(0));
+    let mut handlers = Vec::new();
+    for i in 0..messages {
+        let bytes = Bytes::from(bincode::serialize(&format!("Message {}", i)).unwrap());
+        handlers.push(sender.send(address(1), bytes).await);
+    }
+    assert!(try_join_all(handlers).await.is_ok());
+
+    let mut received = Vec::new();
+    while let Ok(Some(message)) = timeout(Duration::from_secs(1), rx.recv()).await {
+        received.push(message);
+    }
+    received
+}
+
+#[tokio::test(start_paused = true)]
+async fn reliable_delivery_despite_losses() {
+    let parameters = SimulationParameters {
+        drop_rate: 0.2,
+        ..SimulationParameters::default()
+    };
+    let network = SimulatedNetwork::new(0, parameters);
+    let received = run(&network, 20).await;
+
+    // Messages whose ACK got lost are transmitted again, so some may arrive twice.
+    let distinct: BTreeSet<_> = received.into_iter().map(|(message, _)| message).collect();
+    assert_eq!(distinct.len(), 20);
+}
+
+#[tokio::test(start_paused = true)]
+async fn reordering_within_a_connection() {
+    let parameters = SimulationParameters {
+        reorder_rate: 0.5,
+        ..SimulationParameters::default()
+    };
+    let network = SimulatedNetwork::new(0, parameters);
+    let received: Vec<_> = run(&network, 20).await.into_iter().map(|(message, _)| message).collect();
+
+    // Every message arrives once, but not in the order they were sent.
+    let sent: Vec<_> = (0..20).map(|i| format!("Message {}", i)).collect();
+    assert_ne!(received, sent);
+    let mut sorted = received.clone();
+    sorted.sort_by_key(|message| sent.iter().position(|x| x == message));
+    assert_eq!(sorted, sent);
+}
+
+#[tokio::test(start_paused = true)]
+async fn reliable_delivery_despite_losses_and_reordering() {
+    let parameters = SimulationParameters {
+        drop_rate: 0.2,
+        reorder_rate: 0.5,
+        ..SimulationParameters::default()
+    };
+    for seed in 0..10 {
+        let received = run(&SimulatedNetwork::new(seed, parameters.clone()), 20).await;
+
+        // A message overtaken by an acknowledged one is not lost when the connection resets.
+        let distinct: BTreeSet<_> = received.into_iter().map(|(message, _)| message).collect();
+        assert_eq!(distinct.len(), 20);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn same_seed_same_run() {
+    let parameters = SimulationParameters {
+        drop_rate: 0.1,
+        reorder_rate: 0.2,
+        ..SimulationParameters::default()
+    };
+    let start = Instant::now();
+    let first: Vec<_> = run(&SimulatedNetwork::new(7, parameters.clone()), 10)
+        .await
+        .into_iter()
+        .map(|(message, time)| (message, time - start))
+        .collect();
+
+    let start = Instant::now();
+    let second: Vec<_> = run(&SimulatedNetwork::new(7, parameters), 10)
+        .await
+        .into_iter()
+        .map(|(message, time)| (message, time - start))
+        .collect();
+    assert_eq!(first, second);
+}
+
+#[tokio::test(start_paused = true)]
+async fn partition_delays_delivery_until_healed() {
+    let network = SimulatedNetwork::new(0, SimulationParameters::default());
+    network.partition(&[vec![0], vec![1]]);
+
+    let (tx, mut rx) = channel(1);
+    Receiver::spawn_with_transport(network.endpoint(1), address(1), TestHandler { deliver: tx });
+    let mut sender = ReliableSender::with_transport(network.endpoint(0));
+    let bytes = Bytes::from(bincode::serialize("Hello, world!").unwrap());
+    let handler = sender.send(address(1), bytes).await;
+
+    // Nothing gets through while the nodes are apart.
+    assert!(timeout(Duration::from_secs(5), rx.recv()).await.is_err());
+
+    // The message arrives once the partition heals.
+    network.heal();
+    let (message, _) = rx.recv().await.unwrap();
+    assert_eq!(message, "Hello, world!");
+    assert!(handler.await.is_ok());
+}
@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::sink::Sink;
+use futures::stream::{Stream, StreamExt as _};
+use model::types_and_const::MAX_FRAME_SIZE;
+use rand::rngs::SmallRng;
+use rand::SeedableRng as _;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// Convenient alias for the writer end of a connection.
+pub type Writer = Pin<Box<dyn Sink<Bytes, Error = io::Error> + Send>>;
+
+/// Convenient alias for the reader end of a connection.
+pub type Reader = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Opens the connections used by the senders and receivers. A connection carries whole frames,
+/// delivered in the order they were written.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Start listening for incoming connections on `address`.
+    async fn bind(&self, address: SocketAddr) -> io::Result<Box<dyn Listener>>;
+
+    /// Open a connection to the peer listening on `address`.
+    async fn connect(&self, address: SocketAddr) -> io::Result<(Writer, Reader)>;
+
+    /// The RNG with which a sender using this transport shuffles the peers of its broadcasts.
+    fn rng(&self) -> SmallRng {
+        SmallRng::from_entropy()
+    }
+}
+
+#[async_trait]
+pub trait Listener: Send {
+    /// Wait for the next incoming connection. Also returns the address of the peer.
+    async fn accept(&mut self) -> io::Result<(Writer, Reader, SocketAddr)>;
+}
+
+/// The transport of production: length-delimited frames over TCP.
+pub struct TcpTransport;
+
+impl TcpTransport {
+    fn framed(stream: TcpStream) -> (Writer, Reader) {
+        let codec = LengthDelimitedCodec::builder()
+            .max_frame_length(MAX_FRAME_SIZE)
+            .new_codec();
+        let (writer, reader) = Framed::new(stream, codec).split();
+        (
+            Box::pin(writer),
+            Box::pin(reader.map(|frame| frame.map(BytesMut::freeze))),
+        )
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn bind(&self, address: SocketAddr) -> io::Result<Box<dyn Listener>> {
+        let listener = TcpListener::bind(address).await?;
+        Ok(Box::new(listener))
+    }
+
+    async fn connect(&self, address: SocketAddr) -> io::Result<(Writer, Reader)> {
+        let stream = TcpStream::connect(address).await?;
+        Ok(Self::framed(stream))
+    }
+}
+
+#[async_trait]
+impl Listener for TcpListener {
+    async fn accept(&mut self) -> io::Result<(Writer, Reader, SocketAddr)> {
+        let (stream, peer) = TcpListener::accept(self).await?;
+        let (writer, reader) = TcpTransport::framed(stream);
+        Ok((writer, reader, peer))
+    }
+}
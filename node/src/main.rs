@@ -1,20 +1,29 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 
 use anyhow::{Context, Result};
+use bytes::Bytes;
 use clap::{crate_name, crate_version, App, AppSettings, ArgMatches, SubCommand};
 use model::file_io::Export;
 use model::file_io::Import;
-use config::{Committee, KeyPair, Parameters};
-#[cfg(not(feature = "dolphin"))]
-use consensus::Tusk;
+use config::{Committee, KeyPair, Parameters, Reconfiguration, SharedCommittee, Weighting};
+use consensus::{Consensus, GlobalCoin};
 use drb_coordinator::coordinator::Coordinator;
+use drb_coordinator::error::DrbError;
 use env_logger::Env;
-use model::types_and_const::{WorkerId, BEACON_PER_EPOCH, CHANNEL_CAPACITY, MAX_EPOCH};
+use futures::sink::SinkExt as _;
+use futures::stream::StreamExt as _;
+use model::types_and_const::{Epoch, RandomNum, WorkerId, BEACON_PER_EPOCH, CHANNEL_CAPACITY, MAX_EPOCH};
 #[cfg(feature = "pq")]
 use model::types_and_const::MAX_INDEX;
-use primary::{Certificate, Primary};
-// use std::sync::Arc;
+use model::evidence::{EvidenceRequest, EvidenceResponse};
+use model::threshold_coin::CoinKey;
+use network::{TcpTransport, Transport};
+use primary::{AdminMessage, Certificate, EvidenceCollector, Header, KeyStore, Primary};
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::sync::Arc;
 use store::Store;
+use tokio::net::TcpStream;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 // #[cfg(feature = "pq")]
 // use tokio::time::sleep;
@@ -24,12 +33,17 @@ use tokio::sync::watch;
 // use tokio::sync::RwLock;
 use bavss::Breeze;
 use secondary_bft::init_bft::InitBFT;
-#[cfg(feature = "dolphin")]
-use consensus::Dolphin;
-use drb_coordinator::beacon_hub::BeaconHub;
-use model::breeze_universal::{BreezeCertificate, CommonReferenceString};
+use drb_coordinator::beacon_hub::{BeaconHub, ProofQuery};
+use model::breeze_universal::{CommonReferenceString, Committed};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use worker::Worker;
 
+#[cfg(all(test, not(feature = "pq")))]
+#[path = "tests/simulation_tests.rs"]
+mod simulation_tests;
+
+/// The outcome of a beacon request, as sent back by the coordinator.
+type BeaconResult = ((Epoch, usize), Result<RandomNum, DrbError>);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -48,11 +62,13 @@ async fn main() -> Result<()> {
                 .args_from_usage("--keys=<FILE> 'The file containing the node keys'")
                 .args_from_usage("--committee=<FILE> 'The file containing committee information'")
                 .args_from_usage("--parameters=[FILE] 'The file containing the node parameters'")
-                .args_from_usage("--store=<PATH> 'The path where to create the data store'")
+                .args_from_usage("--store=<PATH> 'The path where to create the data store (or `memory` for a throwaway in-memory store)'")
+                .args_from_usage("--reconfiguration=[FILE]... 'The committees installed after the initial one'")
                 .subcommand(
                     SubCommand::with_name("primary")
                         .about("Run a single primary")
                         .args_from_usage("--crs=<FILE> 'The common reference string of breeze'")
+                        .args_from_usage("--coin=<FILE> 'Our key of the threshold coin of the secondary BFT'")
                         .args_from_usage("--bs=<FILE> 'The avss_batch_size configuration'")
                         .args_from_usage("--le=<FILE> 'The leader_per_epoch configuration'"),
                 )
@@ -63,6 +79,26 @@ async fn main() -> Result<()> {
                 )
                 .setting(AppSettings::SubcommandRequiredElseHelp),
         )
+        .subcommand(
+            SubCommand::with_name("reconfigure")
+                .about("Ask our primary to propose a committee taking over at a later epoch")
+                .args_from_usage("--keys=<FILE> 'The file containing the node keys'")
+                .args_from_usage("--committee=<FILE> 'The file containing the current committee information'")
+                .args_from_usage("--reconfiguration=<FILE> 'The file containing the proposed committee and its first epoch'"),
+        )
+        .subcommand(
+            SubCommand::with_name("rotate_key")
+                .about("Ask our primary to sign with a fresh key from a later epoch on")
+                .args_from_usage("--keys=<FILE> 'The file containing the node keys'")
+                .args_from_usage("--committee=<FILE> 'The file containing the current committee information'")
+                .args_from_usage("--epoch=<INT> 'The first epoch signed with the new key'"),
+        )
+        .subcommand(
+            SubCommand::with_name("evidence")
+                .about("Print the evidence of misbehaviour gathered by our primary")
+                .args_from_usage("--keys=<FILE> 'The file containing the node keys'")
+                .args_from_usage("--committee=<FILE> 'The file containing the current committee information'"),
+        )
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .get_matches();
 
@@ -83,6 +119,9 @@ async fn main() -> Result<()> {
             .export(sub_matches.value_of("filename").unwrap())
             .context("Failed to generate key pair")?,
         ("run", Some(sub_matches)) => run(sub_matches).await?,
+        ("reconfigure", Some(sub_matches)) => reconfigure(sub_matches).await?,
+        ("rotate_key", Some(sub_matches)) => rotate_key(sub_matches).await?,
+        ("evidence", Some(sub_matches)) => evidence(sub_matches).await?,
         _ => unreachable!(),
     }
     Ok(())
@@ -97,8 +136,16 @@ async fn run(matches: &ArgMatches<'_>) -> Result<()> {
 
     // Read the committee and node's keypair from file.
     let keypair = KeyPair::import(key_file).context("Failed to load the node's keypair")?;
-    let committee =
-        Committee::import(committee_file).context("Failed to load the committee information")?;
+    let committee: SharedCommittee = Committee::import(committee_file)
+        .context("Failed to load the committee information")?
+        .into();
+
+    // Install the committees that took over since then (for nodes joining or restarting late).
+    for filename in matches.values_of("reconfiguration").into_iter().flatten() {
+        let reconfiguration = Reconfiguration::import(filename)
+            .context("Failed to load the reconfiguration")?;
+        committee.install(reconfiguration)?;
+    }
 
     // Load default parameters if none are specified.
     let parameters = match parameters_file {
@@ -109,13 +156,19 @@ async fn run(matches: &ArgMatches<'_>) -> Result<()> {
     };
 
     // Make the data store.
-    let store = Store::new(store_path).context("Failed to create a store")?;
+    let store = match store_path {
+        "memory" => Store::in_memory(),
+        path => Store::new(path).context("Failed to create a store")?,
+    };
 
     // Channels the sequence of certificates.
     let (tx_output, rx_output) = channel(CHANNEL_CAPACITY);
 
     let (cer_to_coord_sender, cer_to_coord_receiver) =
         channel(CHANNEL_CAPACITY);
+
+    // Our senders and receivers talk over TCP.
+    let transport: Arc<dyn Transport> = Arc::new(TcpTransport);
     // Check whether to run a primary, a worker, or an entire authority.
     match matches.subcommand() {
         // Spawn the primary and consensus core.
@@ -133,131 +186,42 @@ async fn run(matches: &ArgMatches<'_>) -> Result<()> {
             assert!(avss_batch_size >= leader_per_epoch, "avss_batch_size must be greater than leader_per_epoch");
             BEACON_PER_EPOCH.set(avss_batch_size - leader_per_epoch).unwrap(); // set global constant
             MAX_EPOCH.set(leader_per_epoch).unwrap(); // set global constant
-            let (breeze_share_cmd_sender, breeze_share_cmd_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (breeze_certificate_sender, breeze_certificate_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (cer_to_consensus_sender, cer_to_consensus_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (cer_to_init_consensus_sender, cer_to_init_consensus_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (init_cc_to_coord_sender, init_cc_to_coord_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (global_coin_recon_req_sender, global_coin_recon_req_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (beacon_recon_req_sender, beacon_recon_req_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (breeze_reconstruct_cmd_sender, breeze_reconstruct_cmd_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (breeze_result_sender, breeze_result_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (global_coin_res_sender, global_coin_res_receiver) =
-                channel(CHANNEL_CAPACITY);
-            let (beacon_res_sender, beacon_res_receiver) =
-                channel(CHANNEL_CAPACITY);
 
             let crs_file = sub_matches.value_of("crs").unwrap();
             let crs =
                 CommonReferenceString::import(crs_file).context("Failed to load the crs for breeze")?;
+            let coin_file = sub_matches.value_of("coin").unwrap();
+            let coin_key = CoinKey::import(coin_file).context("Failed to load the key of the threshold coin")?;
             #[cfg(feature = "pq")]
             MAX_INDEX.set(crs.g * (BEACON_PER_EPOCH.get().unwrap() + MAX_EPOCH.get().unwrap()) as usize).unwrap();
 
-            let mut address = committee.breeze_address(&keypair.name)?;
-            address.set_ip("0.0.0.0".parse()?);
-            let id = committee.get_id(&keypair.name).unwrap();
-            let mut bft_address = committee.init_bft_address(&keypair.name)?;
-            bft_address.set_ip("0.0.0.0".parse()?);
-
-            Breeze::spawn(
-                keypair.clone(),
-                address,
-                id,
-                committee.clone(),
-                breeze_share_cmd_receiver,
-                breeze_certificate_sender,
-                breeze_reconstruct_cmd_receiver,
-                breeze_result_sender,
-                crs,
-            );
-
-            InitBFT::spawn(
-                keypair.clone(),
-                bft_address,
-                committee.clone(),
-                cer_to_init_consensus_receiver,
-                init_cc_to_coord_sender
-            ).await;
-
-            let (recover_signal_sender, mut recover_signal_receiver) = watch::channel(());
-            Coordinator::spawn(
-                committee.clone(),
-                breeze_share_cmd_sender,
-                breeze_certificate_receiver,
-                cer_to_consensus_sender,
-                cer_to_init_consensus_sender,
-                cer_to_coord_receiver,
-                init_cc_to_coord_receiver,
-                global_coin_recon_req_receiver,
-                beacon_recon_req_receiver,
-                breeze_reconstruct_cmd_sender,
-                breeze_result_receiver,
-                global_coin_res_sender,
-                beacon_res_sender,
-                recover_signal_sender,
-                parameters.eval_beacon
-            ).await;
-
-            recover_signal_receiver.changed().await?;
-
-            let (tx_new_certificates, rx_new_certificates) = channel(CHANNEL_CAPACITY);
-            let (tx_commit, rx_commit) = channel(CHANNEL_CAPACITY);
-            let (tx_metadata, rx_metadata) = channel(CHANNEL_CAPACITY);
-            #[cfg(not(feature = "dolphin"))]
-            {
-                Tusk::spawn(
-                    committee.clone(),
-                    parameters.gc_depth,
-                    /* rx_primary */ rx_new_certificates,
-                    tx_commit,
-                    tx_output,
-
-                    global_coin_recon_req_sender,
-                    global_coin_res_receiver
-                );
-                let _not_used = tx_metadata;
+            let mut beacon_api_address = committee.latest().1.beacon_api_address(&keypair.name)?;
+            if let Some(address) = beacon_api_address.as_mut() {
+                address.set_ip("0.0.0.0".parse()?);
             }
-            #[cfg(feature = "dolphin")]
-            Dolphin::spawn(
-                committee.clone(),
-                parameters.timeout,
-                parameters.gc_depth,
-                /* rx_primary */ rx_new_certificates,
-                tx_commit,
-                tx_metadata,
-                tx_output,
-            
-                global_coin_recon_req_sender,
-                global_coin_res_receiver
-            );
-            
-            Primary::spawn(
+
+            let (beacon_recon_req_sender, beacon_res_receiver, tx_proof_query) = spawn_primary(
                 keypair,
                 committee,
                 parameters.clone(),
                 store,
-                /* tx_output */ tx_new_certificates,
-                rx_commit,
-                rx_metadata,
-            
-                cer_to_consensus_receiver,
+                transport.clone(),
+                rand::random(),
+                crs,
+                coin_key,
+                tx_output,
+                cer_to_coord_receiver,
+            )
+            .await?;
+            BeaconHub::spawn(
+                transport,
+                beacon_api_address,
+                beacon_recon_req_sender,
+                beacon_res_receiver,
+                tx_proof_query,
+                parameters.beacon_req_delay,
+                parameters.eval_beacon,
             );
-            if parameters.eval_beacon{
-                BeaconHub::spawn(
-                    beacon_recon_req_sender,
-                    beacon_res_receiver,
-                    parameters.beacon_req_delay
-                );
-            }
         }
 
         // Spawn a single worker.
@@ -267,7 +231,7 @@ async fn run(matches: &ArgMatches<'_>) -> Result<()> {
                 .unwrap()
                 .parse::<WorkerId>()
                 .context("The worker id must be a positive integer")?;
-            Worker::spawn(keypair.name, id, committee, parameters, store);
+            Worker::spawn(keypair.name, id, committee, parameters, store, transport);
         }
         _ => unreachable!(),
     }
@@ -279,11 +243,260 @@ async fn run(matches: &ArgMatches<'_>) -> Result<()> {
     unreachable!();
 }
 
+// Sends a reconfiguration to our primary, which proposes it in its next headers.
+async fn reconfigure(matches: &ArgMatches<'_>) -> Result<()> {
+    let reconfiguration = Reconfiguration::import(matches.value_of("reconfiguration").unwrap())
+        .context("Failed to load the reconfiguration")?;
+    send_command(matches, AdminMessage::Reconfigure(reconfiguration)).await
+}
+
+// Asks our primary to propose a fresh signing key in its next headers.
+async fn rotate_key(matches: &ArgMatches<'_>) -> Result<()> {
+    let epoch = matches
+        .value_of("epoch")
+        .unwrap()
+        .parse::<Epoch>()
+        .context("The epoch must be a positive integer")?;
+    send_command(matches, AdminMessage::RotateKey(epoch)).await
+}
+
+// Fetches the evidence gathered by our primary and prints it.
+async fn evidence(matches: &ArgMatches<'_>) -> Result<()> {
+    let keypair = KeyPair::import(matches.value_of("keys").unwrap())
+        .context("Failed to load the node's keypair")?;
+    let committee = Committee::import(matches.value_of("committee").unwrap())
+        .context("Failed to load the committee information")?;
+
+    let address = committee
+        .evidence_api_address(&keypair.name)?
+        .context("Our primary has no evidence service")?;
+    let bytes = bincode::serialize(&EvidenceRequest::All).expect("Failed to serialize our request");
+    let mut transport = send_to(address, bytes).await?;
+    let reply = transport
+        .next()
+        .await
+        .context("Our primary closed the connection")?
+        .context("Failed to receive the evidence")?;
+    match bincode::deserialize(&reply).context("Failed to deserialize the evidence")? {
+        EvidenceResponse::Evidence(evidence) => {
+            for evidence in evidence {
+                println!("{}", evidence);
+            }
+        }
+        EvidenceResponse::Error(e) => anyhow::bail!("Our primary failed to answer: {}", e),
+    }
+    Ok(())
+}
+
+// Sends an operator command to our primary, which only takes them on the loopback interface.
+async fn send_command(matches: &ArgMatches<'_>, message: AdminMessage) -> Result<()> {
+    let keypair = KeyPair::import(matches.value_of("keys").unwrap())
+        .context("Failed to load the node's keypair")?;
+    let committee = Committee::import(matches.value_of("committee").unwrap())
+        .context("Failed to load the committee information")?;
+
+    let mut address = committee
+        .admin_api_address(&keypair.name)?
+        .context("Our primary takes no operator commands")?;
+    address.set_ip("127.0.0.1".parse()?);
+    let bytes = bincode::serialize(&message).expect("Failed to serialize our command");
+    send_to(address, bytes).await?;
+    Ok(())
+}
+
+// Sends a message to one of our primary's receivers, which expect it behind the flag of an
+// uncompressed message. Returns the connection to read the reply from.
+async fn send_to(address: SocketAddr, bytes: Vec<u8>) -> Result<Framed<TcpStream, LengthDelimitedCodec>> {
+    let stream = TcpStream::connect(address)
+        .await
+        .context(format!("failed to connect to {}", address))?;
+    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
+    let mut message = vec![0x00];
+    message.extend(bytes);
+    transport
+        .send(Bytes::from(message))
+        .await
+        .context(format!("Failed to send our message to {}", address))?;
+    Ok(transport)
+}
+
+/// Spawns a primary along with its breeze instance, secondary BFT, coordinator and consensus. It
+/// returns once the common core of epoch 0 is decided, with the channels serving the beacon.
+#[allow(clippy::too_many_arguments)]
+async fn spawn_primary(
+    keypair: KeyPair,
+    committee: SharedCommittee,
+    parameters: Parameters,
+    store: Store,
+    transport: Arc<dyn Transport>,
+    dealing_seed: [u8; 32],
+    crs: CommonReferenceString,
+    coin_key: CoinKey,
+    tx_output: Sender<Certificate>,
+    cer_to_coord_receiver: Receiver<Committed>,
+) -> Result<(Sender<(Epoch, Range<usize>)>, Receiver<BeaconResult>, Sender<ProofQuery>)> {
+    // The threshold coin is dealt by count: any f+1 of its holders toss it, whatever their stake.
+    anyhow::ensure!(
+        committee.at(0).weighting == Weighting::Count,
+        "The threshold coin of the secondary BFT cannot serve a stake-weighted committee"
+    );
+
+    let (breeze_share_cmd_sender, breeze_share_cmd_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (breeze_certificate_sender, breeze_certificate_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (cer_to_consensus_sender, cer_to_consensus_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (cer_to_init_consensus_sender, cer_to_init_consensus_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (init_cc_to_coord_sender, init_cc_to_coord_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (global_coin_recon_req_sender, global_coin_recon_req_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (beacon_recon_req_sender, beacon_recon_req_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (breeze_reconstruct_cmd_sender, breeze_reconstruct_cmd_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (breeze_result_sender, breeze_result_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (global_coin_res_sender, global_coin_res_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (beacon_res_sender, beacon_res_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (tx_evidence, rx_evidence) =
+        channel(CHANNEL_CAPACITY);
+    let (tx_complaint, rx_complaints) =
+        channel(CHANNEL_CAPACITY);
+    let (complaint_query_sender, complaint_query_receiver) =
+        channel(CHANNEL_CAPACITY);
+    let (tx_proof_query, rx_proof_query) =
+        channel(CHANNEL_CAPACITY);
+
+    // Our own addresses do not change across committees.
+    let (_, latest) = committee.latest();
+    let mut address = latest.breeze_address(&keypair.name)?;
+    address.set_ip("0.0.0.0".parse()?);
+    let mut bft_address = latest.init_bft_address(&keypair.name)?;
+    bft_address.set_ip("0.0.0.0".parse()?);
+    let mut evidence_address = latest.evidence_api_address(&keypair.name)?;
+    if let Some(address) = evidence_address.as_mut() {
+        address.set_ip("0.0.0.0".parse()?);
+    }
+
+    EvidenceCollector::spawn(committee.clone(), store.clone(), transport.clone(), evidence_address, rx_evidence);
+
+    // Sign with the keys committed before a restart, and verify the ones of the others.
+    let signature_service = KeyStore::new(store.clone()).restore(&keypair, &committee).await;
+
+    Breeze::spawn(
+        keypair.name,
+        signature_service.clone(),
+        transport.clone(),
+        address,
+        committee.clone(),
+        breeze_share_cmd_receiver,
+        breeze_certificate_sender,
+        breeze_reconstruct_cmd_receiver,
+        breeze_result_sender,
+        rx_proof_query,
+        tx_evidence.clone(),
+        tx_complaint,
+        complaint_query_receiver,
+        crs,
+        parameters.beacon_output.clone(),
+        parameters.robust_reconstruction,
+        store.clone(),
+        dealing_seed,
+    ).await;
+
+    // The secondary BFT only decides the common core of epoch 0.
+    InitBFT::spawn(
+        keypair.clone(),
+        transport.clone(),
+        bft_address,
+        (*committee.at(0)).clone(),
+        cer_to_init_consensus_receiver,
+        init_cc_to_coord_sender,
+        tx_evidence.clone(),
+        coin_key,
+    ).await;
+
+    let (recover_signal_sender, mut recover_signal_receiver) = watch::channel(());
+    Coordinator::spawn(
+        committee.clone(),
+        breeze_share_cmd_sender,
+        breeze_certificate_receiver,
+        cer_to_consensus_sender,
+        cer_to_init_consensus_sender,
+        cer_to_coord_receiver,
+        init_cc_to_coord_receiver,
+        complaint_query_sender,
+        global_coin_recon_req_receiver,
+        beacon_recon_req_receiver,
+        breeze_reconstruct_cmd_sender,
+        breeze_result_receiver,
+        global_coin_res_sender,
+        beacon_res_sender,
+        recover_signal_sender,
+        parameters.eval_beacon,
+        parameters.protocol,
+        parameters.coin_lookahead,
+        store.clone(),
+    ).await;
+
+    recover_signal_receiver.changed().await?;
+
+    let (tx_new_certificates, rx_new_certificates) = channel(CHANNEL_CAPACITY);
+    let (tx_commit, rx_commit) = channel(CHANNEL_CAPACITY);
+    let (tx_metadata, rx_metadata) = channel(CHANNEL_CAPACITY);
+    let global_coin = GlobalCoin::spawn(
+        global_coin_recon_req_sender,
+        global_coin_res_receiver,
+        parameters.coin_timeout,
+    );
+    Consensus::spawn(
+        committee.clone(),
+        &parameters,
+        /* rx_primary */ rx_new_certificates,
+        tx_commit,
+        tx_metadata,
+        tx_output,
+
+        global_coin,
+        store.clone(),
+    );
+    
+    Primary::spawn(
+        keypair.name,
+        signature_service,
+        transport,
+        committee,
+        parameters,
+        store,
+        /* tx_output */ tx_new_certificates,
+        rx_commit,
+        rx_metadata,
+    
+        cer_to_consensus_receiver,
+        rx_complaints,
+        tx_evidence,
+    );
+    Ok((beacon_recon_req_sender, beacon_res_receiver, tx_proof_query))
+}
+
 /// Receives an ordered list of certificates and apply any application-specific logic.
-async fn analyze(mut rx_output: Receiver<Certificate>, cer_to_coord_sender: Sender<BreezeCertificate>) {
+async fn analyze(mut rx_output: Receiver<Certificate>, cer_to_coord_sender: Sender<Committed>) {
     while let Some(certificate) = rx_output.recv().await {
-        if let Some(cer) = certificate.header.breeze_cer{
-            cer_to_coord_sender.send(cer).await.unwrap();
+        for committed in coordinator_input(certificate.header) {
+            cer_to_coord_sender.send(committed).await.unwrap();
         }
     }
 }
+
+/// The complaints and the breeze certificate a committed header brings to the coordinator, in the
+/// order the coordinator must apply them.
+fn coordinator_input(header: Header) -> Vec<Committed> {
+    let mut committed: Vec<_> = header.complaints.into_iter().map(Committed::Complaint).collect();
+    committed.extend(header.breeze_cer.map(Committed::Certificate));
+    committed
+}
@@ -0,0 +1,253 @@
+use super::*;
+use bavss::{verify_beacon, BeaconProof};
+use bytes::Bytes;
+use config::{Authority, PrimaryAddresses, Protocol, WorkerAddresses, Weighting};
+use crypto::{generate_keypair, Digest, Hash as _};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::Scalar;
+use network::{CancelHandler, Delivery, ReliableSender, SimulatedNetwork, SimulationParameters};
+use primary::WorkerPrimaryMessage;
+use rand::rngs::StdRng;
+use rand::SeedableRng as _;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+const NODES: usize = 4;
+const BEACONS: usize = 2;
+const COMMITS: usize = 10;
+
+/// What a node observed by the end of the run.
+#[derive(Debug, PartialEq)]
+struct Report {
+    node: usize,
+    beacons: Vec<RandomNum>,
+    /// Whether the node proved its first beacon.
+    proven: bool,
+    committed: Vec<Digest>,
+}
+
+// Fixture
+fn keys() -> Vec<KeyPair> {
+    let mut rng = StdRng::from_seed([0; 32]);
+    (0..NODES)
+        .map(|_| {
+            let (name, secret) = generate_keypair(&mut rng);
+            KeyPair { name, secret }
+        })
+        .collect()
+}
+
+// Fixture
+fn committee() -> Committee {
+    let address = |i: usize, port: usize| format!("127.0.0.1:{}", 9_000 + 10 * i + port).parse().unwrap();
+    Committee {
+        authorities: keys()
+            .iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                let primary = PrimaryAddresses {
+                    primary_to_primary: address(i, 0),
+                    worker_to_primary: address(i, 1),
+                    breeze_addr: address(i, 2),
+                    init_bft_addr: address(i, 3),
+                    beacon_api_addr: Some(address(i, 4)),
+                    evidence_api_addr: Some(address(i, 8)),
+                    admin_api_addr: None,
+                };
+                // Nobody listens on the worker addresses, the test plays the part of the workers.
+                let worker = WorkerAddresses {
+                    primary_to_worker: address(i, 5),
+                    transactions: address(i, 6),
+                    worker_to_worker: address(i, 7),
+                };
+                let authority = Authority {
+                    stake: 1,
+                    primary,
+                    workers: [(0, worker)].iter().cloned().collect(),
+                };
+                (keypair.name, authority)
+            })
+            .collect(),
+        weighting: Weighting::Count,
+    }
+}
+
+// Fixture
+fn crs(committee: &Committee) -> CommonReferenceString {
+    let t = committee.authorities_fault_tolerance();
+    CommonReferenceString {
+        g: (0..t + 1)
+            .map(|i| RISTRETTO_BASEPOINT_POINT * Scalar::from(i as u64 + 2))
+            .collect(),
+        h: RISTRETTO_BASEPOINT_POINT * Scalar::from(1u64),
+    }
+}
+
+// Fixture: the key of node `i` of the threshold coin of the secondary BFT.
+fn coin_key(committee: &Committee, i: usize) -> CoinKey {
+    let names: Vec<_> = keys().into_iter().map(|keypair| keypair.name).collect();
+    let mut coefficient = 0;
+    let threshold = committee.authorities_fault_tolerance() + 1;
+    CoinKey::deal(&names, threshold, || {
+        coefficient += 1;
+        Scalar::from(coefficient * 7919u64)
+    })
+    .remove(i)
+}
+
+// Plays the part of the worker of node `i`: it seals one batch, and has received the batch of
+// every other node. This lets the proposer make headers, and the others accept them.
+async fn announce_batches(sender: &mut ReliableSender, committee: &Committee, i: usize) -> Vec<CancelHandler> {
+    let name = keys()[i].name;
+    let address = committee.primary(&name).unwrap().worker_to_primary;
+    let mut handlers = Vec::new();
+    for j in 0..NODES {
+        let digest = Digest([j as u8; 32]);
+        let message = match i == j {
+            true => WorkerPrimaryMessage::OurBatch(digest, 0),
+            false => WorkerPrimaryMessage::OthersBatch(digest, 0),
+        };
+        let bytes = Bytes::from(bincode::serialize(&message).unwrap());
+        handlers.push(sender.send(address, bytes).await);
+    }
+    handlers
+}
+
+// Runs node `i` over its own endpoint of the network, with dealings drawn from `seed`.
+async fn run_node(i: usize, seed: u64, network: SimulatedNetwork, protocol: Protocol, tx_report: Sender<Report>) {
+    let committee = committee();
+    let mut dealing_seed = [i as u8; 32];
+    dealing_seed[..8].copy_from_slice(&seed.to_le_bytes());
+
+    let (tx_output, mut rx_output) = channel(CHANNEL_CAPACITY);
+    let (tx_cer, rx_cer) = channel(CHANNEL_CAPACITY);
+    let (tx_beacon, mut rx_beacon, tx_proof_query) = spawn_primary(
+        keys().remove(i),
+        committee.clone().into(),
+        Parameters {
+            protocol,
+            ..Parameters::default()
+        },
+        Store::in_memory(),
+        network.endpoint(i),
+        dealing_seed,
+        crs(&committee),
+        coin_key(&committee, i),
+        tx_output,
+        rx_cer,
+    )
+    .await
+    .unwrap();
+
+    let mut sender = ReliableSender::with_transport(network.endpoint(i));
+    let _handlers = announce_batches(&mut sender, &committee, i).await;
+
+    // Get the first beacons of epoch 0, all at once.
+    let mut beacons = HashMap::new();
+    tx_beacon.send((0, 1..BEACONS + 1)).await.unwrap();
+    while beacons.len() < BEACONS {
+        let ((_, index), result) = rx_beacon.recv().await.unwrap();
+        beacons.insert(index, result.unwrap());
+    }
+
+    // Anybody can check the first beacon out of its proof.
+    let (sender, receiver) = oneshot::channel();
+    let batch_index = *MAX_EPOCH.get().unwrap() as usize + 1;
+    tx_proof_query.send(((0, batch_index), sender)).await.unwrap();
+    let proof: BeaconProof = bincode::deserialize(&receiver.await.unwrap().unwrap()).unwrap();
+    let derivation = Parameters::default().beacon_output;
+    let proven = proof.value == beacons[&1]
+        && verify_beacon(&committee.clone().into(), &crs(&committee), &derivation, &proof);
+
+    // Wait for the first certificates to commit, then keep feeding the coordinator as the other
+    // nodes may still need us.
+    let mut committed = Vec::new();
+    while committed.len() < COMMITS {
+        let certificate = rx_output.recv().await.unwrap();
+        committed.push(certificate.digest());
+        for input in coordinator_input(certificate.header) {
+            tx_cer.send(input).await.unwrap();
+        }
+    }
+    let beacons = (1..=BEACONS).map(|index| beacons[&index].clone()).collect();
+    let report = Report {
+        node: i,
+        beacons,
+        proven,
+        committed,
+    };
+    tx_report.send(report).await.unwrap();
+    analyze(rx_output, tx_cer).await;
+}
+
+/// Runs the whole committee on a single thread, with a paused clock, over a lossy network seeded
+/// by `seed`. Returns the report of each node and the frames the network delivered until then.
+fn simulate(protocol: Protocol, seed: u64) -> (Vec<Report>, Vec<Delivery>) {
+    // The first commits happen around round 6: the epochs must be long enough for the
+    // certificates of epoch 1 to commit before the proposers need the ones of epoch 2.
+    let _ = MAX_EPOCH.set(4);
+    let _ = BEACON_PER_EPOCH.set(BEACONS as u64);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    // Dropping the runtime at the end stops the nodes.
+    runtime.block_on(async move {
+        let parameters = SimulationParameters {
+            min_delay: 1,
+            max_delay: 20,
+            drop_rate: 0.01,
+            reorder_rate: 0.05,
+        };
+        let network = SimulatedNetwork::new(seed, parameters);
+        let (tx_report, mut rx_report) = channel(NODES);
+        for i in 0..NODES {
+            tokio::spawn(run_node(i, seed, network.clone(), protocol, tx_report.clone()));
+        }
+        let mut reports = Vec::new();
+        while reports.len() < NODES {
+            let report = timeout(Duration::from_secs(600), rx_report.recv())
+                .await
+                .expect("A node got stuck")
+                .unwrap();
+            reports.push(report);
+        }
+        reports.sort_by_key(|report| report.node);
+        (reports, network.trace())
+    })
+}
+
+fn check(protocol: Protocol, seed: u64) {
+    let (reports, trace) = simulate(protocol, seed);
+
+    // Every node outputs the same beacons, and commits the same sequence of certificates.
+    for report in &reports {
+        assert!(report.proven);
+        assert_eq!(report.beacons, reports[0].beacons);
+        assert_eq!(report.committed, reports[0].committed);
+    }
+
+    // The same seed replays the same run, down to every frame on the network.
+    let (replayed_reports, replayed_trace) = simulate(protocol, seed);
+    assert_eq!(replayed_reports, reports);
+    assert_eq!(replayed_trace, trace);
+}
+
+#[test]
+fn beacon_and_consensus_over_lossy_network() {
+    check(Protocol::Tusk, 0);
+}
+
+#[test]
+fn dolphin_over_lossy_network() {
+    check(Protocol::Dolphin, 0);
+}
+
+#[test]
+fn bullshark_over_lossy_network() {
+    check(Protocol::Bullshark, 0);
+}
@@ -1,6 +1,7 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::{DagError, DagResult};
 use crate::messages::Certificate;
+use crate::primary::CERTIFICATES;
 use crypto::Digest;
 use futures::future::try_join_all;
 use futures::stream::futures_unordered::FuturesUnordered;
@@ -16,7 +17,7 @@ use model::types_and_const::Round;
 /// Waits to receive all the ancestors of a certificate before looping it back to the `Core`
 /// for further processing.
 pub struct CertificateWaiter {
-    /// The persistent storage.
+    /// The certificates column family of the store.
     store: Store,
     /// The current consensus round (used for cleanup).
     consensus_round: Arc<AtomicU64>,
@@ -43,7 +44,7 @@ impl CertificateWaiter {
     ) {
         tokio::spawn(async move {
             Self {
-                store,
+                store: store.family(CERTIFICATES),
                 consensus_round,
                 gc_depth,
                 rx_synchronizer,
@@ -68,6 +69,7 @@ impl CertificateWaiter {
             .map(|(x, y)| y.notify_read(x.to_vec()))
             .collect();
         tokio::select! {
+            biased;
             result = try_join_all(waiting) => {
                 result.map(|_| deliver).map_err(DagError::from)
             }
@@ -80,6 +82,7 @@ impl CertificateWaiter {
 
         loop {
             tokio::select! {
+                biased;
                 Some(certificate) = self.rx_synchronizer.recv() => {
                     let header_id = certificate.header.id.clone();
 
@@ -2,20 +2,22 @@
 use crate::aggregators::{CertificatesAggregator, VotesAggregator};
 use crate::error::{DagError, DagResult};
 use crate::messages::{Certificate, Header, Vote};
-use crate::primary::{PrimaryMessage};
+use crate::primary::{PrimaryMessage, CERTIFICATES, PAYLOAD};
 use crate::synchronizer::Synchronizer;
 use async_recursion::async_recursion;
 use bytes::Bytes;
-use config::Committee;
+use config::SharedCommittee;
 use crypto::Hash as _;
 use crypto::{Digest, PublicKey, SignatureService};
+use futures::stream::StreamExt as _;
 use log::{debug, error, warn};
 use network::{CancelHandler, ReliableSender};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use store::Store;
+use store::{Store, TypedStore};
 use tokio::sync::mpsc::{Receiver, Sender};
+use model::evidence::Evidence;
 use model::types_and_const::Round;
 
 #[cfg(test)]
@@ -25,10 +27,12 @@ pub mod core_tests;
 pub struct Core {
     /// The public key of this primary.
     name: PublicKey,
-    /// The committee information.
-    committee: Committee,
-    /// The persistent storage.
-    store: Store,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
+    /// The certificates column family of the store.
+    certificate_store: TypedStore<Digest, Certificate>,
+    /// The payload column family of the store.
+    payload_store: Store,
     /// Handles synchronization with other nodes and our workers.
     synchronizer: Synchronizer,
     /// Service to sign headers.
@@ -37,6 +41,10 @@ pub struct Core {
     consensus_round: Arc<AtomicU64>,
     /// The depth of the garbage collector.
     gc_depth: Round,
+    /// How many rounds to keep in store beyond the garbage collection depth (no pruning if unset).
+    store_retention: Option<Round>,
+    /// The window (in epochs) from which proposers may embed breeze certificates.
+    breeze_epoch_limit: u64,
 
     /// Receiver for dag messages (headers, votes, certificates).
     rx_primaries: Receiver<PrimaryMessage>,
@@ -50,13 +58,18 @@ pub struct Core {
     tx_consensus: Sender<Certificate>,
     /// Send valid a quorum of certificates' ids to the `Proposer` (along with their round).
     tx_proposer: Sender<(Vec<Digest>, Round)>,
+    /// Reports the authors of equivocating headers.
+    tx_evidence: Sender<Evidence>,
 
     /// The last garbage collected round.
     gc_round: Round,
-    /// The authors of the last voted headers.
-    last_voted: HashMap<Round, HashSet<PublicKey>>,
+    /// The last voted headers, by author.
+    last_voted: HashMap<Round, HashMap<PublicKey, Header>>,
     /// The set of headers we are currently processing.
     processing: HashMap<Round, HashSet<Digest>>,
+    /// The certificates we stored (with the keys of their payload), by round, until we prune them.
+    /// Headers are only stored within their certificates, so they are pruned along with them.
+    stored: BTreeMap<Round, Vec<(Digest, Vec<Vec<u8>>)>>,
     /// The last header we proposed (for which we are waiting votes).
     current_header: Header,
     /// Aggregates votes into a certificate.
@@ -74,42 +87,50 @@ impl Core {
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
-        committee: Committee,
+        committee: SharedCommittee,
         store: Store,
         synchronizer: Synchronizer,
         signature_service: SignatureService,
         consensus_round: Arc<AtomicU64>,
         gc_depth: Round,
+        store_retention: Option<Round>,
+        breeze_epoch_limit: u64,
         rx_primaries: Receiver<PrimaryMessage>,
         rx_header_waiter: Receiver<Header>,
         rx_certificate_waiter: Receiver<Certificate>,
         rx_proposer: Receiver<Header>,
         tx_consensus: Sender<Certificate>,
         tx_proposer: Sender<(Vec<Digest>, Round)>,
-
+        tx_evidence: Sender<Evidence>,
+        network: ReliableSender,
     ) {
         tokio::spawn(async move {
             Self {
                 name,
                 committee,
-                store,
+                certificate_store: TypedStore::new(&store, CERTIFICATES),
+                payload_store: store.family(PAYLOAD),
                 synchronizer,
                 signature_service,
                 consensus_round,
                 gc_depth,
+                store_retention,
+                breeze_epoch_limit,
                 rx_primaries,
                 rx_header_waiter,
                 rx_certificate_waiter,
                 rx_proposer,
                 tx_consensus,
                 tx_proposer,
+                tx_evidence,
                 gc_round: 0,
                 last_voted: HashMap::with_capacity(2 * gc_depth as usize),
                 processing: HashMap::with_capacity(2 * gc_depth as usize),
+                stored: BTreeMap::new(),
                 current_header: Header::default(),
                 votes_aggregator: VotesAggregator::new(),
                 certificates_aggregators: HashMap::with_capacity(2 * gc_depth as usize),
-                network: ReliableSender::new(),
+                network,
                 cancel_handlers: HashMap::with_capacity(2 * gc_depth as usize),
             }
             .run()
@@ -125,6 +146,7 @@ impl Core {
         // Broadcast the new header in a reliable manner.
         let addresses = self
             .committee
+            .at_round(header.round)
             .others_primaries(&self.name)
             .iter()
             .map(|(_, x)| x.primary_to_primary)
@@ -169,16 +191,17 @@ impl Core {
         }
 
         // Check the parent certificates. Ensure the parents form a quorum and are all from the previous round.
+        let committee = self.committee.at_round(header.round.saturating_sub(1));
         let mut stake = 0;
         for x in parents {
             ensure!(
                 x.round() + 1 == header.round,
                 DagError::MalformedHeader(header.id.clone())
             );
-            stake += self.committee.stake(&x.origin());
+            stake += committee.stake(&x.origin());
         }
         ensure!(
-            stake >= self.committee.quorum_threshold(),
+            stake >= committee.quorum_threshold(),
             DagError::HeaderRequiresQuorum(header.id.clone())
         );
 
@@ -203,13 +226,19 @@ impl Core {
             return Ok(());
         }
 
-        // Check if we can vote for this header.
-        if self
-            .last_voted
-            .entry(header.round)
-            .or_insert_with(HashSet::new)
-            .insert(header.author)
-        {
+        // Check if we can vote for this header. Another header of the same author and round is
+        // proof that the author equivocated.
+        let voted = self.last_voted.entry(header.round).or_insert_with(HashMap::new);
+        if let Some(previous) = voted.get(&header.author) {
+            if previous.id != header.id {
+                let evidence = previous.equivocation(header);
+                warn!("{} equivocated at round {}", header.author, header.round);
+                if let Err(e) = self.tx_evidence.send(evidence).await {
+                    warn!("Failed to deliver evidence to the collector: {}", e);
+                }
+            }
+        } else {
+            voted.insert(header.author, header.clone());
             // Make a vote and send it to the header's creator.
             let vote = Vote::new(header, &self.name, &mut self.signature_service).await;
             debug!("Created {:?}", vote);
@@ -220,6 +249,7 @@ impl Core {
             } else {
                 let address = self
                     .committee
+                    .at_round(header.round)
                     .primary(&header.author)
                     .expect("Author of valid header is not in the committee")
                     .primary_to_primary;
@@ -240,15 +270,15 @@ impl Core {
         debug!("Processing {:?}", vote);
 
         // Add it to the votes' aggregator and try to make a new certificate.
+        let committee = self.committee.at_round(vote.round);
         if let Some(certificate) =
             self.votes_aggregator
-                .append(vote, &self.committee, &self.current_header)?
+                .append(vote, &committee, &self.current_header)?
         {
             debug!("Assembled {:?}", certificate);
 
             // Broadcast the certificate.
-            let addresses = self
-                .committee
+            let addresses = committee
                 .others_primaries(&self.name)
                 .iter()
                 .map(|(_, x)| x.primary_to_primary)
@@ -298,15 +328,22 @@ impl Core {
         }
 
         // Store the certificate.
-        let bytes = bincode::serialize(&certificate).expect("Failed to serialize certificate");
-        self.store.write(certificate.digest().to_vec(), bytes).await;
+        self.certificate_store
+            .write(&certificate.digest(), &certificate)
+            .await;
+        if self.store_retention.is_some() {
+            self.stored
+                .entry(certificate.round())
+                .or_insert_with(Vec::new)
+                .push((certificate.digest(), Self::payload_keys(&certificate)));
+        }
 
         // Check if we have enough certificates to enter a new dag round and propose a header.
         if let Some(parents) = self
             .certificates_aggregators
             .entry(certificate.round())
             .or_insert_with(|| Box::new(CertificatesAggregator::new()))
-            .append(certificate.clone(), &self.committee)
+            .append(certificate.clone(), &self.committee.at_round(certificate.round()))
         {
             // Send it to the `Proposer`.
             self.tx_proposer
@@ -333,7 +370,7 @@ impl Core {
         //);
 
         // Verify the header's signature.
-        header.verify(&self.committee)?;
+        header.verify(&self.committee, self.breeze_epoch_limit)?;
 
         // TODO [issue #3]: Prevent bad nodes from sending junk headers with high round numbers.
 
@@ -367,13 +404,56 @@ impl Core {
         //);
 
         // Verify the certificate (and the embedded header).
-        certificate.verify(&self.committee).map_err(DagError::from)
+        certificate
+            .verify(&self.committee, self.breeze_epoch_limit)
+            .map_err(DagError::from)
     }
 
     // Main loop listening to incoming messages.
+    /// Returns the keys of the payload of `certificate` in the payload column family.
+    fn payload_keys(certificate: &Certificate) -> Vec<Vec<u8>> {
+        certificate
+            .header
+            .payload
+            .iter()
+            .map(|(digest, worker_id)| [digest.as_ref(), &worker_id.to_le_bytes()].concat())
+            .collect()
+    }
+
+    /// Rebuilds the index of the certificates to prune from the ones in store, after a restart.
+    async fn restore_stored(&mut self) {
+        let certificates: Vec<_> = self.certificate_store.iter().collect().await;
+        for (digest, certificate) in certificates {
+            self.stored
+                .entry(certificate.round())
+                .or_insert_with(Vec::new)
+                .push((digest, Self::payload_keys(&certificate)));
+        }
+    }
+
     pub async fn run(&mut self) {
+        if self.store_retention.is_some() {
+            self.restore_stored().await;
+        }
+
         loop {
             let result = tokio::select! {
+                // Our own inputs go first: a peer flooding us with messages cannot hold them back, and
+                // the order does not depend on the runtime's random draws.
+                biased;
+
+                // We receive here loopback headers from the `HeaderWaiter`. Those are headers for which we interrupted
+                // execution (we were missing some of their dependencies) and we are now ready to resume processing.
+                Some(header) = self.rx_header_waiter.recv() => self.process_header(&header).await,
+
+                // We receive here loopback certificates from the `CertificateWaiter`. Those are certificates for which
+                // we interrupted execution (we were missing some of their ancestors) and we are now ready to resume
+                // processing.
+                Some(certificate) = self.rx_certificate_waiter.recv() => self.process_certificate(certificate).await,
+
+                // We also receive here our new headers created by the `Proposer`.
+                Some(header) = self.rx_proposer.recv() => self.process_own_header(header).await,
+
                 // We receive here messages from other primaries.
                 Some(message) = self.rx_primaries.recv() => {
                     match message {
@@ -399,18 +479,6 @@ impl Core {
                         _ => panic!("Unexpected core message")
                     }
                 },
-
-                // We receive here loopback headers from the `HeaderWaiter`. Those are headers for which we interrupted
-                // execution (we were missing some of their dependencies) and we are now ready to resume processing.
-                Some(header) = self.rx_header_waiter.recv() => self.process_header(&header).await,
-
-                // We receive here loopback certificates from the `CertificateWaiter`. Those are certificates for which
-                // we interrupted execution (we were missing some of their ancestors) and we are now ready to resume
-                // processing.
-                Some(certificate) = self.rx_certificate_waiter.recv() => self.process_certificate(certificate).await,
-
-                // We also receive here our new headers created by the `Proposer`.
-                Some(header) = self.rx_proposer.recv() => self.process_own_header(header).await,
             };
             match result {
                 Ok(()) | Err(DagError::VoteTooOld(..)) => (),
@@ -431,6 +499,20 @@ impl Core {
                 self.certificates_aggregators.retain(|k, _| k > &gc_round);
                 self.cancel_handlers.retain(|k, _| k > &gc_round);
                 self.gc_round = gc_round;
+
+                // Prune the store of the rounds past the retention window.
+                if let Some(retention) = self.store_retention.filter(|x| gc_round > *x) {
+                    let kept = self.stored.split_off(&(gc_round - retention));
+                    let pruned = std::mem::replace(&mut self.stored, kept);
+                    if !pruned.is_empty() {
+                        let (certificates, payload): (Vec<_>, Vec<_>) =
+                            pruned.into_values().flatten().unzip();
+                        self.certificate_store.delete_batch(&certificates).await;
+                        self.payload_store
+                            .delete_batch(payload.into_iter().flatten().collect())
+                            .await;
+                    }
+                }
             }
         }
     }
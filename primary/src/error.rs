@@ -2,7 +2,7 @@
 use crypto::{CryptoError, Digest, PublicKey};
 use store::StoreError;
 use thiserror::Error;
-use model::types_and_const::Round;
+use model::types_and_const::{Epoch, Round};
 
 #[macro_export]
 macro_rules! bail {
@@ -59,4 +59,22 @@ pub enum DagError {
 
     #[error("Vote {0} (round {1}) too old")]
     VoteTooOld(Digest, Round),
+
+    #[error("Breeze certificate of header {0} is not signed by a quorum")]
+    BreezeCertificateRequiresQuorum(Digest),
+
+    #[error("Breeze certificate of header {0} has an invalid signature from {1}")]
+    InvalidBreezeCertificateSignature(Digest, PublicKey),
+
+    #[error("Breeze certificate of header {0} is signed by unknown authority {1}")]
+    BreezeCertificateUnknownAuthority(Digest, PublicKey),
+
+    #[error("Breeze certificate of header {0} is signed more than once by {1}")]
+    BreezeCertificateAuthorityReuse(Digest, PublicKey),
+
+    #[error("Breeze certificate for epoch {0} cannot be proposed at round {1}")]
+    BreezeCertificateOutOfWindow(Epoch, Round),
+
+    #[error("Invalid rotation of the signing key of {0} for epoch {1}")]
+    InvalidKeyRotation(PublicKey, Epoch),
 }
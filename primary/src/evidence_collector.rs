@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use config::SharedCommittee;
+use futures::sink::SinkExt as _;
+use log::{info, warn};
+use model::evidence::{Evidence, EvidenceRequest, EvidenceResponse};
+use model::types_and_const::CHANNEL_CAPACITY;
+use network::{MessageHandler, Receiver as NetworkReceiver, Transport, Writer};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use store::Store;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::oneshot;
+
+#[cfg(test)]
+#[path = "tests/evidence_collector_tests.rs"]
+pub mod evidence_collector_tests;
+
+const EVIDENCE_KEY: &str = "primary/evidence";
+
+/// Gathers the evidence of misbehaviour found by the other components (the core, breeze and the
+/// secondary BFT), persists it, and serves it to operators over the network. Evidence that does
+/// not check against the signing key of its culprit is dropped, whoever relayed it could have
+/// forged it.
+pub struct EvidenceCollector {
+    committee: SharedCommittee,
+    store: Store,
+    rx_evidence: Receiver<Evidence>,
+    rx_query: Receiver<(EvidenceRequest, oneshot::Sender<EvidenceResponse>)>,
+    /// The evidence gathered so far, including before a restart.
+    evidence: Vec<Evidence>,
+}
+
+impl EvidenceCollector {
+    pub fn spawn(
+        committee: SharedCommittee,
+        store: Store,
+        transport: Arc<dyn Transport>,
+        address: Option<SocketAddr>,
+        rx_evidence: Receiver<Evidence>,
+    ) {
+        let (tx_query, rx_query) = channel(CHANNEL_CAPACITY);
+        match address {
+            Some(address) => {
+                NetworkReceiver::spawn_with_transport(transport, address, EvidenceReceiverHandler { tx_query });
+                info!("Evidence service listening on {}", address);
+            }
+            None => info!("No evidence service address, the evidence is only recorded"),
+        }
+
+        tokio::spawn(async move {
+            let mut collector = Self {
+                committee,
+                store,
+                rx_evidence,
+                rx_query,
+                evidence: Vec::new(),
+            };
+            collector.evidence = collector.load().await;
+            collector.run().await;
+        });
+    }
+
+    async fn load(&mut self) -> Vec<Evidence> {
+        self.store
+            .read(EVIDENCE_KEY.as_bytes().to_vec())
+            .await
+            .expect("Failed to read evidence from store")
+            .map(|bytes| bincode::deserialize(&bytes).expect("Failed to deserialize evidence"))
+            .unwrap_or_default()
+    }
+
+    async fn run(&mut self) {
+        loop {
+            tokio::select! {
+                biased;
+                Some(evidence) = self.rx_evidence.recv() => self.record(evidence).await,
+                Some((request, reply)) = self.rx_query.recv() => {
+                    let _ = reply.send(self.answer(request));
+                },
+                else => break,
+            }
+        }
+    }
+
+    async fn record(&mut self, evidence: Evidence) {
+        let signing_key = self.committee.signing_key(&evidence.culprit, evidence.epoch);
+        if !evidence.verify(&signing_key) {
+            warn!("Dropping unverifiable evidence: {}", evidence);
+            return;
+        }
+        if self.evidence.contains(&evidence) {
+            return;
+        }
+        warn!("Recorded evidence: {}", evidence);
+        self.evidence.push(evidence);
+        let bytes = bincode::serialize(&self.evidence).expect("Failed to serialize evidence");
+        self.store.write(EVIDENCE_KEY.as_bytes().to_vec(), bytes).await;
+    }
+
+    fn answer(&self, request: EvidenceRequest) -> EvidenceResponse {
+        let evidence = match request {
+            EvidenceRequest::All => self.evidence.clone(),
+            EvidenceRequest::Against(culprit) => self
+                .evidence
+                .iter()
+                .filter(|evidence| evidence.culprit == culprit)
+                .cloned()
+                .collect(),
+        };
+        EvidenceResponse::Evidence(evidence)
+    }
+}
+
+/// Defines how the network receiver handles incoming evidence requests.
+#[derive(Clone)]
+struct EvidenceReceiverHandler {
+    tx_query: Sender<(EvidenceRequest, oneshot::Sender<EvidenceResponse>)>,
+}
+
+#[async_trait]
+impl MessageHandler for EvidenceReceiverHandler {
+    async fn dispatch(&self, writer: &mut Writer, serialized: Bytes) -> Result<(), Box<dyn Error>> {
+        let request = bincode::deserialize(&serialized)?;
+        let (sender, receiver) = oneshot::channel();
+        self.tx_query
+            .send((request, sender))
+            .await
+            .expect("Failed to send evidence query");
+        let response = receiver
+            .await
+            .unwrap_or_else(|_| EvidenceResponse::Error("Evidence collector dropped the query".to_string()));
+        let bytes = bincode::serialize(&response)?;
+        writer.send(Bytes::from(bytes)).await?;
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::key_store::KeyStore;
 use crate::messages::Certificate;
 use crate::primary::PrimaryWorkerMessage;
+use crate::reconfiguration_store::ReconfigurationStore;
+use crate::reconfigurer::Reconfigurer;
 use bytes::Bytes;
-use config::Committee;
-use crypto::PublicKey;
+use config::{Reconfiguration, SharedCommittee};
+use crypto::{PublicKey, SignatureService};
+use log::warn;
+use model::types_and_const::round_epoch;
 use network::SimpleSender;
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
@@ -12,6 +18,8 @@ use tokio::sync::mpsc::Receiver;
 
 /// Receives the highest round reached by consensus and update it for all tasks.
 pub struct GarbageCollector {
+    /// The public key of this primary.
+    name: PublicKey,
     /// The current consensus round (used for cleanup).
     consensus_round: Arc<AtomicU64>,
     /// Receives the ordered certificates from consensus.
@@ -20,28 +28,57 @@ pub struct GarbageCollector {
     addresses: Vec<SocketAddr>,
     /// A network sender to notify our workers of cleanup events.
     network: SimpleSender,
+    /// Installs the committees proposed in the committed headers.
+    reconfigurer: Reconfigurer,
+    /// The installed committees our workers do not know yet. They learn each one when the
+    /// consensus reaches its first epoch.
+    pending_reconfigurations: VecDeque<Reconfiguration>,
+    /// Service signing our messages, handed our new keys once committed.
+    signature_service: SignatureService,
+    /// Persists the committed rotations and holds the secrets of the keys we proposed.
+    key_store: KeyStore,
+    /// Persists the installed committees and the pending proposals.
+    reconfiguration_store: ReconfigurationStore,
 }
 
 impl GarbageCollector {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: &PublicKey,
-        committee: &Committee,
+        committee: SharedCommittee,
+        signature_service: SignatureService,
+        key_store: KeyStore,
+        mut reconfiguration_store: ReconfigurationStore,
+        breeze_epoch_limit: u64,
         consensus_round: Arc<AtomicU64>,
         rx_consensus: Receiver<Certificate>,
+        network: SimpleSender,
     ) {
         let addresses = committee
+            .latest()
+            .1
             .our_workers(name)
             .expect("Our public key or worker id is not in the committee")
             .iter()
             .map(|x| x.primary_to_worker)
             .collect();
 
+        let name = *name;
         tokio::spawn(async move {
+            // Reinstall the committees committed before a restart. Our workers learn them again as
+            // the consensus reaches their epochs.
+            let (installed, proposals) = reconfiguration_store.restore(&committee).await;
             Self {
+                name,
                 consensus_round,
                 rx_consensus,
                 addresses,
-                network: SimpleSender::new(),
+                network,
+                reconfigurer: Reconfigurer::new(committee, breeze_epoch_limit, proposals),
+                pending_reconfigurations: installed.into(),
+                signature_service,
+                key_store,
+                reconfiguration_store,
             }
             .run()
             .await;
@@ -53,6 +90,27 @@ impl GarbageCollector {
         while let Some(certificate) = self.rx_consensus.recv().await {
             // TODO [issue #9]: Re-include batch digests that have not been sequenced into our next block.
 
+            if certificate.header.reconfiguration.is_some() {
+                if let Some(reconfiguration) = self.reconfigurer.process(&certificate) {
+                    self.reconfiguration_store
+                        .persist_reconfiguration(&reconfiguration)
+                        .await;
+                    self.pending_reconfigurations.push_back(reconfiguration);
+                }
+                self.reconfiguration_store
+                    .persist_proposals(self.reconfigurer.proposals())
+                    .await;
+            }
+            if let Some(rotation) = self.reconfigurer.process_key_rotation(&certificate) {
+                self.key_store.persist_rotation(&rotation).await;
+                if rotation.name == self.name {
+                    match self.key_store.load_secret(rotation.epoch).await {
+                        Some(secret) => self.signature_service.rotate(rotation.epoch, secret).await,
+                        None => warn!("Missing our signing key for epoch {}", rotation.epoch),
+                    }
+                }
+            }
+
             let round = certificate.round();
             if round > last_committed_round {
                 last_committed_round = round;
@@ -66,6 +124,19 @@ impl GarbageCollector {
                 self.network
                     .broadcast(self.addresses.clone(), Bytes::from(bytes))
                     .await;
+
+                // Hand our workers the committees taking over at this epoch.
+                while matches!(
+                    self.pending_reconfigurations.front(),
+                    Some(x) if x.epoch <= round_epoch(round)
+                ) {
+                    let reconfiguration = self.pending_reconfigurations.pop_front().unwrap();
+                    let bytes = bincode::serialize(&PrimaryWorkerMessage::Reconfigure(reconfiguration))
+                        .expect("Failed to serialize our own message");
+                    self.network
+                        .broadcast(self.addresses.clone(), Bytes::from(bytes))
+                        .await;
+                }
             }
         }
     }
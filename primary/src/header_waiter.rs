@@ -1,19 +1,18 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::{DagError, DagResult};
 use crate::messages::Header;
-use crate::primary::{PrimaryMessage, PrimaryWorkerMessage};
+use crate::primary::{PrimaryMessage, PrimaryWorkerMessage, CERTIFICATES, PAYLOAD};
 use bytes::Bytes;
-use config::{Committee};
+use config::SharedCommittee;
 use crypto::{Digest, PublicKey};
 use futures::future::try_join_all;
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
 use log::{debug, error};
 use network::SimpleSender;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::time::{sleep, Duration, Instant};
@@ -34,10 +33,12 @@ pub enum WaiterMessage {
 pub struct HeaderWaiter {
     /// The name of this authority.
     name: PublicKey,
-    /// The committee information.
-    committee: Committee,
-    /// The persistent storage.
-    store: Store,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
+    /// The certificates column family of the store.
+    certificate_store: Store,
+    /// The payload column family of the store.
+    payload_store: Store,
     /// The current consensus round (used for cleanup).
     consensus_round: Arc<AtomicU64>,
     /// The depth of the garbage collector.
@@ -55,8 +56,8 @@ pub struct HeaderWaiter {
     /// Network driver allowing to send messages.
     network: SimpleSender,
     /// Keeps the digests of the all certificates for which we sent a sync request,
-    /// along with a timestamp (`u128`) indicating when we sent the request.
-    parent_requests: HashMap<Digest, (Round, u128)>,
+    /// along with the time at which we sent the request.
+    parent_requests: HashMap<Digest, (Round, Instant)>,
     /// Keeps the digests of the all tx batches for which we sent a sync request,
     /// similarly to `header_requests`.
     batch_requests: HashMap<Digest, Round>,
@@ -69,7 +70,7 @@ impl HeaderWaiter {
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
-        committee: Committee,
+        committee: SharedCommittee,
         store: Store,
         consensus_round: Arc<AtomicU64>,
         gc_depth: Round,
@@ -77,19 +78,21 @@ impl HeaderWaiter {
         sync_retry_nodes: usize,
         rx_synchronizer: Receiver<WaiterMessage>,
         tx_core: Sender<Header>,
+        network: SimpleSender,
     ) {
         tokio::spawn(async move {
             Self {
                 name,
                 committee,
-                store,
+                certificate_store: store.family(CERTIFICATES),
+                payload_store: store.family(PAYLOAD),
                 consensus_round,
                 gc_depth,
                 sync_retry_delay,
                 sync_retry_nodes,
                 rx_synchronizer,
                 tx_core,
-                network: SimpleSender::new(),
+                network,
                 parent_requests: HashMap::new(),
                 batch_requests: HashMap::new(),
                 pending: HashMap::new(),
@@ -111,6 +114,7 @@ impl HeaderWaiter {
             .map(|(x, y)| y.notify_read(x.to_vec()))
             .collect();
         tokio::select! {
+            biased;
             result = try_join_all(waiting) => {
                 result.map(|_| Some(deliver)).map_err(DagError::from)
             }
@@ -127,6 +131,7 @@ impl HeaderWaiter {
 
         loop {
             tokio::select! {
+                biased;
                 Some(message) = self.rx_synchronizer.recv() => {
                     match message {
                         WaiterMessage::SyncBatches(missing, header) => {
@@ -146,7 +151,7 @@ impl HeaderWaiter {
                                 .iter()
                                 .map(|(digest, worker_id)| {
                                     let key = [digest.as_ref(), &worker_id.to_le_bytes()].concat();
-                                    (key.to_vec(), self.store.clone())
+                                    (key.to_vec(), self.payload_store.clone())
                                 })
                                 .collect();
                             let (tx_cancel, rx_cancel) = channel(1);
@@ -155,15 +160,17 @@ impl HeaderWaiter {
                             waiting.push(fut);
 
                             // Ensure we didn't already send a sync request for these parents.
-                            let mut requires_sync = HashMap::new();
+                            let mut requires_sync = BTreeMap::new();
                             for (digest, worker_id) in missing.into_iter() {
                                 self.batch_requests.entry(digest.clone()).or_insert_with(|| {
                                     requires_sync.entry(worker_id).or_insert_with(Vec::new).push(digest);
                                     round
                                 });
                             }
-                            for (worker_id, digests) in requires_sync {
+                            for (worker_id, mut digests) in requires_sync {
+                                digests.sort();
                                 let address = self.committee
+                                    .at_round(round)
                                     .worker(&author, &worker_id)
                                     .expect("Author of valid header is not in the committee")
                                     .primary_to_worker;
@@ -190,7 +197,7 @@ impl HeaderWaiter {
                             let wait_for = missing
                                 .iter()
                                 .cloned()
-                                .map(|x| (x.to_vec(), self.store.clone()))
+                                .map(|x| (x.to_vec(), self.certificate_store.clone()))
                                 .collect();
                             let (tx_cancel, rx_cancel) = channel(1);
                             self.pending.insert(header_id, (round, tx_cancel));
@@ -200,10 +207,7 @@ impl HeaderWaiter {
                             // Ensure we didn't already sent a sync request for these parents.
                             // Optimistically send the sync request to the node that created the certificate.
                             // If this fails (after a timeout), we broadcast the sync request.
-                            let now = SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .expect("Failed to measure time")
-                                .as_millis();
+                            let now = Instant::now();
                             let mut requires_sync = Vec::new();
                             for missing in missing {
                                 self.parent_requests.entry(missing.clone()).or_insert_with(|| {
@@ -213,6 +217,7 @@ impl HeaderWaiter {
                             }
                             if !requires_sync.is_empty() {
                                 let address = self.committee
+                                    .at_round(round)
                                     .primary(&author)
                                     .expect("Author of valid header not in the committee")
                                     .primary_to_primary;
@@ -249,17 +254,14 @@ impl HeaderWaiter {
                     // We optimistically sent sync requests to a single node. If this timer triggers,
                     // it means we were wrong to trust it. We are done waiting for a reply and we now
                     // broadcast the request to all nodes.
-                    let now = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .expect("Failed to measure time")
-                        .as_millis();
+                    let now = Instant::now();
 
                     let mut retry = Vec::new();
                     for (digest, (_, timestamp)) in &self.parent_requests {
-                        if timestamp + (self.sync_retry_delay as u128) < now {
+                        if *timestamp + Duration::from_millis(self.sync_retry_delay) < now {
                             /*
                             if self
-                                .store
+                                .certificate_store
                                 .read(digest.to_vec())
                                 .await
                                 .expect("Failed to read from store")
@@ -275,7 +277,10 @@ impl HeaderWaiter {
                         }
                     }
 
+                    retry.sort();
+
                     let addresses = self.committee
+                        .at_round(self.consensus_round.load(Ordering::Relaxed))
                         .others_primaries(&self.name)
                         .iter()
                         .map(|(_, x)| x.primary_to_primary)
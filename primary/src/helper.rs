@@ -1,7 +1,7 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
-use crate::primary::PrimaryMessage;
+use crate::primary::{PrimaryMessage, CERTIFICATES};
 use bytes::Bytes;
-use config::Committee;
+use config::SharedCommittee;
 use crypto::{Digest, PublicKey};
 use log::{error, warn};
 use network::SimpleSender;
@@ -10,9 +10,9 @@ use tokio::sync::mpsc::Receiver;
 
 /// A task dedicated to help other authorities by replying to their certificates requests.
 pub struct Helper {
-    /// The committee information.
-    committee: Committee,
-    /// The persistent storage.
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
+    /// The certificates column family of the store.
     store: Store,
     /// Input channel to receive certificates requests.
     rx_primaries: Receiver<(Vec<Digest>, PublicKey)>,
@@ -22,16 +22,17 @@ pub struct Helper {
 
 impl Helper {
     pub fn spawn(
-        committee: Committee,
+        committee: SharedCommittee,
         store: Store,
         rx_primaries: Receiver<(Vec<Digest>, PublicKey)>,
+        network: SimpleSender,
     ) {
         tokio::spawn(async move {
             Self {
                 committee,
-                store,
+                store: store.family(CERTIFICATES),
                 rx_primaries,
-                network: SimpleSender::new(),
+                network,
             }
             .run()
             .await;
@@ -42,8 +43,8 @@ impl Helper {
         while let Some((digests, origin)) = self.rx_primaries.recv().await {
             // TODO [issue #195]: Do some accounting to prevent bad nodes from monopolizing our resources.
 
-            // get the requestors address.
-            let address = match self.committee.primary(&origin) {
+            // get the requestors address (joining authorities sync from the latest committee).
+            let address = match self.committee.latest().1.primary(&origin) {
                 Ok(x) => x.primary_to_primary,
                 Err(e) => {
                     warn!("Unexpected certificate request: {}", e);
@@ -0,0 +1,79 @@
+use crate::messages::KeyRotation;
+use config::{KeyPair, SharedCommittee};
+use crypto::{PublicKey, SecretKey, SignatureService};
+use futures::stream::StreamExt as _;
+use log::warn;
+use model::types_and_const::Epoch;
+use std::collections::BTreeMap;
+use store::{Store, TypedStore};
+
+/// The column family of the primary's store holding the secrets of the signing keys we proposed,
+/// by epoch.
+const SIGNING_KEYS: &str = "signing_keys";
+/// The column family of the primary's store holding the committed key rotations, by authority and
+/// epoch.
+const KEY_ROTATIONS: &str = "key_rotations";
+
+/// Persists the signing keys of the authorities, so that a node restarts verifying (and signing)
+/// with the keys committed so far. Our secrets and the rotations live in column families of their
+/// own, one key per epoch.
+#[derive(Clone)]
+pub struct KeyStore {
+    secrets: TypedStore<Epoch, SecretKey>,
+    rotations: TypedStore<(PublicKey, Epoch), KeyRotation>,
+}
+
+impl KeyStore {
+    pub fn new(store: Store) -> Self {
+        Self {
+            secrets: TypedStore::new(&store, SIGNING_KEYS),
+            rotations: TypedStore::new(&store, KEY_ROTATIONS),
+        }
+    }
+
+    /// Persists the secret of a signing key we proposed for `epoch`, before it is committed.
+    pub async fn persist_secret(&mut self, epoch: Epoch, secret: &SecretKey) {
+        self.secrets.write(&epoch, secret).await;
+    }
+
+    /// Returns the secret of the signing key we proposed for `epoch`, if any.
+    pub async fn load_secret(&mut self, epoch: Epoch) -> Option<SecretKey> {
+        self.secrets
+            .read(&epoch)
+            .await
+            .expect("Failed to read signing keys from store")
+    }
+
+    /// Records a committed rotation.
+    pub async fn persist_rotation(&mut self, rotation: &KeyRotation) {
+        self.rotations
+            .write(&(rotation.name, rotation.epoch), rotation)
+            .await;
+    }
+
+    /// Reinstalls the rotations committed before a restart into `committee`, and returns the
+    /// service signing with our own keys, each one from its epoch on.
+    pub async fn restore(&mut self, keypair: &KeyPair, committee: &SharedCommittee) -> SignatureService {
+        // The keys are not stored in epoch order: install the rotations of each authority in order.
+        let mut rotations: Vec<KeyRotation> = self.rotations.iter().map(|(_, rotation)| rotation).collect().await;
+        rotations.sort_by_key(|rotation| rotation.epoch);
+
+        let mut schedule = BTreeMap::new();
+        schedule.insert(0, keypair.secret.clone());
+        for rotation in rotations {
+            if let Err(e) = committee.rotate(rotation.name, rotation.epoch, rotation.key) {
+                warn!("{}", e);
+                continue;
+            }
+            if rotation.name == keypair.name {
+                match self.load_secret(rotation.epoch).await {
+                    Some(secret) => {
+                        schedule.insert(rotation.epoch, secret);
+                    }
+                    None => warn!("Missing our signing key for epoch {}", rotation.epoch),
+                }
+            }
+        }
+        SignatureService::with_schedule(schedule)
+    }
+}
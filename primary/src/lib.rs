@@ -4,18 +4,24 @@ mod error;
 mod aggregators;
 mod certificate_waiter;
 mod core;
+mod evidence_collector;
 mod garbage_collector;
 mod header_waiter;
 mod helper;
+mod key_store;
 mod messages;
 mod payload_receiver;
 mod primary;
 mod proposer;
+mod reconfiguration_store;
+mod reconfigurer;
 mod synchronizer;
 
 #[cfg(test)]
 #[path = "tests/common.rs"]
 mod common;
 
-pub use crate::messages::{Certificate, Header, Metadata};
-pub use crate::primary::{Primary, PrimaryWorkerMessage, WorkerPrimaryMessage};
+pub use crate::evidence_collector::EvidenceCollector;
+pub use crate::key_store::KeyStore;
+pub use crate::messages::{Certificate, Header, KeyRotation, Metadata};
+pub use crate::primary::{AdminMessage, Primary, PrimaryWorkerMessage, WorkerPrimaryMessage, CERTIFICATES, PAYLOAD};
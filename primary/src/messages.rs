@@ -1,6 +1,6 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::error::{DagError, DagResult};
-use config::{Committee};
+use config::{Committee, Reconfiguration, SharedCommittee};
 use crypto::{Digest, Hash, PublicKey, Signature, SignatureService};
 use ed25519_dalek::Digest as _;
 use ed25519_dalek::Sha512;
@@ -8,8 +8,27 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::convert::TryInto;
 use std::fmt;
-use model::breeze_universal::BreezeCertificate;
-use model::types_and_const::{Round, WorkerId};
+use model::breeze_universal::{BreezeCertificate, CertificateError, Complaint};
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use model::types_and_const::{round_epoch, round_to_epoch_index, Epoch, Round, WorkerId, MAX_EPOCH};
+
+#[cfg(test)]
+#[cfg(not(feature = "pq"))]
+#[path = "tests/messages_tests.rs"]
+pub mod messages_tests;
+
+/// Whether a breeze certificate of `epoch` may be embedded in a header of `round`: from the
+/// epoch of the round on, and less than `breeze_epoch_limit` epochs ahead of it (no bound if the
+/// limit is 0). Proposers only draw certificates from this window, and headers out of it are
+/// rejected.
+pub fn in_breeze_window(epoch: Epoch, round: Round, breeze_epoch_limit: u64) -> bool {
+    if round == 0 {
+        return false;
+    }
+    let (current, _) = round_to_epoch_index(round, *MAX_EPOCH.get().unwrap());
+    epoch >= current && (breeze_epoch_limit < 1 || epoch < current + breeze_epoch_limit)
+}
+
 // TODO: Make metadata generic.
 
 #[derive(Clone, Serialize, Deserialize, Default, Debug)]
@@ -42,6 +61,64 @@ impl AsRef<[u8]> for Metadata {
     }
 }
 
+/// Hands the signing of an authority over to a new key from `epoch` on. It is signed with the key
+/// the authority signs with right before `epoch`, and takes effect once committed.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct KeyRotation {
+    /// The authority rotating its key.
+    pub name: PublicKey,
+    /// The first epoch signed with the new key.
+    pub epoch: Epoch,
+    /// The new signing key.
+    pub key: PublicKey,
+    pub signature: Signature,
+}
+
+impl KeyRotation {
+    pub async fn new(
+        name: PublicKey,
+        epoch: Epoch,
+        key: PublicKey,
+        signature_service: &mut SignatureService,
+    ) -> Self {
+        let rotation = Self {
+            name,
+            epoch,
+            key,
+            signature: Signature::default(),
+        };
+        let signature = signature_service
+            .request_signature_at(epoch.saturating_sub(1), rotation.digest())
+            .await;
+        Self {
+            signature,
+            ..rotation
+        }
+    }
+
+    /// Checks the rotation is signed with the key `name` currently signs with before `epoch`.
+    pub fn verify(&self, committees: &SharedCommittee) -> DagResult<()> {
+        ensure!(
+            self.epoch > 0,
+            DagError::InvalidKeyRotation(self.name, self.epoch)
+        );
+        let signing_key = committees.signing_key(&self.name, self.epoch - 1);
+        self.signature
+            .verify(&self.digest(), &signing_key)
+            .map_err(|_| DagError::InvalidKeyRotation(self.name, self.epoch))
+    }
+}
+
+impl Hash for KeyRotation {
+    fn digest(&self) -> Digest {
+        let mut hasher = Sha512::new();
+        hasher.update(&self.name);
+        hasher.update(self.epoch.to_le_bytes());
+        hasher.update(&self.key);
+        Digest(hasher.finalize().as_slice()[..32].try_into().unwrap())
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Default)]
 pub struct Header {
     pub author: PublicKey,
@@ -52,10 +129,18 @@ pub struct Header {
     pub id: Digest,
     pub signature: Signature,
 
-    pub breeze_cer: Option<BreezeCertificate>
+    pub breeze_cer: Option<BreezeCertificate>,
+    /// A committee proposed by the author to take over at a later epoch.
+    pub reconfiguration: Option<Reconfiguration>,
+    /// A new signing key of the author, for a later epoch.
+    pub key_rotation: Option<KeyRotation>,
+    /// The accusations and revelations of the complaint phase relayed by the author, which the
+    /// coordinator only acts on once committed.
+    pub complaints: Vec<Complaint>,
 }
 
 impl Header {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         author: PublicKey,
         round: Round,
@@ -64,7 +149,10 @@ impl Header {
         metadata: Option<Metadata>,
         signature_service: &mut SignatureService,
 
-        breeze_cer: Option<BreezeCertificate>
+        breeze_cer: Option<BreezeCertificate>,
+        reconfiguration: Option<Reconfiguration>,
+        key_rotation: Option<KeyRotation>,
+        complaints: Vec<Complaint>,
     ) -> Self {
         let header = Self {
             author,
@@ -75,10 +163,15 @@ impl Header {
             id: Digest::default(),
             signature: Signature::default(),
 
-            breeze_cer
+            breeze_cer,
+            reconfiguration,
+            key_rotation,
+            complaints,
         };
         let id = header.digest();
-        let signature = signature_service.request_signature(id.clone()).await;
+        let signature = signature_service
+            .request_signature_at(round_epoch(round), id.clone())
+            .await;
         Self {
             id,
             signature,
@@ -86,11 +179,14 @@ impl Header {
         }
     }
 
-    pub fn verify(&self, committee: &Committee) -> DagResult<()> {
+    /// Verifies the header against the committee in effect at its round (and the breeze
+    /// certificate against the committee of the certificate's epoch).
+    pub fn verify(&self, committees: &SharedCommittee, breeze_epoch_limit: u64) -> DagResult<()> {
         // Ensure the header id is well formed.
         ensure!(self.digest() == self.id, DagError::InvalidHeaderId);
 
         // Ensure the authority has voting rights.
+        let committee = committees.at_round(self.round);
         let voting_rights = committee.stake(&self.author);
         ensure!(voting_rights > 0, DagError::UnknownAuthority(self.author));
 
@@ -101,11 +197,71 @@ impl Header {
                 .map_err(|_| DagError::MalformedHeader(self.id.clone()))?;
         }
 
+        // Check the embedded breeze certificate.
+        if let Some(cer) = &self.breeze_cer {
+            self.verify_breeze_certificate(cer, committees, breeze_epoch_limit)?;
+        }
+
+        // A proposed committee must not be empty. Whether it can still take over at its epoch is
+        // only known once it is committed.
+        if let Some(reconfiguration) = &self.reconfiguration {
+            ensure!(
+                reconfiguration.committee.size() > 0,
+                DagError::MalformedHeader(self.id.clone())
+            );
+        }
+
+        // Authorities only rotate their own key.
+        if let Some(key_rotation) = &self.key_rotation {
+            ensure!(
+                key_rotation.name == self.author,
+                DagError::MalformedHeader(self.id.clone())
+            );
+            key_rotation.verify(committees)?;
+        }
+
         // Check the signature.
+        let signing_key = committees.signing_key(&self.author, round_epoch(self.round));
         self.signature
-            .verify(&self.id, &self.author)
+            .verify(&self.id, &signing_key)
             .map_err(DagError::from)
     }
+
+    /// Ensures `cer` is signed by a quorum of members and that its epoch falls in the window the
+    /// proposer draws certificates from at this round (see `in_breeze_window`).
+    fn verify_breeze_certificate(
+        &self,
+        cer: &BreezeCertificate,
+        committees: &SharedCommittee,
+        breeze_epoch_limit: u64,
+    ) -> DagResult<()> {
+        ensure!(
+            in_breeze_window(cer.epoch, self.round, breeze_epoch_limit),
+            DagError::BreezeCertificateOutOfWindow(cer.epoch, self.round)
+        );
+        let committee = committees.at(cer.epoch);
+        cer.verify(
+            |name| committee.weight(name),
+            |name| committees.signing_key(name, cer.epoch),
+            committee.weighted_quorum_threshold(),
+        )
+        .map_err(|e| match e {
+            CertificateError::RequiresQuorum => DagError::BreezeCertificateRequiresQuorum(self.id),
+            CertificateError::InvalidSignature(name) => DagError::InvalidBreezeCertificateSignature(self.id, name),
+            CertificateError::UnknownAuthority(name) => DagError::BreezeCertificateUnknownAuthority(self.id, name),
+            CertificateError::AuthorityReuse(name) => DagError::BreezeCertificateAuthorityReuse(self.id, name),
+        })
+    }
+
+    /// Returns the proof that the author signed both this header and `other`, of the same round.
+    pub fn equivocation(&self, other: &Header) -> Evidence {
+        let signed = |header: &Header| SignedMessage::new(header, header.id.clone(), header.signature.clone());
+        Evidence::new(
+            self.author,
+            round_epoch(self.round),
+            Misbehaviour::Equivocation(signed(self), signed(other)),
+        )
+    }
 }
 
 impl Hash for Header {
@@ -128,6 +284,18 @@ impl Hash for Header {
             let serialized = bincode::serialize(&cer).expect("Failed to serialize breeze certificate");
             hasher.update(serialized);
         }
+        if let Some(reconfiguration) = &self.reconfiguration {
+            let serialized = bincode::serialize(reconfiguration).expect("Failed to serialize reconfiguration");
+            hasher.update(serialized);
+        }
+        if let Some(key_rotation) = &self.key_rotation {
+            let serialized = bincode::serialize(key_rotation).expect("Failed to serialize key rotation");
+            hasher.update(serialized);
+        }
+        for complaint in &self.complaints {
+            hasher.update((complaint.0.len() as u64).to_le_bytes());
+            hasher.update(&complaint.0);
+        }
 
         Digest(hasher.finalize().as_slice()[..32].try_into().unwrap())
     }
@@ -174,20 +342,24 @@ impl Vote {
             author: *author,
             signature: Signature::default(),
         };
-        let signature = signature_service.request_signature(vote.digest()).await;
+        let signature = signature_service
+            .request_signature_at(round_epoch(vote.round), vote.digest())
+            .await;
         Self { signature, ..vote }
     }
 
-    pub fn verify(&self, committee: &Committee) -> DagResult<()> {
+    /// Verifies the vote against the committee and signing keys in effect at its round.
+    pub fn verify(&self, committees: &SharedCommittee) -> DagResult<()> {
         // Ensure the authority has voting rights.
         ensure!(
-            committee.stake(&self.author) > 0,
+            committees.at_round(self.round).stake(&self.author) > 0,
             DagError::UnknownAuthority(self.author)
         );
 
         // Check the signature.
+        let signing_key = committees.signing_key(&self.author, round_epoch(self.round));
         self.signature
-            .verify(&self.digest(), &self.author)
+            .verify(&self.digest(), &signing_key)
             .map_err(DagError::from)
     }
 }
@@ -236,16 +408,17 @@ impl Certificate {
             .collect()
     }
 
-    pub fn verify(&self, committee: &Committee) -> DagResult<()> {
+    pub fn verify(&self, committees: &SharedCommittee, breeze_epoch_limit: u64) -> DagResult<()> {
         // Genesis certificates are always valid.
-        if Self::genesis(committee).contains(self) {
+        if Self::genesis(&committees.at(0)).contains(self) {
             return Ok(());
         }
 
         // Check the embedded header.
-        self.header.verify(committee)?;
+        self.header.verify(committees, breeze_epoch_limit)?;
 
         // Ensure the certificate has a quorum.
+        let committee = committees.at_round(self.round());
         let mut weight = 0;
         let mut used = HashSet::new();
         for (name, _) in self.votes.iter() {
@@ -260,8 +433,14 @@ impl Certificate {
             DagError::CertificateRequiresQuorum
         );
 
-        // Check the signatures.
-        Signature::verify_batch(&self.digest(), &self.votes).map_err(DagError::from)
+        // Check the signatures, each one against the key its author signs with at this round.
+        let epoch = round_epoch(self.round());
+        let votes: Vec<_> = self
+            .votes
+            .iter()
+            .map(|(name, signature)| (committees.signing_key(name, epoch), signature.clone()))
+            .collect();
+        Signature::verify_batch(&self.digest(), &votes).map_err(DagError::from)
     }
 
     pub fn origin(&self) -> PublicKey {
@@ -272,7 +451,6 @@ impl Certificate {
         self.header.round
     }
 
-    #[cfg(feature = "dolphin")]
     pub fn virtual_round(&self) -> Round {
         self.header
             .metadata
@@ -280,7 +458,6 @@ impl Certificate {
             .map_or_else(|| 0, |x| x.virtual_round)
     }
 
-    #[cfg(feature = "dolphin")]
     pub fn virtual_parents(&self) -> Vec<&Digest> {
         self.header
             .metadata
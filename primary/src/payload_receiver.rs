@@ -1,4 +1,5 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::primary::PAYLOAD;
 use crypto::Digest;
 use store::Store;
 use tokio::sync::mpsc::Receiver;
@@ -7,7 +8,7 @@ use model::types_and_const::WorkerId;
 /// Receives batches' digests of other authorities. These are only needed to verify incoming
 /// headers (ie. make sure we have their payload).
 pub struct PayloadReceiver {
-    /// The persistent storage.
+    /// The payload column family of the store.
     store: Store,
     /// Receives batches' digests from the network.
     rx_workers: Receiver<(Digest, WorkerId)>,
@@ -16,7 +17,12 @@ pub struct PayloadReceiver {
 impl PayloadReceiver {
     pub fn spawn(store: Store, rx_workers: Receiver<(Digest, WorkerId)>) {
         tokio::spawn(async move {
-            Self { store, rx_workers }.run().await;
+            Self {
+                store: store.family(PAYLOAD),
+                rx_workers,
+            }
+            .run()
+            .await;
         });
     }
 
@@ -5,28 +5,33 @@ use crate::error::DagError;
 use crate::garbage_collector::GarbageCollector;
 use crate::header_waiter::HeaderWaiter;
 use crate::helper::Helper;
+use crate::key_store::KeyStore;
 use crate::messages::{Certificate, Header, Metadata, Vote};
 use crate::payload_receiver::PayloadReceiver;
 use crate::proposer::Proposer;
+use crate::reconfiguration_store::ReconfigurationStore;
 use crate::synchronizer::Synchronizer;
 use async_trait::async_trait;
 use bytes::Bytes;
-use config::{Committee, KeyPair, Parameters};
+use config::{Parameters, Reconfiguration, SharedCommittee};
 use crypto::{Digest, PublicKey, SignatureService};
 use futures::sink::SinkExt as _;
 use log::info;
-use network::{MessageHandler, Receiver as NetworkReceiver, Writer};
+use network::{MessageHandler, Receiver as NetworkReceiver, ReliableSender, SimpleSender, Transport, Writer};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use store::Store;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use model::breeze_universal::BreezeCertificate;
-use model::types_and_const::{Round, WorkerId, CHANNEL_CAPACITY};
+use model::breeze_universal::{BreezeCertificate, Complaint};
+use model::evidence::Evidence;
+use model::types_and_const::{Epoch, Round, WorkerId, CHANNEL_CAPACITY};
 
-
-/// The round number.
+/// The column family of the store holding the certificates, by digest.
+pub const CERTIFICATES: &str = "certificates";
+/// The column family of the store recording the batches (digest and worker id) our workers hold.
+pub const PAYLOAD: &str = "payload";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum PrimaryMessage {
@@ -43,6 +48,8 @@ pub enum PrimaryWorkerMessage {
     Synchronize(Vec<Digest>, /* target */ PublicKey),
     /// The primary indicates a round update.
     Cleanup(Round),
+    /// The primary indicates that a new committee takes over.
+    Reconfigure(Reconfiguration),
 }
 
 /// The messages sent by the workers to their primary.
@@ -54,12 +61,25 @@ pub enum WorkerPrimaryMessage {
     OthersBatch(Digest, WorkerId),
 }
 
+/// The commands of the operator of the authority to its primary. The primary only takes them on
+/// the loopback interface.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AdminMessage {
+    /// The operator asks the primary to propose a committee taking over at a later epoch.
+    Reconfigure(Reconfiguration),
+    /// The operator asks the primary to sign with a fresh key from the given epoch on.
+    RotateKey(Epoch),
+}
+
 pub struct Primary;
 
 impl Primary {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
-        keypair: KeyPair,
-        committee: Committee,
+        name: PublicKey,
+        signature_service: SignatureService,
+        transport: Arc<dyn Transport>,
+        committee: SharedCommittee,
         parameters: Parameters,
         store: Store,
         tx_output: Sender<Certificate>,
@@ -67,6 +87,8 @@ impl Primary {
         rx_metadata: Receiver<Metadata>,
 
         cer_to_consensus_receiver: Receiver<BreezeCertificate>,
+        rx_complaints: Receiver<Complaint>,
+        tx_evidence: Sender<Evidence>,
     ) {
         let (tx_others_digests, rx_others_digests) = channel(CHANNEL_CAPACITY);
         let (tx_our_digests, rx_our_digests) = channel(CHANNEL_CAPACITY);
@@ -78,25 +100,26 @@ impl Primary {
         let (tx_certificates_loopback, rx_certificates_loopback) = channel(CHANNEL_CAPACITY);
         let (tx_primary_messages, rx_primary_messages) = channel(CHANNEL_CAPACITY);
         let (tx_cert_requests, rx_cert_requests) = channel(CHANNEL_CAPACITY);
+        let (tx_reconfiguration, rx_reconfiguration) = channel(CHANNEL_CAPACITY);
+        let (tx_key_rotation, rx_key_rotation) = channel(CHANNEL_CAPACITY);
 
         // Write the parameters to the logs.
         parameters.log();
 
-        // Parse the public and secret key of this authority.
-        let name = keypair.name;
-        let secret = keypair.secret;
-
         // Atomic variable use to synchronizer all tasks with the latest consensus round. This is only
         // used for cleanup. The only tasks that write into this variable is `GarbageCollector`.
         let consensus_round = Arc::new(AtomicU64::new(0));
 
-        // Spawn the network receiver listening to messages from the other primaries.
-        let mut address = committee
+        // Spawn the network receiver listening to messages from the other primaries. Our addresses
+        // are the ones of the latest committee, so that a joining authority listens right away.
+        let our_committee = committee.latest().1;
+        let mut address = our_committee
             .primary(&name)
             .expect("Our public key or worker id is not in the committee")
             .primary_to_primary;
         address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(
+        NetworkReceiver::spawn_with_transport(
+            transport.clone(),
             address,
             /* handler */
             PrimaryReceiverHandler {
@@ -110,12 +133,13 @@ impl Primary {
         );
 
         // Spawn the network receiver listening to messages from our workers.
-        let mut address = committee
+        let mut address = our_committee
             .primary(&name)
             .expect("Our public key or worker id is not in the committee")
             .worker_to_primary;
         address.set_ip("0.0.0.0".parse().unwrap());
-        NetworkReceiver::spawn(
+        NetworkReceiver::spawn_with_transport(
+            transport.clone(),
             address,
             /* handler */
             WorkerReceiverHandler {
@@ -128,17 +152,40 @@ impl Primary {
             name, address
         );
 
+        // Spawn the network receiver listening to the commands of our operator. Nobody but the
+        // operator should be able to reconfigure the committee or rotate our keys: we only listen
+        // on the loopback interface.
+        let admin_address = our_committee
+            .admin_api_address(&name)
+            .expect("Our public key or worker id is not in the committee");
+        if let Some(mut address) = admin_address {
+            address.set_ip("127.0.0.1".parse().unwrap());
+            NetworkReceiver::spawn_with_transport(
+                transport.clone(),
+                address,
+                /* handler */
+                AdminReceiverHandler {
+                    tx_reconfiguration,
+                    tx_key_rotation,
+                },
+            );
+            info!(
+                "Primary {} listening to operator commands on {}",
+                name, address
+            );
+        }
+
         // The `Synchronizer` provides auxiliary methods helping to `Core` to sync.
         let synchronizer = Synchronizer::new(
             name,
-            &committee,
+            &committee.at(0),
             store.clone(),
             /* tx_header_waiter */ tx_sync_headers,
             /* tx_certificate_waiter */ tx_sync_certificates,
         );
 
-        // The `SignatureService` is used to require signatures on specific digests.
-        let signature_service = SignatureService::new(secret);
+        // Holds the signing keys we proposed until they are committed.
+        let key_store = KeyStore::new(store.clone());
 
         // The `Core` receives and handles headers, votes, and certificates from the other primaries.
         Core::spawn(
@@ -149,16 +196,30 @@ impl Primary {
             signature_service.clone(),
             consensus_round.clone(),
             parameters.gc_depth,
+            parameters.store_retention,
+            parameters.breeze_epoch_limit,
             /* rx_primaries */ rx_primary_messages,
             /* rx_header_waiter */ rx_headers_loopback,
             /* rx_certificate_waiter */ rx_certificates_loopback,
             /* rx_proposer */ rx_headers,
             /* rx_consensus */ tx_output,
             /* tx_proposer */ tx_parents,
+            tx_evidence,
+            ReliableSender::with_transport(transport.clone()),
         );
 
         // Keeps track of the latest consensus round and allows other tasks to clean up their their internal state
-        GarbageCollector::spawn(&name, &committee, consensus_round.clone(), rx_commit);
+        GarbageCollector::spawn(
+            &name,
+            committee.clone(),
+            signature_service.clone(),
+            key_store.clone(),
+            ReconfigurationStore::new(store.clone()),
+            parameters.breeze_epoch_limit,
+            consensus_round.clone(),
+            rx_commit,
+            SimpleSender::with_transport(transport.clone()),
+        );
 
         // Receives batch digests from other workers. They are only used to validate headers.
         PayloadReceiver::spawn(store.clone(), /* rx_workers */ rx_others_digests);
@@ -176,6 +237,7 @@ impl Primary {
             parameters.sync_retry_nodes,
             /* rx_synchronizer */ rx_sync_headers,
             /* tx_core */ tx_headers_loopback,
+            SimpleSender::with_transport(transport.clone()),
         );
 
         // The `CertificateWaiter` waits to receive all the ancestors of a certificate before looping it back to the
@@ -192,27 +254,32 @@ impl Primary {
         // digests from our workers and it back to the `Core`.
         Proposer::spawn(
             name,
-            &committee,
+            committee.clone(),
             signature_service,
             parameters.header_size,
             parameters.max_header_delay,
             parameters.breeze_epoch_limit,
+            parameters.protocol,
             /* rx_core */ rx_parents,
             /* rx_workers */ rx_our_digests,
             /* tx_core */ tx_headers,
             /* rx_consensus */ rx_metadata,
 
-            cer_to_consensus_receiver
+            cer_to_consensus_receiver,
+            rx_reconfiguration,
+            rx_key_rotation,
+            rx_complaints,
+            key_store,
         );
 
         // The `Helper` is dedicated to reply to certificates requests from other primaries.
-        Helper::spawn(committee.clone(), store, rx_cert_requests);
+        Helper::spawn(committee, store, rx_cert_requests, SimpleSender::with_transport(transport));
 
         // NOTE: This log entry is used to compute performance.
         info!(
             "Primary {} successfully booted on {}",
             name,
-            committee
+            our_committee
                 .primary(&name)
                 .expect("Our public key or worker id is not in the committee")
                 .primary_to_primary
@@ -281,3 +348,34 @@ impl MessageHandler for WorkerReceiverHandler {
         Ok(())
     }
 }
+
+/// Defines how the network receiver handles the commands of our operator.
+#[derive(Clone)]
+struct AdminReceiverHandler {
+    tx_reconfiguration: Sender<Reconfiguration>,
+    tx_key_rotation: Sender<Epoch>,
+}
+
+#[async_trait]
+impl MessageHandler for AdminReceiverHandler {
+    async fn dispatch(
+        &self,
+        _writer: &mut Writer,
+        serialized: Bytes,
+    ) -> Result<(), Box<dyn Error>> {
+        // Deserialize and parse the message.
+        match bincode::deserialize(&serialized).map_err(DagError::SerializationError)? {
+            AdminMessage::Reconfigure(reconfiguration) => self
+                .tx_reconfiguration
+                .send(reconfiguration)
+                .await
+                .expect("Failed to send reconfiguration"),
+            AdminMessage::RotateKey(epoch) => self
+                .tx_key_rotation
+                .send(epoch)
+                .await
+                .expect("Failed to send key rotation"),
+        }
+        Ok(())
+    }
+}
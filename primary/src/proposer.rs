@@ -1,18 +1,17 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::key_store::KeyStore;
 use crate::messages::Metadata;
-use crate::messages::{Certificate, Header};
-use config::{Committee};
+use crate::messages::{in_breeze_window, Certificate, Header, KeyRotation};
+use config::{Protocol, Reconfiguration, SharedCommittee};
 use crypto::Hash as _;
-use crypto::{Digest, PublicKey, SignatureService};
-#[cfg(feature = "benchmark")]
-use log::info;
-use log::{debug, log_enabled};
+use crypto::{generate_production_keypair, Digest, PublicKey, SignatureService};
+use log::{debug, info, log_enabled};
 use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{watch, RwLock};
 use tokio::time::{sleep, Duration, Instant};
-use model::breeze_universal::BreezeCertificate;
+use model::breeze_universal::{BreezeCertificate, Complaint};
 use model::types_and_const::{round_to_epoch_index, Epoch, Round, WorkerId, MAX_EPOCH};
 
 #[cfg(test)]
@@ -23,6 +22,8 @@ pub mod proposer_tests;
 pub struct Proposer {
     /// The public key of this primary.
     name: PublicKey,
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
     /// Service to sign headers.
     signature_service: SignatureService,
     /// The size of the headers' payload.
@@ -49,32 +50,53 @@ pub struct Proposer {
     payload_size: usize,
     /// The metadata to include in the next header.
     metadata: VecDeque<Metadata>,
+    /// Receives the committees our operator proposes to take over at a later epoch.
+    rx_reconfiguration: Receiver<Reconfiguration>,
+    /// The committee to propose in the next header.
+    reconfiguration: Option<Reconfiguration>,
+    /// Receives the epochs from which our operator wants us to sign with a fresh key.
+    rx_key_rotation: Receiver<Epoch>,
+    /// The signing key to propose in the next header.
+    key_rotation: Option<KeyRotation>,
+    /// Holds the secrets of the keys we propose until they are committed.
+    key_store: KeyStore,
+    /// Receives the accusations and revelations Breeze relays through the DAG.
+    rx_complaints: Receiver<Complaint>,
+    /// The complaints to include in the next header.
+    complaints: Vec<Complaint>,
 
     breeze_cer_buffer: Arc<RwLock<Vec<BreezeCertificate>>>,
     bcb_change_receiver: watch::Receiver<()>,
     breeze_cer_proposed: HashSet<Epoch>,
     
     flag: bool,
-    breeze_epoch_limit: u64
+    breeze_epoch_limit: u64,
+    /// The consensus protocol: Dolphin waits for the metadata of its virtual dag in every header.
+    protocol: Protocol,
 }
 
 impl Proposer {
     #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         name: PublicKey,
-        committee: &Committee,
+        committee: SharedCommittee,
         signature_service: SignatureService,
         header_size: usize,
         max_header_delay: u64,
         breeze_epoch_limit: u64,
+        protocol: Protocol,
         rx_core: Receiver<(Vec<Digest>, Round)>,
         rx_workers: Receiver<(Digest, WorkerId)>,
         tx_core: Sender<Header>,
         rx_consensus: Receiver<Metadata>,
 
         mut cer_to_consensus_receiver: Receiver<BreezeCertificate>,
+        rx_reconfiguration: Receiver<Reconfiguration>,
+        rx_key_rotation: Receiver<Epoch>,
+        rx_complaints: Receiver<Complaint>,
+        key_store: KeyStore,
     ) {
-        let genesis = Certificate::genesis(committee)
+        let genesis = Certificate::genesis(&committee.at(0))
             .iter()
             .map(|x| x.digest())
             .collect();
@@ -95,6 +117,7 @@ impl Proposer {
         tokio::spawn(async move {
             Self {
                 name,
+                committee,
                 signature_service,
                 header_size,
                 max_header_delay,
@@ -107,13 +130,21 @@ impl Proposer {
                 digests: Vec::with_capacity(2 * header_size),
                 payload_size: 0,
                 metadata: VecDeque::new(),
+                rx_reconfiguration,
+                reconfiguration: None,
+                rx_key_rotation,
+                key_rotation: None,
+                key_store,
+                rx_complaints,
+                complaints: Vec::new(),
 
                 breeze_cer_buffer,
                 bcb_change_receiver,
                 breeze_cer_proposed: HashSet::new(),
                 
                 flag:false,
-                breeze_epoch_limit
+                breeze_epoch_limit,
+                protocol,
             }
             .run()
             .await;
@@ -155,19 +186,7 @@ impl Proposer {
             }
         }
         else {
-            if self.breeze_epoch_limit < 1{
-                let mut bcb = self.breeze_cer_buffer.write().await;
-                cer = if bcb.is_empty() {
-                    None
-                } else {
-                    let bc = bcb.remove(0);
-                    self.breeze_cer_proposed.insert(bc.epoch);
-                    Some(bc)
-                };
-                drop(bcb);
-            }else {
-                cer = get_certificate(&mut self.breeze_cer_proposed, &self.breeze_cer_buffer, epoch, self.breeze_epoch_limit).await;
-            }
+            cer = get_certificate(&mut self.breeze_cer_proposed, &self.breeze_cer_buffer, self.round, self.breeze_epoch_limit).await;
         }
         // Make a new header.
         let header = Header::new(
@@ -179,6 +198,9 @@ impl Proposer {
             &mut self.signature_service,
 
             cer,
+            self.reconfiguration.take(),
+            self.key_rotation.take(),
+            self.complaints.drain(..).collect(),
         )
         .await;
         debug!("Created {:?}", header);
@@ -224,15 +246,20 @@ impl Proposer {
             let enough_parents = !self.last_parents.is_empty();
             let enough_digests = self.payload_size >= self.header_size;
             let timer_expired = timer.is_elapsed();
-            // let metadata_ready = !self.metadata.is_empty();
-            #[cfg(feature = "dolphin")]
-            let metadata_ready = !self.metadata.is_empty();
-            #[cfg(not(feature = "dolphin"))]
-            let metadata_ready = true;
+            let metadata_ready = match self.protocol {
+                Protocol::Dolphin => !self.metadata.is_empty(),
+                Protocol::Tusk | Protocol::Bullshark => true,
+            };
             if (timer_expired || enough_digests) && enough_parents && metadata_ready && self.flag {
 
-                // Make a new header.
-                self.make_header().await;
+                // Make a new header, unless we are not a member of the committee of this round: we
+                // then only follow the DAG (e.g. before joining or after leaving the committee).
+                if self.committee.at_round(self.round).stake(&self.name) > 0 {
+                    self.make_header().await;
+                } else {
+                    self.digests.clear();
+                    self.last_parents.clear();
+                }
                 self.payload_size = 0;
 
                 // Reschedule the timer.
@@ -241,6 +268,7 @@ impl Proposer {
             }
 
             tokio::select! {
+                biased;
                 Some((parents, round)) = self.rx_core.recv() => {
                     if round < self.round {
                         debug!("Dag received {} round from rx_core too small, skip", round);
@@ -262,6 +290,22 @@ impl Proposer {
                 Some(metadata) = self.rx_consensus.recv() => {
                     self.metadata.push_front(metadata);
                 }
+                Some(reconfiguration) = self.rx_reconfiguration.recv() => {
+                    debug!("Proposing the committee of epoch {}", reconfiguration.epoch);
+                    self.reconfiguration = Some(reconfiguration);
+                }
+                Some(epoch) = self.rx_key_rotation.recv() => {
+                    // Keep the secret before proposing its key, so that we can sign with it once
+                    // committed (even after a restart).
+                    let (key, secret) = generate_production_keypair();
+                    self.key_store.persist_secret(epoch, &secret).await;
+                    let rotation = KeyRotation::new(self.name, epoch, key, &mut self.signature_service).await;
+                    debug!("Proposing a new signing key for epoch {}", epoch);
+                    self.key_rotation = Some(rotation);
+                }
+                Some(complaint) = self.rx_complaints.recv() => {
+                    self.complaints.push(complaint);
+                }
                 // certificate from breeze.
                 // Some(cer) = self.cer_to_consensus_receiver.recv() => {
                 //     self.breeze_cer_buffer.push_back(cer);
@@ -278,7 +322,7 @@ impl Proposer {
 async fn get_certificate(
     breeze_cer_proposed: &mut HashSet<Epoch>,
     breeze_cer_buffer: &Arc<RwLock<Vec<BreezeCertificate>>>,
-    epoch: Epoch,
+    round: Round,
     breeze_epoch_limit: u64
 ) -> Option<BreezeCertificate> {
     let mut bcb = breeze_cer_buffer.write().await;
@@ -291,10 +335,10 @@ async fn get_certificate(
     // Find maximum epoch in breeze_cer_proposed
     // let max_epoch = breeze_cer_proposed.iter().max().copied().unwrap_or(0);
 
-    // Find index of first certificate with epoch in range (max_epoch, max_epoch + 16)
+    // Find index of first certificate the other primaries accept in a header of this round
     let index_to_remove = bcb.iter()
         .enumerate()
-        .find(|(_, cert)| cert.epoch > epoch && cert.epoch < epoch + breeze_epoch_limit)
+        .find(|(_, cert)| in_breeze_window(cert.epoch, round, breeze_epoch_limit))
         .map(|(index, _)| index);
 
     let cer = if let Some(index) = index_to_remove {
@@ -0,0 +1,67 @@
+use config::{Reconfiguration, SharedCommittee};
+use crypto::PublicKey;
+use futures::stream::StreamExt as _;
+use log::warn;
+use model::types_and_const::Epoch;
+use std::collections::BTreeSet;
+use store::{Store, TypedStore};
+
+/// The column family of the primary's store holding the installed committees, by epoch.
+const RECONFIGURATIONS: &str = "reconfigurations";
+/// The column family of the primary's store holding the committees proposed but not installed yet,
+/// under a single key.
+const RECONFIGURATION_PROPOSALS: &str = "reconfiguration_proposals";
+
+/// The committees proposed so far, along with the authorities that proposed them.
+pub type Proposals = Vec<(Reconfiguration, BTreeSet<PublicKey>)>;
+
+/// Persists the committees committed so far, so that a node restarts with the committees it
+/// installed and the proposals it counted before the restart.
+#[derive(Clone)]
+pub struct ReconfigurationStore {
+    installed: TypedStore<Epoch, Reconfiguration>,
+    proposals: TypedStore<(), Proposals>,
+}
+
+impl ReconfigurationStore {
+    pub fn new(store: Store) -> Self {
+        Self {
+            installed: TypedStore::new(&store, RECONFIGURATIONS),
+            proposals: TypedStore::new(&store, RECONFIGURATION_PROPOSALS),
+        }
+    }
+
+    /// Records an installed committee.
+    pub async fn persist_reconfiguration(&mut self, reconfiguration: &Reconfiguration) {
+        self.installed
+            .write(&reconfiguration.epoch, reconfiguration)
+            .await;
+    }
+
+    /// Records the pending proposals.
+    pub async fn persist_proposals(&mut self, proposals: &[(Reconfiguration, BTreeSet<PublicKey>)]) {
+        self.proposals.write(&(), &proposals.to_vec()).await;
+    }
+
+    /// Reinstalls the committees installed before a restart into `committee`, and returns them (in
+    /// epoch order) along with the pending proposals.
+    pub async fn restore(&mut self, committee: &SharedCommittee) -> (Vec<Reconfiguration>, Proposals) {
+        // The epochs are not stored in order: install the committees in order.
+        let mut installed: Vec<Reconfiguration> =
+            self.installed.iter().map(|(_, reconfiguration)| reconfiguration).collect().await;
+        installed.sort_by_key(|reconfiguration| reconfiguration.epoch);
+        for reconfiguration in &installed {
+            if let Err(e) = committee.install(reconfiguration.clone()) {
+                warn!("{}", e);
+            }
+        }
+
+        let proposals = self
+            .proposals
+            .read(&())
+            .await
+            .expect("Failed to read reconfiguration proposals from store")
+            .unwrap_or_default();
+        (installed, proposals)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::messages::{Certificate, KeyRotation};
+use crate::reconfiguration_store::Proposals;
+use config::{Reconfiguration, SharedCommittee};
+use crypto::PublicKey;
+use log::{info, warn};
+use model::types_and_const::{round_epoch, Epoch, Stake};
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+#[path = "tests/reconfigurer_tests.rs"]
+pub mod reconfigurer_tests;
+
+/// Tracks the committees and signing keys proposed in the committed headers. Since every node sees
+/// the same sequence of certificates, they all install the same committees and keys at the same
+/// epochs.
+pub struct Reconfigurer {
+    /// The committees of the successive epochs.
+    committee: SharedCommittee,
+    /// The minimum number of epochs between the commit of a reconfiguration and its first epoch.
+    /// Breeze deals up to `breeze_epoch_limit` epochs ahead of the DAG, and none of them may be
+    /// dealt to the outgoing committee.
+    horizon: Epoch,
+    /// The committees proposed so far, along with the authorities that proposed them.
+    proposals: Proposals,
+}
+
+impl Reconfigurer {
+    /// Starts counting from the `proposals` made so far (e.g. restored after a restart).
+    pub fn new(committee: SharedCommittee, breeze_epoch_limit: u64, proposals: Proposals) -> Self {
+        Self {
+            committee,
+            horizon: breeze_epoch_limit + 2,
+            proposals,
+        }
+    }
+
+    /// Returns the committees proposed so far, along with the authorities that proposed them.
+    pub fn proposals(&self) -> &[(Reconfiguration, BTreeSet<PublicKey>)] {
+        &self.proposals
+    }
+
+    /// Processes a committed certificate. A committee is installed once authorities holding f+1
+    /// stake proposed it (so at least one honest authority vouches for it), provided it takes over
+    /// at least `horizon` epochs after the round completing it. Returns the installed committee.
+    pub fn process(&mut self, certificate: &Certificate) -> Option<Reconfiguration> {
+        let proposal = certificate.header.reconfiguration.as_ref()?;
+        let round = certificate.round();
+        let earliest = round_epoch(round) + self.horizon;
+
+        // Forget the proposals that can no longer take over in time.
+        self.proposals.retain(|(x, _)| x.epoch >= earliest);
+
+        let index = match self.proposals.iter().position(|(x, _)| x == proposal) {
+            Some(index) => index,
+            None => {
+                self.proposals.push((proposal.clone(), BTreeSet::new()));
+                self.proposals.len() - 1
+            }
+        };
+        let proposers = &mut self.proposals[index].1;
+        proposers.insert(certificate.origin());
+
+        let committee = self.committee.at_round(round);
+        let stake: Stake = proposers.iter().map(|name| committee.stake(name)).sum();
+        if stake < committee.validity_threshold() {
+            return None;
+        }
+
+        let (reconfiguration, _) = self.proposals.remove(index);
+        if reconfiguration.epoch < earliest {
+            warn!(
+                "Committee of epoch {} committed at round {}: too late to take over",
+                reconfiguration.epoch, round
+            );
+            return None;
+        }
+        match self.committee.install(reconfiguration.clone()) {
+            Ok(()) => {
+                info!(
+                    "Committee of epoch {} installed at round {}",
+                    reconfiguration.epoch, round
+                );
+                Some(reconfiguration)
+            }
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Processes the key rotation of a committed certificate. Unlike committees, an authority alone
+    /// decides its signing key: the rotation takes effect once committed, provided it is still signed
+    /// with the key it replaces and takes over at least `horizon` epochs after its round (so that no
+    /// message of that epoch was signed yet). Returns the installed rotation.
+    pub fn process_key_rotation(&mut self, certificate: &Certificate) -> Option<KeyRotation> {
+        let rotation = certificate.header.key_rotation.as_ref()?;
+        let round = certificate.round();
+
+        // The signing key may have rotated since the header was verified.
+        if let Err(e) = rotation.verify(&self.committee) {
+            warn!("{}", e);
+            return None;
+        }
+        if rotation.epoch < round_epoch(round) + self.horizon {
+            warn!(
+                "Signing key of {} for epoch {} committed at round {}: too late to take over",
+                rotation.name, rotation.epoch, round
+            );
+            return None;
+        }
+        match self.committee.rotate(rotation.name, rotation.epoch, rotation.key) {
+            Ok(()) => {
+                info!(
+                    "Signing key of {} for epoch {} installed at round {}",
+                    rotation.name, rotation.epoch, round
+                );
+                Some(rotation.clone())
+            }
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        }
+    }
+}
@@ -2,11 +2,12 @@
 use crate::error::DagResult;
 use crate::header_waiter::WaiterMessage;
 use crate::messages::{Certificate, Header};
+use crate::primary::{CERTIFICATES, PAYLOAD};
 use config::Committee;
 use crypto::Hash as _;
 use crypto::{Digest, PublicKey};
 use std::collections::HashMap;
-use store::Store;
+use store::{Store, TypedStore};
 use tokio::sync::mpsc::Sender;
 use model::types_and_const::Round;
 
@@ -15,8 +16,10 @@ use model::types_and_const::Round;
 pub struct Synchronizer {
     /// The public key of this primary.
     name: PublicKey,
-    /// The persistent storage.
-    store: Store,
+    /// The certificates column family of the store.
+    certificate_store: TypedStore<Digest, Certificate>,
+    /// The payload column family of the store.
+    payload_store: Store,
     /// Send commands to the `HeaderWaiter`.
     tx_header_waiter: Sender<WaiterMessage>,
     /// Send commands to the `CertificateWaiter`.
@@ -35,7 +38,8 @@ impl Synchronizer {
     ) -> Self {
         Self {
             name,
-            store,
+            certificate_store: TypedStore::new(&store, CERTIFICATES),
+            payload_store: store.family(PAYLOAD),
             tx_header_waiter,
             tx_certificate_waiter,
             genesis: Certificate::genesis(committee)
@@ -68,7 +72,7 @@ impl Synchronizer {
             //         to workers #1 (rather than workers #0). Also, clients will never be able to retrieve batch
             //         X as they will be querying worker #1.
             let key = [digest.as_ref(), &worker_id.to_le_bytes()].concat();
-            if self.store.read(key).await?.is_none() {
+            if self.payload_store.read(key).await?.is_none() {
                 missing.insert(digest.clone(), *worker_id);
             }
         }
@@ -101,8 +105,8 @@ impl Synchronizer {
                 continue;
             }
 
-            match self.store.read(digest.to_vec()).await? {
-                Some(certificate) => parents.push(bincode::deserialize(&certificate)?),
+            match self.certificate_store.read(digest).await? {
+                Some(certificate) => parents.push(certificate),
                 None => missing.push(digest.clone()),
             };
         }
@@ -125,7 +129,7 @@ impl Synchronizer {
                 if gc_round >= round || self.genesis.iter().any(|(x, _)| x == digest) {
                     continue;
                 }
-                if self.store.read(digest.to_vec()).await?.is_none() {
+                if self.certificate_store.read(digest).await?.is_none() {
                     missing.push(digest.clone());
                 }
             }
@@ -149,7 +153,7 @@ impl Synchronizer {
                 continue;
             }
 
-            if self.store.read(digest.to_vec()).await?.is_none() {
+            if self.certificate_store.read(digest).await?.is_none() {
                 self.tx_certificate_waiter
                     .send(certificate.clone())
                     .await
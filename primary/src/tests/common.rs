@@ -1,13 +1,15 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::messages::{Certificate, Header, Vote};
 use bytes::Bytes;
-use config::{Authority, Committee, PrimaryAddresses, WorkerAddresses};
+use config::{Authority, Committee, PrimaryAddresses, WorkerAddresses, Weighting};
 use crypto::Hash as _;
-use crypto::{generate_keypair, PublicKey, SecretKey, Signature};
+use crypto::{generate_keypair, Digest, PublicKey, SecretKey, Signature};
 use futures::sink::SinkExt as _;
 use futures::stream::StreamExt as _;
+use model::breeze_universal::BreezeCertificate;
 use rand::rngs::StdRng;
 use rand::SeedableRng as _;
+use std::collections::BTreeSet;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
@@ -47,6 +49,9 @@ pub fn committee() -> Committee {
                     worker_to_primary: format!("127.0.0.1:{}", 200 + i).parse().unwrap(),
                     breeze_addr: format!("127.0.0.1:{}", 600 + i).parse().unwrap(),
                     init_bft_addr: format!("127.0.0.1:{}", 700 + i).parse().unwrap(),
+                    beacon_api_addr: Some(format!("127.0.0.1:{}", 800 + i).parse().unwrap()),
+                    evidence_api_addr: Some(format!("127.0.0.1:{}", 900 + i).parse().unwrap()),
+                    admin_api_addr: None,
                 };
                 let workers = vec![(
                     0,
@@ -70,6 +75,7 @@ pub fn committee() -> Committee {
                 )
             })
             .collect(),
+        weighting: Weighting::Count,
     }
 }
 
@@ -182,9 +188,27 @@ pub fn listener(address: SocketAddr) -> JoinHandle<Bytes> {
         match reader.next().await {
             Some(Ok(received)) => {
                 writer.send(Bytes::from("Ack")).await.unwrap();
-                received.freeze()
+                // Skip the byte flagging an uncompressed message.
+                received.freeze().slice(1..)
             }
             _ => panic!("Failed to receive network message"),
         }
     })
 }
+
+// Fixture: a breeze certificate of `epoch`, signed by the first `signers` keys.
+pub fn breeze_certificate(epoch: u64, signers: usize) -> BreezeCertificate {
+    let c = Digest([1; 32]);
+    let dealer = keys()[0].0;
+    let digest = BreezeCertificate::signed_digest(&c, &dealer);
+    let mut certificates = BTreeSet::new();
+    for (name, secret) in keys().into_iter().take(signers) {
+        certificates.insert((name, Signature::new(&digest, &secret)));
+    }
+    BreezeCertificate {
+        c,
+        dealer,
+        epoch,
+        certificates,
+    }
+}
@@ -4,7 +4,6 @@ use crate::common::{
     certificate, committee, committee_with_base_port, header, headers, keys, listener, votes,
 };
 use futures::future::try_join_all;
-use std::fs;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
@@ -24,11 +23,10 @@ async fn process_header() {
     let (_tx_headers, rx_headers) = channel(1);
     let (tx_consensus, _rx_consensus) = channel(1);
     let (tx_parents, _rx_parents) = channel(1);
+    let (tx_evidence, _rx_evidence) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_process_header";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::in_memory();
 
     // Make the vote we expect to receive.
     let expected = Vote::new(&header(), &name, &mut signature_service).await;
@@ -52,18 +50,22 @@ async fn process_header() {
     // Spawn the core.
     Core::spawn(
         name,
-        committee,
+        committee.into(),
         store.clone(),
         synchronizer,
         signature_service,
         /* consensus_round */ Arc::new(AtomicU64::new(0)),
         /* gc_depth */ 50,
+        /* store_retention */ None,
+        /* breeze_epoch_limit */ 20,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
         /* rx_certificate_waiter */ rx_certificates_loopback,
         /* rx_proposer */ rx_headers,
         tx_consensus,
         /* tx_proposer */ tx_parents,
+        tx_evidence,
+        ReliableSender::new(),
     );
 
     // Send a header to the core.
@@ -93,11 +95,10 @@ async fn process_header_missing_parent() {
     let (_tx_headers, rx_headers) = channel(1);
     let (tx_consensus, _rx_consensus) = channel(1);
     let (tx_parents, _rx_parents) = channel(1);
+    let (tx_evidence, _rx_evidence) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_process_header_missing_parent";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::in_memory();
 
     // Make a synchronizer for the core.
     let synchronizer = Synchronizer::new(
@@ -111,18 +112,22 @@ async fn process_header_missing_parent() {
     // Spawn the core.
     Core::spawn(
         name,
-        committee(),
+        committee().into(),
         store.clone(),
         synchronizer,
         signature_service,
         /* consensus_round */ Arc::new(AtomicU64::new(0)),
         /* gc_depth */ 50,
+        /* store_retention */ None,
+        /* breeze_epoch_limit */ 20,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
         /* rx_certificate_waiter */ rx_certificates_loopback,
         /* rx_proposer */ rx_headers,
         tx_consensus,
         /* tx_proposer */ tx_parents,
+        tx_evidence,
+        ReliableSender::new(),
     );
 
     // Send a header to the core.
@@ -153,11 +158,10 @@ async fn process_header_missing_payload() {
     let (_tx_headers, rx_headers) = channel(1);
     let (tx_consensus, _rx_consensus) = channel(1);
     let (tx_parents, _rx_parents) = channel(1);
+    let (tx_evidence, _rx_evidence) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_process_header_missing_payload";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::in_memory();
 
     // Make a synchronizer for the core.
     let synchronizer = Synchronizer::new(
@@ -171,18 +175,22 @@ async fn process_header_missing_payload() {
     // Spawn the core.
     Core::spawn(
         name,
-        committee(),
+        committee().into(),
         store.clone(),
         synchronizer,
         signature_service,
         /* consensus_round */ Arc::new(AtomicU64::new(0)),
         /* gc_depth */ 50,
+        /* store_retention */ None,
+        /* breeze_epoch_limit */ 20,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
         /* rx_certificate_waiter */ rx_certificates_loopback,
         /* rx_proposer */ rx_headers,
         tx_consensus,
         /* tx_proposer */ tx_parents,
+        tx_evidence,
+        ReliableSender::new(),
     );
 
     // Send a header to the core.
@@ -215,11 +223,10 @@ async fn process_votes() {
     let (_tx_headers, rx_headers) = channel(1);
     let (tx_consensus, _rx_consensus) = channel(1);
     let (tx_parents, _rx_parents) = channel(1);
+    let (tx_evidence, _rx_evidence) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_process_vote";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::in_memory();
 
     // Make a synchronizer for the core.
     let synchronizer = Synchronizer::new(
@@ -233,18 +240,22 @@ async fn process_votes() {
     // Spawn the core.
     Core::spawn(
         name,
-        committee.clone(),
+        committee.clone().into(),
         store.clone(),
         synchronizer,
         signature_service,
         /* consensus_round */ Arc::new(AtomicU64::new(0)),
         /* gc_depth */ 50,
+        /* store_retention */ None,
+        /* breeze_epoch_limit */ 20,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
         /* rx_certificate_waiter */ rx_certificates_loopback,
         /* rx_proposer */ rx_headers,
         tx_consensus,
         /* tx_proposer */ tx_parents,
+        tx_evidence,
+        ReliableSender::new(),
     );
 
     // Make the certificate we expect to receive.
@@ -287,11 +298,10 @@ async fn process_certificates() {
     let (_tx_headers, rx_headers) = channel(1);
     let (tx_consensus, mut rx_consensus) = channel(3);
     let (tx_parents, mut rx_parents) = channel(1);
+    let (tx_evidence, _rx_evidence) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_process_certificates";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let store = Store::in_memory();
 
     // Make a synchronizer for the core.
     let synchronizer = Synchronizer::new(
@@ -305,18 +315,22 @@ async fn process_certificates() {
     // Spawn the core.
     Core::spawn(
         name,
-        committee(),
+        committee().into(),
         store.clone(),
         synchronizer,
         signature_service,
         /* consensus_round */ Arc::new(AtomicU64::new(0)),
         /* gc_depth */ 50,
+        /* store_retention */ None,
+        /* breeze_epoch_limit */ 20,
         /* rx_primaries */ rx_primary_messages,
         /* rx_header_waiter */ rx_headers_loopback,
         /* rx_certificate_waiter */ rx_certificates_loopback,
         /* rx_proposer */ rx_headers,
         tx_consensus,
         /* tx_proposer */ tx_parents,
+        tx_evidence,
+        ReliableSender::new(),
     );
 
     // Send enough certificates to the core.
@@ -346,7 +360,7 @@ async fn process_certificates() {
 
     // Ensure the certificates are stored.
     for x in &certificates {
-        let stored = store.read(x.digest().to_vec()).await.unwrap();
+        let stored = store.family(CERTIFICATES).read(x.digest().to_vec()).await.unwrap();
         let serialized = bincode::serialize(x).unwrap();
         assert_eq!(stored, Some(serialized));
     }
@@ -0,0 +1,71 @@
+use super::*;
+use crate::common::{committee, header, keys};
+use crate::messages::Header;
+use crypto::{Digest, Hash as _, Signature};
+use futures::stream::StreamExt as _;
+use network::TcpTransport;
+use model::types_and_const::MAX_EPOCH;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+// Fixture: a header of the same author and round as `header()`, with another payload.
+fn conflicting_header() -> Header {
+    let (_, secret) = keys().pop().unwrap();
+    let mut header = header();
+    header.payload.insert(Digest::default(), 0);
+    header.id = header.digest();
+    header.signature = Signature::new(&header.id, &secret);
+    header
+}
+
+// Fixture
+async fn query(address: SocketAddr, request: EvidenceRequest) -> Vec<Evidence> {
+    let stream = TcpStream::connect(address).await.unwrap();
+    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
+    // The receiver expects the flag of an uncompressed message first.
+    let mut bytes = vec![0x00];
+    bytes.extend(bincode::serialize(&request).unwrap());
+    transport.send(Bytes::from(bytes)).await.unwrap();
+    let reply = transport.next().await.unwrap().unwrap();
+    match bincode::deserialize(&reply).unwrap() {
+        EvidenceResponse::Evidence(evidence) => evidence,
+        EvidenceResponse::Error(e) => panic!("Unexpected error: {}", e),
+    }
+}
+
+#[tokio::test]
+async fn record_and_serve_evidence() {
+    MAX_EPOCH.get_or_init(|| 10);
+    let mut store = Store::in_memory();
+    let address = "127.0.0.1:13000".parse().unwrap();
+    let (tx_evidence, rx_evidence) = channel(1);
+    EvidenceCollector::spawn(committee().into(), store.clone(), Arc::new(TcpTransport), Some(address), rx_evidence);
+
+    // The same evidence twice is only recorded once.
+    let evidence = header().equivocation(&conflicting_header());
+    tx_evidence.send(evidence.clone()).await.unwrap();
+    tx_evidence.send(evidence.clone()).await.unwrap();
+
+    // Evidence blaming a node for messages it did not sign is dropped.
+    let (innocent, _) = keys().remove(0);
+    let forged = Evidence {
+        culprit: innocent,
+        ..evidence.clone()
+    };
+    tx_evidence.send(forged).await.unwrap();
+
+    // So is a header "conflicting" with itself.
+    tx_evidence.send(header().equivocation(&header())).await.unwrap();
+
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(query(address, EvidenceRequest::All).await, vec![evidence.clone()]);
+    assert_eq!(query(address, EvidenceRequest::Against(evidence.culprit)).await, vec![evidence.clone()]);
+    assert!(query(address, EvidenceRequest::Against(innocent)).await.is_empty());
+
+    // The evidence is persisted, to be served again after a restart.
+    let bytes = store.read(EVIDENCE_KEY.as_bytes().to_vec()).await.unwrap().unwrap();
+    let stored: Vec<Evidence> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(stored, vec![evidence]);
+}
@@ -0,0 +1,163 @@
+use super::*;
+use crate::common::{breeze_certificate, committee, keys};
+use config::Weighting;
+use crypto::generate_keypair;
+use rand::rngs::StdRng;
+use rand::SeedableRng as _;
+
+const BREEZE_EPOCH_LIMIT: u64 = 20;
+
+// Fixture
+fn header_with(round: Round, breeze_cer: BreezeCertificate) -> Header {
+    MAX_EPOCH.get_or_init(|| 10);
+    let (author, secret) = keys().pop().unwrap();
+    let header = Header {
+        author,
+        round,
+        breeze_cer: Some(breeze_cer),
+        ..Header::default()
+    };
+    Header {
+        id: header.digest(),
+        signature: Signature::new(&header.digest(), &secret),
+        ..header
+    }
+}
+
+#[test]
+fn verify_breeze_certificate() {
+    let header = header_with(1, breeze_certificate(1, 3));
+    assert!(header.verify(&committee().into(), BREEZE_EPOCH_LIMIT).is_ok());
+}
+
+#[test]
+fn breeze_certificate_without_quorum() {
+    let header = header_with(1, breeze_certificate(1, 2));
+    match header.verify(&committee().into(), BREEZE_EPOCH_LIMIT) {
+        Err(DagError::BreezeCertificateRequiresQuorum(id)) => assert_eq!(id, header.id),
+        x => panic!("Unexpected result: {:?}", x),
+    }
+}
+
+#[test]
+fn stake_weighted_breeze_certificate() {
+    // The three signers hold a quorum of authorities but not a quorum of the stake.
+    let mut committee = committee();
+    let (heavy, _) = keys().pop().unwrap();
+    committee.authorities.get_mut(&heavy).unwrap().stake = 10;
+    let header = header_with(1, breeze_certificate(1, 3));
+    assert!(header.verify(&committee.clone().into(), BREEZE_EPOCH_LIMIT).is_ok());
+
+    committee.weighting = Weighting::Stake;
+    match header.verify(&committee.into(), BREEZE_EPOCH_LIMIT) {
+        Err(DagError::BreezeCertificateRequiresQuorum(id)) => assert_eq!(id, header.id),
+        x => panic!("Unexpected result: {:?}", x),
+    }
+}
+
+#[test]
+fn breeze_certificate_from_unknown_authority() {
+    let mut cer = breeze_certificate(1, 3);
+    let mut rng = StdRng::from_seed([1; 32]);
+    let (unknown, secret) = generate_keypair(&mut rng);
+    cer.insert(unknown, Signature::new(&BreezeCertificate::signed_digest(&cer.c, &cer.dealer), &secret));
+    let header = header_with(1, cer);
+    match header.verify(&committee().into(), BREEZE_EPOCH_LIMIT) {
+        Err(DagError::BreezeCertificateUnknownAuthority(id, name)) => {
+            assert_eq!((id, name), (header.id, unknown))
+        }
+        x => panic!("Unexpected result: {:?}", x),
+    }
+}
+
+#[test]
+fn breeze_certificate_with_invalid_signature() {
+    let mut cer = breeze_certificate(1, 2);
+    let (name, secret) = keys().pop().unwrap();
+    cer.insert(name, Signature::new(&Digest([2; 32]), &secret));
+    let header = header_with(1, cer);
+    match header.verify(&committee().into(), BREEZE_EPOCH_LIMIT) {
+        Err(DagError::InvalidBreezeCertificateSignature(id, signer)) => {
+            assert_eq!((id, signer), (header.id, name))
+        }
+        x => panic!("Unexpected result: {:?}", x),
+    }
+}
+
+#[test]
+fn breeze_certificate_out_of_window() {
+    let header = header_with(1, breeze_certificate(1 + BREEZE_EPOCH_LIMIT, 3));
+    match header.verify(&committee().into(), BREEZE_EPOCH_LIMIT) {
+        Err(DagError::BreezeCertificateOutOfWindow(epoch, round)) => {
+            assert_eq!((epoch, round), (1 + BREEZE_EPOCH_LIMIT, 1))
+        }
+        x => panic!("Unexpected result: {:?}", x),
+    }
+
+    // Certificates of past epochs are never proposed either.
+    let max_epoch = *MAX_EPOCH.get().unwrap();
+    let header = header_with(2 * max_epoch + 1, breeze_certificate(1, 3));
+    assert!(matches!(
+        header.verify(&committee().into(), BREEZE_EPOCH_LIMIT),
+        Err(DagError::BreezeCertificateOutOfWindow(1, _))
+    ));
+}
+
+#[test]
+fn breeze_window_without_limit() {
+    // Without a limit, any later certificate may be proposed, but still no past one.
+    let max_epoch = *MAX_EPOCH.get_or_init(|| 10);
+    let round = 2 * max_epoch + 1;
+    assert!(!in_breeze_window(1, round, 0));
+    assert!(in_breeze_window(2, round, 0));
+    assert!(in_breeze_window(2 + BREEZE_EPOCH_LIMIT, round, 0));
+    assert!(!in_breeze_window(2 + BREEZE_EPOCH_LIMIT, round, BREEZE_EPOCH_LIMIT));
+
+    let header = header_with(round, breeze_certificate(1, 3));
+    assert!(matches!(
+        header.verify(&committee().into(), 0),
+        Err(DagError::BreezeCertificateOutOfWindow(1, _))
+    ));
+}
+
+#[test]
+fn verify_with_rotated_signing_key() {
+    let header = header_with(1, breeze_certificate(1, 3));
+    let committee: SharedCommittee = committee().into();
+    let mut rng = StdRng::from_seed([1; 32]);
+    let (key, secret) = generate_keypair(&mut rng);
+    committee
+        .rotate(header.author, round_epoch(header.round), key)
+        .unwrap();
+
+    // The identity of the author no longer signs for the epoch of the header.
+    assert!(matches!(
+        header.verify(&committee, BREEZE_EPOCH_LIMIT),
+        Err(DagError::InvalidSignature(_))
+    ));
+
+    let header = Header {
+        signature: Signature::new(&header.id, &secret),
+        ..header
+    };
+    assert!(header.verify(&committee, BREEZE_EPOCH_LIMIT).is_ok());
+}
+
+#[tokio::test]
+async fn key_rotation_signed_with_previous_key() {
+    let (name, secret) = keys().pop().unwrap();
+    let mut signature_service = SignatureService::new(secret);
+    let committee: SharedCommittee = committee().into();
+    let mut rng = StdRng::from_seed([1; 32]);
+    let (key, _) = generate_keypair(&mut rng);
+    let rotation = KeyRotation::new(name, 5, key, &mut signature_service).await;
+    assert!(rotation.verify(&committee).is_ok());
+
+    // Once another key takes over before epoch 5, the rotation must be signed with it.
+    let (other, _) = generate_keypair(&mut rng);
+    committee.rotate(name, 3, other).unwrap();
+    match rotation.verify(&committee) {
+        Err(DagError::InvalidKeyRotation(x, epoch)) => assert_eq!((x, epoch), (name, 5)),
+        x => panic!("Unexpected result: {:?}", x),
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
-use crate::common::{committee, keys};
+use crate::common::{breeze_certificate, committee, keys};
+use store::Store;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
@@ -8,33 +9,58 @@ async fn propose_empty() {
     let (name, secret) = keys().pop().unwrap();
     let signature_service = SignatureService::new(secret);
 
-    let (_tx_parents, rx_parents) = channel(1);
-    let (_tx_our_digests, rx_our_digests) = channel(1);
+    // Create a new test store.
+    let store = Store::in_memory();
+
+    let (tx_parents, rx_parents) = channel(1);
+    let (tx_our_digests, rx_our_digests) = channel(1);
     let (tx_headers, mut rx_headers) = channel(1);
     let (_tx_metadata, rx_metadata) = channel(1);
 
-    let (_breeze_sender,breeze_receiver) = channel(1);
+    let (breeze_sender, breeze_receiver) = channel(1);
+    let (_reconfiguration_sender, reconfiguration_receiver) = channel(1);
+    let (_key_rotation_sender, key_rotation_receiver) = channel(1);
+    let (_complaint_sender, complaint_receiver) = channel(1);
+    // The first header of an epoch waits for a breeze certificate of that epoch.
+    MAX_EPOCH.get_or_init(|| 10);
+    breeze_sender.send(breeze_certificate(1, 3)).await.unwrap();
+
     // Spawn the proposer.
     Proposer::spawn(
         name,
-        &committee(),
+        committee().into(),
         signature_service,
         /* header_size */ 1_000,
         /* max_header_delay */ 20,
         20,
+        Protocol::Tusk,
         /* rx_core */ rx_parents,
         /* rx_workers */ rx_our_digests,
         /* tx_core */ tx_headers,
         /* rx_consensus */ rx_metadata,
         
-        breeze_receiver
+        breeze_receiver,
+        reconfiguration_receiver,
+        key_rotation_receiver,
+        complaint_receiver,
+        KeyStore::new(store),
     );
 
-    // Ensure the proposer makes a correct empty header.
+    // The proposer waits for a first batch before proposing.
+    tx_our_digests.send((Digest([0; 32]), 0)).await.unwrap();
     let header = rx_headers.recv().await.unwrap();
     assert_eq!(header.round, 1);
+
+    // Ensure the proposer makes a correct empty header once it has the parents of the next round.
+    let parents = Certificate::genesis(&committee())
+        .iter()
+        .map(|x| x.digest())
+        .collect();
+    tx_parents.send((parents, 1)).await.unwrap();
+    let header = rx_headers.recv().await.unwrap();
+    assert_eq!(header.round, 2);
     assert!(header.payload.is_empty());
-    assert!(header.verify(&committee()).is_ok());
+    assert!(header.verify(&committee().into(), 20).is_ok());
 }
 
 #[tokio::test]
@@ -42,26 +68,41 @@ async fn propose_payload() {
     let (name, secret) = keys().pop().unwrap();
     let signature_service = SignatureService::new(secret);
 
+    // Create a new test store.
+    let store = Store::in_memory();
+
     let (_tx_parents, rx_parents) = channel(1);
     let (tx_our_digests, rx_our_digests) = channel(1);
     let (tx_headers, mut rx_headers) = channel(1);
     let (_tx_metadata, rx_metadata) = channel(1);
 
-    let (_breeze_sender,breeze_receiver) = channel(1);
+    let (breeze_sender, breeze_receiver) = channel(1);
+    let (_reconfiguration_sender, reconfiguration_receiver) = channel(1);
+    let (_key_rotation_sender, key_rotation_receiver) = channel(1);
+    let (_complaint_sender, complaint_receiver) = channel(1);
+    // The first header of an epoch waits for a breeze certificate of that epoch.
+    MAX_EPOCH.get_or_init(|| 10);
+    breeze_sender.send(breeze_certificate(1, 3)).await.unwrap();
+
     // Spawn the proposer.
     Proposer::spawn(
         name,
-        &committee(),
+        committee().into(),
         signature_service,
         /* header_size */ 32,
         /* max_header_delay */ 1_000_000, // Ensure it is not triggered.
         20,
+        Protocol::Tusk,
         /* rx_core */ rx_parents,
         /* rx_workers */ rx_our_digests,
         /* tx_core */ tx_headers,
         /* rx_consensus */ rx_metadata,
 
-        breeze_receiver
+        breeze_receiver,
+        reconfiguration_receiver,
+        key_rotation_receiver,
+        complaint_receiver,
+        KeyStore::new(store),
     );
 
     // Send enough digests for the header payload.
@@ -79,5 +120,5 @@ async fn propose_payload() {
     let header = rx_headers.recv().await.unwrap();
     assert_eq!(header.round, 1);
     assert_eq!(header.payload.get(&digest), Some(&worker_id));
-    assert!(header.verify(&committee()).is_ok());
+    assert!(header.verify(&committee().into(), 20).is_ok());
 }
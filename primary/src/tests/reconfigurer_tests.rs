@@ -0,0 +1,133 @@
+use super::*;
+use crate::common::{committee, committee_with_base_port, keys};
+use crate::messages::Header;
+use crate::reconfiguration_store::ReconfigurationStore;
+use crypto::SignatureService;
+use model::types_and_const::{Round, MAX_EPOCH};
+use store::Store;
+
+const BREEZE_EPOCH_LIMIT: u64 = 20;
+
+// Fixture
+fn proposal(author: PublicKey, round: Round, reconfiguration: &Reconfiguration) -> Certificate {
+    MAX_EPOCH.get_or_init(|| 10);
+    Certificate {
+        header: Header {
+            author,
+            round,
+            reconfiguration: Some(reconfiguration.clone()),
+            ..Header::default()
+        },
+        ..Certificate::default()
+    }
+}
+
+// Fixture
+fn rotation(author: PublicKey, round: Round, key_rotation: &KeyRotation) -> Certificate {
+    MAX_EPOCH.get_or_init(|| 10);
+    Certificate {
+        header: Header {
+            author,
+            round,
+            key_rotation: Some(key_rotation.clone()),
+            ..Header::default()
+        },
+        ..Certificate::default()
+    }
+}
+
+#[test]
+fn install_with_validity_threshold() {
+    let shared = SharedCommittee::new(committee());
+    let mut reconfigurer = Reconfigurer::new(shared.clone(), BREEZE_EPOCH_LIMIT, Vec::new());
+    let reconfiguration = Reconfiguration {
+        epoch: 100,
+        committee: committee_with_base_port(1_000),
+    };
+    let mut names = keys().into_iter().map(|(name, _)| name);
+
+    // A single proposer (or the same one twice) does not reach f+1 stake.
+    let first = names.next().unwrap();
+    assert!(reconfigurer.process(&proposal(first, 1, &reconfiguration)).is_none());
+    assert!(reconfigurer.process(&proposal(first, 2, &reconfiguration)).is_none());
+    assert_eq!(shared.latest().0, 0);
+
+    let second = names.next().unwrap();
+    let installed = reconfigurer.process(&proposal(second, 3, &reconfiguration));
+    assert_eq!(installed, Some(reconfiguration.clone()));
+    assert_eq!(*shared.at(99), committee());
+    assert_eq!(*shared.at(100), reconfiguration.committee);
+}
+
+#[test]
+fn reject_late_reconfiguration() {
+    let shared = SharedCommittee::new(committee());
+    let mut reconfigurer = Reconfigurer::new(shared.clone(), BREEZE_EPOCH_LIMIT, Vec::new());
+    // Round 1 belongs to epoch 1: the earliest epoch a committee may take over is 1 + limit + 2.
+    let reconfiguration = Reconfiguration {
+        epoch: BREEZE_EPOCH_LIMIT + 2,
+        committee: committee_with_base_port(1_000),
+    };
+    for (name, _) in keys() {
+        assert!(reconfigurer.process(&proposal(name, 1, &reconfiguration)).is_none());
+    }
+    assert_eq!(shared.latest().0, 0);
+}
+
+#[tokio::test]
+async fn install_key_rotation() {
+    MAX_EPOCH.get_or_init(|| 10);
+    let shared = SharedCommittee::new(committee());
+    let mut reconfigurer = Reconfigurer::new(shared.clone(), BREEZE_EPOCH_LIMIT, Vec::new());
+    let (name, secret) = keys().pop().unwrap();
+    let mut signature_service = SignatureService::new(secret);
+    let (key, _) = keys().remove(0);
+
+    // Round 1 belongs to epoch 1: a key committed there takes over at epoch 1 + limit + 2 at best.
+    let late = KeyRotation::new(name, BREEZE_EPOCH_LIMIT + 2, key, &mut signature_service).await;
+    assert!(reconfigurer.process_key_rotation(&rotation(name, 1, &late)).is_none());
+    assert_eq!(shared.signing_key(&name, BREEZE_EPOCH_LIMIT + 2), name);
+
+    let epoch = BREEZE_EPOCH_LIMIT + 3;
+    let on_time = KeyRotation::new(name, epoch, key, &mut signature_service).await;
+    let installed = reconfigurer.process_key_rotation(&rotation(name, 1, &on_time));
+    assert_eq!(installed, Some(on_time));
+    assert_eq!(shared.signing_key(&name, epoch - 1), name);
+    assert_eq!(shared.signing_key(&name, epoch), key);
+}
+
+#[tokio::test]
+async fn restore_after_restart() {
+    let store = Store::in_memory();
+    let mut reconfiguration_store = ReconfigurationStore::new(store.clone());
+    let shared = SharedCommittee::new(committee());
+    let mut reconfigurer = Reconfigurer::new(shared.clone(), BREEZE_EPOCH_LIMIT, Vec::new());
+    let installed = Reconfiguration {
+        epoch: 100,
+        committee: committee_with_base_port(1_000),
+    };
+    let pending = Reconfiguration {
+        epoch: 200,
+        committee: committee_with_base_port(2_000),
+    };
+    let mut names = keys().into_iter().map(|(name, _)| name);
+    let (first, second) = (names.next().unwrap(), names.next().unwrap());
+
+    reconfigurer.process(&proposal(first, 1, &installed));
+    let reconfiguration = reconfigurer.process(&proposal(second, 2, &installed)).unwrap();
+    reconfiguration_store.persist_reconfiguration(&reconfiguration).await;
+    reconfigurer.process(&proposal(first, 3, &pending));
+    reconfiguration_store.persist_proposals(reconfigurer.proposals()).await;
+
+    // The restarted node reinstalls the committee, and installs the pending one with one more proposer.
+    let shared = SharedCommittee::new(committee());
+    let mut reconfiguration_store = ReconfigurationStore::new(store);
+    let (restored, proposals) = reconfiguration_store.restore(&shared).await;
+    assert_eq!(restored, vec![installed.clone()]);
+    assert_eq!(*shared.at(100), installed.committee);
+
+    let mut reconfigurer = Reconfigurer::new(shared.clone(), BREEZE_EPOCH_LIMIT, proposals);
+    let reconfiguration = reconfigurer.process(&proposal(second, 4, &pending));
+    assert_eq!(reconfiguration, Some(pending.clone()));
+    assert_eq!(*shared.at(200), pending.committee);
+}
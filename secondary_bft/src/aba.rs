@@ -0,0 +1,222 @@
+use crate::coin::{self, Coin};
+use config::Committee;
+use crypto::PublicKey;
+use model::bft_message::{DumboContent, View};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The messages received in one round of a binary agreement.
+#[derive(Default)]
+struct RoundState {
+    bvals: [HashSet<PublicKey>; 2],
+    bval_sent: [bool; 2],
+    bin_values: BTreeSet<bool>,
+    aux: HashMap<PublicKey, bool>,
+    aux_sent: bool,
+    coin_sent: bool,
+}
+
+/// Asynchronous binary agreement (Mostéfaoui, Moumen and Raynal) run in each view of the MVBA.
+/// Every round goes through a `BVal` phase, which only lets values proposed by honest nodes into
+/// `bin_values`, and an `Aux` phase, after which the nodes agreeing on a single value decide it if
+/// it matches the coin. The share of the coin of a round is only released once its `Aux` phase
+/// completes, so that the coin is unknown while the adversary can still steer the values.
+/// `Term` messages let the other nodes catch up with a decision and tell when the agreement can
+/// halt.
+pub struct Aba {
+    view: View,
+    round: u64,
+    /// The estimate of the current round, `None` until the agreement gets its input.
+    estimate: Option<bool>,
+    rounds: HashMap<u64, RoundState>,
+    decision: Option<bool>,
+    terms: [HashSet<PublicKey>; 2],
+    halted: bool,
+}
+
+impl Aba {
+    pub fn new(view: View) -> Self {
+        Self {
+            view,
+            round: 0,
+            estimate: None,
+            rounds: HashMap::new(),
+            decision: None,
+            terms: [HashSet::new(), HashSet::new()],
+            halted: false,
+        }
+    }
+
+    pub fn decision(&self) -> Option<bool> {
+        self.decision
+    }
+
+    pub fn started(&self) -> bool {
+        self.estimate.is_some()
+    }
+
+    /// Starts the agreement with our `input`. Returns the messages to broadcast.
+    pub fn start<C: Coin>(&mut self, committee: &Committee, coin: &C, input: bool) -> Vec<DumboContent> {
+        if self.started() {
+            return Vec::new();
+        }
+        self.estimate = Some(input);
+        let mut messages = vec![self.send_bval(0, input)];
+        messages.extend(self.progress(committee, coin));
+        messages
+    }
+
+    pub fn handle_bval<C: Coin>(
+        &mut self,
+        committee: &Committee,
+        coin: &C,
+        sender: PublicKey,
+        round: u64,
+        value: bool,
+    ) -> Vec<DumboContent> {
+        let state = self.rounds.entry(round).or_default();
+        state.bvals[value as usize].insert(sender);
+        self.progress(committee, coin)
+    }
+
+    pub fn handle_aux<C: Coin>(
+        &mut self,
+        committee: &Committee,
+        coin: &C,
+        sender: PublicKey,
+        round: u64,
+        value: bool,
+    ) -> Vec<DumboContent> {
+        let state = self.rounds.entry(round).or_default();
+        state.aux.entry(sender).or_insert(value);
+        self.progress(committee, coin)
+    }
+
+    pub fn handle_term<C: Coin>(
+        &mut self,
+        committee: &Committee,
+        coin: &C,
+        sender: PublicKey,
+        value: bool,
+    ) -> Vec<DumboContent> {
+        let terms = &mut self.terms[value as usize];
+        terms.insert(sender);
+        let weight = committee.weight_of(terms.iter());
+
+        let mut messages = Vec::new();
+        // At least one honest node decided `value`.
+        if weight >= committee.weighted_validity_threshold() {
+            messages.extend(self.decide(value));
+        }
+        // Every honest node is bound to decide, so nobody needs our messages anymore.
+        if weight >= committee.weighted_quorum_threshold() {
+            self.halted = true;
+        }
+        messages.extend(self.progress(committee, coin));
+        messages
+    }
+
+    /// Resumes the round waiting for its coin, once a share of it came in.
+    pub fn handle_coin<C: Coin>(&mut self, committee: &Committee, coin: &C) -> Vec<DumboContent> {
+        self.progress(committee, coin)
+    }
+
+    fn send_bval(&mut self, round: u64, value: bool) -> DumboContent {
+        let state = self.rounds.entry(round).or_default();
+        state.bval_sent[value as usize] = true;
+        DumboContent::BVal {
+            view: self.view,
+            round,
+            value,
+        }
+    }
+
+    fn decide(&mut self, value: bool) -> Option<DumboContent> {
+        if self.decision.is_some() {
+            return None;
+        }
+        self.decision = Some(value);
+        Some(DumboContent::Term {
+            view: self.view,
+            value,
+        })
+    }
+
+    /// Moves through the rounds as far as the messages received so far allow.
+    fn progress<C: Coin>(&mut self, committee: &Committee, coin: &C) -> Vec<DumboContent> {
+        let validity_threshold = committee.weighted_validity_threshold();
+        let quorum_threshold = committee.weighted_quorum_threshold();
+        let mut messages = Vec::new();
+
+        while !self.halted && self.started() {
+            let round = self.round;
+            let state = self.rounds.entry(round).or_default();
+
+            // Relay the values backed by f+1 and accept the ones backed by 2f+1.
+            for value in [false, true] {
+                let weight = committee.weight_of(state.bvals[value as usize].iter());
+                if weight >= validity_threshold && !state.bval_sent[value as usize] {
+                    state.bval_sent[value as usize] = true;
+                    messages.push(DumboContent::BVal {
+                        view: self.view,
+                        round,
+                        value,
+                    });
+                }
+                if weight >= quorum_threshold {
+                    state.bin_values.insert(value);
+                }
+            }
+            let first = match state.bin_values.iter().next() {
+                Some(value) => *value,
+                None => break,
+            };
+            if !state.aux_sent {
+                state.aux_sent = true;
+                messages.push(DumboContent::Aux {
+                    view: self.view,
+                    round,
+                    value: first,
+                });
+            }
+
+            // Wait for a quorum of `Aux` messages carrying values of `bin_values`.
+            let supporters: Vec<_> = state
+                .aux
+                .iter()
+                .filter(|(_, value)| state.bin_values.contains(value))
+                .collect();
+            if committee.weight_of(supporters.iter().map(|(name, _)| *name)) < quorum_threshold {
+                break;
+            }
+            let values: BTreeSet<bool> = supporters.iter().map(|(_, value)| **value).collect();
+
+            if !state.coin_sent {
+                state.coin_sent = true;
+                messages.push(DumboContent::Coin {
+                    view: self.view,
+                    round: Some(round),
+                    share: coin.share(self.view, Some(round)),
+                });
+            }
+            let toss = match coin.value(self.view, Some(round)) {
+                Some(value) => coin::toss(&value),
+                None => break,
+            };
+            let estimate = if values.len() == 1 {
+                let value = *values.iter().next().unwrap();
+                if value == toss {
+                    messages.extend(self.decide(value));
+                }
+                value
+            } else {
+                toss
+            };
+            self.round += 1;
+            self.estimate = Some(estimate);
+            if !self.rounds.entry(self.round).or_default().bval_sent[estimate as usize] {
+                messages.push(self.send_bval(self.round, estimate));
+            }
+        }
+        messages
+    }
+}
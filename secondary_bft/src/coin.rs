@@ -0,0 +1,94 @@
+use config::Committee;
+use crypto::PublicKey;
+use model::bft_message::View;
+use model::threshold_coin::{CoinKey, CoinShare};
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(test)]
+#[path = "tests/coin_tests.rs"]
+pub mod coin_tests;
+
+/// The common randomness of the MVBA: the coin electing the leader of each view (`round` is
+/// `None`) and the one tossed in each round of its binary agreement. A coin is only known once the
+/// nodes released enough shares of it, so that the adversary cannot adapt its schedule to it.
+pub trait Coin: Send + 'static {
+    /// Our share of the coin.
+    fn share(&self, view: View, round: Option<u64>) -> CoinShare;
+
+    /// Adds the share of `holder`. Returns false if it is not a valid share.
+    fn add_share(&mut self, holder: PublicKey, view: View, round: Option<u64>, share: CoinShare) -> bool;
+
+    /// The value of the coin, once enough shares of it came in.
+    fn value(&self, view: View, round: Option<u64>) -> Option<[u8; 32]>;
+}
+
+/// Elects the leader of a view out of the value of its coin.
+pub fn elect(committee: &Committee, value: &[u8; 32]) -> PublicKey {
+    let seed = u64::from_le_bytes(value[..8].try_into().unwrap());
+    let index = (seed % committee.size() as u64) as usize;
+    *committee.authorities.keys().nth(index).unwrap()
+}
+
+/// The bit tossed by the value of a coin.
+pub fn toss(value: &[u8; 32]) -> bool {
+    value[0] & 1 == 1
+}
+
+/// A threshold coin, dealt among the members of the committee of epoch 0 (there is no beacon to
+/// draw from before the common core of epoch 0 is decided). It is dealt by count, so it only
+/// serves committees counting every authority for one.
+pub struct ThresholdCoin {
+    key: CoinKey,
+    /// The valid shares received for each coin.
+    shares: HashMap<(View, Option<u64>), BTreeMap<PublicKey, CoinShare>>,
+    values: HashMap<(View, Option<u64>), [u8; 32]>,
+}
+
+impl ThresholdCoin {
+    pub fn new(key: CoinKey) -> Self {
+        Self {
+            key,
+            shares: HashMap::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    fn name(view: View, round: Option<u64>) -> Vec<u8> {
+        let mut name = b"dumbo".to_vec();
+        name.extend(view.to_le_bytes());
+        match round {
+            Some(round) => {
+                name.push(1);
+                name.extend(round.to_le_bytes());
+            }
+            None => name.push(0),
+        }
+        name
+    }
+}
+
+impl Coin for ThresholdCoin {
+    fn share(&self, view: View, round: Option<u64>) -> CoinShare {
+        self.key.share(&Self::name(view, round))
+    }
+
+    fn add_share(&mut self, holder: PublicKey, view: View, round: Option<u64>, share: CoinShare) -> bool {
+        if self.values.contains_key(&(view, round)) {
+            return true;
+        }
+        if !self.key.verify(&holder, &Self::name(view, round), &share) {
+            return false;
+        }
+        let shares = self.shares.entry((view, round)).or_default();
+        shares.insert(holder, share);
+        if let Some(value) = self.key.combine(shares.iter()) {
+            self.shares.remove(&(view, round));
+            self.values.insert((view, round), value);
+        }
+        true
+    }
+
+    fn value(&self, view: View, round: Option<u64>) -> Option<[u8; 32]> {
+        self.values.get(&(view, round)).copied()
+    }
+}
@@ -1 +1,8 @@
+pub mod aba;
+pub mod coin;
 pub mod init_bft;
+pub mod mvba;
+
+#[cfg(test)]
+#[path = "tests/common.rs"]
+mod common;
@@ -0,0 +1,448 @@
+use crate::aba::Aba;
+use crate::coin::{self, Coin};
+use config::Committee;
+use crypto::{Digest, PublicKey, SecretKey, Signature};
+use log::{debug, info, warn};
+use model::bft_message::{DumboContent, DumboMessage, FinishProof, View};
+use model::breeze_universal::BreezeCertificate;
+use model::evidence::{Evidence, Misbehaviour, SignedMessage};
+use sha2::{Digest as ShaDigest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[cfg(test)]
+#[path = "tests/mvba_tests.rs"]
+pub mod mvba_tests;
+
+type Proposal = (BTreeSet<BreezeCertificate>, FinishProof);
+/// A vote as received: its sender, the leader it names, its proposal and its signature.
+type ReceivedVote = (PublicKey, PublicKey, Option<Proposal>, Signature);
+
+/// Where the messages produced by the state machine must go.
+#[derive(Debug)]
+pub enum Dispatch {
+    Broadcast(DumboMessage),
+    Send(PublicKey, DumboMessage),
+}
+
+/// The phases a node goes through, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Gather the certificates disseminated by the dealers.
+    Disseminate,
+    /// Our candidate common core is out, wait for a quorum of echoes.
+    Propose,
+    /// Our proposal finished, wait for a quorum of proposals to finish.
+    Finish,
+    /// Our share of the coin of the view is out, wait for the coin to elect its leader.
+    Elect(View),
+    /// Wait for a quorum of votes on the leader of the view.
+    Vote(View),
+    /// Agree on whether to decide the proposal of the leader of the view.
+    Agree(View),
+    Decided,
+}
+
+/// A Dumbo-style multi-valued validated Byzantine agreement deciding the common core of epoch 0.
+///
+/// Every node proposes a candidate common core through a provable broadcast: the proposal finishes
+/// once a quorum signed an echo for it, and the echo signatures make a transferable `FinishProof`.
+/// Once a quorum of proposals finished, the views start: the nodes release their shares of the
+/// threshold coin of the view, which elects a leader nobody could predict before, the nodes vote
+/// with its proposal if they know it finished, and a binary agreement decides whether to output it
+/// or to move to the next view. Two finished proposals of the same node are echoed by intersecting
+/// quorums, so the decided value is unique; an honest node inputs 1 only if it holds the proof, so
+/// the value is always retrievable from the votes.
+pub struct Mvba<C: Coin> {
+    name: PublicKey,
+    secret: SecretKey,
+    committee: Committee,
+    coin: C,
+    phase: Phase,
+    /// The certificate of the dealing of each dealer, whoever relayed it.
+    certificates: HashMap<PublicKey, BreezeCertificate>,
+    /// The proposal (and its digest) of each proposer, including ours.
+    proposals: HashMap<PublicKey, (BTreeSet<BreezeCertificate>, Digest)>,
+    /// The proposers whose proposal we echoed.
+    echoed: HashSet<PublicKey>,
+    /// The echoes gathered for our own proposal.
+    echoes: BTreeSet<(PublicKey, Signature)>,
+    /// The proposals known to have finished.
+    finished: HashMap<PublicKey, FinishProof>,
+    votes: HashMap<View, HashSet<PublicKey>>,
+    /// The first vote of each voter in each view, as it signed it.
+    signed_votes: HashMap<View, HashMap<PublicKey, SignedMessage>>,
+    /// A finished proposal of the leader of each view, taken from the votes.
+    candidates: HashMap<View, Proposal>,
+    /// The votes received before the coin electing the leader of their view.
+    early_votes: HashMap<View, Vec<ReceivedVote>>,
+    abas: HashMap<View, Aba>,
+    decision: Option<BTreeSet<BreezeCertificate>>,
+    /// The evidence of misbehaviour not yet taken by the caller.
+    evidence: Vec<Evidence>,
+}
+
+impl<C: Coin> Mvba<C> {
+    pub fn new(name: PublicKey, secret: SecretKey, committee: Committee, coin: C) -> Self {
+        Self {
+            name,
+            secret,
+            committee,
+            coin,
+            phase: Phase::Disseminate,
+            certificates: HashMap::new(),
+            proposals: HashMap::new(),
+            echoed: HashSet::new(),
+            echoes: BTreeSet::new(),
+            finished: HashMap::new(),
+            votes: HashMap::new(),
+            signed_votes: HashMap::new(),
+            candidates: HashMap::new(),
+            early_votes: HashMap::new(),
+            abas: HashMap::new(),
+            decision: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn decision(&self) -> Option<&BTreeSet<BreezeCertificate>> {
+        self.decision.as_ref()
+    }
+
+    /// Takes the evidence of misbehaviour found since the last call.
+    pub fn take_evidence(&mut self) -> Vec<Evidence> {
+        std::mem::take(&mut self.evidence)
+    }
+
+    /// Disseminates the certificate of our own dealing.
+    pub fn disseminate(&mut self, certificate: BreezeCertificate) -> Vec<Dispatch> {
+        vec![Dispatch::Broadcast(self.sign(DumboContent::Certificate(certificate)))]
+    }
+
+    /// Handles a message of another node (or one of ours, looped back by the network).
+    pub fn handle(&mut self, message: DumboMessage) -> Vec<Dispatch> {
+        if !self.verify_message(&message) {
+            warn!("Invalid message from {} in the MVBA", message.sender);
+            return Vec::new();
+        }
+        let sender = message.sender;
+        let signature = message.signature;
+        let mut contents = Vec::new();
+        match message.content {
+            DumboContent::Certificate(certificate) => {
+                if self.is_valid_certificate(&certificate) {
+                    self.certificates.entry(certificate.dealer).or_insert(certificate);
+                }
+            }
+            DumboContent::Proposal(value) => return self.handle_proposal(sender, value),
+            DumboContent::Echo {
+                proposer,
+                digest,
+                signature,
+            } => self.handle_echo(sender, proposer, digest, signature),
+            DumboContent::Finish(proof) => {
+                if proof.proposer == sender && self.verify_finish(&proof) {
+                    self.finished.entry(sender).or_insert(proof);
+                }
+            }
+            DumboContent::Vote {
+                view,
+                leader,
+                proposal,
+            } => self.handle_vote(sender, view, leader, proposal, signature),
+            DumboContent::BVal { view, round, value } => {
+                let aba = self.abas.entry(view).or_insert_with(|| Aba::new(view));
+                contents.extend(aba.handle_bval(&self.committee, &self.coin, sender, round, value));
+            }
+            DumboContent::Aux { view, round, value } => {
+                let aba = self.abas.entry(view).or_insert_with(|| Aba::new(view));
+                contents.extend(aba.handle_aux(&self.committee, &self.coin, sender, round, value));
+            }
+            DumboContent::Term { view, value } => {
+                let aba = self.abas.entry(view).or_insert_with(|| Aba::new(view));
+                contents.extend(aba.handle_term(&self.committee, &self.coin, sender, value));
+            }
+            DumboContent::Coin { view, round, share } => {
+                if !self.coin.add_share(sender, view, round, share) {
+                    warn!("Invalid coin share of {} in view {}", sender, view);
+                } else if round.is_some() {
+                    if let Some(aba) = self.abas.get_mut(&view) {
+                        contents.extend(aba.handle_coin(&self.committee, &self.coin));
+                    }
+                } else if self.coin.value(view, None).is_some() {
+                    for (sender, leader, proposal, signature) in self.early_votes.remove(&view).unwrap_or_default() {
+                        self.handle_vote(sender, view, leader, proposal, signature);
+                    }
+                }
+            }
+        }
+        let mut dispatches: Vec<_> = contents
+            .into_iter()
+            .map(|content| Dispatch::Broadcast(self.sign(content)))
+            .collect();
+        dispatches.extend(self.advance());
+        dispatches
+    }
+
+    fn handle_proposal(&mut self, sender: PublicKey, value: BTreeSet<BreezeCertificate>) -> Vec<Dispatch> {
+        // Echo a single proposal per proposer, and only if it is a valid common core.
+        if self.echoed.contains(&sender) || !self.is_valid_core(&value) {
+            return Vec::new();
+        }
+        let digest = Self::hash_value(&value);
+        self.echoed.insert(sender);
+        self.proposals.entry(sender).or_insert((value, digest));
+        let signature = Signature::new(&Self::echo_digest(&sender, &digest), &self.secret);
+        let echo = DumboContent::Echo {
+            proposer: sender,
+            digest,
+            signature,
+        };
+        vec![Dispatch::Send(sender, self.sign(echo))]
+    }
+
+    fn handle_echo(&mut self, sender: PublicKey, proposer: PublicKey, digest: Digest, signature: Signature) {
+        if proposer != self.name {
+            return;
+        }
+        let ours = match self.proposals.get(&self.name) {
+            Some((_, ours)) => *ours,
+            None => return,
+        };
+        if digest == ours
+            && signature.verify(&Self::echo_digest(&proposer, &digest), &sender).is_ok()
+            && !self.echoes.iter().any(|(name, _)| *name == sender)
+        {
+            self.echoes.insert((sender, signature));
+        }
+    }
+
+    fn handle_vote(
+        &mut self,
+        sender: PublicKey,
+        view: View,
+        leader: PublicKey,
+        proposal: Option<Proposal>,
+        signature: Signature,
+    ) {
+        // The leader of the view is unknown until enough nodes released their share of its coin.
+        let elected = match self.coin.value(view, None) {
+            Some(value) => coin::elect(&self.committee, &value),
+            None => {
+                self.early_votes.entry(view).or_default().push((sender, leader, proposal, signature));
+                return;
+            }
+        };
+
+        // Keep the vote as signed by its sender, to hold it against the sender if needed. The
+        // signing keys of the MVBA are the ones of epoch 0.
+        let vote = DumboContent::Vote {
+            view,
+            leader,
+            proposal: proposal.clone(),
+        };
+        let signed = SignedMessage::new(&(sender, &vote), Self::message_digest(&sender, &vote), signature);
+
+        let valid = leader == elected
+            && proposal.as_ref().is_none_or(|(value, proof)| {
+                proof.proposer == leader && proof.digest == Self::hash_value(value) && self.verify_finish(proof)
+            });
+        if !valid {
+            warn!("Invalid vote of {} in view {}", sender, view);
+            self.evidence.push(Evidence::new(sender, 0, Misbehaviour::InvalidVote(signed)));
+            return;
+        }
+        match self.signed_votes.entry(view).or_default().entry(sender) {
+            Entry::Occupied(first) => {
+                if first.get().digest != signed.digest {
+                    warn!("Conflicting votes of {} in view {}", sender, view);
+                    let misbehaviour = Misbehaviour::Equivocation(first.get().clone(), signed);
+                    self.evidence.push(Evidence::new(sender, 0, misbehaviour));
+                }
+                return;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(signed);
+            }
+        }
+        if let Some((value, proof)) = proposal {
+            self.candidates.entry(view).or_insert((value, proof));
+        }
+        self.votes.entry(view).or_default().insert(sender);
+    }
+
+    /// Moves through the phases as far as the messages received so far allow.
+    fn advance(&mut self) -> Vec<Dispatch> {
+        let validity_threshold = self.committee.weighted_validity_threshold();
+        let quorum_threshold = self.committee.weighted_quorum_threshold();
+        let mut dispatches = Vec::new();
+        loop {
+            match self.phase {
+                Phase::Disseminate => {
+                    // Propose the certificates we have once their dealers carry f+1 of the weight.
+                    if self.committee.weight_of(self.certificates.keys()) < validity_threshold {
+                        break;
+                    }
+                    let value: BTreeSet<_> = self.certificates.values().cloned().collect();
+                    debug!("Proposing a common core of {} certificates", value.len());
+                    self.proposals.insert(self.name, (value.clone(), Self::hash_value(&value)));
+                    dispatches.push(Dispatch::Broadcast(self.sign(DumboContent::Proposal(value))));
+                    self.phase = Phase::Propose;
+                }
+                Phase::Propose => {
+                    let echoers = self.echoes.iter().map(|(name, _)| name);
+                    if self.committee.weight_of(echoers) < quorum_threshold {
+                        break;
+                    }
+                    let proof = FinishProof {
+                        proposer: self.name,
+                        digest: self.proposals[&self.name].1,
+                        echoes: self.echoes.clone(),
+                    };
+                    dispatches.push(Dispatch::Broadcast(self.sign(DumboContent::Finish(proof))));
+                    self.phase = Phase::Finish;
+                }
+                Phase::Finish => {
+                    if self.committee.weight_of(self.finished.keys()) < quorum_threshold {
+                        break;
+                    }
+                    dispatches.push(self.elect(0));
+                }
+                Phase::Elect(view) => match self.coin.value(view, None) {
+                    Some(value) => {
+                        let leader = coin::elect(&self.committee, &value);
+                        dispatches.push(self.vote(view, leader));
+                    }
+                    None => break,
+                },
+                Phase::Vote(view) => {
+                    let voters = self.votes.get(&view).into_iter().flatten();
+                    if self.committee.weight_of(voters) < quorum_threshold {
+                        break;
+                    }
+                    let input = self.candidates.contains_key(&view);
+                    let aba = self.abas.entry(view).or_insert_with(|| Aba::new(view));
+                    let contents = aba.start(&self.committee, &self.coin, input);
+                    dispatches.extend(contents.into_iter().map(|content| Dispatch::Broadcast(self.sign(content))));
+                    self.phase = Phase::Agree(view);
+                }
+                Phase::Agree(view) => match self.abas[&view].decision() {
+                    Some(true) => {
+                        // Some honest node voted with the proposal, so its vote eventually arrives.
+                        let (value, _) = match self.candidates.get(&view) {
+                            Some(candidate) => candidate.clone(),
+                            None => break,
+                        };
+                        info!("Common core of epoch 0 decided in view {}", view);
+                        self.decision = Some(value);
+                        self.phase = Phase::Decided;
+                    }
+                    Some(false) => dispatches.push(self.elect(view + 1)),
+                    None => break,
+                },
+                Phase::Decided => break,
+            }
+        }
+        dispatches
+    }
+
+    /// Enters `view` and releases our share of the coin electing its leader.
+    fn elect(&mut self, view: View) -> Dispatch {
+        self.phase = Phase::Elect(view);
+        let share = self.coin.share(view, None);
+        Dispatch::Broadcast(self.sign(DumboContent::Coin {
+            view,
+            round: None,
+            share,
+        }))
+    }
+
+    /// Votes on the leader of `view`.
+    fn vote(&mut self, view: View, leader: PublicKey) -> Dispatch {
+        debug!("Leader of view {} is {}", view, leader);
+        let proposal = self.candidates.get(&view).cloned().or_else(|| {
+            match (self.finished.get(&leader), self.proposals.get(&leader)) {
+                (Some(proof), Some((value, digest))) if proof.digest == *digest => {
+                    Some((value.clone(), proof.clone()))
+                }
+                _ => None,
+            }
+        });
+        self.phase = Phase::Vote(view);
+        Dispatch::Broadcast(self.sign(DumboContent::Vote {
+            view,
+            leader,
+            proposal,
+        }))
+    }
+
+    /// A valid common core holds valid certificates of distinct dealers carrying f+1 of the
+    /// weight.
+    fn is_valid_core(&self, value: &BTreeSet<BreezeCertificate>) -> bool {
+        let dealers: HashSet<_> = value.iter().map(|certificate| &certificate.dealer).collect();
+        dealers.len() == value.len()
+            && self.committee.weight_of(dealers) >= self.committee.weighted_validity_threshold()
+            && value.iter().all(|certificate| self.is_valid_certificate(certificate))
+    }
+
+    /// A valid certificate is the one of an epoch-0 dealing of a member, signed by a quorum.
+    fn is_valid_certificate(&self, certificate: &BreezeCertificate) -> bool {
+        // Signing keys only rotate after epoch 0, so members still sign with their identity.
+        certificate.epoch == 0
+            && self.committee.weight(&certificate.dealer) > 0
+            && certificate
+                .verify(|pk| self.committee.weight(pk), |pk| *pk, self.committee.weighted_quorum_threshold())
+                .is_ok()
+    }
+
+    fn verify_finish(&self, proof: &FinishProof) -> bool {
+        let digest = Self::echo_digest(&proof.proposer, &proof.digest);
+        let echoers: HashSet<_> = proof.echoes.iter().map(|(name, _)| name).collect();
+        echoers.len() == proof.echoes.len()
+            && echoers.iter().all(|name| self.committee.weight(name) > 0)
+            && self.committee.weight_of(echoers) >= self.committee.weighted_quorum_threshold()
+            && proof.echoes.iter().all(|(name, signature)| signature.verify(&digest, name).is_ok())
+    }
+
+    fn verify_message(&self, message: &DumboMessage) -> bool {
+        self.committee.weight(&message.sender) > 0
+            && message
+                .signature
+                .verify(&Self::message_digest(&message.sender, &message.content), &message.sender)
+                .is_ok()
+    }
+
+    fn sign(&self, content: DumboContent) -> DumboMessage {
+        let signature = Signature::new(&Self::message_digest(&self.name, &content), &self.secret);
+        DumboMessage {
+            sender: self.name,
+            content,
+            signature,
+        }
+    }
+
+    fn message_digest(sender: &PublicKey, content: &DumboContent) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(sender).expect("Failed to serialize sender"));
+        hasher.update(bincode::serialize(content).expect("Failed to serialize message"));
+        Digest(hasher.finalize().into())
+    }
+
+    fn echo_digest(proposer: &PublicKey, digest: &Digest) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(b"echo");
+        hasher.update(bincode::serialize(proposer).expect("Failed to serialize proposer"));
+        hasher.update(digest);
+        Digest(hasher.finalize().into())
+    }
+
+    pub fn hash_value(value: &BTreeSet<BreezeCertificate>) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(bincode::serialize(value).expect("Failed to serialize certificates"));
+        Digest(hasher.finalize().into())
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+use crate::common::{coin_keys, keys};
+
+// Fixture
+fn coins() -> Vec<ThresholdCoin> {
+    coin_keys().into_iter().map(ThresholdCoin::new).collect()
+}
+
+#[test]
+fn any_shares_give_the_same_coin() {
+    let names: Vec<_> = keys().into_iter().map(|(name, _)| name).collect();
+    let shares: Vec<_> = coins().iter().map(|coin| coin.share(3, Some(1))).collect();
+
+    let mut values = Vec::new();
+    for (i, j) in [(0, 1), (2, 0), (3, 2)] {
+        let mut coin = coins().remove(1);
+        assert!(coin.add_share(names[i], 3, Some(1), shares[i].clone()));
+        assert_eq!(coin.value(3, Some(1)), None);
+        assert!(coin.add_share(names[j], 3, Some(1), shares[j].clone()));
+        values.push(coin.value(3, Some(1)).unwrap());
+    }
+    assert!(values.iter().all(|value| *value == values[0]));
+}
+
+#[test]
+fn coins_differ() {
+    let names: Vec<_> = keys().into_iter().map(|(name, _)| name).collect();
+    let mut coin = coins().remove(0);
+    for (view, round) in [(0, None), (0, Some(0)), (1, None)] {
+        for (i, holder) in coins().iter().enumerate().take(2) {
+            assert!(coin.add_share(names[i], view, round, holder.share(view, round)));
+        }
+    }
+    let election = coin.value(0, None).unwrap();
+    assert_ne!(election, coin.value(0, Some(0)).unwrap());
+    assert_ne!(election, coin.value(1, None).unwrap());
+}
+
+#[test]
+fn reject_invalid_shares() {
+    let names: Vec<_> = keys().into_iter().map(|(name, _)| name).collect();
+    let mut coin = coins().remove(0);
+
+    // The share of another coin.
+    let share = coins()[1].share(0, Some(1));
+    assert!(!coin.add_share(names[1], 0, None, share));
+
+    // The share of another holder.
+    let share = coins()[2].share(0, None);
+    assert!(!coin.add_share(names[1], 0, None, share));
+
+    // A share that does not match its proof.
+    let mut share = coins()[1].share(0, None);
+    share.value = coins()[2].share(0, None).value;
+    assert!(!coin.add_share(names[1], 0, None, share));
+
+    assert!(coin.add_share(names[0], 0, None, coins()[0].share(0, None)));
+    assert_eq!(coin.value(0, None), None);
+}
@@ -0,0 +1,168 @@
+use crate::coin::ThresholdCoin;
+use crate::mvba::{Dispatch, Mvba};
+use config::{Authority, Committee, PrimaryAddresses, Weighting};
+use crypto::{generate_keypair, Digest, PublicKey, SecretKey, Signature};
+use model::bft_message::DumboMessage;
+use curve25519_dalek::Scalar;
+use model::breeze_universal::BreezeCertificate;
+use model::threshold_coin::CoinKey;
+use rand::rngs::StdRng;
+use rand::{Rng as _, SeedableRng as _};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+// Fixture
+pub fn keys() -> Vec<(PublicKey, SecretKey)> {
+    let mut rng = StdRng::from_seed([0; 32]);
+    (0..4).map(|_| generate_keypair(&mut rng)).collect()
+}
+
+// Fixture
+pub fn committee() -> Committee {
+    Committee {
+        authorities: keys()
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| {
+                let primary = PrimaryAddresses {
+                    primary_to_primary: format!("127.0.0.1:{}", 100 + i).parse().unwrap(),
+                    worker_to_primary: format!("127.0.0.1:{}", 200 + i).parse().unwrap(),
+                    breeze_addr: format!("127.0.0.1:{}", 600 + i).parse().unwrap(),
+                    init_bft_addr: format!("127.0.0.1:{}", 700 + i).parse().unwrap(),
+                    beacon_api_addr: Some(format!("127.0.0.1:{}", 800 + i).parse().unwrap()),
+                    evidence_api_addr: Some(format!("127.0.0.1:{}", 900 + i).parse().unwrap()),
+                    admin_api_addr: None,
+                };
+                (
+                    *id,
+                    Authority {
+                        stake: 1,
+                        primary,
+                        workers: BTreeMap::new(),
+                    },
+                )
+            })
+            .collect(),
+        weighting: Weighting::Count,
+    }
+}
+
+// Fixture: the keys of a coin tossed by f+1 shares, in the order of `keys()`.
+pub fn coin_keys() -> Vec<CoinKey> {
+    let names: Vec<_> = keys().into_iter().map(|(name, _)| name).collect();
+    let mut coefficient = 0;
+    CoinKey::deal(&names, committee().authorities_fault_tolerance() + 1, || {
+        coefficient += 1;
+        Scalar::from(coefficient * 7919u64)
+    })
+}
+
+// Fixture: the certificate of the epoch-0 dealing of the `i`-th node, signed by `signers` nodes.
+pub fn certificate(i: usize, signers: usize) -> BreezeCertificate {
+    let c = Digest([i as u8 + 1; 32]);
+    let dealer = keys()[i].0;
+    let digest = BreezeCertificate::signed_digest(&c, &dealer);
+    let certificates = keys()
+        .into_iter()
+        .take(signers)
+        .map(|(name, secret)| (name, Signature::new(&digest, &secret)))
+        .collect();
+    BreezeCertificate {
+        c,
+        dealer,
+        epoch: 0,
+        certificates,
+    }
+}
+
+/// Runs the nodes of the committee in-process. Every message sent goes to a pool, and a seeded
+/// scheduler picks which one to deliver next, so that each seed explores a different ordering.
+pub struct Harness {
+    pub names: Vec<PublicKey>,
+    pub nodes: Vec<Mvba<ThresholdCoin>>,
+    /// Nodes that neither send nor receive anything.
+    pub crashed: HashSet<usize>,
+    /// Nodes whose messages (in both directions) are only delivered once nothing else is pending.
+    pub slow: HashSet<usize>,
+    pool: Vec<(usize, DumboMessage)>,
+    rng: StdRng,
+}
+
+impl Harness {
+    pub fn new(committee: Committee, seed: u8) -> Self {
+        let (names, nodes) = keys()
+            .into_iter()
+            .zip(coin_keys())
+            .map(|((name, secret), key)| (name, Mvba::new(name, secret, committee.clone(), ThresholdCoin::new(key))))
+            .unzip();
+        Self {
+            names,
+            nodes,
+            crashed: HashSet::new(),
+            slow: HashSet::new(),
+            pool: Vec::new(),
+            rng: StdRng::from_seed([seed; 32]),
+        }
+    }
+
+    /// Every live node disseminates the certificate of its own dealing.
+    pub fn disseminate(&mut self) {
+        for i in 0..self.nodes.len() {
+            if self.crashed.contains(&i) {
+                continue;
+            }
+            let dispatches = self.nodes[i].disseminate(certificate(i, 3));
+            self.push(i, dispatches);
+        }
+    }
+
+    /// Delivers messages until none is left.
+    pub fn run(&mut self) {
+        while !self.pool.is_empty() {
+            let fast: Vec<_> = (0..self.pool.len())
+                .filter(|&k| !self.involves_slow(k))
+                .collect();
+            let k = match fast.is_empty() {
+                true => self.rng.gen_range(0, self.pool.len()),
+                false => fast[self.rng.gen_range(0, fast.len())],
+            };
+            let (to, message) = self.pool.swap_remove(k);
+            let dispatches = self.nodes[to].handle(message);
+            self.push(to, dispatches);
+        }
+    }
+
+    /// The common core decided by each live node.
+    pub fn decisions(&self) -> Vec<Option<&BTreeSet<BreezeCertificate>>> {
+        (0..self.nodes.len())
+            .filter(|i| !self.crashed.contains(i))
+            .map(|i| self.nodes[i].decision())
+            .collect()
+    }
+
+    fn involves_slow(&self, k: usize) -> bool {
+        let (to, message) = &self.pool[k];
+        let from = self.names.iter().position(|name| *name == message.sender).unwrap();
+        self.slow.contains(to) || self.slow.contains(&from)
+    }
+
+    fn push(&mut self, from: usize, dispatches: Vec<Dispatch>) {
+        if self.crashed.contains(&from) {
+            return;
+        }
+        for dispatch in dispatches {
+            match dispatch {
+                Dispatch::Broadcast(message) => {
+                    for to in 0..self.nodes.len() {
+                        self.pool.push((to, message.clone()));
+                    }
+                }
+                Dispatch::Send(name, message) => {
+                    let to = self.names.iter().position(|x| *x == name).unwrap();
+                    self.pool.push((to, message));
+                }
+            }
+        }
+        let crashed = &self.crashed;
+        self.pool.retain(|(to, _)| !crashed.contains(to));
+    }
+}
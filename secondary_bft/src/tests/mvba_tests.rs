@@ -0,0 +1,176 @@
+use super::*;
+use crate::coin::ThresholdCoin;
+use crate::common::{certificate, coin_keys, committee, keys, Harness};
+
+// Fixture: the MVBA of the `i`-th node.
+fn node(i: usize) -> Mvba<ThresholdCoin> {
+    let (name, secret) = keys().remove(i);
+    Mvba::new(name, secret, committee(), ThresholdCoin::new(coin_keys().remove(i)))
+}
+
+// Fixture: the message of the `i`-th node releasing its share of the coin electing the leader of
+// `view`.
+fn election_share(i: usize, view: View) -> DumboMessage {
+    let share = ThresholdCoin::new(coin_keys().remove(i)).share(view, None);
+    node(i).sign(DumboContent::Coin {
+        view,
+        round: None,
+        share,
+    })
+}
+
+fn assert_agreement(harness: &Harness) {
+    let decisions = harness.decisions();
+    let first = decisions[0].expect("Node did not decide");
+    assert!(first.len() > committee().authorities_fault_tolerance());
+    for decision in decisions {
+        assert_eq!(decision, Some(first));
+    }
+}
+
+#[test]
+fn decide_under_random_schedules() {
+    for seed in 0..10 {
+        let mut harness = Harness::new(committee(), seed);
+        harness.disseminate();
+        harness.run();
+        assert_agreement(&harness);
+        assert!(harness.nodes.iter().all(|node| node.phase() == Phase::Decided));
+    }
+}
+
+#[test]
+fn decide_with_crashed_node() {
+    for crashed in 0..4 {
+        let mut harness = Harness::new(committee(), crashed as u8);
+        harness.crashed.insert(crashed);
+        harness.disseminate();
+        harness.run();
+        assert_agreement(&harness);
+    }
+}
+
+#[test]
+fn decide_with_slow_node() {
+    // The messages of the slow node are delivered after everything else: the others decide
+    // without it, and it catches up with the same common core.
+    for slow in 0..4 {
+        let mut harness = Harness::new(committee(), 10 + slow as u8);
+        harness.slow.insert(slow);
+        harness.disseminate();
+        harness.run();
+        assert_agreement(&harness);
+    }
+}
+
+#[test]
+fn propose_relayed_certificates() {
+    // The certificates count for their dealers, whoever relays them.
+    let mut mvba = node(3);
+    let relay = node(2);
+    let message = relay.sign(DumboContent::Certificate(certificate(0, 3)));
+    assert!(mvba.handle(message).is_empty());
+    let message = relay.sign(DumboContent::Certificate(certificate(1, 3)));
+    match mvba.handle(message).as_slice() {
+        [Dispatch::Broadcast(proposal)] => {
+            let expected: BTreeSet<_> = (0..2).map(|i| certificate(i, 3)).collect();
+            assert!(matches!(&proposal.content, DumboContent::Proposal(value) if *value == expected));
+        }
+        x => panic!("Unexpected dispatches: {:?}", x),
+    }
+}
+
+#[test]
+fn reject_invalid_proposal() {
+    let mut mvba = node(3);
+    let proposer = keys()[0].0;
+    let mut other = node(0);
+
+    // Too little weight of dealers.
+    let value: BTreeSet<_> = (0..1).map(|i| certificate(i, 3)).collect();
+    let message = other.sign(DumboContent::Proposal(value));
+    assert!(mvba.handle(message).is_empty());
+
+    // Certificates without a quorum of signatures.
+    let value: BTreeSet<_> = (0..2).map(|i| certificate(i, 2)).collect();
+    let message = other.sign(DumboContent::Proposal(value));
+    assert!(mvba.handle(message).is_empty());
+
+    // A valid proposal is echoed back to its proposer, only once.
+    let value: BTreeSet<_> = (0..2).map(|i| certificate(i, 3)).collect();
+    let message = other.sign(DumboContent::Proposal(value.clone()));
+    match mvba.handle(message.clone()).as_slice() {
+        [Dispatch::Send(to, echo)] => {
+            assert_eq!(*to, proposer);
+            assert!(matches!(echo.content, DumboContent::Echo { digest, .. } if digest == Mvba::<ThresholdCoin>::hash_value(&value)));
+        }
+        x => panic!("Unexpected dispatches: {:?}", x),
+    }
+    assert!(mvba.handle(message).is_empty());
+
+    // A message whose signature does not match the sender is dropped.
+    let mut message = other.sign(DumboContent::Proposal(value));
+    message.sender = keys()[1].0;
+    other = node(3);
+    assert!(other.handle(message).is_empty());
+}
+
+#[test]
+fn report_invalid_and_conflicting_votes() {
+    let mut mvba = node(3);
+    let voter = keys()[0].0;
+    let other = node(0);
+    let mut coin = ThresholdCoin::new(coin_keys().remove(3));
+    for i in 0..2 {
+        let share = ThresholdCoin::new(coin_keys().remove(i)).share(0, None);
+        assert!(coin.add_share(keys()[i].0, 0, None, share));
+    }
+    let leader = crate::coin::elect(&committee(), &coin.value(0, None).unwrap());
+    let vote = |leader| DumboContent::Vote {
+        view: 0,
+        leader,
+        proposal: None,
+    };
+
+    // A vote for a node that is not the leader of the view, only checked once f+1 nodes released
+    // their share of the coin electing it.
+    let impostor = *committee().authorities.keys().find(|name| **name != leader).unwrap();
+    mvba.handle(other.sign(vote(impostor)));
+    mvba.handle(election_share(0, 0));
+    assert!(mvba.take_evidence().is_empty());
+    mvba.handle(election_share(1, 0));
+    match mvba.take_evidence().as_slice() {
+        [evidence] => {
+            assert_eq!(evidence.culprit, voter);
+            assert!(matches!(evidence.misbehaviour, Misbehaviour::InvalidVote(_)));
+            assert!(evidence.verify(&voter));
+        }
+        x => panic!("Unexpected evidence: {:?}", x),
+    }
+
+    // The same vote twice is not an equivocation, but a different one in the same view is.
+    mvba.handle(other.sign(vote(leader)));
+    mvba.handle(other.sign(vote(leader)));
+    assert!(mvba.take_evidence().is_empty());
+    let value = BTreeSet::new();
+    let digest = Mvba::<ThresholdCoin>::hash_value(&value);
+    let echo = Mvba::<ThresholdCoin>::echo_digest(&leader, &digest);
+    let proof = FinishProof {
+        proposer: leader,
+        digest,
+        echoes: keys().iter().map(|(name, secret)| (*name, Signature::new(&echo, secret))).collect(),
+    };
+    let finished = DumboContent::Vote {
+        view: 0,
+        leader,
+        proposal: Some((value, proof)),
+    };
+    mvba.handle(other.sign(finished));
+    match mvba.take_evidence().as_slice() {
+        [evidence] => {
+            assert!(matches!(evidence.misbehaviour, Misbehaviour::Equivocation(..)));
+            assert!(evidence.verify(&voter));
+        }
+        x => panic!("Unexpected evidence: {:?}", x),
+    }
+}
@@ -0,0 +1,196 @@
+#[cfg(feature = "rocksdb")]
+use crate::{StoreError, DEFAULT_FAMILY};
+use crate::{Key, StoreResult, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+/// The storage engine behind a `Store`. Its methods are called one at a time, from the task of the
+/// store (which also keeps track of the pending `notify_read`). The column families are created on
+/// first use.
+pub trait StoreBackend: Send + 'static {
+    fn put(&mut self, family: &str, key: &[u8], value: &[u8]) -> StoreResult<()>;
+
+    /// Writes all the key-value pairs atomically.
+    fn put_batch(&mut self, family: &str, values: &[(Key, Value)]) -> StoreResult<()>;
+
+    fn get(&mut self, family: &str, key: &[u8]) -> StoreResult<Option<Value>>;
+
+    fn delete(&mut self, family: &str, key: &[u8]) -> StoreResult<()>;
+
+    /// Deletes all the keys atomically.
+    fn delete_batch(&mut self, family: &str, keys: &[Key]) -> StoreResult<()>;
+
+    /// Deletes the keys from `from` (included) to `to` (excluded).
+    fn delete_range(&mut self, family: &str, from: &[u8], to: &[u8]) -> StoreResult<()>;
+
+    /// Reads (in key order) at most `limit` key-value pairs, from `from` (included) to `to`
+    /// (excluded, if any).
+    fn scan(
+        &mut self,
+        family: &str,
+        from: &[u8],
+        to: Option<&[u8]>,
+        limit: usize,
+    ) -> Vec<(Key, Value)>;
+}
+
+/// Persists the store in RocksDB, one column family of the database per family of the store.
+#[cfg(feature = "rocksdb")]
+pub struct RocksDbBackend {
+    db: rocksdb::DB,
+}
+
+#[cfg(feature = "rocksdb")]
+impl RocksDbBackend {
+    pub fn open(path: &str) -> StoreResult<Self> {
+        // All the existing column families must be opened, the others are created on first use.
+        let mut options = rocksdb::Options::default();
+        options.create_if_missing(true);
+        let mut families = rocksdb::DB::list_cf(&options, path).unwrap_or_default();
+        if families.is_empty() {
+            families.push(DEFAULT_FAMILY.to_string());
+        }
+        let db = rocksdb::DB::open_cf(&options, path, families)
+            .map_err(|e| StoreError::Open(e.to_string()))?;
+        Ok(Self { db })
+    }
+
+    fn handle(&mut self, family: &str) -> (&rocksdb::DB, &rocksdb::ColumnFamily) {
+        if self.db.cf_handle(family).is_none() {
+            self.db
+                .create_cf(family, &rocksdb::Options::default())
+                .expect("Failed to create column family");
+        }
+        let db = &self.db;
+        (db, db.cf_handle(family).unwrap())
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl StoreBackend for RocksDbBackend {
+    fn put(&mut self, family: &str, key: &[u8], value: &[u8]) -> StoreResult<()> {
+        let (db, cf) = self.handle(family);
+        db.put_cf(cf, key, value).map_err(StoreError::from)
+    }
+
+    fn put_batch(&mut self, family: &str, values: &[(Key, Value)]) -> StoreResult<()> {
+        let (db, cf) = self.handle(family);
+        let mut batch = rocksdb::WriteBatch::default();
+        for (key, value) in values {
+            batch.put_cf(cf, key, value);
+        }
+        db.write(batch).map_err(StoreError::from)
+    }
+
+    fn get(&mut self, family: &str, key: &[u8]) -> StoreResult<Option<Value>> {
+        let (db, cf) = self.handle(family);
+        db.get_cf(cf, key).map_err(StoreError::from)
+    }
+
+    fn delete(&mut self, family: &str, key: &[u8]) -> StoreResult<()> {
+        let (db, cf) = self.handle(family);
+        db.delete_cf(cf, key).map_err(StoreError::from)
+    }
+
+    fn delete_batch(&mut self, family: &str, keys: &[Key]) -> StoreResult<()> {
+        let (db, cf) = self.handle(family);
+        let mut batch = rocksdb::WriteBatch::default();
+        for key in keys {
+            batch.delete_cf(cf, key);
+        }
+        db.write(batch).map_err(StoreError::from)
+    }
+
+    fn delete_range(&mut self, family: &str, from: &[u8], to: &[u8]) -> StoreResult<()> {
+        let (db, cf) = self.handle(family);
+        db.delete_range_cf(cf, from, to).map_err(StoreError::from)
+    }
+
+    fn scan(
+        &mut self,
+        family: &str,
+        from: &[u8],
+        to: Option<&[u8]>,
+        limit: usize,
+    ) -> Vec<(Key, Value)> {
+        let (db, cf) = self.handle(family);
+        let mode = rocksdb::IteratorMode::From(from, rocksdb::Direction::Forward);
+        db.iterator_cf(cf, mode)
+            .take_while(|(key, _)| to.is_none_or(|to| key.as_ref() < to))
+            .take(limit)
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl From<rocksdb::Error> for StoreError {
+    fn from(e: rocksdb::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+/// Keeps the store in memory, for tests and throwaway nodes: nothing survives the process.
+#[derive(Default)]
+pub struct MemoryBackend {
+    families: HashMap<String, BTreeMap<Key, Value>>,
+}
+
+impl MemoryBackend {
+    fn family(&mut self, family: &str) -> &mut BTreeMap<Key, Value> {
+        self.families.entry(family.to_string()).or_default()
+    }
+}
+
+impl StoreBackend for MemoryBackend {
+    fn put(&mut self, family: &str, key: &[u8], value: &[u8]) -> StoreResult<()> {
+        self.family(family).insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn put_batch(&mut self, family: &str, values: &[(Key, Value)]) -> StoreResult<()> {
+        self.family(family).extend(values.iter().cloned());
+        Ok(())
+    }
+
+    fn get(&mut self, family: &str, key: &[u8]) -> StoreResult<Option<Value>> {
+        Ok(self.family(family).get(key).cloned())
+    }
+
+    fn delete(&mut self, family: &str, key: &[u8]) -> StoreResult<()> {
+        self.family(family).remove(key);
+        Ok(())
+    }
+
+    fn delete_batch(&mut self, family: &str, keys: &[Key]) -> StoreResult<()> {
+        let values = self.family(family);
+        for key in keys {
+            values.remove(key);
+        }
+        Ok(())
+    }
+
+    fn delete_range(&mut self, family: &str, from: &[u8], to: &[u8]) -> StoreResult<()> {
+        if from < to {
+            let values = self.family(family);
+            let mut tail = values.split_off(from);
+            values.append(&mut tail.split_off(to));
+        }
+        Ok(())
+    }
+
+    fn scan(
+        &mut self,
+        family: &str,
+        from: &[u8],
+        to: Option<&[u8]>,
+        limit: usize,
+    ) -> Vec<(Key, Value)> {
+        let to = to.map_or(Bound::Unbounded, Bound::Excluded);
+        self.family(family)
+            .range::<[u8], _>((Bound::Included(from), to))
+            .take(limit)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
@@ -1,54 +1,112 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use futures::stream::{self, Stream, StreamExt as _};
 use std::collections::{HashMap, VecDeque};
+use thiserror::Error;
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::sync::oneshot;
 
+mod backend;
+mod typed_store;
+
+#[cfg(feature = "rocksdb")]
+pub use crate::backend::RocksDbBackend;
+pub use crate::backend::{MemoryBackend, StoreBackend};
+pub use crate::typed_store::TypedStore;
+
 #[cfg(test)]
 #[path = "tests/store_tests.rs"]
 pub mod store_tests;
 
-pub type StoreError = rocksdb::Error;
+/// The failures of a store backend, whatever its storage engine.
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Failed to open the store: {0}")]
+    Open(String),
+
+    #[error("Storage engine failure: {0}")]
+    Backend(String),
+}
+
 type StoreResult<T> = Result<T, StoreError>;
 
 type Key = Vec<u8>;
 type Value = Vec<u8>;
 
+/// The column family of the handles returned by `Store::new`.
+pub const DEFAULT_FAMILY: &str = "default";
+
+/// The number of key-value pairs the store reads at once when streaming a range.
+const SCAN_PAGE_SIZE: usize = 1_000;
+
 pub enum StoreCommand {
     Write(Key, Value),
+    /// Writes all the key-value pairs atomically.
+    WriteBatch(Vec<(Key, Value)>),
     Read(Key, oneshot::Sender<StoreResult<Option<Value>>>),
     NotifyRead(Key, oneshot::Sender<StoreResult<Value>>),
+    Delete(Key),
+    DeleteBatch(Vec<Key>),
+    /// Deletes the keys from the first one (included) to the second one (excluded).
+    DeleteRange(Key, Key),
+    /// Reads (in key order) a page of the key-value pairs from the first key (included) to the
+    /// second one (excluded, if any).
+    Scan(Key, Option<Key>, oneshot::Sender<Vec<(Key, Value)>>),
 }
 
+/// A handle to one column family of the store. Each family is an independent keyspace (that
+/// RocksDB compacts separately); the handles of all families share the same backend.
 #[derive(Clone)]
 pub struct Store {
-    channel: Sender<StoreCommand>,
+    channel: Sender<(&'static str, StoreCommand)>,
+    family: &'static str,
 }
 
 impl Store {
+    /// Opens (or creates) the RocksDB store at `path`.
+    #[cfg(feature = "rocksdb")]
     pub fn new(path: &str) -> StoreResult<Self> {
-        let db = rocksdb::DB::open_default(path)?;
+        Ok(Self::with_backend(RocksDbBackend::open(path)?))
+    }
+
+    /// Creates an empty store kept in memory.
+    pub fn in_memory() -> Self {
+        Self::with_backend(MemoryBackend::default())
+    }
+
+    /// Serves the store from `backend`, in a task of its own.
+    pub fn with_backend<B: StoreBackend>(mut backend: B) -> Self {
         let mut obligations = HashMap::<_, VecDeque<oneshot::Sender<_>>>::new();
         let (tx, mut rx) = channel(100);
         tokio::spawn(async move {
-            while let Some(command) = rx.recv().await {
+            while let Some((family, command)) = rx.recv().await {
                 match command {
                     StoreCommand::Write(key, value) => {
-                        let _ = db.put(&key, &value);
-                        if let Some(mut senders) = obligations.remove(&key) {
+                        let _ = backend.put(family, &key, &value);
+                        if let Some(mut senders) = obligations.remove(&(family, key)) {
                             while let Some(s) = senders.pop_front() {
                                 let _ = s.send(Ok(value.clone()));
                             }
                         }
                     }
+                    StoreCommand::WriteBatch(values) => {
+                        let _ = backend.put_batch(family, &values);
+                        for (key, value) in values {
+                            if let Some(mut senders) = obligations.remove(&(family, key)) {
+                                while let Some(s) = senders.pop_front() {
+                                    let _ = s.send(Ok(value.clone()));
+                                }
+                            }
+                        }
+                    }
                     StoreCommand::Read(key, sender) => {
-                        let response = db.get(&key);
+                        let response = backend.get(family, &key);
                         let _ = sender.send(response);
                     }
                     StoreCommand::NotifyRead(key, sender) => {
-                        let response = db.get(&key);
+                        let response = backend.get(family, &key);
                         match response {
                             Ok(None) => obligations
-                                .entry(key)
+                                .entry((family, key))
                                 .or_insert_with(VecDeque::new)
                                 .push_back(sender),
                             _ => {
@@ -56,21 +114,64 @@ impl Store {
                             }
                         }
                     }
+                    StoreCommand::Delete(key) => {
+                        let _ = backend.delete(family, &key);
+                    }
+                    StoreCommand::DeleteBatch(keys) => {
+                        let _ = backend.delete_batch(family, &keys);
+                    }
+                    StoreCommand::DeleteRange(from, to) => {
+                        let _ = backend.delete_range(family, &from, &to);
+                    }
+                    StoreCommand::Scan(from, to, sender) => {
+                        let page = backend.scan(family, &from, to.as_deref(), SCAN_PAGE_SIZE);
+                        let _ = sender.send(page);
+                    }
                 }
             }
         });
-        Ok(Self { channel: tx })
+        Self {
+            channel: tx,
+            family: DEFAULT_FAMILY,
+        }
+    }
+
+    /// Returns a handle to the column family `family` of the same store.
+    pub fn family(&self, family: &'static str) -> Self {
+        Self {
+            channel: self.channel.clone(),
+            family,
+        }
     }
 
     pub async fn write(&mut self, key: Key, value: Value) {
-        if let Err(e) = self.channel.send(StoreCommand::Write(key, value)).await {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::Write(key, value)))
+            .await
+        {
             panic!("Failed to send Write command to store: {}", e);
         }
     }
 
+    /// Writes all the key-value pairs atomically.
+    pub async fn write_batch(&mut self, values: Vec<(Key, Value)>) {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::WriteBatch(values)))
+            .await
+        {
+            panic!("Failed to send WriteBatch command to store: {}", e);
+        }
+    }
+
     pub async fn read(&mut self, key: Key) -> StoreResult<Option<Value>> {
         let (sender, receiver) = oneshot::channel();
-        if let Err(e) = self.channel.send(StoreCommand::Read(key, sender)).await {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::Read(key, sender)))
+            .await
+        {
             panic!("Failed to send Read command to store: {}", e);
         }
         receiver
@@ -82,7 +183,7 @@ impl Store {
         let (sender, receiver) = oneshot::channel();
         if let Err(e) = self
             .channel
-            .send(StoreCommand::NotifyRead(key, sender))
+            .send((self.family, StoreCommand::NotifyRead(key, sender)))
             .await
         {
             panic!("Failed to send NotifyRead command to store: {}", e);
@@ -91,4 +192,94 @@ impl Store {
             .await
             .expect("Failed to receive reply to NotifyRead command from store")
     }
+
+    pub async fn delete(&mut self, key: Key) {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::Delete(key)))
+            .await
+        {
+            panic!("Failed to send Delete command to store: {}", e);
+        }
+    }
+
+    /// Deletes all the keys atomically.
+    pub async fn delete_batch(&mut self, keys: Vec<Key>) {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::DeleteBatch(keys)))
+            .await
+        {
+            panic!("Failed to send DeleteBatch command to store: {}", e);
+        }
+    }
+
+    /// Deletes the keys from `from` (included) to `to` (excluded).
+    pub async fn delete_range(&mut self, from: Key, to: Key) {
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::DeleteRange(from, to)))
+            .await
+        {
+            panic!("Failed to send DeleteRange command to store: {}", e);
+        }
+    }
+
+    async fn scan(&mut self, from: Key, to: Option<Key>) -> Vec<(Key, Value)> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self
+            .channel
+            .send((self.family, StoreCommand::Scan(from, to, sender)))
+            .await
+        {
+            panic!("Failed to send Scan command to store: {}", e);
+        }
+        receiver
+            .await
+            .expect("Failed to receive reply to Scan command from store")
+    }
+
+    /// Streams (in key order) the key-value pairs from `from` (included) to `to` (excluded, if
+    /// any). The pairs are read a page at a time, so the other commands are not held up meanwhile;
+    /// a stream may thus reflect the writes made while it runs.
+    pub fn range_iter(&self, from: Key, to: Option<Key>) -> impl Stream<Item = (Key, Value)> {
+        let store = self.clone();
+        stream::unfold(Some(from), move |from| {
+            let mut store = store.clone();
+            let to = to.clone();
+            async move {
+                let page = store.scan(from?, to).await;
+                // Resume right after the last key of a full page.
+                let next = match page.len() {
+                    SCAN_PAGE_SIZE => page.last().map(|(key, _)| [key.as_slice(), &[0]].concat()),
+                    _ => None,
+                };
+                Some((stream::iter(page), next))
+            }
+        })
+        .flatten()
+    }
+
+    /// Streams (in key order) the key-value pairs whose key starts with `prefix`.
+    pub fn prefix_iter(&self, prefix: Key) -> impl Stream<Item = (Key, Value)> {
+        // The keys with this prefix are those below the prefix incremented (as a big number), if
+        // it can be incremented without overflowing.
+        let mut to = prefix.clone();
+        while to.last() == Some(&u8::MAX) {
+            to.pop();
+        }
+        let to = match to.last_mut() {
+            Some(last) => {
+                *last += 1;
+                Some(to)
+            }
+            None => None,
+        };
+        self.range_iter(prefix, to)
+    }
+
+    /// Streams (in key order) all the key-value pairs of the column family.
+    pub fn iter(&self) -> impl Stream<Item = (Key, Value)> {
+        self.range_iter(Key::default(), None)
+    }
 }
@@ -1,7 +1,9 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
+#[cfg(feature = "rocksdb")]
 use std::fs;
 
+#[cfg(feature = "rocksdb")]
 #[tokio::test]
 async fn create_store() {
     // Create new store.
@@ -11,6 +13,7 @@ async fn create_store() {
     assert!(store.is_ok());
 }
 
+#[cfg(feature = "rocksdb")]
 #[tokio::test]
 async fn read_write_value() {
     // Create new store.
@@ -31,6 +34,7 @@ async fn read_write_value() {
     assert_eq!(read_value.unwrap(), value);
 }
 
+#[cfg(feature = "rocksdb")]
 #[tokio::test]
 async fn read_unknown_key() {
     // Create new store.
@@ -45,6 +49,7 @@ async fn read_unknown_key() {
     assert!(result.unwrap().is_none());
 }
 
+#[cfg(feature = "rocksdb")]
 #[tokio::test]
 async fn read_notify() {
     // Create new store.
@@ -72,3 +77,174 @@ async fn read_notify() {
     store.write(key, value).await;
     assert!(handle.await.is_ok());
 }
+
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn delete_values() {
+    // Create new store.
+    let path = ".db_test_delete_values";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Write a few values to the store.
+    for i in 0u8..5 {
+        store.write(vec![i], vec![i]).await;
+    }
+
+    // Delete a single key, then a batch of keys.
+    store.delete(vec![0u8]).await;
+    store.delete_batch(vec![vec![1u8], vec![3u8]]).await;
+
+    // Ensure only the other values remain.
+    for i in 0u8..5 {
+        let expected = if i == 2 || i == 4 { Some(vec![i]) } else { None };
+        assert_eq!(store.read(vec![i]).await.unwrap(), expected);
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn delete_range() {
+    // Create new store.
+    let path = ".db_test_delete_range";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Write a few values to the store.
+    for i in 0u8..5 {
+        store.write(vec![i], vec![i]).await;
+    }
+
+    // Delete the range [1, 4): the upper bound is kept.
+    store.delete_range(vec![1u8], vec![4u8]).await;
+    for i in 0u8..5 {
+        let expected = if i == 0 || i == 4 { Some(vec![i]) } else { None };
+        assert_eq!(store.read(vec![i]).await.unwrap(), expected);
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn separate_families() {
+    // Create new store.
+    let path = ".db_test_separate_families";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+    let mut family = store.family("family");
+
+    // The same key holds a different value in each family.
+    let key = vec![0u8, 1u8, 2u8, 3u8];
+    store.write(key.clone(), vec![4u8]).await;
+    family.write(key.clone(), vec![5u8]).await;
+    assert_eq!(store.read(key.clone()).await.unwrap(), Some(vec![4u8]));
+    assert_eq!(family.read(key.clone()).await.unwrap(), Some(vec![5u8]));
+
+    // Deleting it from one family leaves the other untouched.
+    family.delete(key.clone()).await;
+    assert_eq!(store.read(key.clone()).await.unwrap(), Some(vec![4u8]));
+    assert!(family.read(key).await.unwrap().is_none());
+}
+
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn write_batch_and_notify() {
+    // Create new store.
+    let path = ".db_test_write_batch_and_notify";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Wait for a value of the batch before it is written.
+    let mut store_copy = store.clone();
+    let handle = tokio::spawn(async move { store_copy.notify_read(vec![1u8]).await.unwrap() });
+
+    // Write the batch and ensure all its values are there.
+    store
+        .write_batch(vec![(vec![0u8], vec![0u8]), (vec![1u8], vec![1u8])])
+        .await;
+    assert_eq!(handle.await.unwrap(), vec![1u8]);
+    assert_eq!(store.read(vec![0u8]).await.unwrap(), Some(vec![0u8]));
+}
+
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn iterate_ranges() {
+    // Create new store.
+    let path = ".db_test_iterate_ranges";
+    let _ = fs::remove_dir_all(path);
+    let mut store = Store::new(path).unwrap();
+
+    // Write more values than fit in a page, under two prefixes.
+    let mut values = Vec::new();
+    for i in 0..2 * SCAN_PAGE_SIZE as u16 + 1 {
+        for prefix in [0u8, u8::MAX] {
+            values.push(([&[prefix][..], &i.to_be_bytes()].concat(), vec![prefix]));
+        }
+    }
+    store.write_batch(values.clone()).await;
+
+    // Stream them all back, in key order.
+    let mut expected = values;
+    expected.sort();
+    let all: Vec<_> = store.iter().collect().await;
+    assert_eq!(all, expected);
+
+    // Stream the keys of each prefix.
+    let zeros: Vec<_> = store.prefix_iter(vec![0u8]).collect().await;
+    assert_eq!(zeros, expected[..expected.len() / 2]);
+    let maxes: Vec<_> = store.prefix_iter(vec![u8::MAX]).collect().await;
+    assert_eq!(maxes, expected[expected.len() / 2..]);
+
+    // Stream a range.
+    let range: Vec<_> = store
+        .range_iter(vec![0u8, 0u8, 1u8], Some(vec![0u8, 0u8, 3u8]))
+        .collect()
+        .await;
+    assert_eq!(range, expected[1..3]);
+}
+
+#[tokio::test]
+async fn in_memory_read_notify() {
+    let mut store = Store::in_memory();
+    let mut family = store.family("family");
+
+    // Wait for a value that does not yet exist, in another family.
+    let mut family_copy = family.clone();
+    let handle = tokio::spawn(async move { family_copy.notify_read(vec![0u8]).await.unwrap() });
+
+    // Writing the key to the default family does not fulfil it; writing it to the right one does.
+    store.write(vec![0u8], vec![1u8]).await;
+    family.write(vec![0u8], vec![2u8]).await;
+    assert_eq!(handle.await.unwrap(), vec![2u8]);
+    assert_eq!(store.read(vec![0u8]).await.unwrap(), Some(vec![1u8]));
+    assert!(store.read(vec![1u8]).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn in_memory_delete_and_iterate() {
+    let mut store = Store::in_memory();
+
+    // Write more values than fit in a page.
+    let values: Vec<_> = (0..SCAN_PAGE_SIZE as u16 + 5)
+        .map(|i| (i.to_be_bytes().to_vec(), vec![0u8]))
+        .collect();
+    store.write_batch(values.clone()).await;
+
+    // Delete a single key, a batch of keys and a range of keys.
+    store.delete(values[0].0.clone()).await;
+    store.delete_batch(vec![values[1].0.clone()]).await;
+    store
+        .delete_range(values[3].0.clone(), values[5].0.clone())
+        .await;
+
+    // Stream the others back, in key order.
+    let expected: Vec<_> = values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| ![0, 1, 3, 4].contains(i))
+        .map(|(_, x)| x.clone())
+        .collect();
+    let all: Vec<_> = store.iter().collect().await;
+    assert_eq!(all, expected);
+    let prefix: Vec<_> = store.prefix_iter(vec![0u8]).collect().await;
+    assert_eq!(prefix, expected[..252]);
+}
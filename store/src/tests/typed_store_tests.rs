@@ -0,0 +1,50 @@
+use super::*;
+use std::fs;
+
+#[tokio::test]
+async fn read_write_typed_values() {
+    // Create new store.
+    let path = ".db_test_read_write_typed_values";
+    let _ = fs::remove_dir_all(path);
+    let store = Store::new(path).unwrap();
+    let mut typed: TypedStore<u64, String> = TypedStore::new(&store, "typed");
+
+    // Write a value and read it back.
+    typed.write(&1, &"one".to_string()).await;
+    assert_eq!(typed.read(&1).await.unwrap(), Some("one".to_string()));
+    assert_eq!(typed.read(&2).await.unwrap(), None);
+
+    // Delete it.
+    typed.delete(&1).await;
+    assert_eq!(typed.read(&1).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn typed_notify_read() {
+    // Create new store.
+    let path = ".db_test_typed_notify_read";
+    let _ = fs::remove_dir_all(path);
+    let store = Store::new(path).unwrap();
+    let mut typed: TypedStore<u64, String> = TypedStore::new(&store, "typed");
+
+    // Wait for a value that is not yet written.
+    let mut typed_copy = typed.clone();
+    let handle = tokio::spawn(async move { typed_copy.notify_read(&1).await.unwrap() });
+    typed.write(&1, &"one".to_string()).await;
+    assert_eq!(handle.await.unwrap(), "one");
+}
+
+#[tokio::test]
+async fn iterate_typed_values() {
+    // Create new store.
+    let path = ".db_test_iterate_typed_values";
+    let _ = fs::remove_dir_all(path);
+    let store = Store::new(path).unwrap();
+    let mut typed: TypedStore<String, u64> = TypedStore::new(&store, "typed");
+
+    // Write a batch and stream it back.
+    let values = vec![("one".to_string(), 1), ("two".to_string(), 2)];
+    typed.write_batch(&values).await;
+    let all: Vec<_> = typed.iter().collect().await;
+    assert_eq!(all, values);
+}
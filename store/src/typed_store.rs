@@ -0,0 +1,91 @@
+use crate::{Store, StoreResult};
+use futures::stream::{Stream, StreamExt as _};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+#[cfg(all(test, feature = "rocksdb"))]
+#[path = "tests/typed_store_tests.rs"]
+pub mod typed_store_tests;
+
+/// A column family of the store holding values of type `V` under keys of type `K`, both
+/// serialized with bincode.
+pub struct TypedStore<K, V> {
+    store: Store,
+    _marker: PhantomData<fn(K) -> V>,
+}
+
+impl<K, V> Clone for TypedStore<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Serialize, V: Serialize + DeserializeOwned> TypedStore<K, V> {
+    pub fn new(store: &Store, family: &'static str) -> Self {
+        Self {
+            store: store.family(family),
+            _marker: PhantomData,
+        }
+    }
+
+    fn key(key: &K) -> Vec<u8> {
+        bincode::serialize(key).expect("Failed to serialize key")
+    }
+
+    fn value(bytes: &[u8]) -> V {
+        bincode::deserialize(bytes).expect("Failed to deserialize value")
+    }
+
+    pub async fn write(&mut self, key: &K, value: &V) {
+        let bytes = bincode::serialize(value).expect("Failed to serialize value");
+        self.store.write(Self::key(key), bytes).await;
+    }
+
+    /// Writes all the key-value pairs atomically.
+    pub async fn write_batch(&mut self, values: &[(K, V)]) {
+        let values = values
+            .iter()
+            .map(|(key, value)| {
+                let bytes = bincode::serialize(value).expect("Failed to serialize value");
+                (Self::key(key), bytes)
+            })
+            .collect();
+        self.store.write_batch(values).await;
+    }
+
+    pub async fn read(&mut self, key: &K) -> StoreResult<Option<V>> {
+        let bytes = self.store.read(Self::key(key)).await?;
+        Ok(bytes.map(|x| Self::value(&x)))
+    }
+
+    pub async fn notify_read(&mut self, key: &K) -> StoreResult<V> {
+        let bytes = self.store.notify_read(Self::key(key)).await?;
+        Ok(Self::value(&bytes))
+    }
+
+    pub async fn delete(&mut self, key: &K) {
+        self.store.delete(Self::key(key)).await;
+    }
+
+    /// Deletes all the keys atomically.
+    pub async fn delete_batch(&mut self, keys: &[K]) {
+        self.store
+            .delete_batch(keys.iter().map(Self::key).collect())
+            .await;
+    }
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned> TypedStore<K, V> {
+    /// Streams all the key-value pairs, in the order of their serialized keys.
+    pub fn iter(&self) -> impl Stream<Item = (K, V)> {
+        self.store.iter().map(|(key, value)| {
+            let key = bincode::deserialize(&key).expect("Failed to deserialize key");
+            let value = bincode::deserialize(&value).expect("Failed to deserialize value");
+            (key, value)
+        })
+    }
+}
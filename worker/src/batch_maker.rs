@@ -2,6 +2,7 @@
 use crate::quorum_waiter::QuorumWaiterMessage;
 use crate::worker::WorkerMessage;
 use bytes::Bytes;
+use config::SharedCommittee;
 #[cfg(feature = "benchmark")]
 use crypto::Digest;
 use crypto::PublicKey;
@@ -12,7 +13,7 @@ use log::info;
 use network::ReliableSender;
 #[cfg(feature = "benchmark")]
 use std::convert::TryInto as _;
-use std::net::SocketAddr;
+use model::types_and_const::WorkerId;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::{sleep, Duration, Instant};
 
@@ -33,8 +34,12 @@ pub struct BatchMaker {
     rx_transaction: Receiver<Transaction>,
     /// Output channel to deliver sealed batches to the `QuorumWaiter`.
     tx_message: Sender<QuorumWaiterMessage>,
-    /// The network addresses of the other workers that share our worker id.
-    workers_addresses: Vec<(PublicKey, SocketAddr)>,
+    /// The public key of this authority.
+    name: PublicKey,
+    /// The id of this worker, shared by the workers we broadcast our batches to.
+    id: WorkerId,
+    /// The committee information.
+    committee: SharedCommittee,
     /// Holds the current batch.
     current_batch: Batch,
     /// Holds the size of the current batch (in bytes).
@@ -44,12 +49,16 @@ pub struct BatchMaker {
 }
 
 impl BatchMaker {
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         batch_size: usize,
         max_batch_delay: u64,
         rx_transaction: Receiver<Transaction>,
         tx_message: Sender<QuorumWaiterMessage>,
-        workers_addresses: Vec<(PublicKey, SocketAddr)>,
+        name: PublicKey,
+        id: WorkerId,
+        committee: SharedCommittee,
+        network: ReliableSender,
     ) {
         tokio::spawn(async move {
             Self {
@@ -57,10 +66,12 @@ impl BatchMaker {
                 max_batch_delay,
                 rx_transaction,
                 tx_message,
-                workers_addresses,
+                name,
+                id,
+                committee,
                 current_batch: Batch::with_capacity(batch_size * 2),
                 current_batch_size: 0,
-                network: ReliableSender::new(),
+                network,
             }
             .run()
             .await;
@@ -140,8 +151,15 @@ impl BatchMaker {
             info!("Batch {:?} contains {} B", digest, size);
         }
 
-        // Broadcast the batch through the network.
-        let (names, addresses): (Vec<_>, _) = self.workers_addresses.iter().cloned().unzip();
+        // Broadcast the batch through the network, to the workers of the latest committee.
+        let (names, addresses): (Vec<_>, _) = self
+            .committee
+            .latest()
+            .1
+            .others_workers(&self.name, &self.id)
+            .into_iter()
+            .map(|(name, addresses)| (name, addresses.worker_to_worker))
+            .unzip();
         let bytes = Bytes::from(serialized.clone());
         let handlers = self.network.broadcast(addresses, bytes).await;
 
@@ -1,6 +1,6 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use bytes::Bytes;
-use config::{Committee};
+use config::SharedCommittee;
 use crypto::{Digest, PublicKey};
 use log::{error, warn};
 use network::SimpleSender;
@@ -17,7 +17,7 @@ pub struct Helper {
     /// The id of this worker.
     id: WorkerId,
     /// The committee information.
-    committee: Committee,
+    committee: SharedCommittee,
     /// The persistent storage.
     store: Store,
     /// Input channel to receive batch requests.
@@ -29,9 +29,10 @@ pub struct Helper {
 impl Helper {
     pub fn spawn(
         id: WorkerId,
-        committee: Committee,
+        committee: SharedCommittee,
         store: Store,
         rx_request: Receiver<(Vec<Digest>, PublicKey)>,
+        network: SimpleSender,
     ) {
         tokio::spawn(async move {
             Self {
@@ -39,7 +40,7 @@ impl Helper {
                 committee,
                 store,
                 rx_request,
-                network: SimpleSender::new(),
+                network,
             }
             .run()
             .await;
@@ -51,7 +52,7 @@ impl Helper {
             // TODO [issue #7]: Do some accounting to prevent bad nodes from monopolizing our resources.
 
             // get the requestors address.
-            let address = match self.committee.worker(&origin, &self.id) {
+            let address = match self.committee.latest().1.worker(&origin, &self.id) {
                 Ok(x) => x.worker_to_worker,
                 Err(e) => {
                     warn!("Unexpected batch request: {}", e);
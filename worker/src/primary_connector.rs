@@ -16,12 +16,16 @@ pub struct PrimaryConnector {
 }
 
 impl PrimaryConnector {
-    pub fn spawn(primary_address: SocketAddr, rx_digest: Receiver<SerializedBatchDigestMessage>) {
+    pub fn spawn(
+        primary_address: SocketAddr,
+        rx_digest: Receiver<SerializedBatchDigestMessage>,
+        network: SimpleSender,
+    ) {
         tokio::spawn(async move {
             Self {
                 primary_address,
                 rx_digest,
-                network: SimpleSender::new(),
+                network,
             }
             .run()
             .await;
@@ -29,6 +29,8 @@ impl Processor {
         mut rx_batch: Receiver<SerializedBatchMessage>,
         // Output channel to send out batches' digests.
         tx_digest: Sender<SerializedBatchDigestMessage>,
+        // Output channel to report the stored batches (to prune them later).
+        tx_stored: Sender<Digest>,
         // Whether we are processing our own batches or the batches of other nodes.
         own_digest: bool,
     ) {
@@ -39,6 +41,10 @@ impl Processor {
 
                 // Store the batch.
                 store.write(digest.to_vec(), batch).await;
+                tx_stored
+                    .send(digest)
+                    .await
+                    .expect("Failed to report stored batch");
 
                 // Deliver the batch's digest.
                 let message = match own_digest {
@@ -1,6 +1,6 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::processor::SerializedBatchMessage;
-use config::{Committee};
+use config::SharedCommittee;
 use crypto::PublicKey;
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
@@ -23,9 +23,9 @@ pub struct QuorumWaiterMessage {
 /// The QuorumWaiter waits for 2f authorities to acknowledge reception of a batch.
 pub struct QuorumWaiter {
     /// The committee information.
-    committee: Committee,
-    /// The stake of this authority.
-    stake: Stake,
+    committee: SharedCommittee,
+    /// The public key of this authority.
+    name: PublicKey,
     /// Input Channel to receive commands.
     rx_message: Receiver<QuorumWaiterMessage>,
     /// Channel to deliver batches for which we have enough acknowledgements.
@@ -35,15 +35,15 @@ pub struct QuorumWaiter {
 impl QuorumWaiter {
     /// Spawn a new QuorumWaiter.
     pub fn spawn(
-        committee: Committee,
-        stake: Stake,
+        committee: SharedCommittee,
+        name: PublicKey,
         rx_message: Receiver<QuorumWaiterMessage>,
         tx_batch: Sender<Vec<u8>>,
     ) {
         tokio::spawn(async move {
             Self {
                 committee,
-                stake,
+                name,
                 rx_message,
                 tx_batch,
             }
@@ -61,10 +61,11 @@ impl QuorumWaiter {
     /// Main loop.
     async fn run(&mut self) {
         while let Some(QuorumWaiterMessage { batch, handlers }) = self.rx_message.recv().await {
+            let committee = self.committee.latest().1;
             let mut wait_for_quorum: FuturesUnordered<_> = handlers
                 .into_iter()
                 .map(|(name, handler)| {
-                    let stake = self.committee.stake(&name);
+                    let stake = committee.stake(&name);
                     Self::waiter(handler, stake)
                 })
                 .collect();
@@ -72,10 +73,10 @@ impl QuorumWaiter {
             // Wait for the first 2f nodes to send back an Ack. Then we consider the batch
             // delivered and we send its digest to the primary (that will include it into
             // the dag). This should reduce the amount of synching.
-            let mut total_stake = self.stake;
+            let mut total_stake = committee.stake(&self.name);
             while let Some(stake) = wait_for_quorum.next().await {
                 total_stake += stake;
-                if total_stake >= self.committee.quorum_threshold() {
+                if total_stake >= committee.quorum_threshold() {
                     self.tx_batch
                         .send(batch)
                         .await
@@ -1,14 +1,15 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use crate::worker::{Round, WorkerMessage};
 use bytes::Bytes;
-use config::{Committee};
+use config::SharedCommittee;
 use crypto::{Digest, PublicKey};
 use futures::stream::futures_unordered::FuturesUnordered;
 use futures::stream::StreamExt as _;
-use log::{debug, error};
+use log::{debug, error, info, warn};
 use network::SimpleSender;
 use primary::PrimaryWorkerMessage;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto as _;
 use std::time::{SystemTime, UNIX_EPOCH};
 use store::{Store, StoreError};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -28,12 +29,15 @@ pub struct Synchronizer {
     name: PublicKey,
     /// The id of this worker.
     id: WorkerId,
-    /// The committee information.
-    committee: Committee,
+    /// The committee information (our primary installs the new committees).
+    committee: SharedCommittee,
     // The persistent storage.
     store: Store,
     /// The depth of the garbage collection.
     gc_depth: Round,
+    /// How many rounds to keep the batches in store beyond the garbage collection depth (no pruning
+    /// if unset).
+    store_retention: Option<Round>,
     /// The delay to wait before re-trying to send sync requests.
     sync_retry_delay: u64,
     /// Determine with how many nodes to sync when re-trying to send sync-requests. These nodes
@@ -41,6 +45,8 @@ pub struct Synchronizer {
     sync_retry_nodes: usize,
     /// Input channel to receive the commands from the primary.
     rx_message: Receiver<PrimaryWorkerMessage>,
+    /// Receives the digests of the batches stored by the `Processor`s.
+    rx_stored: Receiver<Digest>,
     /// A network sender to send requests to the other workers.
     network: SimpleSender,
     /// Loosely keep track of the primary's round number (only used for cleanup).
@@ -49,6 +55,11 @@ pub struct Synchronizer {
     /// processing will resume when we get the missing batches in the store or we no longer need them.
     /// It also keeps the round number and a timestamp (`u128`) of each request we sent.
     pending: HashMap<Digest, (Round, Sender<()>, u128)>,
+    /// The digests of the stored batches, by the primary's round at the time we stored them.
+    stored: BTreeMap<Round, Vec<Digest>>,
+    /// The digests of the batches found in store at startup. Their round is unknown: they are filed
+    /// under the first round the primary reports, so they are kept for a full retention window.
+    restored: Vec<Digest>,
 }
 
 impl Synchronizer {
@@ -56,12 +67,15 @@ impl Synchronizer {
     pub fn spawn(
         name: PublicKey,
         id: WorkerId,
-        committee: Committee,
+        committee: SharedCommittee,
         store: Store,
         gc_depth: Round,
+        store_retention: Option<Round>,
         sync_retry_delay: u64,
         sync_retry_nodes: usize,
         rx_message: Receiver<PrimaryWorkerMessage>,
+        rx_stored: Receiver<Digest>,
+        network: SimpleSender,
     ) {
         tokio::spawn(async move {
             Self {
@@ -70,12 +84,16 @@ impl Synchronizer {
                 committee,
                 store,
                 gc_depth,
+                store_retention,
                 sync_retry_delay,
                 sync_retry_nodes,
                 rx_message,
-                network: SimpleSender::new(),
+                rx_stored,
+                network,
                 round: Round::default(),
                 pending: HashMap::new(),
+                stored: BTreeMap::new(),
+                restored: Vec::new(),
             }
             .run()
             .await;
@@ -100,6 +118,16 @@ impl Synchronizer {
 
     /// Main loop listening to the primary's messages.
     async fn run(&mut self) {
+        // Rebuild the index of the batches to prune from the ones in store, after a restart.
+        if self.store_retention.is_some() {
+            self.restored = self
+                .store
+                .iter()
+                .filter_map(|(key, _)| async move { key[..].try_into().ok().map(Digest) })
+                .collect()
+                .await;
+        }
+
         let mut waiting = FuturesUnordered::new();
 
         let timer = sleep(Duration::from_millis(TIMER_RESOLUTION));
@@ -147,7 +175,7 @@ impl Synchronizer {
 
                         // Send sync request to a single node. If this fails, we will send it
                         // to other nodes when a timer times out.
-                        let address = match self.committee.worker(&target, &self.id) {
+                        let address = match self.committee.latest().1.worker(&target, &self.id) {
                             Ok(address) => address.worker_to_worker,
                             Err(e) => {
                                 error!("The primary asked us to sync with an unknown node: {}", e);
@@ -161,6 +189,10 @@ impl Synchronizer {
                     PrimaryWorkerMessage::Cleanup(round) => {
                         // Keep track of the primary's round number.
                         self.round = round;
+                        if !self.restored.is_empty() {
+                            let restored = std::mem::take(&mut self.restored);
+                            self.stored.entry(round).or_insert_with(Vec::new).extend(restored);
+                        }
 
                         // Cleanup internal state.
                         if self.round < self.gc_depth {
@@ -174,6 +206,30 @@ impl Synchronizer {
                             }
                         }
                         self.pending.retain(|_, (r, _, _)| r > &mut gc_round);
+
+                        // Prune the store of the batches past the retention window.
+                        if let Some(retention) = self.store_retention.filter(|x| gc_round > *x) {
+                            let kept = self.stored.split_off(&(gc_round - retention));
+                            let pruned = std::mem::replace(&mut self.stored, kept);
+                            if !pruned.is_empty() {
+                                let keys = pruned.into_values().flatten().map(|x| x.to_vec()).collect();
+                                self.store.delete_batch(keys).await;
+                            }
+                        }
+                    },
+                    PrimaryWorkerMessage::Reconfigure(reconfiguration) => {
+                        let epoch = reconfiguration.epoch;
+                        match self.committee.install(reconfiguration) {
+                            Ok(()) => info!("Committee of epoch {} installed", epoch),
+                            Err(e) => warn!("{}", e),
+                        }
+                    }
+                },
+
+                // Keep track of the batches we stored, to prune them later.
+                Some(digest) = self.rx_stored.recv() => {
+                    if self.store_retention.is_some() {
+                        self.stored.entry(self.round).or_insert_with(Vec::new).push(digest);
                     }
                 },
 
@@ -208,6 +264,8 @@ impl Synchronizer {
                     }
                     if !retry.is_empty() {
                         let addresses = self.committee
+                            .latest()
+                            .1
                             .others_workers(&self.name, &self.id)
                             .iter().map(|(_, address)| address.worker_to_worker)
                             .collect();
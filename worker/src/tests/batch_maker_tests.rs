@@ -1,13 +1,13 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
-use crate::common::transaction;
+use crate::common::{committee, keys, transaction};
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
 async fn make_batch() {
     let (tx_transaction, rx_transaction) = channel(1);
     let (tx_message, mut rx_message) = channel(1);
-    let dummy_addresses = vec![(PublicKey::default(), "127.0.0.1:0".parse().unwrap())];
+    let (name, _) = keys().pop().unwrap();
 
     // Spawn a `BatchMaker` instance.
     BatchMaker::spawn(
@@ -15,7 +15,10 @@ async fn make_batch() {
         /* max_batch_delay */ 1_000_000, // Ensure the timer is not triggered.
         rx_transaction,
         tx_message,
-        /* workers_addresses */ dummy_addresses,
+        name,
+        /* id */ 0,
+        committee().into(),
+        ReliableSender::new(),
     );
 
     // Send enough transactions to seal a batch.
@@ -35,7 +38,7 @@ async fn make_batch() {
 async fn batch_timeout() {
     let (tx_transaction, rx_transaction) = channel(1);
     let (tx_message, mut rx_message) = channel(1);
-    let dummy_addresses = vec![(PublicKey::default(), "127.0.0.1:0".parse().unwrap())];
+    let (name, _) = keys().pop().unwrap();
 
     // Spawn a `BatchMaker` instance.
     BatchMaker::spawn(
@@ -43,7 +46,10 @@ async fn batch_timeout() {
         /* max_batch_delay */ 50, // Ensure the timer is triggered.
         rx_transaction,
         tx_message,
-        /* workers_addresses */ dummy_addresses,
+        name,
+        /* id */ 0,
+        committee().into(),
+        ReliableSender::new(),
     );
 
     // Do not send enough transactions to seal a batch..
@@ -2,7 +2,7 @@
 use crate::batch_maker::{Batch, Transaction};
 use crate::worker::WorkerMessage;
 use bytes::Bytes;
-use config::{Authority, Committee, PrimaryAddresses, WorkerAddresses};
+use config::{Authority, Committee, PrimaryAddresses, WorkerAddresses, Weighting};
 use crypto::{generate_keypair, Digest, PublicKey, SecretKey};
 use ed25519_dalek::Digest as _;
 use ed25519_dalek::Sha512;
@@ -40,6 +40,9 @@ pub fn committee() -> Committee {
                     worker_to_primary: format!("127.0.0.1:{}", 200 + i).parse().unwrap(),
                     breeze_addr: format!("127.0.0.1:{}", 600 + i).parse().unwrap(),
                     init_bft_addr: format!("127.0.0.1:{}", 700 + i).parse().unwrap(),
+                    beacon_api_addr: Some(format!("127.0.0.1:{}", 800 + i).parse().unwrap()),
+                    evidence_api_addr: Some(format!("127.0.0.1:{}", 900 + i).parse().unwrap()),
+                    admin_api_addr: None,
                 };
                 let workers = vec![(
                     0,
@@ -62,6 +65,7 @@ pub fn committee() -> Committee {
                 )
             })
             .collect(),
+        weighting: Weighting::Count,
     }
 }
 
@@ -126,8 +130,9 @@ pub fn listener(address: SocketAddr, expected: Option<Bytes>) -> JoinHandle<()>
         match reader.next().await {
             Some(Ok(received)) => {
                 writer.send(Bytes::from("Ack")).await.unwrap();
+                // Skip the byte flagging an uncompressed message.
                 if let Some(expected) = expected {
-                    assert_eq!(received.freeze(), expected);
+                    assert_eq!(received.freeze().slice(1..), expected);
                 }
             }
             _ => panic!("Failed to receive network message"),
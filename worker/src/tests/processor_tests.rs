@@ -2,18 +2,16 @@
 use super::*;
 use crate::common::batch;
 use crate::worker::WorkerMessage;
-use std::fs;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
 async fn hash_and_store() {
     let (tx_batch, rx_batch) = channel(1);
     let (tx_digest, mut rx_digest) = channel(1);
+    let (tx_stored, mut rx_stored) = channel(1);
 
     // Create a new test store.
-    let path = ".db_test_hash_and_store";
-    let _ = fs::remove_dir_all(path);
-    let mut store = Store::new(path).unwrap();
+    let mut store = Store::in_memory();
 
     // Spawn a new `Processor` instance.
     let id = 0;
@@ -22,6 +20,7 @@ async fn hash_and_store() {
         store.clone(),
         rx_batch,
         tx_digest,
+        tx_stored,
         /* own_batch */ true,
     );
 
@@ -40,6 +39,9 @@ async fn hash_and_store() {
     let expected = bincode::serialize(&WorkerPrimaryMessage::OurBatch(digest.clone(), id)).unwrap();
     assert_eq!(output, expected);
 
+    // Ensure the `Processor` reports the stored batch (to prune it later).
+    assert_eq!(rx_stored.recv().await.unwrap(), digest);
+
     // Ensure the `Processor` correctly stored the batch.
     let stored_batch = store.read(digest.to_vec()).await.unwrap();
     assert!(stored_batch.is_some(), "The batch is not in the store");
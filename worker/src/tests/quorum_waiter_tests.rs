@@ -15,7 +15,7 @@ async fn wait_for_quorum() {
     let committee = committee_with_base_port(7_000);
 
     // Spawn a `QuorumWaiter` instance.
-    QuorumWaiter::spawn(committee.clone(), /* stake */ 1, rx_message, tx_batch);
+    QuorumWaiter::spawn(committee.clone().into(), myself, rx_message, tx_batch);
 
     // Make a batch.
     let message = WorkerMessage::Batch(batch());
@@ -1,12 +1,12 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
 use crate::common::{batch_digest, committee_with_base_port, keys, listener};
-use std::fs;
 use tokio::sync::mpsc::channel;
 
 #[tokio::test]
 async fn synchronize() {
     let (tx_message, rx_message) = channel(1);
+    let (_tx_stored, rx_stored) = channel(1);
 
     let mut keys = keys();
     let (name, _) = keys.pop().unwrap();
@@ -14,20 +14,21 @@ async fn synchronize() {
     let committee = committee_with_base_port(9_000);
 
     // Create a new test store.
-    let path = ".db_test_synchronize";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::in_memory();
 
     // Spawn a `Synchronizer` instance.
     Synchronizer::spawn(
         name,
         id,
-        committee.clone(),
+        committee.clone().into(),
         store.clone(),
         /* gc_depth */ 50, // Not used in this test.
+        /* store_retention */ None, // Not used in this test.
         /* sync_retry_delay */ 1_000_000, // Ensure it is not triggered.
         /* sync_retry_nodes */ 3, // Not used in this test.
         rx_message,
+        rx_stored,
+        SimpleSender::new(),
     );
 
     // Spawn a listener to receive our batch requests.
@@ -45,3 +46,89 @@ async fn synchronize() {
     // Ensure the target receives the sync request.
     assert!(handle.await.is_ok());
 }
+
+#[tokio::test]
+async fn prune_batches() {
+    let (tx_message, rx_message) = channel(1);
+    let (tx_stored, rx_stored) = channel(1);
+
+    let (name, _) = keys().pop().unwrap();
+    let id = 0;
+    let committee = committee_with_base_port(9_100);
+
+    // Create a new test store.
+    let mut store = Store::in_memory();
+
+    // Spawn a `Synchronizer` instance.
+    Synchronizer::spawn(
+        name,
+        id,
+        committee.into(),
+        store.clone(),
+        /* gc_depth */ 10,
+        /* store_retention */ Some(5),
+        /* sync_retry_delay */ 1_000_000, // Ensure it is not triggered.
+        /* sync_retry_nodes */ 3, // Not used in this test.
+        rx_message,
+        rx_stored,
+        SimpleSender::new(),
+    );
+
+    // Store a batch (at round 0) and report it.
+    let digest = batch_digest();
+    store.write(digest.to_vec(), Vec::default()).await;
+    tx_stored.send(digest).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+
+    // The batch is kept within the retention window.
+    tx_message.send(PrimaryWorkerMessage::Cleanup(15)).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(store.read(digest.to_vec()).await.unwrap().is_some());
+
+    // And pruned once past it.
+    tx_message.send(PrimaryWorkerMessage::Cleanup(16)).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(store.read(digest.to_vec()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn prune_restored_batches() {
+    let (tx_message, rx_message) = channel(1);
+    let (_tx_stored, rx_stored) = channel(1);
+
+    let (name, _) = keys().pop().unwrap();
+    let id = 0;
+    let committee = committee_with_base_port(9_200);
+
+    // A batch stored before the restart.
+    let mut store = Store::in_memory();
+    let digest = batch_digest();
+    store.write(digest.to_vec(), Vec::default()).await;
+
+    // Spawn a `Synchronizer` instance.
+    Synchronizer::spawn(
+        name,
+        id,
+        committee.into(),
+        store.clone(),
+        /* gc_depth */ 10,
+        /* store_retention */ Some(5),
+        /* sync_retry_delay */ 1_000_000, // Ensure it is not triggered.
+        /* sync_retry_nodes */ 3, // Not used in this test.
+        rx_message,
+        rx_stored,
+        SimpleSender::new(),
+    );
+
+    // The batch is filed under the first round the primary reports, and kept within the retention
+    // window from there.
+    tx_message.send(PrimaryWorkerMessage::Cleanup(15)).await.unwrap();
+    tx_message.send(PrimaryWorkerMessage::Cleanup(30)).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(store.read(digest.to_vec()).await.unwrap().is_some());
+
+    // And pruned once past it.
+    tx_message.send(PrimaryWorkerMessage::Cleanup(31)).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    assert!(store.read(digest.to_vec()).await.unwrap().is_none());
+}
@@ -1,9 +1,8 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
 use crate::common::{batch_digest, committee_with_base_port, keys, listener, transaction};
-use network::SimpleSender;
+use network::{SimpleSender, TcpTransport};
 use primary::WorkerPrimaryMessage;
-use std::fs;
 
 #[tokio::test]
 async fn handle_clients_transactions() {
@@ -16,12 +15,10 @@ async fn handle_clients_transactions() {
     };
 
     // Create a new test store.
-    let path = ".db_test_handle_clients_transactions";
-    let _ = fs::remove_dir_all(path);
-    let store = Store::new(path).unwrap();
+    let store = Store::in_memory();
 
     // Spawn a `Worker` instance.
-    Worker::spawn(name, id, committee.clone(), parameters, store);
+    Worker::spawn(name, id, committee.clone().into(), parameters, store, Arc::new(TcpTransport));
 
     // Spawn a network listener to receive our batch's digest.
     let primary_address = committee.primary(&name).unwrap().worker_to_primary;
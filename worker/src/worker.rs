@@ -7,16 +7,17 @@ use crate::quorum_waiter::QuorumWaiter;
 use crate::synchronizer::Synchronizer;
 use async_trait::async_trait;
 use bytes::Bytes;
-use config::{Committee, Parameters};
+use config::{Parameters, SharedCommittee};
 use crypto::{Digest, PublicKey};
 use futures::sink::SinkExt as _;
 use log::{error, info, warn};
-use network::{MessageHandler, Receiver, Writer};
+use network::{MessageHandler, Receiver, ReliableSender, SimpleSender, Transport, Writer};
 use primary::PrimaryWorkerMessage;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::sync::Arc;
 use store::Store;
-use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::mpsc::{self, channel, Sender};
 use model::types_and_const::{WorkerId, CHANNEL_CAPACITY};
 
 #[cfg(test)]
@@ -28,6 +29,9 @@ pub mod worker_tests;
 // TODO: Move to the primary.
 pub type Round = u64;
 
+/// The column family of the store holding the batches, by digest.
+pub const BATCHES: &str = "batches";
+
 /// Indicates a serialized `WorkerPrimaryMessage` message.
 pub type SerializedBatchDigestMessage = Vec<u8>;
 
@@ -43,21 +47,25 @@ pub struct Worker {
     name: PublicKey,
     /// The id of this worker.
     id: WorkerId,
-    /// The committee information.
-    committee: Committee,
+    /// The committees of the successive epochs. Our primary hands us the new committees as they
+    /// take over.
+    committee: SharedCommittee,
     /// The configuration parameters.
     parameters: Parameters,
-    /// The persistent storage.
+    /// The batches column family of the store.
     store: Store,
+    /// The transport of our senders and receivers.
+    transport: Arc<dyn Transport>,
 }
 
 impl Worker {
     pub fn spawn(
         name: PublicKey,
         id: WorkerId,
-        committee: Committee,
+        committee: SharedCommittee,
         parameters: Parameters,
         store: Store,
+        transport: Arc<dyn Transport>,
     ) {
         // Define a worker instance.
         let worker = Self {
@@ -65,23 +73,28 @@ impl Worker {
             id,
             committee,
             parameters,
-            store,
+            store: store.family(BATCHES),
+            transport,
         };
 
         // Spawn all worker tasks.
         let (tx_primary, rx_primary) = channel(CHANNEL_CAPACITY);
-        worker.handle_primary_messages();
-        worker.handle_clients_transactions(tx_primary.clone());
-        worker.handle_workers_messages(tx_primary);
+        let (tx_stored, rx_stored) = channel(CHANNEL_CAPACITY);
+        worker.handle_primary_messages(rx_stored);
+        worker.handle_clients_transactions(tx_primary.clone(), tx_stored.clone());
+        worker.handle_workers_messages(tx_primary, tx_stored);
 
         // The `PrimaryConnector` allows the worker to send messages to its primary.
         PrimaryConnector::spawn(
             worker
                 .committee
+                .latest()
+                .1
                 .primary(&worker.name)
                 .expect("Our public key is not in the committee")
                 .worker_to_primary,
             rx_primary,
+            SimpleSender::with_transport(worker.transport.clone()),
         );
 
         // NOTE: This log entry is used to compute performance.
@@ -90,6 +103,8 @@ impl Worker {
             id,
             worker
                 .committee
+                .latest()
+                .1
                 .worker(&worker.name, &worker.id)
                 .expect("Our public key or worker id is not in the committee")
                 .transactions
@@ -98,33 +113,40 @@ impl Worker {
     }
 
     /// Spawn all tasks responsible to handle messages from our primary.
-    fn handle_primary_messages(&self) {
+    fn handle_primary_messages(&self, rx_stored: mpsc::Receiver<Digest>) {
         let (tx_synchronizer, rx_synchronizer) = channel(CHANNEL_CAPACITY);
 
         // Receive incoming messages from our primary.
         let mut address = self
             .committee
+            .latest()
+            .1
             .worker(&self.name, &self.id)
             .expect("Our public key or worker id is not in the committee")
             .primary_to_worker;
         address.set_ip("0.0.0.0".parse().unwrap());
-        Receiver::spawn(
+        Receiver::spawn_with_transport(
+            self.transport.clone(),
             address,
             /* handler */
             PrimaryReceiverHandler { tx_synchronizer },
         );
 
         // The `Synchronizer` is responsible to keep the worker in sync with the others. It handles the commands
-        // it receives from the primary (which are mainly notifications that we are out of sync).
+        // it receives from the primary (which are mainly notifications that we are out of sync). It also prunes
+        // the batches stored by the `Processor`s once they fall past the garbage collection horizon.
         Synchronizer::spawn(
             self.name,
             self.id,
             self.committee.clone(),
             self.store.clone(),
             self.parameters.gc_depth,
+            self.parameters.store_retention,
             self.parameters.sync_retry_delay,
             self.parameters.sync_retry_nodes,
             /* rx_message */ rx_synchronizer,
+            rx_stored,
+            SimpleSender::with_transport(self.transport.clone()),
         );
 
         info!(
@@ -134,7 +156,11 @@ impl Worker {
     }
 
     /// Spawn all tasks responsible to handle clients transactions.
-    fn handle_clients_transactions(&self, tx_primary: Sender<SerializedBatchDigestMessage>) {
+    fn handle_clients_transactions(
+        &self,
+        tx_primary: Sender<SerializedBatchDigestMessage>,
+        tx_stored: Sender<Digest>,
+    ) {
         let (tx_batch_maker, rx_batch_maker) = channel(CHANNEL_CAPACITY);
         let (tx_quorum_waiter, rx_quorum_waiter) = channel(CHANNEL_CAPACITY);
         let (tx_processor, rx_processor) = channel(CHANNEL_CAPACITY);
@@ -142,11 +168,14 @@ impl Worker {
         // We first receive clients' transactions from the network.
         let mut address = self
             .committee
+            .latest()
+            .1
             .worker(&self.name, &self.id)
             .expect("Our public key or worker id is not in the committee")
             .transactions;
         address.set_ip("0.0.0.0".parse().unwrap());
-        Receiver::spawn(
+        Receiver::spawn_with_transport(
+            self.transport.clone(),
             address,
             /* handler */ TxReceiverHandler { tx_batch_maker },
         );
@@ -159,19 +188,17 @@ impl Worker {
             self.parameters.max_batch_delay,
             /* rx_transaction */ rx_batch_maker,
             /* tx_message */ tx_quorum_waiter,
-            /* workers_addresses */
-            self.committee
-                .others_workers(&self.name, &self.id)
-                .iter()
-                .map(|(name, addresses)| (*name, addresses.worker_to_worker))
-                .collect(),
+            self.name,
+            self.id,
+            self.committee.clone(),
+            ReliableSender::with_transport(self.transport.clone()),
         );
 
         // The `QuorumWaiter` waits for 2f authorities to acknowledge reception of the batch. It then forwards
         // the batch to the `Processor`.
         QuorumWaiter::spawn(
             self.committee.clone(),
-            /* stake */ self.committee.stake(&self.name),
+            self.name,
             /* rx_message */ rx_quorum_waiter,
             /* tx_batch */ tx_processor,
         );
@@ -183,6 +210,7 @@ impl Worker {
             self.store.clone(),
             /* rx_batch */ rx_processor,
             /* tx_digest */ tx_primary,
+            tx_stored,
             /* own_batch */ true,
         );
 
@@ -193,18 +221,25 @@ impl Worker {
     }
 
     /// Spawn all tasks responsible to handle messages from other workers.
-    fn handle_workers_messages(&self, tx_primary: Sender<SerializedBatchDigestMessage>) {
+    fn handle_workers_messages(
+        &self,
+        tx_primary: Sender<SerializedBatchDigestMessage>,
+        tx_stored: Sender<Digest>,
+    ) {
         let (tx_helper, rx_helper) = channel(CHANNEL_CAPACITY);
         let (tx_processor, rx_processor) = channel(CHANNEL_CAPACITY);
 
         // Receive incoming messages from other workers.
         let mut address = self
             .committee
+            .latest()
+            .1
             .worker(&self.name, &self.id)
             .expect("Our public key or worker id is not in the committee")
             .worker_to_worker;
         address.set_ip("0.0.0.0".parse().unwrap());
-        Receiver::spawn(
+        Receiver::spawn_with_transport(
+            self.transport.clone(),
             address,
             /* handler */
             WorkerReceiverHandler {
@@ -219,6 +254,7 @@ impl Worker {
             self.committee.clone(),
             self.store.clone(),
             /* rx_request */ rx_helper,
+            SimpleSender::with_transport(self.transport.clone()),
         );
 
         // This `Processor` hashes and stores the batches we receive from the other workers. It then forwards the
@@ -228,6 +264,7 @@ impl Worker {
             self.store.clone(),
             /* rx_batch */ rx_processor,
             /* tx_digest */ tx_primary,
+            tx_stored,
             /* own_batch */ false,
         );
 